@@ -0,0 +1,292 @@
+//! Measuring how long serving a tool tree takes, for `mcp-serve bench`.
+//!
+//! Operators of large tool trees need to know where startup time goes: the
+//! directory scan, or parsing the definitions, and which definitions are
+//! slow. A [`BenchReport`] holds the times of each, over several runs, and
+//! optionally of calls to one tool, summarized as percentiles:
+//!
+//! ```text
+//! Benchmarked 120 tool(s) over 10 run(s)
+//!
+//!                   min       p50       p90       p99       max
+//! scan           3.21ms    3.40ms    3.92ms    4.10ms    4.10ms
+//! parse         18.02ms   18.77ms   19.61ms   20.33ms   20.33ms
+//! call deploy   41.87ms   43.05ms   47.90ms   52.12ms   52.12ms
+//!
+//! Slowest definitions to parse (p50):
+//!      1.02ms  tools/billing/invoice
+//! ```
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::time::Duration;
+
+/// How many of the slowest definitions the text report lists.
+const SLOWEST: usize = 5;
+
+/// Durations measured over several runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Samples(Vec<Duration>);
+
+impl Samples {
+    /// Add a measurement.
+    pub fn push(&mut self, duration: Duration) {
+        self.0.push(duration);
+    }
+
+    /// The number of measurements.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether nothing was measured.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The duration `percent` percent of the measurements do not exceed
+    /// (by nearest rank), or zero if there are none.
+    pub fn percentile(&self, percent: f64) -> Duration {
+        let mut sorted = self.0.clone();
+        sorted.sort();
+        let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted
+            .get(rank.clamp(1, sorted.len().max(1)) - 1)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The mean duration, or zero if there are no measurements.
+    pub fn mean(&self) -> Duration {
+        match self.0.len() {
+            0 => Duration::ZERO,
+            n => self.0.iter().sum::<Duration>() / n as u32,
+        }
+    }
+
+    /// The summary written to JSON reports, in milliseconds.
+    pub fn to_json(&self) -> Value {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        json!({
+            "runs": self.len(),
+            "min_ms": millis(self.percentile(0.0)),
+            "p50_ms": millis(self.percentile(50.0)),
+            "p90_ms": millis(self.percentile(90.0)),
+            "p99_ms": millis(self.percentile(99.0)),
+            "max_ms": millis(self.percentile(100.0)),
+            "mean_ms": millis(self.mean()),
+        })
+    }
+}
+
+/// The times of calls to a tool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallBench {
+    /// The tool called
+    pub tool: String,
+
+    /// How long each call took
+    pub samples: Samples,
+
+    /// How many calls returned an error result
+    pub errors: usize,
+}
+
+/// The times of scanning, parsing, and calling, over several runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BenchReport {
+    /// How many times each step was measured
+    pub runs: usize,
+
+    /// How many tools the scan found
+    pub tools: usize,
+
+    /// How long scanning every tool directory took
+    pub scan: Samples,
+
+    /// How long parsing every definition took
+    pub parse: Samples,
+
+    /// How long parsing each tool's definitions took, by the tool's path
+    pub definitions: Vec<(String, Samples)>,
+
+    /// How many tools' definitions could not be loaded
+    pub failures: usize,
+
+    /// Calls to a tool, if asked for
+    pub call: Option<CallBench>,
+}
+
+impl BenchReport {
+    /// The definitions slowest to parse, by median, slowest first.
+    pub fn slowest(&self) -> Vec<&(String, Samples)> {
+        let mut definitions: Vec<_> = self.definitions.iter().collect();
+        definitions.sort_by_key(|(_, samples)| std::cmp::Reverse(samples.percentile(50.0)));
+        definitions
+    }
+
+    /// The report as JSON, for scripts: each step's summary, and every
+    /// definition's, slowest first.
+    pub fn to_json(&self) -> Value {
+        #[derive(Serialize)]
+        struct Definition<'a> {
+            path: &'a str,
+            #[serde(flatten)]
+            summary: Value,
+        }
+
+        let definitions: Vec<Definition> = self
+            .slowest()
+            .into_iter()
+            .map(|(path, samples)| Definition {
+                path,
+                summary: samples.to_json(),
+            })
+            .collect();
+        let mut report = json!({
+            "runs": self.runs,
+            "tools": self.tools,
+            "failures": self.failures,
+            "scan": self.scan.to_json(),
+            "parse": self.parse.to_json(),
+            "definitions": definitions,
+        });
+        if let Some(call) = &self.call {
+            let mut summary = call.samples.to_json();
+            summary["tool"] = json!(call.tool);
+            summary["errors"] = json!(call.errors);
+            report["call"] = summary;
+        }
+        report
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Benchmarked {} tool(s) over {} run(s)",
+            self.tools, self.runs
+        )?;
+        if self.failures > 0 {
+            writeln!(
+                f,
+                "{} tool(s) could not be loaded and are left out",
+                self.failures
+            )?;
+        }
+
+        let mut rows = vec![
+            ("scan".to_string(), &self.scan),
+            ("parse".to_string(), &self.parse),
+        ];
+        if let Some(call) = &self.call {
+            rows.push((format!("call {}", call.tool), &call.samples));
+        }
+        let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        writeln!(f)?;
+        write!(f, "{:width$}", "", width = width)?;
+        for heading in ["min", "p50", "p90", "p99", "max"] {
+            write!(f, "  {:>8}", heading)?;
+        }
+        writeln!(f)?;
+        for (label, samples) in rows {
+            write!(f, "{:width$}", label, width = width)?;
+            for percent in [0.0, 50.0, 90.0, 99.0, 100.0] {
+                write!(f, "  {:>8}", format!("{:.2?}", samples.percentile(percent)))?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(call) = self.call.as_ref().filter(|call| call.errors > 0) {
+            writeln!(
+                f,
+                "{} of {} call(s) to {} returned an error",
+                call.errors,
+                call.samples.len(),
+                call.tool
+            )?;
+        }
+
+        let slowest = self.slowest();
+        if !slowest.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Slowest definitions to parse (p50):")?;
+            for (path, samples) in slowest.into_iter().take(SLOWEST) {
+                writeln!(
+                    f,
+                    "  {:>9}  {}",
+                    format!("{:.2?}", samples.percentile(50.0)),
+                    path
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(millis: &[u64]) -> Samples {
+        Samples(millis.iter().map(|&ms| Duration::from_millis(ms)).collect())
+    }
+
+    #[test]
+    fn test_percentile() {
+        let ten = samples(&[10, 1, 9, 2, 8, 3, 7, 4, 6, 5]);
+        assert_eq!(ten.percentile(0.0), Duration::from_millis(1));
+        assert_eq!(ten.percentile(50.0), Duration::from_millis(5));
+        assert_eq!(ten.percentile(90.0), Duration::from_millis(9));
+        assert_eq!(ten.percentile(99.0), Duration::from_millis(10));
+        assert_eq!(ten.percentile(100.0), Duration::from_millis(10));
+        assert_eq!(ten.mean(), Duration::from_micros(5500));
+
+        assert_eq!(samples(&[7]).percentile(50.0), Duration::from_millis(7));
+        assert_eq!(Samples::default().percentile(50.0), Duration::ZERO);
+        assert_eq!(Samples::default().mean(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_report() {
+        let report = BenchReport {
+            runs: 3,
+            tools: 2,
+            scan: samples(&[2, 1, 3]),
+            parse: samples(&[4, 5, 6]),
+            definitions: vec![
+                ("fast".to_string(), samples(&[1, 1, 1])),
+                ("slow".to_string(), samples(&[3, 4, 5])),
+            ],
+            failures: 0,
+            call: Some(CallBench {
+                tool: "deploy".to_string(),
+                samples: samples(&[20, 30, 40]),
+                errors: 1,
+            }),
+        };
+
+        let text = report.to_string();
+        assert!(
+            text.starts_with("Benchmarked 2 tool(s) over 3 run(s)\n"),
+            "{}",
+            text
+        );
+        assert!(text.contains("\nscan           1.00ms"), "{}", text);
+        assert!(text.contains("\ncall deploy   20.00ms"), "{}", text);
+        assert!(text.contains("1 of 3 call(s) to deploy returned an error"));
+        assert!(
+            text.ends_with("(p50):\n     4.00ms  slow\n     1.00ms  fast\n"),
+            "{}",
+            text
+        );
+
+        let json = report.to_json();
+        assert_eq!(json["scan"]["p50_ms"], 2.0);
+        assert_eq!(json["definitions"][0]["path"], "slow");
+        assert_eq!(json["definitions"][0]["max_ms"], 5.0);
+        assert_eq!(json["call"]["tool"], "deploy");
+        assert_eq!(json["call"]["errors"], 1);
+    }
+}
@@ -0,0 +1,834 @@
+//! Configuration files, for `--config`.
+//!
+//! A deployment can keep its options in a YAML file rather than on the
+//! command line. Every key is optional, and options given as flags or
+//! `MCP_SERVE_*` environment variables take precedence over the file:
+//!
+//! ```yaml
+//! dirs: [./tools, ops=/srv/ops-tools]
+//! detect_shebang: true
+//! transport: http
+//! bind: 0.0.0.0:8080
+//! drain_timeout: 30s
+//! allow: [greet, ops/*]      # serve nothing else, whatever the flags say
+//! deny: ["*-prod"]
+//! confirm_destructive: true  # ask before running tools marked destructiveHint
+//! no_confirm: [ops/restart-*]
+//! read_only: true            # serve only tools marked readOnlyHint
+//! env_passthrough: [HOME, PATH, AWS_*]
+//! defaults:                  # for every tool whose definition does not say
+//!   timeout: 30s
+//!   max_output_size: 1048576
+//!   retries: 1
+//!   sandbox: isolated
+//!   allowed_roots: [data]    # where `format: path` arguments may point
+//! limits:
+//!   max_concurrency: 8
+//!   max_request_size: 1048576
+//! security:
+//!   allowed_origins: [https://agents.example.com]
+//!   trusted_keys: [RWQBAgMEBQYHCIqI4910CfGV/VLbLTy6XXLKZwm/HZQSG/N0iAG0D29c]
+//!   strict: true             # refuse world-writable, foreign, and setuid executables
+//! ```
+//!
+//! A server reloads the file on SIGHUP, or when it changes, and applies
+//! what can change while it serves: `verbose` and `quiet`, `allow` and
+//! `deny`, `read_only`, `limits`, `security`, and `drain_timeout`.
+//!
+//! Relative paths are resolved against the directory of the file, so a
+//! configuration reads the same wherever mcp-serve is started from. Keys the
+//! format does not know are kept in [`Config::unknown_keys`] rather than
+//! failing the load.
+//!
+//! Unlike `only` and `exclude`, which flags replace, `allow` and `deny` have
+//! no flags: they bound every command that loads tools, so only vetted tools
+//! are ever exposed, even if new executables appear in a directory.
+//!
+//! `mcp-serve config check` reports what a file gets wrong without starting
+//! anything ([`Config::check`]), and `mcp-serve config schema` prints the
+//! JSON Schema of the format ([`schema`]) for editors, as `mcp-serve schema`
+//! does for definitions.
+
+use minisign_verify::PublicKey;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::definitions::SchemaDraft;
+use crate::doctor::Finding;
+use crate::filter::{NamePattern, ToolPolicy};
+use crate::registry::DuplicatePolicy;
+use crate::tool_discovery::diagnostics::closest;
+use crate::tool_discovery::execution::Timeout;
+use crate::tool_discovery::schema::root_schema;
+use crate::tool_discovery::{ExecutionConfig, SandboxProfile, ToolRoot, ToolSource};
+use crate::transport::Transport;
+
+/// The options a configuration file sets; `None` (or empty) leaves an
+/// option to the command line and its default.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[schemars(title = "mcp-serve configuration", deny_unknown_fields)]
+pub struct Config {
+    /// Directories (or repositories and archives) to discover tools from,
+    /// each optionally `NAMESPACE=DIR`
+    #[serde(default, deserialize_with = "tool_roots")]
+    #[schemars(with = "Option<Vec<String>>")]
+    pub dirs: Option<Vec<ToolRoot>>,
+
+    /// Prefix tool names with the name of their directory
+    pub namespace_dirs: Option<bool>,
+
+    /// Resolve these tool names on `$PATH` instead of scanning
+    pub from_path: Option<Vec<String>>,
+
+    /// Treat files starting with `#!` as tools, even without the executable
+    /// bit
+    pub detect_shebang: Option<bool>,
+
+    /// Descend into subdirectories
+    pub recursive: Option<bool>,
+
+    /// Descend into symlinked directories
+    pub follow_symlinks: Option<bool>,
+
+    /// Scan dotfiles and dot-directories
+    pub include_hidden: Option<bool>,
+
+    /// Maximum number of directory levels to descend into
+    pub max_depth: Option<usize>,
+
+    /// Directory to cache fetched repositories and archives in
+    pub cache_dir: Option<PathBuf>,
+
+    /// What to do when two tools declare the same name
+    #[serde(default, deserialize_with = "value_enum")]
+    #[schemars(schema_with = "value_enum_schema::<DuplicatePolicy>")]
+    pub on_duplicate: Option<DuplicatePolicy>,
+
+    /// File to cache scan results in
+    pub index: Option<PathBuf>,
+
+    /// JSON Schema draft tool schemas must conform to
+    #[serde(default, deserialize_with = "value_enum")]
+    #[schemars(schema_with = "value_enum_schema::<SchemaDraft>")]
+    pub schema_draft: Option<SchemaDraft>,
+
+    /// Leave out tools marked as deprecated
+    pub hide_deprecated: Option<bool>,
+
+    /// Only serve tools with at least one of these tags (or categories)
+    pub tags: Option<Vec<String>>,
+
+    /// Only serve the tools with these names (or name patterns)
+    #[schemars(with = "Option<Vec<String>>")]
+    pub only: Option<Vec<NamePattern>>,
+
+    /// Leave out the tools with these names (or name patterns)
+    #[schemars(with = "Option<Vec<String>>")]
+    pub exclude: Option<Vec<NamePattern>>,
+
+    /// Reject definitions with fields the format does not define
+    pub strict: Option<bool>,
+
+    /// File pinning executables to their SHA-256 digests, as `sha256sum`
+    /// writes it; executables it does not list are refused
+    pub lockfile: Option<PathBuf>,
+
+    /// Serve and run tools whose executable does not match its pin, with a
+    /// warning
+    pub allow_unpinned: Option<bool>,
+
+    /// Never serve tools other than those with these names (or name
+    /// patterns); an empty list serves none
+    #[schemars(with = "Option<Vec<String>>")]
+    pub allow: Option<Vec<NamePattern>>,
+
+    /// Never serve the tools with these names (or name patterns)
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub deny: Vec<NamePattern>,
+
+    /// Run tools with only the variables of the server's environment whose
+    /// names match these patterns
+    #[schemars(with = "Option<Vec<String>>")]
+    pub env_passthrough: Option<Vec<NamePattern>>,
+
+    /// Execution settings of every tool, unless set by its definition or
+    /// its directory's `.mcp-serve.yaml`
+    #[serde(default)]
+    pub defaults: Defaults,
+
+    /// How clients connect
+    #[serde(default, deserialize_with = "value_enum")]
+    #[schemars(schema_with = "value_enum_schema::<Transport>")]
+    pub transport: Option<Transport>,
+
+    /// Address the HTTP transport listens on
+    pub bind: Option<String>,
+
+    /// How long running tool calls are given to finish when the server is
+    /// stopped (seconds, or `30s`, `2m`)
+    #[serde(
+        default,
+        deserialize_with = "crate::tool_discovery::execution::deserialize_duration"
+    )]
+    #[schemars(with = "Option<Timeout>")]
+    pub drain_timeout: Option<Duration>,
+
+    /// Run in the background once listening
+    pub daemon: Option<bool>,
+
+    /// File to write the server's process ID to
+    pub pid_file: Option<PathBuf>,
+
+    /// File the daemon writes its logs to
+    pub log_file: Option<PathBuf>,
+
+    /// Ask the user before running tools marked `destructiveHint: true`
+    pub confirm_destructive: Option<bool>,
+
+    /// Run the tools with these names (or name patterns) without
+    /// confirmation
+    #[schemars(with = "Option<Vec<String>>")]
+    pub no_confirm: Option<Vec<NamePattern>>,
+
+    /// Serve only tools annotated `readOnlyHint: true`
+    pub read_only: Option<bool>,
+
+    /// Log more: each request and tool run (`1`), and every message (`2`)
+    pub verbose: Option<u8>,
+
+    /// Log errors only
+    pub quiet: Option<bool>,
+
+    /// Bounds on the work clients can cause
+    #[serde(default)]
+    pub limits: Limits,
+
+    /// Who may reach the server
+    #[serde(default)]
+    pub security: Security,
+
+    /// Keys the format does not define, such as misspellings, by path
+    /// (`limits.max_concurency`)
+    #[serde(skip)]
+    pub unknown_keys: Vec<String>,
+}
+
+/// Execution settings every tool inherits, as the `execution` section of
+/// its definition.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Defaults {
+    /// How long a call may run before it is killed (seconds, or `30s`,
+    /// `2m`)
+    #[serde(
+        default,
+        deserialize_with = "crate::tool_discovery::execution::deserialize_duration"
+    )]
+    #[schemars(with = "Option<Timeout>")]
+    pub timeout: Option<Duration>,
+
+    /// Most bytes a tool may write to stdout, or to stderr
+    pub max_output_size: Option<u64>,
+
+    /// How many times a failed call is run again
+    pub retries: Option<u32>,
+
+    /// How far tools are kept from the server's environment
+    pub sandbox: Option<SandboxProfile>,
+
+    /// Directories that `format: path` arguments must lie within
+    pub allowed_roots: Option<Vec<PathBuf>>,
+}
+
+impl Defaults {
+    /// The settings, as a definition's `execution` section inherits them.
+    pub fn execution(&self) -> ExecutionConfig {
+        ExecutionConfig {
+            timeout: self.timeout,
+            max_output_size: self.max_output_size,
+            retries: self.retries,
+            sandbox: self.sandbox,
+            allowed_roots: self.allowed_roots.clone(),
+            ..ExecutionConfig::default()
+        }
+    }
+}
+
+/// Bounds on the work clients can cause.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Limits {
+    /// Most tool calls running at once, across all tools
+    pub max_concurrency: Option<NonZeroUsize>,
+
+    /// Largest HTTP request body accepted, in bytes
+    pub max_request_size: Option<u64>,
+}
+
+/// Who may reach the server, and whose tools it serves.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Security {
+    /// Web origins (`https://app.example.com`) allowed to call the HTTP
+    /// transport, besides those on this machine
+    pub allowed_origins: Option<Vec<String>>,
+
+    /// Minisign public keys (`RWQ...`) tools must be signed with; tools
+    /// whose files are not are refused
+    #[serde(default, deserialize_with = "public_keys")]
+    #[schemars(with = "Option<Vec<String>>")]
+    pub trusted_keys: Option<Vec<PublicKey>>,
+
+    /// Refuse executables others can change, or that are setuid, rather
+    /// than serve them with a warning
+    pub strict: Option<bool>,
+}
+
+/// A configuration file that could not be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read.
+    Read { path: PathBuf, source: io::Error },
+
+    /// The file is not a valid configuration.
+    Parse {
+        path: PathBuf,
+        source: serde_yaml_ng::Error,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => {
+                write!(f, "cannot read config {}: {}", path.display(), source)
+            }
+            ConfigError::Parse { path, source } => {
+                write!(f, "invalid config {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Read { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+        }
+    }
+}
+
+impl Config {
+    /// The tools the configuration permits to be served.
+    pub fn policy(&self) -> ToolPolicy {
+        ToolPolicy {
+            allow: self.allow.clone(),
+            deny: self.deny.clone(),
+        }
+    }
+
+    /// Load the configuration file at `path`.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut config = Self::from_yaml(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        config.resolve_paths(path.parent().unwrap_or(Path::new("")));
+        Ok(config)
+    }
+
+    /// Parse a configuration, leaving its relative paths as they are.
+    pub fn from_yaml(text: &str) -> Result<Self, serde_yaml_ng::Error> {
+        // An empty file configures nothing.
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let mut unknown_keys = Vec::new();
+        let mut config: Self =
+            serde_ignored::deserialize(serde_yaml_ng::Deserializer::from_str(text), |path| {
+                unknown_keys.push(path.to_string())
+            })?;
+        config.unknown_keys = unknown_keys;
+        Ok(config)
+    }
+
+    /// The problems of the configuration loaded from `path`: options the
+    /// format does not know, options that contradict each other, and tools
+    /// directories that do not exist.
+    pub fn check(&self, path: &Path) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let schema = schema();
+        for key in &self.unknown_keys {
+            let suggestion = match closest_key(&schema, key) {
+                Some(known) => format!("did you mean `{}`?", known),
+                None => "remove it; it is ignored".to_string(),
+            };
+            findings.push(Finding::warning(
+                path,
+                format!("unknown option `{}`", key),
+                suggestion,
+            ));
+        }
+
+        if self.verbose.is_some_and(|verbose| verbose > 0) && self.quiet == Some(true) {
+            findings.push(Finding::error(
+                path,
+                "`verbose` and `quiet` contradict each other",
+                "remove one of them",
+            ));
+        }
+        let http = self.transport == Some(Transport::Http);
+        if self.daemon == Some(true) && !http {
+            findings.push(Finding::error(
+                path,
+                "`daemon` needs the HTTP transport, as a daemon has no standard input",
+                "set `transport: http`, or remove `daemon`",
+            ));
+        }
+        let http_only = [
+            ("bind", self.bind.is_some()),
+            (
+                "limits.max_request_size",
+                self.limits.max_request_size.is_some(),
+            ),
+            (
+                "security.allowed_origins",
+                self.security.allowed_origins.is_some(),
+            ),
+        ];
+        for (key, _) in http_only.into_iter().filter(|&(_, set)| set && !http) {
+            findings.push(Finding::warning(
+                path,
+                format!("`{}` only applies to the HTTP transport", key),
+                format!("set `transport: http`, or remove `{}`", key),
+            ));
+        }
+        if self.log_file.is_some() && self.daemon != Some(true) {
+            findings.push(Finding::warning(
+                path,
+                "`log_file` is only written by a daemon",
+                "set `daemon: true`, or remove `log_file`",
+            ));
+        }
+        if self.no_confirm.is_some() && self.confirm_destructive != Some(true) {
+            findings.push(Finding::warning(
+                path,
+                "`no_confirm` only applies when destructive tools are confirmed",
+                "set `confirm_destructive: true`, or remove `no_confirm`",
+            ));
+        }
+        if self.read_only == Some(true) && self.confirm_destructive == Some(true) {
+            findings.push(Finding::warning(
+                path,
+                "`confirm_destructive` does nothing with `read_only`, as no destructive tool is served",
+                "remove `confirm_destructive`",
+            ));
+        }
+        if self.allow.as_ref().is_some_and(Vec::is_empty) {
+            findings.push(Finding::warning(
+                path,
+                "`allow` is empty, so no tool is served",
+                "list the tools to serve, or remove `allow`",
+            ));
+        }
+        if self
+            .security
+            .trusted_keys
+            .as_ref()
+            .is_some_and(Vec::is_empty)
+        {
+            findings.push(Finding::warning(
+                path,
+                "`security.trusted_keys` is empty, so no tool is served",
+                "list the public keys tools are signed with, or remove `trusted_keys`",
+            ));
+        }
+
+        for root in self.dirs.iter().flatten() {
+            let (location, directory) = match root.source() {
+                ToolSource::Local(dir) => (dir, true),
+                ToolSource::Archive { location, .. } if !location.contains("://") => {
+                    (PathBuf::from(location), false)
+                }
+                ToolSource::Archive { .. } | ToolSource::Git { .. } => continue,
+            };
+            let problem = match fs::metadata(&location) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => "does not exist",
+                Err(_) => continue,
+                Ok(metadata) if directory && !metadata.is_dir() => "is not a directory",
+                Ok(_) => continue,
+            };
+            findings.push(Finding::error(
+                path,
+                format!("`dirs`: {} {}", location.display(), problem),
+                "create it, or correct the path (relative paths start at the file's directory)",
+            ));
+        }
+        if let Some(lockfile) = self.lockfile.as_ref().filter(|lockfile| !lockfile.exists()) {
+            findings.push(Finding::error(
+                path,
+                format!("`lockfile`: {} does not exist", lockfile.display()),
+                "write it with `sha256sum`, or correct the path",
+            ));
+        }
+        findings
+    }
+
+    /// Make the relative paths of the configuration relative to `base`.
+    fn resolve_paths(&mut self, base: &Path) {
+        for root in self.dirs.iter_mut().flatten() {
+            let local = match root.source() {
+                ToolSource::Local(_) => true,
+                ToolSource::Archive { location, .. } => !location.contains("://"),
+                ToolSource::Git { .. } => false,
+            };
+            if local && root.path.is_relative() {
+                root.path = base.join(&root.path);
+            }
+        }
+        let paths = [
+            &mut self.cache_dir,
+            &mut self.index,
+            &mut self.lockfile,
+            &mut self.pid_file,
+            &mut self.log_file,
+        ];
+        let roots = self.defaults.allowed_roots.iter_mut().flatten();
+        for path in paths.into_iter().flatten().chain(roots) {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        }
+    }
+}
+
+/// Deserialize `DIR` and `NAMESPACE=DIR` strings, as given on the command
+/// line.
+fn tool_roots<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<ToolRoot>>, D::Error> {
+    let specs = Option::<Vec<String>>::deserialize(deserializer)?;
+    Ok(specs.map(|specs| {
+        specs
+            .iter()
+            .map(|spec| spec.parse().unwrap_or_else(|e| match e {}))
+            .collect()
+    }))
+}
+
+/// Deserialize minisign public keys from their base64 encoding, as the
+/// second line of a `.pub` file holds it.
+fn public_keys<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<PublicKey>>, D::Error> {
+    let keys = Option::<Vec<String>>::deserialize(deserializer)?;
+    keys.map(|keys| {
+        keys.iter()
+            .map(|key| {
+                PublicKey::from_base64(key.trim()).map_err(|e| {
+                    de::Error::custom(format!("invalid minisign public key {:?}: {}", key, e))
+                })
+            })
+            .collect()
+    })
+    .transpose()
+}
+
+/// Deserialize a value by the name the command line knows it by, such as
+/// `auto-suffix` or `2020-12`.
+fn value_enum<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: clap::ValueEnum,
+{
+    /// A name, which YAML may read as a number (`schema_draft: 7`).
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Name {
+        Text(String),
+        Number(u64),
+    }
+
+    let name = match Option::<Name>::deserialize(deserializer)? {
+        Some(Name::Text(text)) => text,
+        Some(Name::Number(number)) => number.to_string(),
+        None => return Ok(None),
+    };
+    T::from_str(&name, true).map(Some).map_err(|_| {
+        let names: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|value| Some(value.to_possible_value()?.get_name().to_string()))
+            .collect();
+        de::Error::custom(format!(
+            "unknown value `{}`, expected one of: {}",
+            name,
+            names.join(", ")
+        ))
+    })
+}
+
+/// The JSON Schema of configuration files.
+pub fn schema() -> Value {
+    root_schema::<Config>()
+}
+
+/// The schema of a value named as the command line names it, for
+/// [`value_enum`]. Names that are numbers may be written as numbers.
+fn value_enum_schema<T: clap::ValueEnum>(_: &mut SchemaGenerator) -> Schema {
+    let mut names = Vec::new();
+    for value in T::value_variants() {
+        let Some(value) = value.to_possible_value() else {
+            continue;
+        };
+        let name = value.get_name();
+        names.push(Value::from(name));
+        if let Ok(number) = name.parse::<u64>() {
+            names.push(Value::from(number));
+        }
+    }
+    json_schema!({ "enum": names })
+}
+
+/// The option of `schema` closest to the unknown `key`
+/// (`limits.max_concurency`), if any is close enough to be a typo.
+fn closest_key(schema: &Value, key: &str) -> Option<String> {
+    let (section, name) = match key.rsplit_once('.') {
+        Some((section, name)) => (Some(section), name),
+        None => (None, key),
+    };
+    // Sections are definitions the root schema refers to.
+    let mut object = schema;
+    for part in section.into_iter().flat_map(|section| section.split('.')) {
+        let property = &object["properties"][part];
+        let reference = property["$ref"]
+            .as_str()
+            .or_else(|| property["allOf"][0]["$ref"].as_str())?;
+        object = schema.pointer(reference.strip_prefix('#')?)?;
+    }
+    let options = object["properties"].as_object()?;
+    let option = closest(name, options.keys().map(String::as_str))?;
+    Some(match section {
+        Some(section) => format!("{}.{}", section, option),
+        None => option.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("mcp-serve.yaml");
+        fs::write(
+            &path,
+            r#"
+dirs: [tools, ops=/srv/ops, bundle.zip, "https://github.com/team/tools.git"]
+detect_shebang: true
+recursive: false
+on_duplicate: auto-suffix
+schema_draft: 7
+index: cache/index.json
+lockfile: mcp-serve.lock
+transport: http
+bind: 0.0.0.0:8080
+drain_timeout: 30s
+pid_file: run/mcp-serve.pid
+confirm_destructive: true
+no_confirm: [ops/restart-*]
+read_only: false
+verbose: 1
+defaults: { timeout: 1m, retries: 2, sandbox: isolated, allowed_roots: [data] }
+allow: [greet, ops/*]
+deny: ["*-prod"]
+limits: { max_concurrency: 4, max_concurency: 5 }
+security:
+  allowed_origins: [https://agents.example.com]
+  trusted_keys: [RWQBAgMEBQYHCIqI4910CfGV/VLbLTy6XXLKZwm/HZQSG/N0iAG0D29c]
+  strict: true
+"#,
+        )
+        .unwrap();
+
+        let config = Config::load(&path).unwrap();
+        let policy = config.policy();
+        assert!(policy.permits("ops/deploy"));
+        assert!(!policy.permits("ops/deploy-prod"));
+        assert!(!policy.permits("build"));
+
+        assert_eq!(
+            config.dirs.unwrap(),
+            [
+                ToolRoot::new(dir.path().join("tools")),
+                ToolRoot::new("/srv/ops").with_namespace("ops"),
+                ToolRoot::new(dir.path().join("bundle.zip")),
+                ToolRoot::new("https://github.com/team/tools.git"),
+            ]
+        );
+        assert_eq!(config.detect_shebang, Some(true));
+        assert_eq!(config.recursive, Some(false));
+        assert_eq!(config.on_duplicate, Some(DuplicatePolicy::AutoSuffix));
+        assert_eq!(config.schema_draft, Some(SchemaDraft::Draft7));
+        assert_eq!(config.index, Some(dir.path().join("cache/index.json")));
+        assert_eq!(config.lockfile, Some(dir.path().join("mcp-serve.lock")));
+        assert_eq!(config.transport, Some(Transport::Http));
+        assert_eq!(config.bind.as_deref(), Some("0.0.0.0:8080"));
+        assert_eq!(config.drain_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(config.pid_file, Some(dir.path().join("run/mcp-serve.pid")));
+        assert_eq!(config.confirm_destructive, Some(true));
+        assert!(config.no_confirm.unwrap()[0].matches("ops/restart-web"));
+        assert_eq!(config.read_only, Some(false));
+        assert_eq!(config.verbose, Some(1));
+        let defaults = config.defaults.execution();
+        assert_eq!(defaults.timeout, Some(Duration::from_secs(60)));
+        assert_eq!(defaults.retries, Some(2));
+        assert_eq!(defaults.sandbox, Some(SandboxProfile::Isolated));
+        assert_eq!(defaults.max_output_size, None);
+        assert_eq!(defaults.allowed_roots.unwrap(), [dir.path().join("data")]);
+        assert_eq!(config.limits.max_concurrency, NonZeroUsize::new(4));
+        assert_eq!(
+            config.security.allowed_origins.unwrap(),
+            ["https://agents.example.com"]
+        );
+        assert_eq!(config.security.trusted_keys.unwrap().len(), 1);
+        assert_eq!(config.security.strict, Some(true));
+        assert_eq!(config.unknown_keys, ["limits.max_concurency"]);
+        assert_eq!(config.strict, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Config::from_yaml("").unwrap(), Config::default());
+
+        let error = Config::from_yaml("transport: carrier-pigeon\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("unknown value `carrier-pigeon`, expected one of: stdio, http"),
+            "{}",
+            error
+        );
+        assert!(Config::from_yaml("limits: { max_concurrency: 0 }\n").is_err());
+        let error = Config::from_yaml("security: { trusted_keys: [RWQ] }\n").unwrap_err();
+        assert!(
+            error.to_string().contains("invalid minisign public key"),
+            "{}",
+            error
+        );
+
+        let error = Config::load(Path::new("no/such/mcp-serve.yaml")).unwrap_err();
+        assert!(matches!(error, ConfigError::Read { .. }));
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("tools")).unwrap();
+        let path = dir.path().join("mcp-serve.yaml");
+        let check = |yaml: &str| {
+            fs::write(&path, yaml).unwrap();
+            let findings = Config::load(&path).unwrap().check(&path);
+            findings
+                .iter()
+                .map(|finding| {
+                    format!(
+                        "{}: {} ({})",
+                        finding.severity, finding.problem, finding.suggestion
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let valid = "dirs: [tools]\ntransport: http\nbind: 0.0.0.0:8080\ndaemon: true\n";
+        assert_eq!(check(valid), Vec::<String>::new());
+
+        let findings = check(
+            "dirs: [tools, missing, mcp-serve.yaml]\ntransprot: http\nlimits: { max_concurency: 4 }\n",
+        );
+        assert_eq!(
+            findings[..2],
+            [
+                "warning: unknown option `transprot` (did you mean `transport`?)",
+                "warning: unknown option `limits.max_concurency` (did you mean `limits.max_concurrency`?)",
+            ]
+        );
+        assert!(
+            findings[2].starts_with("error: `dirs`: "),
+            "{}",
+            findings[2]
+        );
+        assert!(
+            findings[2].contains("missing does not exist"),
+            "{}",
+            findings[2]
+        );
+        assert!(
+            findings[3].contains("mcp-serve.yaml is not a directory"),
+            "{}",
+            findings[3]
+        );
+        assert_eq!(findings.len(), 4);
+
+        let findings = check(
+            "verbose: 1\nquiet: true\ndaemon: true\nbind: 0.0.0.0:8080\nno_confirm: [deploy]\n",
+        );
+        assert_eq!(
+            findings,
+            [
+                "error: `verbose` and `quiet` contradict each other (remove one of them)",
+                "error: `daemon` needs the HTTP transport, as a daemon has no standard input (set `transport: http`, or remove `daemon`)",
+                "warning: `bind` only applies to the HTTP transport (set `transport: http`, or remove `bind`)",
+                "warning: `no_confirm` only applies when destructive tools are confirmed (set `confirm_destructive: true`, or remove `no_confirm`)",
+            ]
+        );
+        assert_eq!(
+            check("read_only: true\nconfirm_destructive: true\n"),
+            ["warning: `confirm_destructive` does nothing with `read_only`, as no destructive tool is served (remove `confirm_destructive`)"]
+        );
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = schema();
+        assert_eq!(schema["title"], "mcp-serve configuration");
+        let is_valid = |yaml: &str| {
+            let instance: Value = serde_yaml_ng::from_str(yaml).unwrap();
+            jsonschema::validator_for(&schema)
+                .unwrap()
+                .is_valid(&instance)
+        };
+
+        let config = r#"
+dirs: [./tools, ops=/srv/ops-tools]
+on_duplicate: auto-suffix
+schema_draft: 7
+transport: http
+drain_timeout: 30s
+allow: [greet, ops/*]
+defaults: { timeout: 30, sandbox: isolated }
+limits: { max_concurrency: 8 }
+security: { allowed_origins: [https://agents.example.com] }
+"#;
+        assert!(Config::from_yaml(config).unwrap().unknown_keys.is_empty());
+        assert!(is_valid(config));
+        assert!(!is_valid("transport: carrier-pigeon\n"));
+        assert!(!is_valid("limits: { max_concurency: 8 }\n"));
+        assert!(!is_valid("defaults: { sandbox: jail }\n"));
+    }
+}
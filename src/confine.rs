@@ -0,0 +1,404 @@
+//! Keeping path arguments within the directories a tool may use.
+//!
+//! A property of a tool's input schema tagged `format: path` holds a file
+//! or directory for the tool to work on. Nothing stops the model from
+//! filling it with `/etc/shadow`, so when `execution.allowed_roots` (or the
+//! configuration's `defaults.allowed_roots`) is set, every such argument
+//! must lie within one of those directories:
+//!
+//! ```yaml
+//! input:
+//!   template: "{{file}}"
+//!   schema:
+//!     type: object
+//!     properties:
+//!       file: { type: string, format: path }
+//! execution:
+//!   allowed_roots: [./data, /srv/shared]
+//! ```
+//!
+//! Values are resolved against the directory the tool runs in and
+//! canonicalized, following symbolic links, before they are compared, so
+//! neither `..` nor a link leads out. Of a path that does not exist yet,
+//! the longest part that does is canonicalized, and a link whose target
+//! does not exist yet stands for that target. The tool is handed the
+//! canonical path. Without `allowed_roots`, paths are not checked.
+//!
+//! Properties are found wherever the schema can describe them: through
+//! `properties`, `patternProperties`, `additionalProperties`, `items`,
+//! `prefixItems`, and the other keywords that apply subschemas, and through
+//! `$ref`s within the schema. Every subschema of `anyOf`, `oneOf`, and
+//! `if`/`then`/`else` is applied, whichever one the value matches, so that
+//! a path is never let through for fitting an alternative.
+
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How many subschemas may apply to one value by `$ref` and the other
+/// in-place keywords, so that a schema referring to itself is not followed
+/// forever.
+const MAX_DEPTH: usize = 64;
+
+/// Keywords whose lists of subschemas apply to the value itself.
+const IN_PLACE_LISTS: &[&str] = &["allOf", "anyOf", "oneOf"];
+
+/// Keywords whose subschema applies to the value itself.
+const IN_PLACE: &[&str] = &["if", "then", "else"];
+
+/// Check every value `schema` marks `format: path` within `value` against
+/// the directories `roots`, resolving relative paths against `base`, and
+/// replace it with its canonical path. The error names the argument by its
+/// JSON pointer.
+pub fn confine(
+    schema: &Value,
+    value: &mut Value,
+    roots: &[PathBuf],
+    base: &Path,
+) -> Result<(), String> {
+    let roots: Vec<PathBuf> = roots
+        .iter()
+        .filter_map(|root| fs::canonicalize(root).ok())
+        .collect();
+    let confiner = Confiner {
+        root: schema,
+        roots: &roots,
+        base,
+    };
+    confiner.visit(schema, value, "", 0)
+}
+
+struct Confiner<'a> {
+    /// The whole schema, which `$ref`s point into
+    root: &'a Value,
+    roots: &'a [PathBuf],
+    base: &'a Path,
+}
+
+impl Confiner<'_> {
+    /// Confine the paths of `value`, at `pointer`, that `schema` describes;
+    /// `depth` counts the subschemas already applied to it in place.
+    fn visit(
+        &self,
+        schema: &Value,
+        value: &mut Value,
+        pointer: &str,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err(format!(
+                "{}: the input schema applies too many subschemas to the argument",
+                pointer_or_root(pointer)
+            ));
+        }
+        for subschema in in_place(schema, value) {
+            self.visit(subschema, value, pointer, depth + 1)?;
+        }
+        if let Some(target) = schema["$ref"].as_str().and_then(|r| self.resolve(r)) {
+            self.visit(target, value, pointer, depth + 1)?;
+        }
+        match value {
+            Value::String(path) if schema["format"] == "path" => {
+                *path = self.confine(path, pointer)?;
+            }
+            Value::Object(object) => {
+                for (name, value) in object.iter_mut() {
+                    let pointer = format!("{}/{}", pointer, escape(name));
+                    for subschema in property_schemas(schema, name) {
+                        self.visit(subschema, value, &pointer, 0)?;
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    let pointer = format!("{}/{}", pointer, index);
+                    for subschema in item_schemas(schema, index) {
+                        self.visit(subschema, item, &pointer, 0)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The canonical form of `path`, if it lies within a root.
+    fn confine(&self, path: &str, pointer: &str) -> Result<String, String> {
+        let canonical = canonicalize(&self.base.join(path)).map_err(|e| {
+            format!(
+                "{}: cannot resolve {:?}: {}",
+                pointer_or_root(pointer),
+                path,
+                e
+            )
+        })?;
+        if !self.roots.iter().any(|root| canonical.starts_with(root)) {
+            return Err(format!(
+                "{}: {:?} is outside the directories the tool may use ({})",
+                pointer_or_root(pointer),
+                path,
+                list(self.roots)
+            ));
+        }
+        Ok(canonical.to_string_lossy().into_owned())
+    }
+
+    /// The subschema a `$ref` within the schema points to. Other references
+    /// were resolved when the definition was loaded, or are URLs.
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+/// The subschemas of `schema` that apply to `value` itself.
+fn in_place<'a>(schema: &'a Value, value: &Value) -> Vec<&'a Value> {
+    let mut found: Vec<&Value> = IN_PLACE_LISTS
+        .iter()
+        .filter_map(|keyword| schema[keyword].as_array())
+        .flatten()
+        .collect();
+    found.extend(IN_PLACE.iter().map(|keyword| &schema[keyword]));
+    if let (Some(dependents), Value::Object(object)) =
+        (schema["dependentSchemas"].as_object(), value)
+    {
+        found.extend(
+            dependents
+                .iter()
+                .filter(|(name, _)| object.contains_key(*name))
+                .map(|(_, subschema)| subschema),
+        );
+    }
+    found.retain(|subschema| subschema.is_object());
+    found
+}
+
+/// The subschemas of `schema` that apply to its object's property `name`.
+fn property_schemas<'a>(schema: &'a Value, name: &str) -> Vec<&'a Value> {
+    let mut found = Vec::new();
+    found.extend(schema["properties"].get(name));
+    let patterns = schema["patternProperties"].as_object();
+    // A pattern the regex crate cannot read is taken to match, so that the
+    // property is still confined.
+    found.extend(
+        patterns
+            .into_iter()
+            .flat_map(Map::iter)
+            .filter(|(pattern, _)| Regex::new(pattern).map_or(true, |re| re.is_match(name)))
+            .map(|(_, subschema)| subschema),
+    );
+    if found.is_empty() {
+        found.push(&schema["additionalProperties"]);
+    }
+    found.push(&schema["unevaluatedProperties"]);
+    found.retain(|subschema| subschema.is_object());
+    found
+}
+
+/// The subschemas of `schema` that apply to item `index` of its array.
+fn item_schemas(schema: &Value, index: usize) -> Vec<&Value> {
+    // Before draft 2020-12, `items` could be a list, for a tuple.
+    let tuple = schema["prefixItems"]
+        .as_array()
+        .or_else(|| schema["items"].as_array());
+    let mut found = Vec::new();
+    match tuple {
+        Some(tuple) if index < tuple.len() => found.push(&tuple[index]),
+        Some(_) if schema["prefixItems"].is_array() => found.push(&schema["items"]),
+        Some(_) => found.push(&schema["additionalItems"]),
+        None => found.push(&schema["items"]),
+    }
+    found.push(&schema["contains"]);
+    found.push(&schema["unevaluatedItems"]);
+    found.retain(|subschema| subschema.is_object());
+    found
+}
+
+/// The canonical form of `path`, which need not exist: its longest
+/// existing ancestor is canonicalized, and the rest appended. A `..` past
+/// that ancestor cannot be resolved.
+///
+/// A dangling symbolic link is followed to where its target would be, since
+/// a tool creating the file writes through the link.
+fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(path) => Ok(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                return Err(e);
+            };
+            let path = canonicalize(parent)?.join(name);
+            match fs::symlink_metadata(&path) {
+                Err(_) => Ok(path),
+                Ok(metadata) if metadata.is_symlink() => {
+                    let target = fs::read_link(&path)?;
+                    canonicalize(&path.parent().unwrap_or(Path::new("/")).join(target))
+                }
+                Ok(_) => Err(e),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn pointer_or_root(pointer: &str) -> &str {
+    if pointer.is_empty() {
+        "/"
+    } else {
+        pointer
+    }
+}
+
+/// Escape a property name for a JSON pointer.
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+fn list(roots: &[PathBuf]) -> String {
+    if roots.is_empty() {
+        return "none".to_string();
+    }
+    roots
+        .iter()
+        .map(|root| root.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_confine() {
+        let dir = TempDir::new().unwrap();
+        let data = dir.path().join("data");
+        fs::create_dir(&data).unwrap();
+        fs::write(data.join("notes.txt"), "notes").unwrap();
+        let data = fs::canonicalize(&data).unwrap();
+        let roots = [dir.path().join("data")];
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "file": {"type": "string", "format": "path"},
+                "files": {"type": "array", "items": {"type": "string", "format": "path"}},
+                "title": {"type": "string"},
+            },
+        });
+        let check = |arguments: Value| {
+            let mut arguments = arguments;
+            confine(&schema, &mut arguments, &roots, dir.path()).map(|()| arguments)
+        };
+
+        let arguments = check(json!({"file": "data/notes.txt", "title": "/etc"})).unwrap();
+        assert_eq!(arguments["file"], json!(data.join("notes.txt")));
+        assert_eq!(arguments["title"], "/etc");
+        let arguments = check(json!({"files": ["data/new.txt", "data/./notes.txt"]}));
+        assert_eq!(
+            arguments.unwrap()["files"],
+            json!([data.join("new.txt"), data.join("notes.txt")])
+        );
+
+        let error = check(json!({"file": "data/../secret"})).unwrap_err();
+        assert!(error.starts_with("/file: "), "{}", error);
+        assert!(error.contains("is outside the directories"), "{}", error);
+        let error = check(json!({"files": ["data/a", "/"]})).unwrap_err();
+        assert!(error.starts_with("/files/1: "), "{}", error);
+        let error = check(json!({"file": "data/missing/../../secret"})).unwrap_err();
+        assert!(error.contains("cannot resolve"), "{}", error);
+
+        let mut arguments = json!({"file": "data/notes.txt"});
+        assert!(confine(&schema, &mut arguments, &[], dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_confine_keywords() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("data")).unwrap();
+        let roots = [dir.path().join("data")];
+        let path = json!({"type": "string", "format": "path"});
+        let file = json!({"f": "/etc/shadow"});
+        let tuple = json!({"f": [1, "/etc/shadow"]});
+        let cases = [
+            (json!({"additionalProperties": path}), &file),
+            (json!({"patternProperties": {"^f": path}}), &file),
+            (
+                json!({"properties": {"f": {"anyOf": [{"type": "integer"}, path]}}}),
+                &file,
+            ),
+            (json!({"properties": {"f": {"oneOf": [path]}}}), &file),
+            (json!({"allOf": [{"properties": {"f": path}}]}), &file),
+            (
+                json!({"if": {"required": ["f"]}, "then": {"properties": {"f": path}}}),
+                &file,
+            ),
+            (
+                json!({"properties": {"f": {"$ref": "#/$defs/f"}}, "$defs": {"f": path}}),
+                &file,
+            ),
+            (
+                json!({"dependentSchemas": {"f": {"properties": {"f": path}}}}),
+                &file,
+            ),
+            (
+                json!({"properties": {"f": {"prefixItems": [{}, path]}}}),
+                &tuple,
+            ),
+            (json!({"properties": {"f": {"items": [{}, path]}}}), &tuple),
+            (json!({"properties": {"f": {"contains": path}}}), &tuple),
+        ];
+        for (schema, arguments) in cases {
+            let error = confine(&schema, &mut arguments.clone(), &roots, dir.path());
+            assert!(
+                error.is_err_and(|error| error.contains("is outside the directories")),
+                "{}",
+                schema
+            );
+        }
+
+        // A schema referring to itself is followed as deep as the value.
+        let schema = json!({
+            "type": "object",
+            "properties": {"file": path, "children": {"type": "array", "items": {"$ref": "#"}}},
+        });
+        let mut arguments = json!({"children": [{"children": [{"file": "/etc/shadow"}]}]});
+        let error = confine(&schema, &mut arguments, &roots, dir.path()).unwrap_err();
+        assert!(
+            error.starts_with("/children/0/children/0/file: "),
+            "{}",
+            error
+        );
+        let mut arguments = json!({"file": "data/a"});
+        assert!(confine(&json!({"$ref": "#"}), &mut arguments, &roots, dir.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_confine_symlink() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("data")).unwrap();
+        std::os::unix::fs::symlink("/etc", dir.path().join("data/etc")).unwrap();
+        let schema = json!({"type": "string", "format": "path"});
+
+        let mut value = json!("data/etc/passwd");
+        let roots = [dir.path().join("data")];
+        let error = confine(&schema, &mut value, &roots, dir.path());
+        assert!(error.unwrap_err().starts_with("/: "));
+
+        // The tool would create the file the link points to, outside.
+        std::os::unix::fs::symlink(dir.path().join("shadow"), dir.path().join("data/new")).unwrap();
+        let mut value = json!("data/new");
+        let error = confine(&schema, &mut value, &roots, dir.path()).unwrap_err();
+        assert!(error.contains("is outside the directories"), "{}", error);
+
+        std::os::unix::fs::symlink("inside", dir.path().join("data/link")).unwrap();
+        let mut value = json!("data/link");
+        confine(&schema, &mut value, &roots, dir.path()).unwrap();
+        let data = fs::canonicalize(dir.path().join("data")).unwrap();
+        assert_eq!(value, json!(data.join("inside")));
+    }
+}
@@ -0,0 +1,192 @@
+//! Confirming calls of destructive tools, for `serve --confirm-destructive`.
+//!
+//! Clients may ask their user before calling a tool marked
+//! `destructiveHint: true`, but nothing makes them. With confirmation on,
+//! the server asks itself, before the tool runs:
+//!
+//! 1. Through the client, with an MCP elicitation request, if the client
+//!    declared the `elicitation` capability and its transport carries
+//!    requests from the server (stdio does; the HTTP transport, without
+//!    server-initiated streams, does not).
+//! 2. Otherwise on the terminal the server runs in, if it has one.
+//!
+//! A call nobody confirms is answered with an error result saying so,
+//! rather than run. Tools whose names match a `--no-confirm` pattern are
+//! run without asking.
+
+use serde_json::{json, Map, Value};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::{Mutex, PoisonError};
+
+use crate::filter::NamePattern;
+use crate::redact::Redactor;
+use crate::tool_discovery::ToolDefinition;
+
+/// Which calls must be confirmed before they run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfirmPolicy {
+    /// Tools run without asking, by name (or name pattern)
+    pub skip: Vec<NamePattern>,
+}
+
+/// What became of a request for confirmation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    /// The user agreed to the call.
+    Confirmed,
+
+    /// The user refused the call, or dismissed the question.
+    Declined,
+
+    /// Nobody could be asked, for the reason given.
+    Unavailable(String),
+}
+
+impl ConfirmPolicy {
+    /// Whether calls of the tool `definition` defines must be confirmed.
+    pub fn requires(&self, definition: &ToolDefinition) -> bool {
+        let destructive = definition.annotations.as_ref().is_some_and(|annotations| {
+            annotations.destructive_hint == Some(true) && !annotations.is_read_only()
+        });
+        destructive
+            && !self
+                .skip
+                .iter()
+                .any(|pattern| pattern.matches(&definition.name))
+    }
+}
+
+/// The question asked about calling the tool `definition` defines with
+/// `arguments`, whose sensitive values are not shown.
+pub fn question(definition: &ToolDefinition, arguments: &Map<String, Value>) -> String {
+    let arguments =
+        Redactor::for_definition(definition).redact_arguments(&Value::Object(arguments.clone()));
+    let arguments = serde_json::to_string_pretty(&arguments).expect("JSON values serialize");
+    format!(
+        "Run `{}`? It is marked as destructive.\nArguments: {}",
+        definition.name, arguments
+    )
+}
+
+/// The parameters of an `elicitation/create` request asking `question`.
+pub fn elicitation(question: &str) -> Value {
+    json!({
+        "message": question,
+        "requestedSchema": {
+            "type": "object",
+            "properties": {
+                "confirm": {
+                    "type": "boolean",
+                    "title": "Run the tool",
+                    "default": false,
+                },
+            },
+            "required": ["confirm"],
+        },
+    })
+}
+
+/// Read the client's result for an [`elicitation`] request: only an
+/// accepted form with `confirm` checked confirms the call.
+pub fn read_elicitation(result: &Value) -> Answer {
+    let accepted = result["action"] == "accept" && result["content"]["confirm"] == true;
+    if accepted {
+        Answer::Confirmed
+    } else {
+        Answer::Declined
+    }
+}
+
+/// Ask `question` on the terminal the server runs in, waiting for an
+/// answer. Only one question is asked at a time.
+pub fn ask_terminal(question: &str) -> Answer {
+    static TERMINAL: Mutex<()> = Mutex::new(());
+    let _terminal = TERMINAL.lock().unwrap_or_else(PoisonError::into_inner);
+    let answer = open_terminal().and_then(|(input, mut output)| {
+        write!(output, "\n{}\nRun it? [y/N] ", question)?;
+        output.flush()?;
+        let mut answer = String::new();
+        BufReader::new(input).read_line(&mut answer)?;
+        Ok(answer)
+    });
+    match answer {
+        Ok(answer) if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") => {
+            Answer::Confirmed
+        }
+        Ok(_) => Answer::Declined,
+        Err(e) => Answer::Unavailable(format!("there is no terminal to ask on ({})", e)),
+    }
+}
+
+/// The terminal the server runs in, to read from and write to.
+#[cfg(unix)]
+fn open_terminal() -> io::Result<(File, File)> {
+    let terminal = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    Ok((terminal.try_clone()?, terminal))
+}
+
+/// The console the server runs in, to read from and write to.
+#[cfg(not(unix))]
+fn open_terminal() -> io::Result<(File, File)> {
+    let input = OpenOptions::new().read(true).write(true).open("CONIN$")?;
+    let output = OpenOptions::new().write(true).open("CONOUT$")?;
+    Ok((input, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requires() {
+        let definition = |annotations: &str| {
+            ToolDefinition::from_yaml(&format!(
+                "name: deploy\ndescription: Deploys\ninput: {{ template: '', schema: {{ type: object }} }}\noutput: {{ template: '', schema: {{ type: object }} }}\n{}",
+                annotations
+            ))
+            .unwrap()
+        };
+        let policy = ConfirmPolicy::default();
+        assert!(policy.requires(&definition("annotations: { destructiveHint: true }\n")));
+        assert!(!policy.requires(&definition("")));
+        assert!(!policy.requires(&definition("annotations: { destructiveHint: false }\n")));
+        assert!(!policy.requires(&definition(
+            "annotations: { destructiveHint: true, readOnlyHint: true }\n"
+        )));
+
+        let policy = ConfirmPolicy {
+            skip: vec!["dep*".parse().unwrap()],
+        };
+        assert!(!policy.requires(&definition("annotations: { destructiveHint: true }\n")));
+    }
+
+    #[test]
+    fn test_question() {
+        let definition = ToolDefinition::from_yaml(
+            "name: deploy\ndescription: Deploys\ninput: { template: '', schema: { type: object, properties: { token: { type: string, x-sensitive: true } } } }\noutput: { template: '', schema: { type: object } }\n",
+        )
+        .unwrap();
+        let arguments = json!({"token": "s3cret", "env": "prod"});
+        let question = question(&definition, arguments.as_object().unwrap());
+        assert!(question.starts_with("Run `deploy`?"), "{}", question);
+        assert!(question.contains("[REDACTED]") && question.contains("prod"));
+        assert!(!question.contains("s3cret"), "{}", question);
+    }
+
+    #[test]
+    fn test_read_elicitation() {
+        let confirmed = json!({"action": "accept", "content": {"confirm": true}});
+        assert_eq!(read_elicitation(&confirmed), Answer::Confirmed);
+        let unchecked = json!({"action": "accept", "content": {"confirm": false}});
+        assert_eq!(read_elicitation(&unchecked), Answer::Declined);
+        assert_eq!(
+            read_elicitation(&json!({"action": "decline"})),
+            Answer::Declined
+        );
+        assert_eq!(
+            read_elicitation(&json!({"action": "cancel"})),
+            Answer::Declined
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Converting sidecar definitions between formats, for `mcp-serve convert`.
+//!
+//! A sidecar reads the same as YAML, JSON, or TOML, so its definitions can
+//! be written back in any of them, or as the pure MCP tool clients receive
+//! ([`ConvertFormat::Mcp`]), which keeps only what the protocol defines.
+//!
+//! [`migrate`] rewrites a sidecar in place: in another format, or in the
+//! same one to bring it up to date with the current definition format.
+//! Definitions are written as they are read, so comments and formatting
+//! are not kept.
+
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::init::{self, InitError};
+use crate::tool_discovery::scanner::TOOL_DIRECTORY_DEFINITION;
+use crate::tool_discovery::{SidecarError, SidecarFormat, ToolDefinition};
+
+/// What definitions are converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConvertFormat {
+    /// A YAML sidecar
+    Yaml,
+
+    /// A JSON sidecar
+    Json,
+
+    /// A TOML sidecar
+    Toml,
+
+    /// The tools as MCP clients receive them, as JSON; this cannot be read
+    /// back as a sidecar
+    Mcp,
+}
+
+impl ConvertFormat {
+    /// The sidecar format written, unless the definitions are converted to
+    /// their MCP form.
+    pub fn sidecar_format(self) -> Option<SidecarFormat> {
+        match self {
+            ConvertFormat::Yaml => Some(SidecarFormat::Yaml),
+            ConvertFormat::Json => Some(SidecarFormat::Json),
+            ConvertFormat::Toml => Some(SidecarFormat::Toml),
+            ConvertFormat::Mcp => None,
+        }
+    }
+}
+
+impl From<SidecarFormat> for ConvertFormat {
+    fn from(format: SidecarFormat) -> Self {
+        match format {
+            SidecarFormat::Yaml => ConvertFormat::Yaml,
+            SidecarFormat::Json => ConvertFormat::Json,
+            SidecarFormat::Toml => ConvertFormat::Toml,
+        }
+    }
+}
+
+impl fmt::Display for ConvertFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConvertFormat::Yaml => "YAML",
+            ConvertFormat::Json => "JSON",
+            ConvertFormat::Toml => "TOML",
+            ConvertFormat::Mcp => "MCP",
+        })
+    }
+}
+
+/// Errors that can occur while converting definitions.
+#[derive(Debug)]
+pub enum ConvertError {
+    /// The sidecar could not be loaded.
+    Load(SidecarError),
+
+    /// The definitions cannot be written in the format, as TOML cannot
+    /// write `null`s.
+    Serialize {
+        format: ConvertFormat,
+        message: String,
+    },
+
+    /// The converted sidecar could not be written.
+    Write(InitError),
+
+    /// The sidecar replaced by the converted one could not be removed.
+    Remove { path: PathBuf, source: io::Error },
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Load(e) => write!(f, "{}", e),
+            ConvertError::Serialize { format, message } => {
+                write!(f, "cannot write the definitions as {}: {}", format, message)
+            }
+            ConvertError::Write(e) => write!(f, "{}", e),
+            ConvertError::Remove { path, source } => {
+                write!(f, "failed to remove {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConvertError::Load(e) => Some(e),
+            ConvertError::Serialize { .. } => None,
+            ConvertError::Write(e) => Some(e),
+            ConvertError::Remove { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Write `definitions` in `format`: a single definition as a document of
+/// its own, several under a `tools` key.
+pub fn render(
+    definitions: &[ToolDefinition],
+    format: ConvertFormat,
+) -> Result<String, ConvertError> {
+    #[derive(Serialize)]
+    struct ToolList<T> {
+        tools: Vec<T>,
+    }
+
+    match format {
+        ConvertFormat::Mcp => {
+            let mut tools: Vec<_> = definitions
+                .iter()
+                .map(|definition| definition.to_mcp_tool().to_json())
+                .collect();
+            let json = match tools.len() {
+                1 => serde_json::to_string_pretty(&tools.remove(0)),
+                _ => serde_json::to_string_pretty(&ToolList { tools }),
+            };
+            Ok(json.expect("tool serialization cannot fail") + "\n")
+        }
+        _ => match definitions {
+            [definition] => serialize(definition, format),
+            _ => serialize(
+                &ToolList {
+                    tools: definitions.iter().collect(),
+                },
+                format,
+            ),
+        },
+    }
+}
+
+fn serialize<T: Serialize>(value: &T, format: ConvertFormat) -> Result<String, ConvertError> {
+    let error = |message: String| ConvertError::Serialize { format, message };
+    match format {
+        ConvertFormat::Yaml => serde_yaml_ng::to_string(value).map_err(|e| error(e.to_string())),
+        ConvertFormat::Json | ConvertFormat::Mcp => serde_json::to_string_pretty(value)
+            .map(|json| json + "\n")
+            .map_err(|e| error(e.to_string())),
+        ConvertFormat::Toml => toml::to_string(value).map_err(|e| error(e.to_string())),
+    }
+}
+
+/// Read the sidecar at `path` and write its definitions in `format`.
+pub fn convert_file(path: &Path, format: ConvertFormat) -> Result<String, ConvertError> {
+    let definitions = ToolDefinition::all_from_sidecar(path).map_err(ConvertError::Load)?;
+    render(&definitions, format)
+}
+
+/// Where [`migrate`] writes the sidecar at `path` in `format`: next to it,
+/// with the extension of the format, or `path` itself if it is already in
+/// that format. `None` for the `tool.yaml` of a tool directory in another
+/// format, which a tool directory is only recognized by.
+pub fn migration_target(path: &Path, format: SidecarFormat) -> Option<PathBuf> {
+    if SidecarFormat::from_path(path) == Some(format) {
+        return Some(path.to_path_buf());
+    }
+    if path
+        .file_name()
+        .is_some_and(|name| name == TOOL_DIRECTORY_DEFINITION)
+    {
+        return None;
+    }
+    let extension = match format {
+        SidecarFormat::Yaml => "yaml",
+        SidecarFormat::Json => "json",
+        SidecarFormat::Toml => "toml",
+    };
+    Some(path.with_extension(extension))
+}
+
+/// Rewrite the sidecar at `path` in `format`, replacing it, and return the
+/// path written (see [`migration_target`]), or `None` if the sidecar must
+/// stay as it is. Unless `force` is set, an existing sidecar in the new
+/// format is not replaced.
+pub fn migrate(
+    path: &Path,
+    format: SidecarFormat,
+    force: bool,
+) -> Result<Option<PathBuf>, ConvertError> {
+    let Some(target) = migration_target(path, format) else {
+        return Ok(None);
+    };
+    let contents = convert_file(path, format.into())?;
+    init::write_definition(&target, &contents, force || target == path)
+        .map_err(ConvertError::Write)?;
+    if target != path {
+        fs::remove_file(path).map_err(|source| ConvertError::Remove {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    }
+    Ok(Some(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DEFINITION: &str = r#"
+name: greet
+description: Greets someone
+annotations: { readOnlyHint: true }
+input:
+  template: "{{name}}"
+  schema:
+    type: object
+    properties:
+      name: { type: string }
+output:
+  template: "(?<greeting>.*)"
+  schema:
+    type: object
+    properties:
+      greeting: { type: string }
+"#;
+
+    #[test]
+    fn test_render() {
+        let definition = ToolDefinition::from_yaml(DEFINITION).unwrap();
+        for format in [
+            ConvertFormat::Yaml,
+            ConvertFormat::Json,
+            ConvertFormat::Toml,
+        ] {
+            let text = render(std::slice::from_ref(&definition), format).unwrap();
+            let parsed = format.sidecar_format().unwrap().parse_all(&text).unwrap();
+            assert_eq!(parsed, std::slice::from_ref(&definition), "{}", format);
+        }
+
+        let pair = [definition.clone(), definition.clone()];
+        let toml = render(&pair, ConvertFormat::Toml).unwrap();
+        assert!(toml.contains("[[tools]]"), "{}", toml);
+        assert_eq!(SidecarFormat::Toml.parse_all(&toml).unwrap(), pair);
+
+        let mcp: serde_json::Value = serde_json::from_str(
+            &render(std::slice::from_ref(&definition), ConvertFormat::Mcp).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(mcp, definition.to_mcp_tool().to_json());
+        assert_eq!(mcp["annotations"]["readOnlyHint"], true);
+        assert!(mcp.get("input").is_none());
+
+        let mut nullable = definition;
+        nullable.input.schema["properties"]["name"]["default"] = serde_json::Value::Null;
+        let error = render(&[nullable], ConvertFormat::Toml).unwrap_err();
+        assert!(matches!(
+            error,
+            ConvertError::Serialize {
+                format: ConvertFormat::Toml,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_migrate() {
+        let dir = TempDir::new().unwrap();
+        let yaml = dir.path().join("greet.yaml");
+        fs::write(&yaml, DEFINITION).unwrap();
+
+        let json = migrate(&yaml, SidecarFormat::Json, false).unwrap().unwrap();
+        assert_eq!(json, dir.path().join("greet.json"));
+        assert!(!yaml.exists());
+        let definition = ToolDefinition::from_sidecar(&json).unwrap();
+        assert_eq!(definition, ToolDefinition::from_yaml(DEFINITION).unwrap());
+
+        // Rewriting a sidecar in its own format replaces it.
+        assert_eq!(
+            migrate(&json, SidecarFormat::Json, false).unwrap(),
+            Some(json.clone())
+        );
+
+        fs::write(&yaml, DEFINITION).unwrap();
+        let error = migrate(&yaml, SidecarFormat::Json, false).unwrap_err();
+        assert!(matches!(
+            error,
+            ConvertError::Write(InitError::Exists { .. })
+        ));
+        assert!(yaml.exists());
+
+        let tool_yaml = dir.path().join(TOOL_DIRECTORY_DEFINITION);
+        fs::write(&tool_yaml, DEFINITION).unwrap();
+        assert_eq!(
+            migrate(&tool_yaml, SidecarFormat::Toml, false).unwrap(),
+            None
+        );
+        assert!(tool_yaml.exists());
+    }
+}
@@ -0,0 +1,165 @@
+//! Running the server in the background, for `serve --daemon`, and the pid
+//! files supervisors find it by.
+//!
+//! [`detach`] forks. The original process waits until the daemon reports
+//! that it is serving ([`Readiness::serving`]), or exits, and only then
+//! exits itself: a daemon that cannot start, say because its port is taken,
+//! still fails the command that started it. The daemon leaves the
+//! terminal's session, and writes its logs to a file, or nowhere.
+//!
+//! The daemon keeps the working directory it was started in, so relative
+//! tool directories and paths mean what they did on the command line.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Which process [`detach`] returned in.
+#[derive(Debug)]
+pub enum Detached {
+    /// The process that was started, once the daemon is serving or has
+    /// exited.
+    Parent {
+        /// The daemon's process ID
+        pid: u32,
+
+        /// Whether the daemon started serving
+        serving: bool,
+    },
+
+    /// The daemon, which reports when it is serving.
+    Daemon(Readiness),
+}
+
+/// The daemon's side of [`detach`]: the process that started it waits for
+/// [`serving`](Readiness::serving), or for the daemon to exit.
+#[derive(Debug)]
+pub struct Readiness(io::PipeWriter);
+
+impl Readiness {
+    /// Tell the process that started the daemon that it is serving, so that
+    /// it exits successfully.
+    pub fn serving(mut self) {
+        use std::io::Write;
+
+        // The parent may have been killed; the daemon serves regardless.
+        let _ = self.0.write_all(b"1");
+    }
+}
+
+/// Continue in a background process detached from the terminal, writing
+/// its logs (standard error) to `log_file`, or discarding them.
+///
+/// This must be called before any thread is started: only the calling
+/// thread continues in the daemon.
+#[cfg(unix)]
+pub fn detach(log_file: Option<&Path>) -> io::Result<Detached> {
+    use std::io::Read;
+    use std::os::fd::AsRawFd;
+
+    // Opened before forking, so that the command fails if it cannot be.
+    let log = match log_file {
+        Some(path) => fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?,
+        None => fs::OpenOptions::new().write(true).open("/dev/null")?,
+    };
+    let null = fs::File::open("/dev/null")?;
+    let (mut reader, writer) = io::pipe()?;
+
+    // SAFETY: no other thread runs yet, so the child inherits no lock held
+    // by one.
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => {
+            drop(reader);
+            // SAFETY: these calls only act on the process and on file
+            // descriptors it owns.
+            unsafe {
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                for (from, to) in [(&null, 0), (&null, 1), (&log, 2)] {
+                    if libc::dup2(from.as_raw_fd(), to) == -1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+            }
+            Ok(Detached::Daemon(Readiness(writer)))
+        }
+        pid => {
+            drop(writer);
+            let mut report = Vec::new();
+            // The pipe closes when the daemon reports, or when it exits.
+            reader.read_to_end(&mut report)?;
+            Ok(Detached::Parent {
+                pid: pid as u32,
+                serving: !report.is_empty(),
+            })
+        }
+    }
+}
+
+/// Continue in a background process; only supported on Unix.
+#[cfg(not(unix))]
+pub fn detach(_log_file: Option<&Path>) -> io::Result<Detached> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "running as a daemon is only supported on Unix; use a service manager instead",
+    ))
+}
+
+/// A file holding the server's process ID, removed when the server exits.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Write the process ID of this process to `path`, replacing any file
+    /// left by an earlier server.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        fs::write(path, format!("{}\n", std::process::id()))
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Where the process ID is written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pid_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("mcp-serve.pid");
+
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(pid_file.path(), path);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        drop(pid_file);
+        assert!(!path.exists());
+
+        let error = PidFile::create(&dir.path().join("missing/mcp-serve.pid")).unwrap_err();
+        assert!(error.to_string().contains("missing"), "{}", error);
+    }
+}
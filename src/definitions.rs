@@ -0,0 +1,1400 @@
+//! Validation of tool definitions.
+//!
+//! Parsing only checks that a definition has the right shape. [`validate`]
+//! checks what parsing cannot: that the name is one MCP clients accept, that
+//! the schemas are valid JSON Schema, that the output template compiles,
+//! and that the templates and schemas are consistent with each other. The
+//! server validates every definition as it loads it, and `--lint` reports
+//! the results without serving anything.
+
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::output::extract::Extract;
+use crate::output::{declares_exit_code, OutputEncoding, OutputTemplate, EXIT_CODE_PROPERTY};
+use crate::quote::QuoteStyle;
+use crate::redact;
+use crate::template::jinja::JinjaTemplate;
+use crate::template::shell::ShellTemplate;
+use crate::template::{
+    CommandTemplate, Filter, InputTemplate, Template, TemplateEngine, TemplateErrorKind,
+};
+use crate::tool_discovery::{
+    ExecutionConfig, OutputContent, ToolDefinition, ToolInput, ToolOutput,
+};
+
+/// Maximum length of a tool name, per the MCP specification.
+pub const MAX_NAME_LENGTH: usize = 128;
+
+/// How serious a [`ValidationIssue`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The tool works, but something is likely a mistake.
+    Warning,
+
+    /// The tool cannot be served correctly.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a tool definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// How serious the problem is
+    pub severity: Severity,
+
+    /// Dotted path of the offending field, such as `input.schema.required`
+    pub field: String,
+
+    /// What is wrong
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn error(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Whether the issue prevents the tool from being served.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Check `definition` for problems with default settings.
+///
+/// See [`Validator::validate`].
+pub fn validate(definition: &ToolDefinition) -> Vec<ValidationIssue> {
+    Validator::new().validate(definition)
+}
+
+/// JSON Schema drafts that tool schemas can be checked against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaDraft {
+    /// Draft 4
+    #[value(name = "4")]
+    Draft4,
+
+    /// Draft 6
+    #[value(name = "6")]
+    Draft6,
+
+    /// Draft 7
+    #[value(name = "7")]
+    Draft7,
+
+    /// Draft 2019-09
+    #[value(name = "2019-09")]
+    Draft201909,
+
+    /// Draft 2020-12, the MCP specification's default dialect.
+    #[default]
+    #[value(name = "2020-12")]
+    Draft202012,
+}
+
+impl SchemaDraft {
+    fn meta_validator(self) -> jsonschema::meta::MetaValidator<'static> {
+        match self {
+            SchemaDraft::Draft4 => jsonschema::draft4::meta::validator(),
+            SchemaDraft::Draft6 => jsonschema::draft6::meta::validator(),
+            SchemaDraft::Draft7 => jsonschema::draft7::meta::validator(),
+            SchemaDraft::Draft201909 => jsonschema::draft201909::meta::validator(),
+            SchemaDraft::Draft202012 => jsonschema::draft202012::meta::validator(),
+        }
+    }
+}
+
+/// Checks tool definitions for problems.
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    draft: SchemaDraft,
+}
+
+impl Validator {
+    /// Create a validator with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check schemas against the meta-schema of `draft`.
+    pub fn with_draft(mut self, draft: SchemaDraft) -> Self {
+        self.draft = draft;
+        self
+    }
+
+    /// Check `definition` for problems, returning them in field order.
+    ///
+    /// An empty result means the definition is valid. Definitions with any
+    /// [`Severity::Error`] issue should not be served.
+    pub fn validate(&self, definition: &ToolDefinition) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        validate_name(&definition.name, "name", &mut issues);
+        validate_aliases(definition, &mut issues);
+        validate_tags(definition, &mut issues);
+        if definition.description.trim().is_empty() {
+            issues.push(ValidationIssue::error(
+                "description",
+                "must not be empty; clients show it to the model to explain the tool",
+            ));
+        }
+        self.validate_schema_root(&definition.input.schema, "input.schema", &mut issues);
+        self.validate_schema_root(&definition.output.schema, "output.schema", &mut issues);
+        validate_input(&definition.input, &mut issues);
+        validate_output(&definition.output, &mut issues);
+        validate_execution(&definition.execution, &mut issues);
+        if definition.execution.shell {
+            validate_shell(definition, &mut issues);
+        }
+        let mut markers = Vec::new();
+        redact::invalid_markers(&definition.input.schema, "input.schema", &mut markers);
+        for field in markers {
+            issues.push(ValidationIssue::error(
+                field,
+                "must be true or false; a misspelled marker would leave a secret unredacted",
+            ));
+        }
+        issues
+    }
+
+    /// MCP requires tool input and output schemas to describe objects, and
+    /// they must be valid under the configured draft's meta-schema.
+    fn validate_schema_root(&self, schema: &Value, field: &str, issues: &mut Vec<ValidationIssue>) {
+        if !schema.is_object() {
+            issues.push(ValidationIssue::error(
+                field,
+                "must be a JSON Schema object",
+            ));
+            return;
+        }
+        match schema.get("type") {
+            Some(Value::String(t)) if t == "object" => {}
+            Some(_) => issues.push(ValidationIssue::error(
+                format!("{}.type", field),
+                "must be \"object\"",
+            )),
+            None => issues.push(ValidationIssue::warning(
+                format!("{}.type", field),
+                "is missing; MCP clients expect \"object\"",
+            )),
+        }
+
+        let mut errors: Vec<ValidationIssue> = self
+            .draft
+            .meta_validator()
+            .iter_errors(schema)
+            .map(|error| {
+                let location: String = error
+                    .instance_path()
+                    .into_iter()
+                    .map(|segment| format!(".{}", segment))
+                    .collect();
+                ValidationIssue::error(format!("{}{}", field, location), error.to_string())
+            })
+            .collect();
+        errors.sort_by(|a, b| a.field.cmp(&b.field));
+        issues.extend(errors);
+
+        validate_required(schema, field, issues);
+    }
+}
+
+/// Names must be 1 to [`MAX_NAME_LENGTH`] characters of ASCII letters,
+/// digits, `_`, `-`, and `.`.
+fn validate_name(name: &str, field: &str, issues: &mut Vec<ValidationIssue>) {
+    if name.is_empty() {
+        issues.push(ValidationIssue::error(field, "must not be empty"));
+        return;
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        issues.push(ValidationIssue::error(
+            field,
+            format!(
+                "is {} characters long; the maximum is {}",
+                name.len(),
+                MAX_NAME_LENGTH
+            ),
+        ));
+    }
+    let invalid: Vec<char> = name
+        .chars()
+        .filter(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+        .collect();
+    if !invalid.is_empty() {
+        issues.push(ValidationIssue::error(
+            field,
+            format!(
+                "contains {:?}; only ASCII letters, digits, '_', '-', and '.' are allowed",
+                invalid.into_iter().collect::<String>()
+            ),
+        ));
+    }
+}
+
+/// Aliases follow the rules for names, and should each add a new name.
+fn validate_aliases(definition: &ToolDefinition, issues: &mut Vec<ValidationIssue>) {
+    let mut seen = BTreeSet::from([definition.name.as_str()]);
+    for (index, alias) in definition.aliases.iter().enumerate() {
+        let field = format!("aliases.{}", index);
+        validate_name(alias, &field, issues);
+        if !seen.insert(alias) {
+            issues.push(ValidationIssue::warning(
+                field,
+                format!("repeats the name \"{}\"", alias),
+            ));
+        }
+    }
+}
+
+/// Tags and the category must be non-empty, and cannot be selected with
+/// `--tags` if they contain a comma.
+fn validate_tags(definition: &ToolDefinition, issues: &mut Vec<ValidationIssue>) {
+    let category = definition
+        .category
+        .iter()
+        .map(|category| ("category".to_string(), category));
+    let tags = definition
+        .tags
+        .iter()
+        .enumerate()
+        .map(|(index, tag)| (format!("tags.{}", index), tag));
+    for (field, tag) in category.chain(tags) {
+        if tag.trim().is_empty() {
+            issues.push(ValidationIssue::error(field, "must not be empty"));
+        } else if tag.contains(',') {
+            issues.push(ValidationIssue::warning(
+                field,
+                format!("\"{}\" contains a comma, so --tags cannot select it", tag),
+            ));
+        }
+    }
+}
+
+/// Whether `schema` allows arrays.
+fn is_array(schema: &Value) -> bool {
+    match schema.get("type") {
+        Some(Value::String(t)) => t == "array",
+        Some(Value::Array(types)) => types.iter().any(|t| t == "array"),
+        _ => false,
+    }
+}
+
+/// Every placeholder in the input templates should name an input property,
+/// required properties should be passed whenever the tool runs, and arrays
+/// can only be expanded by repetition sections.
+fn validate_input(input: &ToolInput, issues: &mut Vec<ValidationIssue>) {
+    // Only the main template written as text is split into arguments; its
+    // tokens, and the other templates, are parsed as text. Tokens are
+    // parsed one by one, so that problems are reported on each.
+    let mut sources: Vec<(String, &String)> = Vec::new();
+    match &input.template {
+        CommandTemplate::Text(source) => sources.push(("input.template".to_string(), source)),
+        CommandTemplate::Tokens(tokens) => sources.extend(
+            tokens
+                .iter()
+                .enumerate()
+                .map(|(index, token)| (format!("input.template.{}", index), token)),
+        ),
+    }
+    let split = matches!(input.template, CommandTemplate::Text(_));
+    sources.extend(
+        input
+            .stdin_template
+            .iter()
+            .map(|source| ("input.stdin_template".to_string(), source)),
+    );
+    for (name, source) in &input.env_template {
+        let field = format!("input.env_template.{}", name);
+        if !is_env_name(name) {
+            issues.push(ValidationIssue::error(
+                &field,
+                format!("\"{}\" is not a valid environment variable name", name),
+            ));
+        }
+        sources.push((field, source));
+    }
+    let mut templates = Vec::new();
+    for (index, (field, source)) in sources.iter().enumerate() {
+        let parsed = if index == 0 && split {
+            InputTemplate::parse(source, input.engine)
+        } else {
+            InputTemplate::parse_text(source, input.engine)
+        };
+        match parsed {
+            Ok(template) => templates.push((field.as_str(), template)),
+            Err(error) => issues.push(ValidationIssue::error(field, error.to_string())),
+        }
+    }
+    let properties = input.schema.get("properties").and_then(Value::as_object);
+
+    let mut placeholders = Vec::new();
+    for (field, template) in &templates {
+        match template {
+            InputTemplate::Builtin(template) => {
+                validate_template(field, template, properties, issues);
+                placeholders.extend(template.placeholders());
+            }
+            InputTemplate::Jinja(template) => {
+                validate_jinja_input(field, template, properties, issues);
+            }
+            // Only parsed from a whole `input.template`, never above.
+            InputTemplate::Tokens(_) => {}
+        }
+    }
+    if templates.len() < sources.len() || input.engine == TemplateEngine::Jinja {
+        // Either a template is already reported, or its uses are unknown.
+        return;
+    }
+
+    let required = input.schema.get("required").and_then(Value::as_array);
+    for name in required.into_iter().flatten().filter_map(Value::as_str) {
+        if !properties.is_some_and(|p| p.contains_key(name)) {
+            // Already reported by the schema checks.
+            continue;
+        }
+        let mut uses = placeholders.iter().filter(|p| p.name == name).peekable();
+        if uses.peek().is_none() {
+            issues.push(ValidationIssue::warning(
+                "input.template",
+                format!("never uses required property \"{}\"", name),
+            ));
+        } else if uses.all(|p| p.optional) {
+            issues.push(ValidationIssue::warning(
+                "input.template",
+                format!(
+                    "only uses required property \"{}\" inside optional sections",
+                    name
+                ),
+            ));
+        }
+    }
+}
+
+/// Check the placeholders and sections of one template in the built-in
+/// syntax, reporting problems on `field`.
+fn validate_template(
+    field: &str,
+    template: &Template,
+    properties: Option<&Map<String, Value>>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let placeholders = template.placeholders();
+    for span in template.constant_sections() {
+        issues.push(ValidationIssue::warning(
+            field,
+            format!(
+                "section at byte {} uses no properties, so it is always included",
+                span.start
+            ),
+        ));
+    }
+    let mut reported = BTreeSet::new();
+    for placeholder in &placeholders {
+        let name = placeholder.name;
+        let message = match properties.and_then(|p| p.get(name)) {
+            None => format!(
+                "{{{{{}}}}} does not refer to a property of the input schema",
+                name
+            ),
+            Some(schema)
+                if is_array(schema)
+                    && !placeholder.repeated
+                    && !placeholder.condition
+                    && !placeholder.filters.contains(&Filter::Json) =>
+            {
+                format!(
+                    "{{{{{0}}}}} is an array, so it can only be used inside a repetition \
+                     section such as \"[--{0} {{{{{0}}}}}...]\"",
+                    name
+                )
+            }
+            Some(_) if placeholder.entry.is_some() && !placeholder.repeated => format!(
+                "{{{{{0}.key}}}} and {{{{{0}.value}}}} can only be used inside a repetition \
+                 section such as \"[--{{{{{0}.key}}}} {{{{{0}.value}}}}...]\"",
+                name
+            ),
+            Some(_) => continue,
+        };
+        if reported.insert(name) {
+            issues.push(ValidationIssue::warning(field, message));
+        }
+    }
+
+    let mut positions = BTreeMap::new();
+    for placeholder in &placeholders {
+        let Some(position) = placeholder.position else {
+            continue;
+        };
+        match positions.insert(position, placeholder.name) {
+            Some(other) if other != placeholder.name => {
+                issues.push(ValidationIssue::warning(
+                    field,
+                    format!(
+                        "\"{}\" and \"{}\" are both positional argument {}, so their order \
+                         is unclear",
+                        other, placeholder.name, position
+                    ),
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Check a MiniJinja template, whose loops and filters leave only its
+/// variables to compare with the schema.
+fn validate_jinja_input(
+    field: &str,
+    template: &JinjaTemplate,
+    properties: Option<&Map<String, Value>>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    for name in template.variables() {
+        if !properties.is_some_and(|p| p.contains_key(&name)) {
+            issues.push(ValidationIssue::warning(
+                field,
+                format!(
+                    "\"{}\" does not refer to a property of the input schema",
+                    name
+                ),
+            ));
+        }
+    }
+}
+
+/// The output patterns must compile, and their named capture groups (and
+/// the properties they set) should match the properties of the output
+/// schema: a capture without a property produces an undocumented value, and
+/// a property no pattern fills in is never returned. An extract expression
+/// replaces the patterns, and must parse; a capture of the whole output
+/// replaces both. Patterns for stderr add to any of them.
+fn validate_output(output: &ToolOutput, issues: &mut Vec<ValidationIssue>) {
+    let properties = output.schema.get("properties").and_then(Value::as_object);
+    let mut captures = BTreeSet::new();
+    let mut compiled = validate_patterns(
+        "output.stderr_template",
+        &output.stderr_template,
+        properties,
+        &mut captures,
+        issues,
+    );
+    if let Some(capture) = &output.capture {
+        if output.extract.is_some() {
+            issues.push(ValidationIssue::error(
+                "output.capture",
+                "cannot be combined with output.extract",
+            ));
+        }
+        if !output.template.is_empty() {
+            issues.push(ValidationIssue::warning(
+                "output.template",
+                "is not used, because output.capture returns the whole output",
+            ));
+        }
+        let property = capture.property();
+        if properties.is_some_and(|p| !p.contains_key(property)) {
+            issues.push(ValidationIssue::warning(
+                "output.capture",
+                format!(
+                    "captures \"{}\", which is not a property of the output schema",
+                    property
+                ),
+            ));
+        }
+        captures.insert(property.to_string());
+    } else if let Some(extract) = &output.extract {
+        if output.encoding == OutputEncoding::Base64 {
+            issues.push(ValidationIssue::warning(
+                "output.encoding",
+                "is base64, so output.extract gets base64 text rather than JSON",
+            ));
+        }
+        if let Err(error) = Extract::parse(extract) {
+            issues.push(ValidationIssue::error(
+                "output.extract",
+                format!("is not a valid expression: {}", error),
+            ));
+        }
+        if !output.template.is_empty() {
+            issues.push(ValidationIssue::warning(
+                "output.template",
+                "is not used, because output.extract parses the output as JSON",
+            ));
+        }
+        // What the expression produces is not known until it runs.
+        return;
+    } else if output.template.is_empty() {
+        if !declares_exit_code(&output.schema) {
+            issues.push(ValidationIssue::error(
+                "output.template",
+                "is required unless output.extract or output.capture is given",
+            ));
+            return;
+        }
+    } else {
+        compiled &= validate_patterns(
+            "output.template",
+            &output.template,
+            properties,
+            &mut captures,
+            issues,
+        );
+    }
+    if declares_exit_code(&output.schema) {
+        captures.insert(EXIT_CODE_PROPERTY.to_string());
+    }
+    if compiled {
+        validate_captured(output, &captures, issues);
+    }
+}
+
+/// Check the patterns of the template written at `base`, adding what they
+/// capture to `captures`. Returns whether they all compiled.
+fn validate_patterns(
+    base: &str,
+    template: &OutputTemplate,
+    properties: Option<&Map<String, Value>>,
+    captures: &mut BTreeSet<String>,
+    issues: &mut Vec<ValidationIssue>,
+) -> bool {
+    let mut compiled = true;
+    for (index, pattern) in template.patterns().iter().enumerate() {
+        let field = template.field(base, index);
+        let parsed = match pattern.compile() {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                issues.push(ValidationIssue::error(
+                    field,
+                    format!("is not a valid regular expression: {}", e),
+                ));
+                compiled = false;
+                continue;
+            }
+        };
+        for (name, capture_type) in &parsed.types {
+            let Some(types) = properties
+                .and_then(|p| p.get(name))
+                .and_then(|property| property.get("type"))
+            else {
+                continue;
+            };
+            let types: Vec<&str> = match types {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => continue,
+            };
+            if !types
+                .iter()
+                .any(|t| capture_type.schema_types().contains(t))
+            {
+                issues.push(ValidationIssue::warning(
+                    &field,
+                    format!(
+                        "captures \"{}\" as {}, but the output schema gives it type {}",
+                        name,
+                        capture_type,
+                        types.join(" or ")
+                    ),
+                ));
+            }
+        }
+        let names = parsed
+            .regex
+            .capture_names()
+            .flatten()
+            .map(|name| ("captures", name));
+        let set = pattern.set.keys().map(|name| ("sets", name.as_str()));
+        for (verb, name) in names.chain(set) {
+            if properties.is_some_and(|p| !p.contains_key(name)) {
+                issues.push(ValidationIssue::warning(
+                    &field,
+                    format!(
+                        "{} \"{}\", which is not a property of the output schema",
+                        verb, name
+                    ),
+                ));
+            }
+            captures.insert(name.to_string());
+        }
+    }
+    compiled
+}
+
+/// Resource links should only use captured properties, and every property
+/// of the output schema should be captured.
+fn validate_captured(
+    output: &ToolOutput,
+    captures: &BTreeSet<String>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let properties = output.schema.get("properties").and_then(Value::as_object);
+    for (index, content) in output.content.iter().enumerate() {
+        let OutputContent::ResourceLink(link) = content else {
+            continue;
+        };
+        for name in link.placeholders() {
+            if !captures.contains(name) {
+                issues.push(ValidationIssue::warning(
+                    format!("output.content.{}.resource_link", index),
+                    format!(
+                        "uses \"{}\", which is not captured by the output template",
+                        name
+                    ),
+                ));
+            }
+        }
+    }
+    for name in properties.into_iter().flat_map(Map::keys) {
+        if !captures.contains(name) {
+            issues.push(ValidationIssue::warning(
+                format!("output.schema.properties.{}", name),
+                "is never captured by the output template",
+            ));
+        }
+    }
+}
+
+fn validate_execution(execution: &ExecutionConfig, issues: &mut Vec<ValidationIssue>) {
+    if execution.timeout.is_some_and(|timeout| timeout.is_zero()) {
+        issues.push(ValidationIssue::error(
+            "execution.timeout",
+            "must be longer than zero",
+        ));
+    }
+    for name in execution.env.keys() {
+        if !is_env_name(name) {
+            issues.push(ValidationIssue::error(
+                "execution.env",
+                format!("\"{}\" is not a valid environment variable name", name),
+            ));
+        }
+    }
+    if execution
+        .runtime
+        .as_ref()
+        .is_some_and(|runtime| runtime.trim().is_empty())
+    {
+        issues.push(ValidationIssue::error(
+            "execution.runtime",
+            "must not be empty",
+        ));
+    }
+}
+
+/// Running through a shell is risky enough to always mention, and quotes
+/// every value already, but only where the shell reads plain words. The
+/// script runs instead of the executable, so nothing about the executable
+/// applies.
+fn validate_shell(definition: &ToolDefinition, issues: &mut Vec<ValidationIssue>) {
+    let input = &definition.input;
+    if definition.execution.runtime.is_some() {
+        issues.push(ValidationIssue::error(
+            "execution.runtime",
+            "cannot be used with execution.shell, which runs input.template instead of the \
+             executable; call the interpreter in the template",
+        ));
+    }
+    issues.push(ValidationIssue::warning(
+        "execution.shell",
+        "runs input.template as a shell script: values are quoted, but the template's own \
+         text is shell syntax, and a mistake in it can run unintended commands; run the tool \
+         directly unless it needs pipes, globs, or redirection",
+    ));
+    // Other parse errors are reported with the template itself.
+    if let Err(error) =
+        ShellTemplate::parse(&input.template, input.engine, QuoteStyle::native_shell())
+    {
+        if matches!(
+            error.kind,
+            TemplateErrorKind::ShellValue(_) | TemplateErrorKind::ShellSection(_)
+        ) {
+            issues.push(ValidationIssue::error("input.template", error.to_string()));
+        }
+    }
+    if definition.sha256.is_some() {
+        issues.push(ValidationIssue::error(
+            "sha256",
+            "cannot be used with execution.shell, which never runs the executable it pins",
+        ));
+    }
+    if input.engine != TemplateEngine::Builtin {
+        return;
+    }
+    let sources = match &input.template {
+        CommandTemplate::Text(source) => vec![source],
+        CommandTemplate::Tokens(tokens) => tokens.iter().collect(),
+    };
+    let templates = sources.into_iter().filter_map(|s| Template::parse(s).ok());
+    for template in templates {
+        for placeholder in template.placeholders() {
+            if placeholder.filters.contains(&Filter::ShellQuote) {
+                issues.push(ValidationIssue::warning(
+                    "input.template",
+                    format!(
+                        "{{{{{}|shell-quote}}}} is quoted twice, because values are already \
+                         quoted for the shell",
+                        placeholder.name
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Whether `name` can be set as an environment variable.
+fn is_env_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['=', '\0'])
+}
+
+/// Check that the properties each (sub)schema requires are ones it declares.
+///
+/// Malformed keywords are skipped here; meta-validation reports them.
+fn validate_required(schema: &Value, field: &str, issues: &mut Vec<ValidationIssue>) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let required = schema.get("required").and_then(Value::as_array);
+    for name in required.into_iter().flatten().filter_map(Value::as_str) {
+        if !properties.is_some_and(|p| p.contains_key(name)) {
+            issues.push(ValidationIssue::error(
+                format!("{}.required", field),
+                format!("\"{}\" is not a declared property", name),
+            ));
+        }
+    }
+
+    for (name, property) in properties.into_iter().flatten() {
+        validate_required(property, &format!("{}.properties.{}", field, name), issues);
+    }
+    if let Some(items) = schema.get("items") {
+        validate_required(items, &format!("{}.items", field), issues);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_discovery::{ToolInput, ToolOutput};
+    use serde_json::json;
+
+    fn definition(name: &str, input_schema: Value) -> ToolDefinition {
+        ToolDefinition {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            title: None,
+            version: None,
+            description: "Does something".to_string(),
+            input: ToolInput {
+                template: "".into(),
+                stdin_template: None,
+                env_template: BTreeMap::new(),
+                engine: TemplateEngine::Builtin,
+                schema: input_schema,
+            },
+            output: ToolOutput {
+                template: "(?<result>.*)".into(),
+                stderr_template: Default::default(),
+                extract: None,
+                capture: None,
+                encoding: Default::default(),
+                schema: json!({"type": "object"}),
+                content: Vec::new(),
+            },
+            annotations: None,
+            execution: ExecutionConfig::default(),
+            sha256: None,
+            deprecated: None,
+            category: None,
+            tags: Vec::new(),
+            icon: None,
+            meta: None,
+            tests: Vec::new(),
+        }
+    }
+
+    fn fields(issues: &[ValidationIssue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.field.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_definition() {
+        let mut tool = definition(
+            "read_file",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string"},
+                    "lines": {"type": ["integer", "null"]},
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                },
+                "required": ["path"]
+            }),
+        );
+        tool.input.template = "{{path}} [--lines {{ lines }}] [--tag {{tags}}...]".into();
+
+        assert_eq!(validate(&tool), vec![]);
+    }
+
+    #[test]
+    fn test_name_constraints() {
+        let schema = json!({"type": "object"});
+        assert!(validate(&definition("v1.read-file_x", schema.clone())).is_empty());
+
+        let issues = validate(&definition("", schema.clone()));
+        assert_eq!(fields(&issues), ["name"]);
+
+        let issues = validate(&definition("read file!", schema.clone()));
+        assert_eq!(fields(&issues), ["name"]);
+        assert!(issues[0].message.contains("\" !\""));
+
+        let issues = validate(&definition(&"a".repeat(MAX_NAME_LENGTH + 1), schema));
+        assert_eq!(fields(&issues), ["name"]);
+        assert!(issues.iter().all(ValidationIssue::is_error));
+    }
+
+    #[test]
+    fn test_empty_description() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.description = "  ".to_string();
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["description"]);
+        assert!(issues[0].is_error());
+    }
+
+    #[test]
+    fn test_schema_types() {
+        let tool = definition(
+            "tool",
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "strnig"},
+                    "items": {"type": "array", "items": {"type": ["string", 3]}}
+                }
+            }),
+        );
+
+        let issues = validate(&tool);
+
+        assert_eq!(
+            fields(&issues),
+            [
+                "input.schema.properties.items.items.type",
+                "input.schema.properties.name.type",
+            ]
+        );
+        assert!(issues[1].message.contains("\"strnig\""));
+    }
+
+    #[test]
+    fn test_schema_draft() {
+        let tool = definition(
+            "tool",
+            json!({
+                "type": "object",
+                "properties": {
+                    "count": {"type": "integer", "minimum": 0, "exclusiveMinimum": true}
+                }
+            }),
+        );
+
+        let issues = Validator::new().validate(&tool);
+        assert_eq!(
+            fields(&issues),
+            ["input.schema.properties.count.exclusiveMinimum"]
+        );
+
+        let draft4 = Validator::new().with_draft(SchemaDraft::Draft4);
+        assert_eq!(draft4.validate(&tool), vec![]);
+    }
+
+    #[test]
+    fn test_schema_root_must_be_object() {
+        let mut tool = definition("tool", json!({"type": "string"}));
+        tool.output.schema = json!({"additionalProperties": true});
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["input.schema.type", "output.schema.type"]);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[1].severity, Severity::Warning);
+
+        let issues = validate(&definition("tool", json!("object")));
+        assert_eq!(fields(&issues), ["input.schema"]);
+    }
+
+    #[test]
+    fn test_invalid_output_regex() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.output.template = "Created: (?<url>.*".into();
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["output.template"]);
+        assert!(issues[0].is_error());
+        assert!(issues[0].message.contains("regular expression"));
+    }
+
+    #[test]
+    fn test_output_captures_match_schema() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.output = ToolOutput {
+            template: "(?<url>\\S+) (?<id>\\d+)".into(),
+            stderr_template: Default::default(),
+            extract: None,
+            capture: None,
+            encoding: Default::default(),
+            schema: json!({
+                "type": "object",
+                "properties": {"url": {"type": "string"}, "status": {"type": "string"}}
+            }),
+            content: Vec::new(),
+        };
+
+        let issues = validate(&tool);
+
+        assert_eq!(
+            fields(&issues),
+            ["output.template", "output.schema.properties.status"]
+        );
+        assert!(issues[0].message.contains("\"id\""));
+        assert!(issues.iter().all(|issue| !issue.is_error()));
+    }
+
+    #[test]
+    fn test_output_patterns() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.output.schema = json!({
+            "type": "object",
+            "properties": {"url": {"type": "string"}, "error": {"type": "string"}}
+        });
+        tool.output.template = serde_yaml_ng::from_str(
+            "['(?<url>\\S+)', {pattern: 'Error: (?<error>.*)', set: {ok: false}}, '(?<x']",
+        )
+        .unwrap();
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["output.template.1", "output.template.2"]);
+        assert_eq!(
+            issues[0].message,
+            "sets \"ok\", which is not a property of the output schema"
+        );
+        assert!(issues[1].is_error());
+
+        tool.output.template = "(?<url:int>\\d+) (?<error:json>.*)".into();
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["output.template"]);
+        assert_eq!(
+            issues[0].message,
+            "captures \"url\" as int, but the output schema gives it type string"
+        );
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.output.template = Default::default();
+        tool.output.schema = json!({
+            "type": "object",
+            "properties": {"exit_code": {"type": "integer"}}
+        });
+        assert!(validate(&tool).is_empty());
+
+        tool.output.schema = json!({"type": "object"});
+        assert_eq!(fields(&validate(&tool)), ["output.template"]);
+    }
+
+    #[test]
+    fn test_output_extract() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.output.extract = Some(".items[] | {name".to_string());
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["output.extract", "output.template"]);
+        assert!(issues[0].is_error());
+        assert!(!issues[1].is_error());
+
+        tool.output.template = Default::default();
+        tool.output.extract = Some(".items[] | {name}".to_string());
+        assert!(validate(&tool).is_empty());
+        tool.output.encoding = OutputEncoding::Base64;
+        assert_eq!(fields(&validate(&tool)), ["output.encoding"]);
+        tool.output.encoding = OutputEncoding::Lossy;
+        tool.output.extract = None;
+        assert_eq!(fields(&validate(&tool)), ["output.template"]);
+    }
+
+    #[test]
+    fn test_output_capture() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.output.capture = Some(serde_yaml_ng::from_str("full").unwrap());
+        assert_eq!(fields(&validate(&tool)), ["output.template"]);
+
+        tool.output.template = Default::default();
+        tool.output.schema = json!({
+            "type": "object",
+            "properties": {"text": {"type": "string"}}
+        });
+        assert_eq!(
+            fields(&validate(&tool)),
+            ["output.capture", "output.schema.properties.text"]
+        );
+
+        tool.output.capture = Some(serde_yaml_ng::from_str("{full: text}").unwrap());
+        assert!(validate(&tool).is_empty());
+
+        tool.output.extract = Some(".".to_string());
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["output.capture"]);
+        assert!(issues[0].is_error());
+    }
+
+    #[test]
+    fn test_stderr_template() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.output.capture = Some(serde_yaml_ng::from_str("full").unwrap());
+        tool.output.template = Default::default();
+        tool.output.schema = json!({
+            "type": "object",
+            "properties": {"result": {"type": "string"}, "code": {"type": "integer"}}
+        });
+        assert_eq!(fields(&validate(&tool)), ["output.schema.properties.code"]);
+
+        tool.output.stderr_template = r"error (?<code:int>\d+)(?<detail>.*)".into();
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["output.stderr_template"]);
+        assert!(issues[0].message.contains("\"detail\""));
+
+        tool.output.stderr_template = "(?<code".into();
+        assert!(validate(&tool)[0].is_error());
+    }
+
+    #[test]
+    fn test_resource_links_use_captures() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.output.content = serde_yaml_ng::from_str(
+            "[text, {resource_link: {uri: \"{{result}}\", name: \"{{title}}\"}}]",
+        )
+        .unwrap();
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["output.content.1.resource_link"]);
+        assert!(issues[0].message.contains("\"title\""));
+    }
+
+    #[test]
+    fn test_execution_settings() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.execution = serde_yaml_ng::from_str("{timeout: 0, env: {\"A=B\": x, OK: y}}").unwrap();
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["execution.timeout", "execution.env"]);
+        assert!(issues[1].message.contains("\"A=B\""));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut tool = definition("create_issue", json!({"type": "object"}));
+        tool.aliases = vec![
+            "create_ticket".to_string(),
+            "create issue".to_string(),
+            "create_issue".to_string(),
+        ];
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["aliases.1", "aliases.2"]);
+        assert!(issues[0].is_error());
+        assert!(!issues[1].is_error());
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.category = Some(" ".to_string());
+        tool.tags = vec!["ok".to_string(), "a,b".to_string()];
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["category", "tags.1"]);
+        assert!(issues[0].is_error());
+        assert!(!issues[1].is_error());
+    }
+
+    #[test]
+    fn test_sensitive_markers_are_booleans() {
+        let tool = definition(
+            "tool",
+            json!({
+                "type": "object",
+                "properties": {"token": {"type": "string", "x-sensitive": "true"}}
+            }),
+        );
+
+        let issues = validate(&tool);
+
+        assert_eq!(
+            fields(&issues),
+            ["input.schema.properties.token.x-sensitive"]
+        );
+        assert!(issues[0].is_error());
+    }
+
+    #[test]
+    fn test_required_inputs_must_exist() {
+        let mut tool = definition(
+            "tool",
+            json!({
+                "type": "object",
+                "properties": {"path": {"type": "string"}},
+                "required": ["path", "mode"]
+            }),
+        );
+        tool.input.template = "{{path}}".into();
+
+        let issues = validate(&tool);
+
+        assert_eq!(fields(&issues), ["input.schema.required"]);
+        assert!(issues[0].message.contains("\"mode\""));
+    }
+
+    #[test]
+    fn test_template_placeholders_match_schema() {
+        let mut tool = definition(
+            "tool",
+            json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string"},
+                    "body": {"type": "string"},
+                    "labels": {"type": "array", "items": {"type": "string"}}
+                },
+                "required": ["title", "body"]
+            }),
+        );
+        tool.input.template =
+            "--title {{title}} [--body {{body}}] --label {{labels}} {{titel}} {{titel}}".into();
+
+        let messages: Vec<String> = validate(&tool)
+            .into_iter()
+            .map(|issue| {
+                assert_eq!(issue.field, "input.template");
+                assert_eq!(issue.severity, Severity::Warning);
+                issue.message
+            })
+            .collect();
+
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].contains("{{labels}} is an array"));
+        assert!(messages[1].contains("{{titel}} does not refer"));
+        assert!(messages[2].contains("\"body\" inside optional sections"));
+    }
+
+    #[test]
+    fn test_stdin_template() {
+        let mut tool = definition(
+            "tool",
+            json!({
+                "type": "object",
+                "properties": {"title": {"type": "string"}, "body": {"type": "string"}},
+                "required": ["title", "body"]
+            }),
+        );
+        tool.input.template = "--title {{title}}".into();
+        tool.input.stdin_template = Some("{{body}}\n{{bdoy}}".to_string());
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["input.stdin_template"]);
+        assert!(issues[0].message.contains("{{bdoy}} does not refer"));
+
+        tool.input.stdin_template = Some("[{{body}}".to_string());
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["input.stdin_template"]);
+        assert!(issues[0].is_error());
+
+        tool.input.stdin_template = None;
+        tool.input.env_template = BTreeMap::from([
+            ("BODY".to_string(), "{{body}}".to_string()),
+            ("A=B".to_string(), "x".to_string()),
+        ]);
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["input.env_template.A=B"]);
+        assert!(issues[0].is_error());
+    }
+
+    #[test]
+    fn test_shell() {
+        let mut tool = definition(
+            "tool",
+            json!({"type": "object", "properties": {"pattern": {"type": "string"}}}),
+        );
+        tool.input.template = "grep {{pattern|shell-quote}} src | wc -l".into();
+        assert!(validate(&tool).is_empty());
+
+        tool.execution.shell = true;
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["execution.shell", "input.template"]);
+        assert!(issues.iter().all(|issue| !issue.is_error()));
+        assert!(issues[1].message.contains("quoted twice"));
+
+        // Quoted again by the shell's own quotes, the value would run.
+        tool.input.template = "echo \"{{pattern}}\"".into();
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["execution.shell", "input.template"]);
+        assert!(issues[1].is_error());
+        assert!(issues[1].message.contains("inside double quotes"));
+
+        tool.input.template = "grep {{pattern}} src | wc -l".into();
+        tool.execution.runtime = Some("python3".to_string());
+        tool.sha256 = Some("0".repeat(64));
+        let issues = validate(&tool);
+        assert_eq!(
+            fields(&issues),
+            ["execution.runtime", "execution.shell", "sha256"]
+        );
+        assert!(issues[0].is_error() && issues[2].is_error());
+    }
+
+    #[test]
+    fn test_template_tokens() {
+        let mut tool = definition(
+            "tool",
+            json!({
+                "type": "object",
+                "properties": {"title": {"type": "string"}, "body": {"type": "string"}},
+                "required": ["title", "body"]
+            }),
+        );
+        tool.input.template = CommandTemplate::Tokens(vec![
+            "--title={{title}}".to_string(),
+            "[--body {{body}}]".to_string(),
+            "{{titel".to_string(),
+        ]);
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["input.template.2"]);
+        assert!(issues[0].is_error());
+
+        tool.input.template = CommandTemplate::Tokens(vec![
+            "--title={{title}}".to_string(),
+            "[--body {{body}}]".to_string(),
+        ]);
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["input.template"]);
+        assert!(issues[0]
+            .message
+            .contains("only uses required property \"body\""));
+    }
+
+    #[test]
+    fn test_template_sections() {
+        let template = Template::parse("{{a}} [x {{b}} [{{c}}...]] [{{d}}...]").unwrap();
+        let flags: Vec<(&str, bool, bool)> = template
+            .placeholders()
+            .iter()
+            .map(|p| (p.name, p.optional, p.repeated))
+            .collect();
+        assert_eq!(
+            flags,
+            [
+                ("a", false, false),
+                ("b", true, false),
+                ("c", true, true),
+                ("d", true, true),
+            ]
+        );
+
+        let mut tool = definition("tool", json!({"type": "object"}));
+        tool.input.template = "[--a".into();
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["input.template"]);
+        assert!(issues[0].is_error());
+        assert_eq!(issues[0].message, "\"[\" at byte 0 is never closed");
+
+        tool.input.template = "[--verbose]".into();
+        let issues = validate(&tool);
+        assert_eq!(
+            issues[0].message,
+            "section at byte 0 uses no properties, so it is always included"
+        );
+
+        let mut tool = definition(
+            "tool",
+            json!({"type": "object", "properties": {"labels": {"type": "array"}}}),
+        );
+        tool.input.template = "[?labels:--labelled] [?verbose:-v] {{labels|json}}".into();
+        let issues = validate(&tool);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("{{verbose}} does not refer"));
+
+        tool.input.template = "{{labels@1|json}} [{{labels@1}}...]".into();
+        assert!(validate(&tool).is_empty());
+        tool.input.schema["properties"]["path"] = json!({"type": "string"});
+        tool.input.template = "{{path@1}} {{labels@1|json}}".into();
+        assert_eq!(
+            validate(&tool)[0].message,
+            "\"path\" and \"labels\" are both positional argument 1, so their order is unclear"
+        );
+
+        tool.input.schema["properties"]["env"] = json!({"type": "object"});
+        tool.input.template = "[-e {{env.key}}={{env.value}}...] {{env.key}}".into();
+        let issues = validate(&tool);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].message,
+            "{{env.key}} and {{env.value}} can only be used inside a repetition section \
+             such as \"[--{{env.key}} {{env.value}}...]\""
+        );
+    }
+
+    #[test]
+    fn test_jinja_template() {
+        let mut tool = definition(
+            "tool",
+            json!({"type": "object", "properties": {"labels": {"type": "array"}}}),
+        );
+        tool.input.engine = TemplateEngine::Jinja;
+        tool.input.template =
+            "{% for label in labels %}--label {{ label }} {% endfor %}{{ titel }}".into();
+        let issues = validate(&tool);
+        assert_eq!(fields(&issues), ["input.template"]);
+        assert_eq!(
+            issues[0].message,
+            "\"titel\" does not refer to a property of the input schema"
+        );
+
+        tool.input.template = "{% if labels %}".into();
+        let issues = validate(&tool);
+        assert!(issues[0].is_error());
+    }
+}
@@ -0,0 +1,369 @@
+//! Checking the environment tools run in, for `mcp-serve doctor`.
+//!
+//! Where `validate` checks what definitions say, the doctor checks what
+//! surrounds them: whether the tools directories can be read, whether the
+//! scripts in them can run (and only their owner can change them), and
+//! whether every definition belongs to a tool.
+//! Each problem is a [`Finding`] with a suggestion for fixing it:
+//!
+//! ```text
+//! warning: tools/deploy.sh: starts with `#!` but is not executable
+//!   = help: run `chmod +x tools/deploy.sh`, or serve with --detect-shebang
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::definitions::Severity;
+use crate::tool_discovery::{
+    permissions, DirectoryScanner, DiscoveredTool, PermissionError, ScanReport, SidecarFormat,
+    SkipReason,
+};
+
+/// Longest shebang line read, in bytes.
+const MAX_SHEBANG_LENGTH: u64 = 256;
+
+/// A problem with the environment of some tools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// How serious the problem is
+    pub severity: Severity,
+
+    /// The file or directory with the problem
+    pub path: PathBuf,
+
+    /// What is wrong
+    pub problem: String,
+
+    /// How to fix it
+    pub suggestion: String,
+}
+
+impl Finding {
+    pub(crate) fn error(
+        path: &Path,
+        problem: impl Into<String>,
+        suggestion: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.to_path_buf(),
+            problem: problem.into(),
+            suggestion: suggestion.into(),
+        }
+    }
+
+    pub(crate) fn warning(
+        path: &Path,
+        problem: impl Into<String>,
+        suggestion: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(path, problem, suggestion)
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}\n  = help: {}",
+            self.severity,
+            self.path.display(),
+            self.problem,
+            self.suggestion
+        )
+    }
+}
+
+/// Check that `dir` and the directories below it can be read. Hidden
+/// directories are not descended into.
+pub fn check_directory(dir: &Path) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                let suggestion = if !dir.exists() {
+                    "check the path, or create the directory with `mcp-serve init`".to_string()
+                } else {
+                    format!(
+                        "give the user running mcp-serve permission to read it (`chmod u+rx {}`)",
+                        dir.display()
+                    )
+                };
+                findings.push(Finding::error(
+                    &dir,
+                    format!("cannot read directory: {}", e),
+                    suggestion,
+                ));
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let hidden = entry.file_name().as_encoded_bytes().starts_with(b".");
+            if !hidden && entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                pending.push(entry.path());
+            }
+        }
+    }
+    findings
+}
+
+/// Check the tools `scanner` found, and the files it passed over as
+/// `report` records them.
+pub fn examine(
+    scanner: &DirectoryScanner,
+    tools: &[DiscoveredTool],
+    report: &ScanReport,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for entry in &report.skipped {
+        if entry.reason == SkipReason::NotExecutable {
+            findings.extend(check_not_executable(&entry.path));
+        }
+    }
+    let mut checked = HashSet::new();
+    for tool in tools {
+        if checked.insert(&tool.path) {
+            findings.extend(check_interpreter(scanner, &tool.path));
+            findings.extend(check_permissions(&tool.path));
+        }
+    }
+    let definitions: HashSet<&Path> = tools.iter().map(DiscoveredTool::definition_file).collect();
+    for entry in &report.skipped {
+        if entry.reason == SkipReason::MetadataFile && !definitions.contains(entry.path.as_path()) {
+            findings.extend(unused_definition(&entry.path));
+        }
+    }
+    findings
+}
+
+/// An executable others could change, or that runs with privileges of its
+/// own.
+fn check_permissions(path: &Path) -> Option<Finding> {
+    let (problem, suggestion) = match permissions::check(path).err()? {
+        // Reading it is reported as any other unreadable tool.
+        PermissionError::Io { .. } => return None,
+        PermissionError::WorldWritable { .. } => (
+            "is writable by every user".to_string(),
+            format!("run `chmod o-w {}`", path.display()),
+        ),
+        PermissionError::ForeignOwner { uid, .. } => (
+            format!("is owned by another user (uid {}), who can change it", uid),
+            format!(
+                "run `chown $(id -u) {}` after checking what it does",
+                path.display()
+            ),
+        ),
+        PermissionError::Setuid { .. } => (
+            "is setuid or setgid, so it runs with privileges of its own".to_string(),
+            format!("run `chmod ug-s {}`", path.display()),
+        ),
+    };
+    Some(Finding::warning(
+        path,
+        problem,
+        format!("{} (`security.strict` refuses it)", suggestion),
+    ))
+}
+
+/// A file that was not taken for a tool, though it looks like one.
+fn check_not_executable(path: &Path) -> Option<Finding> {
+    let shebang = shebang(path).is_some();
+    let sidecar = SidecarFormat::EXTENSIONS
+        .iter()
+        .map(|extension| append_extension(path, extension))
+        .find(|sidecar| sidecar.is_file());
+    let why = match (&sidecar, shebang) {
+        (Some(sidecar), _) => format!("has a definition ({})", sidecar.display()),
+        (None, true) => "starts with `#!`".to_string(),
+        (None, false) => return None,
+    };
+
+    if cfg!(windows) {
+        // Windows runs files by their extension rather than a permission.
+        let Some(extension) = path.extension() else {
+            return Some(Finding::warning(
+                path,
+                format!("{} but has no extension, so Windows cannot run it", why),
+                "rename it with an extension listed in PATHEXT, such as .cmd or .ps1",
+            ));
+        };
+        let extension = extension.to_string_lossy().to_ascii_uppercase();
+        return Some(Finding::warning(
+            path,
+            format!("{} but `.{}` is not listed in PATHEXT", why, extension),
+            format!("add `;.{}` to the PATHEXT environment variable", extension),
+        ));
+    }
+    let mut suggestion = format!("run `chmod +x {}`", path.display());
+    if shebang {
+        suggestion.push_str(", or serve with --detect-shebang");
+    }
+    Some(Finding::warning(
+        path,
+        format!("{} but is not executable", why),
+        suggestion,
+    ))
+}
+
+/// The interpreter named by the shebang of the tool at `path`, if it
+/// cannot be found.
+fn check_interpreter(scanner: &DirectoryScanner, path: &Path) -> Option<Finding> {
+    let line = shebang(path)?;
+    let mut words = line.split_whitespace();
+    let program = PathBuf::from(words.next()?);
+    let name = program.file_name()?.to_string_lossy().into_owned();
+    if name == "env" {
+        // `#!/usr/bin/env [-S] [NAME=VALUE...] program`
+        let command = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+        if scanner.find_executable(command).is_none() {
+            return Some(Finding::error(
+                path,
+                format!("its interpreter `{}` is not on PATH", command),
+                format!(
+                    "install `{}`, or add the directory it is in to PATH",
+                    command
+                ),
+            ));
+        }
+        return None;
+    }
+    // Windows has no such path, and runs the interpreter found on PATH.
+    if program.is_file() || (cfg!(windows) && scanner.find_executable(&name).is_some()) {
+        return None;
+    }
+    let suggestion = match scanner.find_executable(&name) {
+        Some(found) => format!(
+            "change the shebang to `#!{}`, or to `#!/usr/bin/env {}`",
+            found.display(),
+            name
+        ),
+        None => format!("install `{}`, or change the shebang", name),
+    };
+    Some(Finding::error(
+        path,
+        format!("its interpreter {} does not exist", program.display()),
+        suggestion,
+    ))
+}
+
+/// A definition file that no tool reads, unless it belongs to a file that
+/// is not executable (which is reported instead).
+fn unused_definition(path: &Path) -> Option<Finding> {
+    if path.with_extension("").is_file() {
+        return None;
+    }
+    Some(Finding::warning(
+        path,
+        "does not match any executable",
+        "name it after its executable with the extension appended \
+         (`deploy.sh.yaml` for `deploy.sh`), or remove it",
+    ))
+}
+
+/// The text after the `#!` of the file at `path`, if it starts with one.
+fn shebang(path: &Path) -> Option<String> {
+    let file = fs::File::open(path).ok()?;
+    let mut line = Vec::new();
+    BufReader::new(file.take(MAX_SHEBANG_LENGTH))
+        .read_until(b'\n', &mut line)
+        .ok()?;
+    let line = String::from_utf8_lossy(&line);
+    line.strip_prefix("#!").map(|rest| rest.trim().to_string())
+}
+
+/// Append `.{extension}` to the full file name of `path`.
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use {std::os::unix::fs::PermissionsExt, tempfile::TempDir};
+
+    #[cfg(unix)]
+    fn write(path: &Path, contents: &str, mode: u32) {
+        fs::write(path, contents).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_examine() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        write(&path.join("ok"), "#!/bin/sh\n", 0o755);
+        write(
+            &path.join("missing"),
+            "#!/usr/bin/env -S no-such-interpreter -u\n",
+            0o755,
+        );
+        write(&path.join("absent"), "#!/no/such/sh\n", 0o755);
+        write(&path.join("shared"), "#!/bin/sh\n", 0o777);
+        write(&path.join("script.sh"), "#!/bin/sh\n", 0o644);
+        write(&path.join("notes.txt"), "notes\n", 0o644);
+        write(&path.join("stray.yaml"), "name: stray\n", 0o644);
+        let scanner = DirectoryScanner::new();
+        let (tools, report) = scanner.scan_directory_with_report(path).unwrap();
+
+        let findings: Vec<(Severity, PathBuf, String)> = examine(&scanner, &tools, &report)
+            .into_iter()
+            .map(|finding| (finding.severity, finding.path, finding.problem))
+            .collect();
+
+        assert_eq!(
+            findings,
+            [
+                (
+                    Severity::Warning,
+                    path.join("script.sh"),
+                    "starts with `#!` but is not executable".to_string()
+                ),
+                (
+                    Severity::Error,
+                    path.join("absent"),
+                    "its interpreter /no/such/sh does not exist".to_string()
+                ),
+                (
+                    Severity::Error,
+                    path.join("missing"),
+                    "its interpreter `no-such-interpreter` is not on PATH".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    path.join("shared"),
+                    "is writable by every user".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    path.join("stray.yaml"),
+                    "does not match any executable".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_directory() {
+        let missing = Path::new("no/such/tools");
+        let findings = check_directory(missing);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert!(findings[0].suggestion.contains("mcp-serve init"));
+
+        assert!(check_directory(Path::new("src")).is_empty());
+    }
+}
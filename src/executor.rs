@@ -0,0 +1,1175 @@
+//! Running tools for `tools/call` requests.
+//!
+//! [`Executor::call`] takes a call from its arguments to its result:
+//!
+//! 1. The arguments are checked against the tool's input schema.
+//! 2. They are expanded through the input templates into the command line,
+//!    standard input, and environment (see [`preview::invocation`]).
+//! 3. The executable runs with them, directly or through the platform shell
+//!    (`execution.shell`), in its working directory (or its sandbox) and
+//!    within its `execution.timeout`, `max_output_size`, and `concurrency`
+//!    limits, and is run again up to `execution.retries` times if it fails.
+//! 4. Its output is parsed as the definition describes ([`parse_result`]),
+//!    and presented as `output.content` asks.
+//!
+//! Native tools are handed the checked arguments instead. A call that fails
+//! at any step is still answered with a result, marked `isError`, whose text
+//! says what went wrong: the model can read it and try again, which it could
+//! not do with a protocol error. Sensitive values ([`redact`](crate::redact))
+//! are hidden from that text, and from what is logged.
+//!
+//! When the server stops, [`Executor::shut_down`] refuses new calls and lets
+//! those running finish, up to a deadline past which their tools are killed.
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use wait_timeout::ChildExt;
+
+use crate::confine::confine;
+use crate::output::{declares_exit_code, parse_result};
+use crate::preview::{self, CommandLine, Invocation, PreviewError};
+use crate::redact::Redactor;
+use crate::registry::{RegisteredTool, ToolBackend};
+use crate::tool_discovery::wire::{CallToolResult, ContentBlock};
+use crate::tool_discovery::{content, pin};
+use crate::tool_discovery::{DiscoveredTool, SandboxProfile, ToolDefinition};
+
+/// Runs the tools of a registry, keeping each within its concurrency limit.
+#[derive(Debug, Default)]
+pub struct Executor {
+    slots: Mutex<HashMap<String, Arc<Slots>>>,
+    /// Shared by the calls of all tools, if their total is limited
+    total: Mutex<Option<Arc<Slots>>>,
+    calls: Calls,
+}
+
+/// How often a running tool is checked on, for its timeout and for
+/// shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long tools stopped at shutdown are given to exit.
+const STOP_GRACE: Duration = Duration::from_secs(1);
+
+impl Executor {
+    /// Create an executor with no calls running.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run at most `limit` calls at once, across all tools.
+    pub fn with_max_concurrency(self, limit: NonZeroUsize) -> Self {
+        self.set_max_concurrency(Some(limit));
+        self
+    }
+
+    /// Change how many calls may run at once, across all tools; `None`
+    /// removes the limit. Calls already running count against the limit
+    /// they started under.
+    pub fn set_max_concurrency(&self, limit: Option<NonZeroUsize>) {
+        *self.total.lock().unwrap_or_else(PoisonError::into_inner) =
+            limit.map(|limit| Arc::new(Slots::new(limit.get())));
+    }
+
+    /// Call `tool` with `arguments`, waiting for a free slot if the tool
+    /// already runs as many calls as `execution.concurrency` allows, or the
+    /// executor as many as its maximum.
+    pub fn call(&self, tool: &RegisteredTool, arguments: &Map<String, Value>) -> CallToolResult {
+        let definition = &tool.definition;
+        if let Err(message) = check_arguments(definition, arguments) {
+            return CallToolResult::error(message);
+        }
+        let redactor = Redactor::for_definition(definition);
+        let Some(_call) = self.calls.start() else {
+            return CallToolResult::error("mcp-serve is shutting down");
+        };
+        let _slot = definition
+            .execution
+            .concurrency
+            .map(|limit| self.slots(&definition.name, limit.get()).acquire());
+        // Taken after the tool's own slot, so that a call waiting on its
+        // tool does not hold up the calls of others.
+        let total = self
+            .total
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let _total = total.map(Slots::acquire);
+        let stopped = &self.calls.stopped;
+        let result = match &tool.source {
+            _ if stopped.load(Ordering::Relaxed) => Err(stopped_message(&definition.name)),
+            ToolBackend::Executable(executable) => {
+                run_tool(executable, definition, arguments, &redactor, stopped)
+            }
+            ToolBackend::Native(handler) => handler
+                .call(arguments)
+                .map(|output| present_native(definition, output))
+                .map_err(|e| e.message),
+        };
+        result.unwrap_or_else(|message| {
+            let message = redactor.redact_text(&message, &Value::Object(arguments.clone()));
+            tracing::debug!("call to {} failed: {}", definition.name, message);
+            CallToolResult::error(message)
+        })
+    }
+
+    /// The number of calls in progress.
+    pub fn running(&self) -> usize {
+        self.calls.lock().running
+    }
+
+    /// Refuse new calls, and wait up to `timeout` for those in progress to
+    /// finish. Calls still running then are stopped, killing their tools,
+    /// and answered with an error; returns how many were.
+    pub fn shut_down(&self, timeout: Duration) -> usize {
+        let mut state = self.calls.lock();
+        state.closed = true;
+        let (state, _) = self
+            .calls
+            .finished
+            .wait_timeout_while(state, timeout, |state| state.running > 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        let stopped = state.running;
+        if stopped > 0 {
+            self.calls.stopped.store(true, Ordering::Relaxed);
+            // Native tools cannot be stopped, and are not waited for long.
+            let _ = self
+                .calls
+                .finished
+                .wait_timeout_while(state, STOP_GRACE, |state| state.running > 0);
+        }
+        stopped
+    }
+
+    /// The slots of the tool `name`, replaced if its limit changed since
+    /// they were made (as when its definition is reloaded). Calls already
+    /// running count against the limit they started under.
+    fn slots(&self, name: &str, limit: usize) -> Arc<Slots> {
+        let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        match slots.get(name) {
+            Some(current) if current.limit == limit => Arc::clone(current),
+            _ => {
+                let replacement = Arc::new(Slots::new(limit));
+                slots.insert(name.to_string(), Arc::clone(&replacement));
+                replacement
+            }
+        }
+    }
+}
+
+/// Check `arguments` against the tool's input schema, describing every
+/// violation, with sensitive values hidden.
+pub fn check_arguments(
+    definition: &ToolDefinition,
+    arguments: &Map<String, Value>,
+) -> Result<(), String> {
+    let validator = jsonschema::validator_for(&definition.input.schema)
+        .map_err(|e| format!("the tool's input schema is invalid: {}", e))?;
+    let instance = Value::Object(arguments.clone());
+    let problems: Vec<String> = validator
+        .iter_errors(&instance)
+        .map(|error| match error.instance_path().to_string() {
+            path if path.is_empty() => error.to_string(),
+            path => format!("{}: {}", path, error),
+        })
+        .collect();
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        let message = format!("invalid arguments: {}", problems.join("; "));
+        Err(Redactor::for_definition(definition).redact_text(&message, &instance))
+    }
+}
+
+/// Run an executable tool and build the result from its output, killing it
+/// if `stopped` is set. What is logged goes through `redactor`.
+fn run_tool(
+    tool: &DiscoveredTool,
+    definition: &ToolDefinition,
+    arguments: &Map<String, Value>,
+    redactor: &Redactor,
+    stopped: &AtomicBool,
+) -> Result<CallToolResult, String> {
+    let execution = &definition.execution;
+    let definition_dir = tool.definition_file().parent().unwrap_or(Path::new(""));
+    let isolated = execution.sandbox == Some(SandboxProfile::Isolated);
+    let could_not_run = |e: io::Error| format!("could not run {}: {}", tool.path.display(), e);
+    // Removed when the call is over.
+    let scratch = if isolated && execution.cwd.is_none() {
+        Some(tempfile::tempdir().map_err(could_not_run)?)
+    } else {
+        None
+    };
+    let cwd = match &scratch {
+        Some(scratch) => Some(scratch.path().to_path_buf()),
+        None => execution
+            .resolve_cwd(definition_dir)
+            .or_else(|| tool.working_dir.clone()),
+    };
+    let given = Value::Object(arguments.clone());
+    // Paths are handed to the tool canonical, once checked.
+    let mut confined = given.clone();
+    if let Some(roots) = execution.resolve_allowed_roots(definition_dir) {
+        let base = match &cwd {
+            Some(cwd) => cwd.clone(),
+            None => std::env::current_dir().map_err(could_not_run)?,
+        };
+        confine(&definition.input.schema, &mut confined, &roots, &base)
+            .map_err(|e| format!("invalid arguments: {}", e))?;
+    }
+    let arguments = confined.as_object().expect("arguments are an object");
+    let invocation = preview::invocation(definition, arguments).map_err(|e| match e {
+        PreviewError::Expand(e) => format!("invalid arguments: {}", e),
+        e => e.to_string(),
+    })?;
+    // A tool whose output declares its exit code fails only if it cannot
+    // run.
+    let exit_code_declared = declares_exit_code(&definition.output.schema);
+    // The executable may have been replaced since it was loaded.
+    if let Some(expected) = &definition.sha256 {
+        pin::verify(&tool.path, expected)
+            .map_err(|e| format!("refusing to run {}: {}", definition.name, e))?;
+    }
+    let attempts = execution.retries.unwrap_or(0).saturating_add(1);
+    let mut attempt = 1;
+    let output = loop {
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            let shown = invocation.redact(redactor, &given);
+            let shown = command(tool, definition, &shown, cwd.as_deref()).map_err(could_not_run)?;
+            tracing::debug!("running {}: {:?}", definition.name, shown);
+        }
+        let command =
+            command(tool, definition, &invocation, cwd.as_deref()).map_err(could_not_run)?;
+        let started = Instant::now();
+        let output = run(
+            command,
+            invocation.stdin.clone(),
+            execution.timeout,
+            execution.max_output_size,
+            stopped,
+        );
+        if let Ok(output) = &output {
+            tracing::debug!(
+                "{} {} after {:?}",
+                definition.name,
+                output.status,
+                started.elapsed()
+            );
+        }
+        let failure = match output {
+            Ok(output) if output.status.success() || exit_code_declared => break output,
+            Ok(output) => failure_message(&definition.name, &output),
+            Err(RunError::Stopped) => return Err(stopped_message(&definition.name)),
+            Err(RunError::TimedOut(timeout)) => format!(
+                "{} did not finish within {:?} and was stopped",
+                definition.name, timeout
+            ),
+            Err(RunError::OutputTooLarge(limit)) => format!(
+                "{} wrote more than {} bytes of output and was stopped",
+                definition.name, limit
+            ),
+            Err(RunError::Io(e)) => could_not_run(e),
+        };
+        if attempt == attempts || stopped.load(Ordering::Relaxed) {
+            return Err(failure);
+        }
+        tracing::debug!(
+            "{} failed (attempt {} of {}), retrying: {}",
+            definition.name,
+            attempt,
+            attempts,
+            redactor.redact_text(&failure, &given)
+        );
+        attempt += 1;
+    };
+    let exit_code = output.status.code();
+    let parsed = parse_result(
+        &definition.output,
+        &output.stdout,
+        &output.stderr,
+        exit_code,
+    )
+    .map_err(|e| format!("could not parse the output of {}: {}", definition.name, e))?;
+    let raw = String::from_utf8_lossy(&output.stdout);
+    Ok(content::render(&definition.output.content, &raw, &parsed))
+}
+
+/// Build the command for an invocation: the executable (or its runtime)
+/// with the expanded arguments, or the platform shell with the script.
+fn command(
+    tool: &DiscoveredTool,
+    definition: &ToolDefinition,
+    invocation: &Invocation,
+    cwd: Option<&Path>,
+) -> io::Result<Command> {
+    // The tool may run in another directory, so its path must not be
+    // relative to this one.
+    let program = std::path::absolute(&tool.path)?;
+    let mut command = match &invocation.command {
+        CommandLine::Args(args) => {
+            let mut command = match &definition.execution.runtime {
+                Some(runtime) => {
+                    let mut command = Command::new(runtime);
+                    command.arg(&program);
+                    command
+                }
+                None => Command::new(&program),
+            };
+            command.args(args);
+            command
+        }
+        CommandLine::Script(script) => shell_command(script),
+    };
+    // An isolated tool passes nothing through unless told to.
+    let isolated = definition.execution.sandbox == Some(SandboxProfile::Isolated);
+    let passthrough = match &definition.execution.env_passthrough {
+        Some(passthrough) => Some(passthrough.as_slice()),
+        None => isolated.then_some(&[][..]),
+    };
+    if let Some(passthrough) = passthrough {
+        command.env_clear();
+        command.envs(std::env::vars_os().filter(|(name, _)| {
+            name.to_str()
+                .is_some_and(|name| passthrough.iter().any(|pattern| pattern.matches(name)))
+        }));
+    }
+    command.envs(&invocation.env);
+    if let Some(cwd) = cwd {
+        command.current_dir(std::path::absolute(cwd)?);
+    }
+    if isolated {
+        isolate(&mut command);
+    }
+    Ok(command)
+}
+
+/// Have the tool run in a session of its own, without core dumps and, on
+/// Linux, without a way to gain privileges.
+#[cfg(unix)]
+fn isolate(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: between fork and exec, the closure only makes system calls
+    // that act on the new process, and allocates nothing.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            let none = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            if libc::setrlimit(libc::RLIMIT_CORE, &none) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            #[cfg(target_os = "linux")]
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Windows has no such settings to apply; the environment and working
+/// directory are still isolated.
+#[cfg(windows)]
+fn isolate(_command: &mut Command) {}
+
+#[cfg(not(windows))]
+fn shell_command(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script);
+    command
+}
+
+#[cfg(windows)]
+fn shell_command(script: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    let mut command = Command::new("cmd");
+    command.arg("/C").raw_arg(script);
+    command
+}
+
+/// What a finished tool wrote and how it exited.
+#[derive(Debug)]
+struct ProcessOutput {
+    status: ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum RunError {
+    Io(io::Error),
+    TimedOut(Duration),
+    OutputTooLarge(u64),
+    Stopped,
+}
+
+impl From<io::Error> for RunError {
+    fn from(error: io::Error) -> Self {
+        RunError::Io(error)
+    }
+}
+
+/// Run `command`, writing `stdin` to it and collecting its output, and kill
+/// it if it runs past `timeout`, writes more than `max_output` bytes to
+/// either pipe, or `stopped` is set.
+fn run(
+    mut command: Command,
+    stdin: Option<String>,
+    timeout: Option<Duration>,
+    max_output: Option<u64>,
+    stopped: &AtomicBool,
+) -> Result<ProcessOutput, RunError> {
+    command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command.spawn()?;
+
+    // The pipes are served from their own threads, so that a tool filling
+    // one of them cannot block while it is not being read.
+    let writer = child.stdin.take().zip(stdin).map(|(mut pipe, text)| {
+        thread::spawn(move || {
+            // A tool may exit without reading its input.
+            let _ = pipe.write_all(text.as_bytes());
+        })
+    });
+    let overflowed = Arc::new(AtomicBool::new(false));
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| read_to_end(pipe, max_output, &overflowed));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| read_to_end(pipe, max_output, &overflowed));
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.wait_timeout(POLL_INTERVAL)? {
+            break status;
+        }
+        let error = match (timeout, max_output) {
+            _ if stopped.load(Ordering::Relaxed) => RunError::Stopped,
+            (_, Some(limit)) if overflowed.load(Ordering::Relaxed) => {
+                RunError::OutputTooLarge(limit)
+            }
+            (Some(timeout), _) if started.elapsed() >= timeout => RunError::TimedOut(timeout),
+            _ => continue,
+        };
+        child.kill()?;
+        child.wait()?;
+        return Err(error);
+    };
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    let collect = |reader: Option<thread::JoinHandle<io::Result<Vec<u8>>>>| match reader {
+        Some(reader) => reader
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("reader thread panicked"))),
+        None => Ok(Vec::new()),
+    };
+    let output = ProcessOutput {
+        status,
+        stdout: collect(stdout)?,
+        stderr: collect(stderr)?,
+    };
+    // The tool may have exited before it was checked on.
+    match max_output {
+        Some(limit) if overflowed.load(Ordering::Relaxed) => Err(RunError::OutputTooLarge(limit)),
+        _ => Ok(output),
+    }
+}
+
+/// Read `pipe` to its end on a thread of its own, or up to `limit` bytes,
+/// setting `overflowed` if there is more.
+fn read_to_end(
+    pipe: impl Read + Send + 'static,
+    limit: Option<u64>,
+    overflowed: &Arc<AtomicBool>,
+) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    let overflowed = Arc::clone(overflowed);
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        match limit {
+            Some(limit) => {
+                pipe.take(limit.saturating_add(1))
+                    .read_to_end(&mut buffer)?;
+                if buffer.len() as u64 > limit {
+                    overflowed.store(true, Ordering::Relaxed);
+                }
+            }
+            None => {
+                let mut pipe = pipe;
+                pipe.read_to_end(&mut buffer)?;
+            }
+        }
+        Ok(buffer)
+    })
+}
+
+/// Describe a call stopped by shutdown.
+fn stopped_message(name: &str) -> String {
+    format!("{} was stopped as mcp-serve shut down", name)
+}
+
+/// Describe a tool that exited unsuccessfully, with what it wrote to stderr
+/// (or to stdout, if stderr is empty).
+fn failure_message(name: &str, output: &ProcessOutput) -> String {
+    let status = match output.status.code() {
+        Some(code) => format!("exited with status {}", code),
+        None => "was terminated by a signal".to_string(),
+    };
+    let details = [&output.stderr, &output.stdout]
+        .into_iter()
+        .map(|text| String::from_utf8_lossy(text).trim().to_string())
+        .find(|text| !text.is_empty());
+    match details {
+        Some(details) => format!("{} {}: {}", name, status, details),
+        None => format!("{} {}", name, status),
+    }
+}
+
+/// Present what a native handler returned: objects as parsed output, and
+/// anything else as JSON text.
+fn present_native(definition: &ToolDefinition, output: Value) -> CallToolResult {
+    match output {
+        Value::Object(object) => {
+            let raw = Value::Object(object.clone()).to_string();
+            content::render(&definition.output.content, &raw, &object)
+        }
+        other => CallToolResult {
+            content: vec![ContentBlock::Text {
+                text: other.to_string(),
+            }],
+            ..CallToolResult::default()
+        },
+    }
+}
+
+/// A count of the calls a tool may still start.
+#[derive(Debug)]
+struct Slots {
+    limit: usize,
+    free: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Slots {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            free: Mutex::new(limit),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Wait for a free slot and take it until the guard is dropped.
+    fn acquire(self: Arc<Self>) -> SlotGuard {
+        let mut free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        while *free == 0 {
+            free = self
+                .released
+                .wait(free)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *free -= 1;
+        drop(free);
+        SlotGuard(self)
+    }
+}
+
+struct SlotGuard(Arc<Slots>);
+
+/// The calls in progress, for shutting down once they finish.
+#[derive(Debug, Default)]
+struct Calls {
+    state: Mutex<CallState>,
+    finished: Condvar,
+    /// Set when the calls still running at shutdown are to be stopped
+    stopped: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct CallState {
+    running: usize,
+    /// Whether new calls are refused
+    closed: bool,
+}
+
+impl Calls {
+    fn lock(&self) -> std::sync::MutexGuard<'_, CallState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Count a call as running until the guard is dropped, unless new calls
+    /// are refused.
+    fn start(&self) -> Option<CallGuard<'_>> {
+        let mut state = self.lock();
+        if state.closed {
+            return None;
+        }
+        state.running += 1;
+        Some(CallGuard(self))
+    }
+}
+
+struct CallGuard<'a>(&'a Calls);
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        self.0.lock().running -= 1;
+        self.0.finished.notify_all();
+    }
+}
+
+impl Drop for SlotGuard {
+    fn drop(&mut self) {
+        *self.0.free.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+        self.0.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::ToolError;
+    use crate::registry::{DuplicatePolicy, ToolRegistry};
+    use serde_json::json;
+    #[cfg(unix)]
+    use {crate::tool_discovery::MetadataSource, tempfile::TempDir};
+
+    /// Register `script` as an executable tool described by `yaml`.
+    #[cfg(unix)]
+    fn registry(dir: &TempDir, script: &str, yaml: &str) -> ToolRegistry {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.path().join("tool");
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let tool = DiscoveredTool {
+            path,
+            relative_path: "tool".into(),
+            metadata_source: MetadataSource::Embedded,
+            namespace: None,
+            working_dir: None,
+            docs: None,
+            defaults: Box::default(),
+            directory_configs: Box::default(),
+        };
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register(ToolDefinition::from_yaml(yaml).unwrap(), tool)
+            .unwrap();
+        registry
+    }
+
+    fn call(registry: &ToolRegistry, arguments: Value) -> CallToolResult {
+        let tool = &registry.tools()[0];
+        Executor::new().call(tool, arguments.as_object().unwrap())
+    }
+
+    fn text(result: &CallToolResult) -> &str {
+        match &result.content[0] {
+            ContentBlock::Text { text } => text,
+            block => panic!("expected text, got {:?}", block),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_call_executable() {
+        let dir = TempDir::new().unwrap();
+        let registry = registry(
+            &dir,
+            "#!/bin/sh\nread body\necho \"$1 $2 [$body] $PRIORITY\"\n",
+            r#"
+name: echo
+description: Echo arguments
+input:
+  template: "--title {{title}}"
+  stdin_template: "{{body}}"
+  env_template: { PRIORITY: "{{priority}}" }
+  schema:
+    type: object
+    properties:
+      title: { type: string }
+      body: { type: string }
+      priority: { type: integer }
+    required: [title]
+output:
+  capture: full
+  schema: { type: object, properties: { result: { type: string } } }
+"#,
+        );
+
+        let result = call(
+            &registry,
+            json!({"title": "Fix it", "body": "Details", "priority": 2}),
+        );
+        assert!(!result.is_error, "{:?}", result);
+        assert_eq!(
+            result.structured_content,
+            Some(json!({"result": "--title Fix it [Details] 2"}))
+        );
+
+        let result = call(&registry, json!({"priority": "high"}));
+        assert!(result.is_error);
+        assert!(text(&result).starts_with("invalid arguments: "));
+        assert!(text(&result).contains("\"title\" is a required property"));
+        assert!(text(&result).contains("/priority: "));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_call_failure() {
+        let dir = TempDir::new().unwrap();
+        let yaml = |schema: &str| {
+            format!(
+                r#"
+name: fail
+description: Fails
+input:
+  template: ""
+  schema: {{ type: object }}
+output:
+  template: "(?<message>.*)"
+  schema: {}
+execution:
+  timeout: 200ms
+"#,
+                schema
+            )
+        };
+        let script = "#!/bin/sh\necho partial\necho 'disk full' >&2\nexit 3\n";
+
+        let registry_without_code = registry(
+            &dir,
+            script,
+            &yaml("{ type: object, properties: { message: { type: string } } }"),
+        );
+        let result = call(&registry_without_code, json!({}));
+        assert!(result.is_error);
+        assert_eq!(text(&result), "fail exited with status 3: disk full");
+
+        let registry_with_code = registry(
+            &dir,
+            script,
+            &yaml("{ type: object, properties: { message: { type: string }, exit_code: { type: integer } } }"),
+        );
+        let result = call(&registry_with_code, json!({}));
+        assert!(!result.is_error);
+        assert_eq!(
+            result.structured_content,
+            Some(json!({"message": "partial", "exit_code": 3}))
+        );
+
+        let slow = registry(&dir, "#!/bin/sh\nexec sleep 5\n", &yaml("{ type: object }"));
+        let result = call(&slow, json!({}));
+        assert!(result.is_error);
+        assert_eq!(
+            text(&result),
+            "fail did not finish within 200ms and was stopped"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_redaction() {
+        /// Collects what is logged.
+        #[derive(Clone, Default)]
+        struct Log(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Log {
+            fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(bytes);
+                Ok(bytes.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let dir = TempDir::new().unwrap();
+        let registry = registry(
+            &dir,
+            "#!/bin/sh\necho \"no access to $3 with $2\" >&2\nexit 1\n",
+            r#"
+name: clone
+description: Clone a repository
+input:
+  template: "--token {{token}} {{repo}}"
+  env_template: { TOKEN: "{{token}}" }
+  schema:
+    type: object
+    properties:
+      token: { type: string, pattern: "^[a-z0-9]+$", x-sensitive: true }
+      repo: { type: string }
+output: { template: "", schema: { type: object } }
+execution: { retries: 1 }
+"#,
+        );
+        let log = Log::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer({
+                let log = log.clone();
+                move || log.clone()
+            })
+            .finish();
+
+        let (failed, invalid) = tracing::subscriber::with_default(subscriber, || {
+            (
+                call(&registry, json!({"token": "s3cret", "repo": "mcp-serve"})),
+                call(&registry, json!({"token": "S3CRET", "repo": "mcp-serve"})),
+            )
+        });
+        assert!(failed.is_error);
+        assert_eq!(
+            text(&failed),
+            "clone exited with status 1: no access to mcp-serve with [REDACTED]"
+        );
+        assert!(invalid.is_error);
+        assert!(text(&invalid).contains("\"[REDACTED]\" does not match"));
+        assert!(!text(&invalid).contains("S3CRET"), "{}", text(&invalid));
+        let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            log.contains("running clone") && log.contains("retrying"),
+            "{}",
+            log
+        );
+        assert!(log.contains("TOKEN=\"[REDACTED]\""), "{}", log);
+        assert!(
+            !log.contains("s3cret") && !log.contains("S3CRET"),
+            "{}",
+            log
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_call_shell() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "x\n").unwrap();
+        let registry = registry(
+            &dir,
+            "#!/bin/sh\n",
+            r#"
+name: count
+description: Count files
+input:
+  template: "ls *.txt {{pattern}} | wc -l"
+  schema: { type: object, properties: { pattern: { type: string } } }
+output:
+  template: '(?<count:int>\d+)'
+  schema: { type: object, properties: { count: { type: integer } } }
+execution:
+  shell: true
+  cwd: .
+"#,
+        );
+
+        let result = call(&registry, json!({"pattern": "a.txt; echo injected"}));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({"count": 1})),
+            "{:?}",
+            result
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_passthrough() {
+        let dir = TempDir::new().unwrap();
+        let registry = registry(
+            &dir,
+            "#!/bin/sh\necho \"${HOME:-unset} ${PATH:+set} $LOG_FORMAT\"\n",
+            r#"
+name: env
+description: Print the environment
+input: { template: "", schema: { type: object } }
+output:
+  capture: full
+  schema: { type: object, properties: { result: { type: string } } }
+execution:
+  env: { LOG_FORMAT: json }
+  env_passthrough: [PAT?]
+"#,
+        );
+
+        let result = call(&registry, json!({}));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({"result": "unset set json"})),
+            "{:?}",
+            result
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execution_limits() {
+        let dir = TempDir::new().unwrap();
+        let yaml = |execution: &str| {
+            format!(
+                r#"
+name: tool
+description: Runs
+input: {{ template: "", schema: {{ type: object }} }}
+output:
+  capture: full
+  schema: {{ type: object, properties: {{ result: {{ type: string }} }} }}
+execution: {}
+"#,
+                execution
+            )
+        };
+
+        // Fails twice, then succeeds.
+        let flaky = "#!/bin/sh\nn=$(cat \"$0.count\" 2>/dev/null || echo 0)\n\
+                     echo $((n + 1)) > \"$0.count\"\n[ \"$n\" -ge 2 ] || exit 1\necho ok\n";
+        let result = call(&registry(&dir, flaky, &yaml("{ retries: 1 }")), json!({}));
+        assert_eq!(text(&result), "tool exited with status 1");
+        std::fs::remove_file(dir.path().join("tool.count")).unwrap();
+        let result = call(&registry(&dir, flaky, &yaml("{ retries: 2 }")), json!({}));
+        assert_eq!(result.structured_content, Some(json!({"result": "ok"})));
+
+        let chatty = "#!/bin/sh\nwhile true; do echo chatter; done\n";
+        let result = call(
+            &registry(&dir, chatty, &yaml("{ max_output_size: 1000 }")),
+            json!({}),
+        );
+        assert!(result.is_error);
+        assert_eq!(
+            text(&result),
+            "tool wrote more than 1000 bytes of output and was stopped"
+        );
+
+        let isolated = "#!/bin/sh\necho \"${HOME:-unset} $(pwd)\"\n";
+        let result = call(
+            &registry(&dir, isolated, &yaml("{ sandbox: isolated }")),
+            json!({}),
+        );
+        let output = result.structured_content.unwrap()["result"].to_string();
+        assert!(output.starts_with("\"unset "), "{}", output);
+        assert!(
+            !output.contains(&*dir.path().to_string_lossy()),
+            "{}",
+            output
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pinned() {
+        let dir = TempDir::new().unwrap();
+        let script = "#!/bin/sh\necho ok\n";
+        let original = dir.path().join("original");
+        std::fs::write(&original, script).unwrap();
+        let digest = crate::tool_discovery::source::sha256_file(&original).unwrap();
+        let yaml = format!(
+            "name: tool\ndescription: Runs\ninput: {{ template: '', schema: {{ type: object }} }}\n\
+             output: {{ capture: full, schema: {{ type: object }} }}\nsha256: {}\n",
+            digest
+        );
+        let registry = registry(&dir, script, &yaml);
+        assert!(!call(&registry, json!({})).is_error);
+
+        std::fs::write(dir.path().join("tool"), "#!/bin/sh\necho replaced\n").unwrap();
+        let result = call(&registry, json!({}));
+        assert!(result.is_error);
+        assert!(
+            text(&result).starts_with("refusing to run tool: "),
+            "{}",
+            text(&result)
+        );
+        assert!(text(&result).contains("does not match its pinned digest"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_allowed_roots() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("data")).unwrap();
+        let registry = registry(
+            &dir,
+            "#!/bin/sh\necho \"$1\"\n",
+            r#"
+name: read
+description: Reads a file
+input:
+  template: "{{file}}"
+  schema: { type: object, properties: { file: { type: string, format: path } } }
+output:
+  capture: full
+  schema: { type: object, properties: { result: { type: string } } }
+execution:
+  allowed_roots: [data]
+"#,
+        );
+        let data = std::fs::canonicalize(dir.path().join("data")).unwrap();
+
+        let file = dir.path().join("data/../data/notes.txt");
+        let result = call(&registry, json!({ "file": file }));
+        assert!(!result.is_error, "{:?}", result);
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "result": data.join("notes.txt") }))
+        );
+
+        let result = call(&registry, json!({"file": "/etc/shadow"}));
+        assert!(result.is_error);
+        assert!(
+            text(&result).starts_with("invalid arguments: /file: \"/etc/shadow\" is outside"),
+            "{}",
+            text(&result)
+        );
+    }
+
+    #[test]
+    fn test_call_native() {
+        let definition = ToolDefinition::from_yaml(
+            r#"
+name: greet
+description: Greet someone
+input:
+  template: ""
+  schema: { type: object, properties: { name: { type: string } } }
+output:
+  template: ""
+  schema: { type: object, properties: { greeting: { type: string } } }
+"#,
+        )
+        .unwrap();
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register_handler(definition, |arguments: &Map<String, Value>| match arguments
+                .get("name")
+                .and_then(Value::as_str)
+            {
+                Some(name) => Ok(json!({"greeting": format!("Hello, {}!", name)})),
+                None => Err(ToolError::new("who should be greeted?")),
+            })
+            .unwrap();
+
+        let result = call(&registry, json!({"name": "Ada"}));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({"greeting": "Hello, Ada!"}))
+        );
+        let result = call(&registry, json!({}));
+        assert!(result.is_error);
+        assert_eq!(text(&result), "who should be greeted?");
+    }
+
+    /// Call the first tool of `registry` on a thread, once it is running.
+    #[cfg(unix)]
+    fn spawn_call(
+        registry: &Arc<ToolRegistry>,
+        executor: &Arc<Executor>,
+        arguments: Value,
+    ) -> thread::JoinHandle<CallToolResult> {
+        let (registry, executor) = (Arc::clone(registry), Arc::clone(executor));
+        let running = executor.running();
+        let call = {
+            let executor = Arc::clone(&executor);
+            thread::spawn(move || {
+                executor.call(&registry.tools()[0], arguments.as_object().unwrap())
+            })
+        };
+        while executor.running() == running {
+            thread::sleep(Duration::from_millis(10));
+        }
+        call
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shut_down() {
+        let dir = TempDir::new().unwrap();
+        let registry = Arc::new(registry(
+            &dir,
+            "#!/bin/sh\nexec sleep \"$1\"\n",
+            r#"
+name: slow
+description: Sleeps
+input: { template: "{{seconds}}", schema: { type: object } }
+output: { capture: full, schema: { type: object } }
+"#,
+        ));
+
+        // A call finishing within the timeout is answered, and later calls
+        // are refused.
+        let executor = Arc::new(Executor::new());
+        let quick = spawn_call(&registry, &executor, json!({"seconds": "0.2"}));
+        assert_eq!(executor.shut_down(Duration::from_secs(5)), 0);
+        assert!(!quick.join().unwrap().is_error);
+        let refused = executor.call(&registry.tools()[0], &Map::new());
+        assert_eq!(text(&refused), "mcp-serve is shutting down");
+
+        // A call outlasting it is stopped.
+        let executor = Arc::new(Executor::new());
+        let slow = spawn_call(&registry, &executor, json!({"seconds": "5"}));
+        let started = Instant::now();
+        assert_eq!(executor.shut_down(Duration::from_millis(100)), 1);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(
+            text(&slow.join().unwrap()),
+            "slow was stopped as mcp-serve shut down"
+        );
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let slots = Arc::new(Slots::new(1));
+        let first = Arc::clone(&slots).acquire();
+        let waiter = {
+            let slots = Arc::clone(&slots);
+            thread::spawn(move || drop(slots.acquire()))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        drop(first);
+        waiter.join().unwrap();
+        assert_eq!(*slots.free.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_concurrency_limit_changed() {
+        let executor = Executor::new();
+        let first = executor.slots("tool", 1).acquire();
+        assert!(Arc::ptr_eq(
+            &executor.slots("tool", 1),
+            &executor.slots("tool", 1)
+        ));
+
+        // A new limit takes effect for the next call, without waiting on
+        // those started under the old one.
+        let raised = executor.slots("tool", 2);
+        assert_eq!(*raised.free.lock().unwrap(), 2);
+        let second = Arc::clone(&raised).acquire();
+        assert!(Arc::ptr_eq(&raised, &executor.slots("tool", 2)));
+        assert_eq!(*raised.free.lock().unwrap(), 1);
+        drop((first, second));
+    }
+}
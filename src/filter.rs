@@ -0,0 +1,230 @@
+//! Choosing which of the tools found are served.
+//!
+//! An operator can expose a subset of a directory's tools without moving
+//! files around: a [`ToolFilter`] admits tools by name ([`NamePattern`]s
+//! such as `deploy` or `ops/*`), by tag or category, by whether they are
+//! deprecated, and by whether they are declared read-only. Names are
+//! matched as they are served, namespace included.
+//!
+//! A [`ToolPolicy`] is the operator's side of the same choice: the `allow`
+//! and `deny` lists of a configuration file bound what is served at all,
+//! whatever a filter asks for, so an executable dropped into a directory is
+//! not exposed until it is vetted.
+
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::tool_discovery::{ToolAnnotations, ToolDefinition};
+
+/// A tool name, or a pattern of names in which `*` stands for any run of
+/// characters and `?` for any single one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct NamePattern(String);
+
+impl NamePattern {
+    /// Whether `name` matches the pattern, in full.
+    pub fn matches(&self, name: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        // Where to resume after the last `*`: the pattern position after
+        // it, and the name position it has matched up to.
+        let mut star: Option<(usize, usize)> = None;
+        let (mut p, mut n) = (0, 0);
+        while n < name.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    star = Some((p + 1, n));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match star {
+                    // Let the `*` take one more character.
+                    Some((after, matched)) => {
+                        star = Some((after, matched + 1));
+                        p = after;
+                        n = matched + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        pattern[p..].iter().all(|&c| c == '*')
+    }
+}
+
+impl From<String> for NamePattern {
+    fn from(pattern: String) -> Self {
+        Self(pattern)
+    }
+}
+
+impl From<NamePattern> for String {
+    fn from(pattern: NamePattern) -> Self {
+        pattern.0
+    }
+}
+
+impl FromStr for NamePattern {
+    type Err = Infallible;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Ok(Self(pattern.to_string()))
+    }
+}
+
+impl fmt::Display for NamePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Which tools are served; the default admits every tool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolFilter {
+    /// If not empty, only tools with a name matching one of these
+    pub only: Vec<NamePattern>,
+
+    /// No tools with a name matching one of these
+    pub exclude: Vec<NamePattern>,
+
+    /// If not empty, only tools with one of these tags or categories
+    pub tags: Vec<String>,
+
+    /// No tools marked as deprecated
+    pub hide_deprecated: bool,
+
+    /// Only tools declared read-only (`readOnlyHint: true`)
+    pub read_only: bool,
+}
+
+impl ToolFilter {
+    /// Whether the tool `definition` defines, served as `name`, is served.
+    pub fn admits(&self, name: &str, definition: &ToolDefinition) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|pattern| pattern.matches(name)))
+            && !self.exclude.iter().any(|pattern| pattern.matches(name))
+            && (self.tags.is_empty() || definition.matches_tags(&self.tags))
+            && !(self.hide_deprecated && definition.is_deprecated())
+            && (!self.read_only
+                || (definition.annotations.as_ref()).is_some_and(ToolAnnotations::is_read_only))
+    }
+}
+
+/// Which tools may be served at all; the default permits every tool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolPolicy {
+    /// If given, only tools with a name matching one of these; an empty
+    /// list permits none
+    pub allow: Option<Vec<NamePattern>>,
+
+    /// No tools with a name matching one of these, even if allowed
+    pub deny: Vec<NamePattern>,
+}
+
+impl ToolPolicy {
+    /// Whether the tool served as `name` may be served.
+    pub fn permits(&self, name: &str) -> bool {
+        self.allow
+            .as_ref()
+            .is_none_or(|allow| allow.iter().any(|pattern| pattern.matches(name)))
+            && !self.deny.iter().any(|pattern| pattern.matches(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(pattern: &str) -> NamePattern {
+        pattern.parse().unwrap()
+    }
+
+    #[test]
+    fn test_name_pattern() {
+        assert!(pattern("deploy").matches("deploy"));
+        assert!(!pattern("deploy").matches("deploy_2"));
+        assert!(pattern("ops/*").matches("ops/restart"));
+        assert!(!pattern("ops/*").matches("dev/restart"));
+        assert!(pattern("*_ticket").matches("create_ticket"));
+        assert!(pattern("*a*b").matches("xaxxab"));
+        assert!(!pattern("*a*b").matches("xaxxabc"));
+        assert!(pattern("v?").matches("v2"));
+        assert!(!pattern("v?").matches("v"));
+        assert!(pattern("*").matches(""));
+    }
+
+    #[test]
+    fn test_admits() {
+        let definition = ToolDefinition::from_yaml(
+            r#"
+name: deploy
+description: Deploys
+tags: [ops]
+deprecated: true
+input: { template: "", schema: { type: object } }
+output: { template: "", schema: { type: object } }
+"#,
+        )
+        .unwrap();
+        assert!(ToolFilter::default().admits("deploy", &definition));
+
+        let filter = ToolFilter {
+            only: vec![pattern("deploy"), pattern("ops/*")],
+            exclude: vec![pattern("ops/*-prod")],
+            ..ToolFilter::default()
+        };
+        assert!(filter.admits("deploy", &definition));
+        assert!(filter.admits("ops/deploy", &definition));
+        assert!(!filter.admits("ops/deploy-prod", &definition));
+        assert!(!filter.admits("build", &definition));
+
+        let tagged = |tags: &[&str]| ToolFilter {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..ToolFilter::default()
+        };
+        assert!(tagged(&["ops", "dev"]).admits("deploy", &definition));
+        assert!(!tagged(&["dev"]).admits("deploy", &definition));
+        let current = ToolFilter {
+            hide_deprecated: true,
+            ..ToolFilter::default()
+        };
+        assert!(!current.admits("deploy", &definition));
+
+        let read_only = ToolFilter {
+            read_only: true,
+            ..ToolFilter::default()
+        };
+        assert!(!read_only.admits("deploy", &definition));
+        let mut lookup = definition.clone();
+        lookup.annotations = Some(ToolAnnotations {
+            read_only_hint: Some(true),
+            ..ToolAnnotations::default()
+        });
+        assert!(read_only.admits("deploy", &lookup));
+    }
+
+    #[test]
+    fn test_permits() {
+        assert!(ToolPolicy::default().permits("deploy"));
+
+        let policy = ToolPolicy {
+            allow: Some(vec![pattern("ops/*"), pattern("greet")]),
+            deny: vec![pattern("*-prod")],
+        };
+        assert!(policy.permits("ops/deploy"));
+        assert!(policy.permits("greet"));
+        assert!(!policy.permits("ops/deploy-prod"));
+        assert!(!policy.permits("rm_rf"));
+
+        let closed = ToolPolicy {
+            allow: Some(Vec::new()),
+            ..ToolPolicy::default()
+        };
+        assert!(!closed.permits("greet"));
+    }
+}
@@ -0,0 +1,60 @@
+//! Tools implemented in-process rather than by an executable.
+//!
+//! Code embedding the server can register a [`ToolHandler`] (any closure
+//! with the right signature qualifies) together with a [`ToolDefinition`]
+//! describing it. The registry keeps such tools alongside discovered
+//! executables, so clients see no difference between the two: the
+//! definition supplies the name, description, and schemas, and the handler
+//! turns validated arguments into structured output.
+//!
+//! ```ignore
+//! registry.register_handler(definition, |arguments: &Map<String, Value>| {
+//!     let name = arguments["name"].as_str().unwrap_or("world");
+//!     Ok(json!({ "greeting": format!("Hello, {}!", name) }))
+//! })?;
+//! ```
+//!
+//! [`ToolDefinition`]: crate::tool_discovery::ToolDefinition
+
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// A tool implemented by Rust code.
+pub trait ToolHandler: Send + Sync {
+    /// Run the tool with the arguments of a call, returning its structured
+    /// output (which should match the definition's output schema).
+    fn call(&self, arguments: &Map<String, Value>) -> Result<Value, ToolError>;
+}
+
+impl<F> ToolHandler for F
+where
+    F: Fn(&Map<String, Value>) -> Result<Value, ToolError> + Send + Sync,
+{
+    fn call(&self, arguments: &Map<String, Value>) -> Result<Value, ToolError> {
+        self(arguments)
+    }
+}
+
+/// A tool call that failed; the message is reported to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolError {
+    /// What went wrong, in terms the caller can act on
+    pub message: String,
+}
+
+impl ToolError {
+    /// Create an error with the given message.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ToolError {}
@@ -0,0 +1,685 @@
+//! Starting tools directories and drafting tool definitions.
+//!
+//! `mcp-serve init [DIR]` creates an example tools directory: a [`SCAFFOLD`]
+//! of one executable, its sidecar, and a README explaining the layout.
+//!
+//! `mcp-serve init tool ./mytool` writes a [`definition_skeleton`] next to an
+//! existing executable: a definition with placeholder values and comments
+//! on what each field does.
+//!
+//! `mcp-serve init --from-help ./mytool` runs the executable with `--help`
+//! and turns what it can recognize into a sidecar definition: the first line
+//! of the help becomes the description, `-f, --flag VALUE` options become
+//! optional string (or integer) inputs, and `<ARG>`s on the usage line become
+//! positional inputs. The result is a starting point for the author to
+//! refine, not a finished definition.
+
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::template::TemplateEngine;
+
+use crate::tool_discovery::{ExecutionConfig, ToolDefinition, ToolInput, ToolOutput};
+
+/// Value names that suggest an option takes a number.
+const NUMERIC_VALUE_NAMES: [&str; 8] = [
+    "N", "NUM", "NUMBER", "INT", "COUNT", "SECONDS", "SIZE", "LIMIT",
+];
+
+/// A definition drafted from an executable's help text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HelpDraft {
+    /// The drafted definition
+    pub definition: ToolDefinition,
+
+    /// Flags that take no value; the template cannot toggle them, so they
+    /// are left for the author to wire up.
+    pub boolean_flags: Vec<String>,
+}
+
+impl HelpDraft {
+    /// Parse help text printed by the executable `name`.
+    pub fn from_help(name: &str, help: &str) -> Self {
+        let option = Regex::new(
+            r"^\s+(?:-(?<short>[A-Za-z0-9])(?:,\s*|\s+|$))?(?:--(?<long>[A-Za-z0-9][A-Za-z0-9-]*))?(?:[ =](?<value><[^>]+>|\[[^\]]+\]|[A-Z][A-Z0-9_-]*))?(?:\s{2,}(?<description>\S.*))?$",
+        )
+        .expect("option pattern is valid");
+
+        let mut description = None;
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        let mut flags = Vec::new();
+        let mut positionals = Vec::new();
+        let mut boolean_flags = Vec::new();
+        // An option whose description is on the following line, as in long
+        // help output.
+        let mut undescribed: Option<String> = None;
+
+        for line in help.lines() {
+            let trimmed = line.trim();
+            if let Some(usage) = trimmed
+                .strip_prefix("Usage:")
+                .or_else(|| trimmed.strip_prefix("usage:"))
+            {
+                positionals.extend(usage_arguments(usage));
+                continue;
+            }
+            if description.is_none() && !trimmed.is_empty() && !trimmed.starts_with('-') {
+                description = Some(trimmed.to_string());
+                continue;
+            }
+            if !trimmed.starts_with('-') {
+                if let Some(property) = undescribed.take().filter(|_| !trimmed.is_empty()) {
+                    properties[&property]["description"] = json!(trimmed);
+                }
+                continue;
+            }
+            undescribed = None;
+            let Some(captures) = option.captures(line) else {
+                continue;
+            };
+            let (flag, property) = match (captures.name("long"), captures.name("short")) {
+                (Some(long), _) => (format!("--{}", long.as_str()), long.as_str().to_string()),
+                (None, Some(short)) => (format!("-{}", short.as_str()), short.as_str().to_string()),
+                (None, None) => continue,
+            };
+            if matches!(flag.as_str(), "--help" | "--version" | "-h" | "-V") {
+                continue;
+            }
+            let property = property.replace('-', "_");
+            let mut schema = Map::new();
+            match captures.name("value") {
+                Some(value) => {
+                    let value_name = value
+                        .as_str()
+                        .trim_matches(|c| matches!(c, '<' | '>' | '[' | ']'))
+                        .to_ascii_uppercase();
+                    let kind = if NUMERIC_VALUE_NAMES.contains(&value_name.as_str()) {
+                        "integer"
+                    } else {
+                        "string"
+                    };
+                    schema.insert("type".to_string(), json!(kind));
+                    flags.push(format!("[{} {{{{{}}}}}]", flag, property));
+                }
+                None => {
+                    schema.insert("type".to_string(), json!("boolean"));
+                    boolean_flags.push(flag);
+                }
+            }
+            match captures.name("description") {
+                Some(text) => {
+                    schema.insert("description".to_string(), json!(text.as_str().trim()));
+                }
+                None => undescribed = Some(property.clone()),
+            }
+            properties.entry(property).or_insert(Value::Object(schema));
+        }
+
+        let mut template = flags;
+        for (argument, optional) in positionals {
+            if properties.contains_key(&argument) {
+                continue;
+            }
+            properties.insert(argument.clone(), json!({"type": "string"}));
+            if optional {
+                template.push(format!("[{{{{{}}}}}]", argument));
+            } else {
+                template.push(format!("{{{{{}}}}}", argument));
+                required.push(json!(argument));
+            }
+        }
+
+        let mut input_schema = json!({"type": "object", "properties": properties});
+        if !required.is_empty() {
+            input_schema["required"] = Value::Array(required);
+        }
+
+        HelpDraft {
+            definition: ToolDefinition {
+                name: tool_name(name),
+                aliases: Vec::new(),
+                title: None,
+                version: None,
+                description: description.unwrap_or_default(),
+                input: ToolInput {
+                    template: template.join(" ").into(),
+                    stdin_template: None,
+                    env_template: BTreeMap::new(),
+                    engine: TemplateEngine::Builtin,
+                    schema: input_schema,
+                },
+                output: ToolOutput {
+                    template: r"(?<output>[\s\S]*)".into(),
+                    stderr_template: Default::default(),
+                    extract: None,
+                    capture: None,
+                    encoding: Default::default(),
+                    schema: json!({
+                        "type": "object",
+                        "properties": {"output": {"type": "string"}}
+                    }),
+                    content: Vec::new(),
+                },
+                annotations: None,
+                execution: ExecutionConfig::default(),
+                sha256: None,
+                deprecated: None,
+                category: None,
+                tags: Vec::new(),
+                icon: None,
+                meta: None,
+                tests: Vec::new(),
+            },
+            boolean_flags,
+        }
+    }
+
+    /// Run `executable --help` and draft a definition from its output.
+    ///
+    /// The exit status is ignored, since many tools exit non-zero after
+    /// printing help; help printed to stderr is used if stdout is empty.
+    pub fn from_executable(executable: &Path) -> Result<Self, InitError> {
+        let output = Command::new(executable)
+            .arg("--help")
+            .output()
+            .map_err(|source| InitError::Run {
+                executable: executable.to_path_buf(),
+                source,
+            })?;
+        let help = if output.stdout.iter().all(u8::is_ascii_whitespace) {
+            output.stderr
+        } else {
+            output.stdout
+        };
+        let name = executable
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self::from_help(&name, &String::from_utf8_lossy(&help)))
+    }
+
+    /// The draft as a sidecar YAML document, headed by comments on what to
+    /// review.
+    pub fn to_yaml(&self, source: &str) -> String {
+        let mut yaml = format!(
+            "# Draft definition generated from `{} --help`.\n\
+             # Review the description, input types, and templates before serving it.\n",
+            source
+        );
+        if !self.boolean_flags.is_empty() {
+            yaml.push_str(&format!(
+                "# The input template does not pass these flags yet: {}\n",
+                self.boolean_flags.join(", ")
+            ));
+        }
+        yaml.push_str(
+            &serde_yaml_ng::to_string(&self.definition)
+                .expect("definition serialization cannot fail"),
+        );
+        yaml
+    }
+}
+
+/// A file of the example tools directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaffoldFile {
+    /// Path within the directory
+    pub path: &'static str,
+
+    /// What the file holds
+    pub contents: &'static str,
+
+    /// Whether the file is a tool to mark executable
+    pub executable: bool,
+}
+
+/// The files `init` creates in an example tools directory.
+pub const SCAFFOLD: &[ScaffoldFile] = &[
+    ScaffoldFile {
+        path: "greet",
+        contents: GREET_SCRIPT,
+        executable: true,
+    },
+    ScaffoldFile {
+        path: "greet.yaml",
+        contents: GREET_DEFINITION,
+        executable: false,
+    },
+    ScaffoldFile {
+        path: "README.md",
+        contents: SCAFFOLD_README,
+        executable: false,
+    },
+];
+
+const GREET_SCRIPT: &str = "\
+#!/bin/sh
+# An example tool: greets someone by name. Its definition is in greet.yaml.
+echo \"Hello, $1!\"
+";
+
+const GREET_DEFINITION: &str = "\
+# The definition of ./greet, as MCP clients see it.
+name: greet
+description: Greets someone by name
+
+input:
+  # The arguments ./greet is run with: {{name}} is replaced by the `name`
+  # argument of the call.
+  template: \"{{name}}\"
+  schema:
+    type: object
+    properties:
+      name:
+        type: string
+        description: Who to greet
+    required: [name]
+
+output:
+  # Named groups of this regular expression become the result's properties.
+  template: \"(?<greeting>.*)\"
+  schema:
+    type: object
+    properties:
+      greeting:
+        type: string
+
+# Calls `mcp-serve test` checks: what they run, and what sample output
+# parses to. With `run: true`, a case also calls the tool for real.
+tests:
+  - name: greets by name
+    arguments: { name: Ada }
+    argv: [Ada]
+    stdout: \"Hello, Ada!\\n\"
+    result: { greeting: \"Hello, Ada!\" }
+    run: true
+";
+
+const SCAFFOLD_README: &str = "\
+# Tools
+
+Each executable in this directory is a tool that `mcp-serve` serves to MCP
+clients. A tool is described by a sidecar definition next to it, named after
+the executable with `.yaml` appended (`greet` is described by `greet.yaml`),
+or by a YAML block in the executable's leading comments.
+
+To add a tool, place its executable here, then write its definition:
+
+```sh
+mcp-serve init tool ./my-tool         # A commented skeleton to fill in
+mcp-serve init --from-help ./my-tool  # Or a draft from its --help output
+```
+
+Check the definitions, and serve the tools:
+
+```sh
+mcp-serve validate .
+mcp-serve test .
+mcp-serve serve .
+```
+";
+
+/// The definition `init tool` writes, with `$NAME` standing for the tool's
+/// name and `$EXECUTABLE` for the executable's file name.
+const SKELETON: &str = "\
+# The definition of $EXECUTABLE, as MCP clients see it.
+# Replace the placeholders, then check it with `mcp-serve validate`.
+
+# The name clients call the tool by: letters, digits, `_`, `-`, and `.`.
+name: $NAME
+
+# What the tool does. Models read this to decide when to call the tool.
+description: TODO describe what $NAME does
+
+input:
+  # The arguments $EXECUTABLE is run with. {{target}} is replaced by the
+  # `target` argument of the call; wrap optional parts in brackets, as in
+  # `[--limit {{limit}}]`, and repeat array items with `[--tag {{tag}}...]`.
+  template: \"{{target}}\"
+
+  # A JSON Schema of the arguments clients call the tool with.
+  schema:
+    type: object
+    properties:
+      target:
+        type: string
+        description: TODO describe this argument
+    required: [target]
+
+output:
+  # Return the whole output as the `result` property. Alternatively, set a
+  # `template` regular expression whose named groups, as in `(?<id>\\d+)`,
+  # become the result's properties.
+  capture: full
+
+  # A JSON Schema of the result.
+  schema:
+    type: object
+    properties:
+      result:
+        type: string
+";
+
+/// Create the [`SCAFFOLD`] in `dir`, creating the directory if needed, and
+/// return the paths written.
+///
+/// Nothing is written if any of the files already exists, unless `force` is
+/// set.
+pub fn scaffold(dir: &Path, force: bool) -> Result<Vec<PathBuf>, InitError> {
+    let paths: Vec<PathBuf> = SCAFFOLD.iter().map(|file| dir.join(file.path)).collect();
+    if !force {
+        if let Some(path) = paths.iter().find(|path| path.exists()) {
+            return Err(InitError::Exists { path: path.clone() });
+        }
+    }
+    std::fs::create_dir_all(dir).map_err(|source| InitError::Write {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    for (file, path) in SCAFFOLD.iter().zip(&paths) {
+        write_definition(path, file.contents, true)?;
+        if file.executable {
+            make_executable(path)?;
+        }
+    }
+    Ok(paths)
+}
+
+/// A commented definition for `executable`, with placeholder values for its
+/// author to replace.
+pub fn definition_skeleton(executable: &Path) -> String {
+    let name = executable
+        .file_stem()
+        .map(|stem| tool_name(&stem.to_string_lossy()))
+        .unwrap_or_default();
+    SKELETON.replace("$NAME", &name).replace(
+        "$EXECUTABLE",
+        &executable.file_name().unwrap_or_default().to_string_lossy(),
+    )
+}
+
+/// `<ARG>`, `[ARG]`, and `[<ARG>]` placeholders on a usage line, with
+/// whether each is optional. `[OPTIONS]` is skipped.
+fn usage_arguments(usage: &str) -> Vec<(String, bool)> {
+    let argument = Regex::new(
+        r"\[<?(?<optional>[A-Za-z][A-Za-z0-9_-]*)>?\]|<(?<required>[A-Za-z][A-Za-z0-9_-]*)>",
+    )
+    .expect("argument pattern is valid");
+    argument
+        .captures_iter(usage)
+        .filter_map(|captures| {
+            let (name, optional) = match (captures.name("optional"), captures.name("required")) {
+                (Some(name), _) => (name.as_str(), true),
+                (None, Some(name)) => (name.as_str(), false),
+                (None, None) => return None,
+            };
+            if name.eq_ignore_ascii_case("options") {
+                return None;
+            }
+            Some((name.to_ascii_lowercase().replace('-', "_"), optional))
+        })
+        .collect()
+}
+
+/// An MCP-compatible tool name derived from an executable's name.
+fn tool_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Write `contents` to `path`, refusing to replace an existing file unless
+/// `force` is set.
+pub fn write_definition(path: &Path, contents: &str, force: bool) -> Result<(), InitError> {
+    if !force && path.exists() {
+        return Err(InitError::Exists {
+            path: path.to_path_buf(),
+        });
+    }
+    std::fs::write(path, contents).map_err(|source| InitError::Write {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Mark a written tool as executable.
+pub fn make_executable(path: &Path) -> Result<(), InitError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).map_err(
+            |source| InitError::Write {
+                path: path.to_path_buf(),
+                source,
+            },
+        )?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Errors that can occur while drafting a definition.
+#[derive(Debug)]
+pub enum InitError {
+    /// The executable could not be run.
+    Run {
+        executable: PathBuf,
+        source: io::Error,
+    },
+
+    /// The definition file already exists.
+    Exists { path: PathBuf },
+
+    /// The definition file could not be written.
+    Write { path: PathBuf, source: io::Error },
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::Run { executable, source } => {
+                write!(f, "failed to run {}: {}", executable.display(), source)
+            }
+            InitError::Exists { path } => write!(
+                f,
+                "{} already exists; pass --force to replace it",
+                path.display()
+            ),
+            InitError::Write { path, source } => {
+                write!(f, "failed to write {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InitError::Run { source, .. } | InitError::Write { source, .. } => Some(source),
+            InitError::Exists { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAP_HELP: &str = "\
+Searches files for a pattern
+
+Usage: search [OPTIONS] <PATTERN> [PATH]
+
+Arguments:
+  <PATTERN>  Pattern to look for
+  [PATH]     Where to look
+
+Options:
+  -i, --ignore-case        Match case-insensitively
+  -m, --max-count <NUM>    Stop after this many matches
+      --type <TYPE>        Only search files of this type
+  -h, --help               Print help
+  -V, --version            Print version
+";
+
+    #[test]
+    fn test_draft_from_clap_help() {
+        let draft = HelpDraft::from_help("search", CLAP_HELP);
+        let definition = &draft.definition;
+
+        assert_eq!(definition.name, "search");
+        assert_eq!(definition.description, "Searches files for a pattern");
+        assert_eq!(
+            definition.input.template,
+            "[--max-count {{max_count}}] [--type {{type}}] {{pattern}} [{{path}}]"
+        );
+        assert_eq!(
+            definition.input.schema,
+            json!({
+                "type": "object",
+                "properties": {
+                    "ignore_case": {"type": "boolean", "description": "Match case-insensitively"},
+                    "max_count": {"type": "integer", "description": "Stop after this many matches"},
+                    "type": {"type": "string", "description": "Only search files of this type"},
+                    "pattern": {"type": "string"},
+                    "path": {"type": "string"}
+                },
+                "required": ["pattern"]
+            })
+        );
+        assert_eq!(draft.boolean_flags, ["--ignore-case"]);
+        assert!(crate::definitions::validate(definition)
+            .iter()
+            .all(|issue| !issue.is_error()));
+    }
+
+    #[test]
+    fn test_draft_from_gnu_help() {
+        let help = "\
+usage: fetch [-q] [--output=FILE] URL
+Download a URL.
+
+  -q               quiet
+  -o, --output=FILE
+";
+        let draft = HelpDraft::from_help("fetch url", help);
+
+        assert_eq!(draft.definition.name, "fetch_url");
+        assert_eq!(draft.definition.description, "Download a URL.");
+        assert_eq!(draft.definition.input.template, "[--output {{output}}]");
+        assert_eq!(draft.boolean_flags, ["-q"]);
+    }
+
+    #[test]
+    fn test_draft_from_long_help() {
+        let help = "\
+Greets someone
+
+Usage: greet [OPTIONS]
+
+Options:
+      --name <NAME>
+          Who to greet
+
+  -l, --loud
+          Shout the greeting
+";
+        let draft = HelpDraft::from_help("greet", help);
+        let properties = &draft.definition.input.schema["properties"];
+
+        assert_eq!(properties["name"]["description"], "Who to greet");
+        assert_eq!(properties["loud"]["description"], "Shout the greeting");
+    }
+
+    #[test]
+    fn test_draft_yaml() {
+        let draft = HelpDraft::from_help("search", CLAP_HELP);
+        let yaml = draft.to_yaml("./search");
+
+        assert!(yaml.starts_with("# Draft definition generated from `./search --help`."));
+        assert!(yaml.contains("# The input template does not pass these flags yet: --ignore-case"));
+        assert!(!yaml.contains("null"));
+        assert_eq!(ToolDefinition::from_yaml(&yaml).unwrap(), draft.definition);
+    }
+
+    #[test]
+    fn test_scaffold() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let tools = dir.path().join("tools");
+
+        let paths = scaffold(&tools, false).unwrap();
+        assert_eq!(paths.len(), SCAFFOLD.len());
+        let definition =
+            ToolDefinition::from_yaml(&std::fs::read_to_string(tools.join("greet.yaml")).unwrap())
+                .unwrap();
+        assert_eq!(definition.name, "greet");
+        assert!(crate::definitions::validate(&definition).is_empty());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(tools.join("greet"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+
+        assert!(matches!(
+            scaffold(&tools, false),
+            Err(InitError::Exists { path }) if path == tools.join("greet")
+        ));
+        scaffold(&tools, true).unwrap();
+    }
+
+    #[test]
+    fn test_definition_skeleton() {
+        let skeleton = definition_skeleton(Path::new("bin/file info.sh"));
+
+        assert!(skeleton.starts_with("# The definition of file info.sh,"));
+        let definition = ToolDefinition::from_yaml(&skeleton).unwrap();
+        assert_eq!(definition.name, "file_info");
+        assert!(crate::definitions::validate(&definition).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_draft_from_executable() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let script = dir.path().join("greet.sh");
+        fs::write(
+            &script,
+            "#!/bin/sh\necho 'Says hello' >&2\necho 'Usage: greet <NAME>' >&2\nexit 2\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let draft = HelpDraft::from_executable(&script).unwrap();
+        assert_eq!(draft.definition.name, "greet");
+        assert_eq!(draft.definition.input.template, "{{name}}");
+
+        let sidecar = dir.path().join("greet.sh.yaml");
+        write_definition(&sidecar, "first", false).unwrap();
+        assert!(matches!(
+            write_definition(&sidecar, "second", false),
+            Err(InitError::Exists { .. })
+        ));
+        write_definition(&sidecar, "second", true).unwrap();
+        assert_eq!(fs::read_to_string(&sidecar).unwrap(), "second");
+    }
+}
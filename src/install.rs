@@ -0,0 +1,349 @@
+//! Registering the server with MCP clients, for `mcp-serve install`.
+//!
+//! Each client keeps the servers it starts in a JSON file of its own, in a
+//! place that depends on the platform. [`install`] adds an entry for
+//! mcp-serve to that file (or updates the entry of the same name), leaving
+//! the client's other servers, and any fields of the entry it does not
+//! set, as they were:
+//!
+//! ```json
+//! {
+//!   "mcpServers": {
+//!     "mcp-serve": { "command": "/usr/local/bin/mcp-serve", "args": ["serve", "/home/ada/tools"] }
+//!   }
+//! }
+//! ```
+//!
+//! The file the entry replaces is kept next to it, with `.bak` appended.
+
+use serde_json::{json, Map, Value};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An MCP client mcp-serve can register with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Client {
+    /// Claude Desktop
+    ClaudeDesktop,
+
+    /// Cursor, for all projects
+    Cursor,
+
+    /// Visual Studio Code, for all workspaces
+    Vscode,
+}
+
+/// How a client starts a server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerEntry {
+    /// The name the client lists the server under
+    pub name: String,
+    pub command: PathBuf,
+    pub args: Vec<String>,
+}
+
+/// What [`install`] did to the client's configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Installed {
+    /// The file did not list the server before.
+    Added,
+
+    /// The file listed the server under the same name, differently.
+    Updated,
+
+    /// The file already listed the server as it would be written.
+    Unchanged,
+}
+
+/// A client configuration that could not be updated.
+#[derive(Debug)]
+pub enum InstallError {
+    /// The client's configuration file is not known on this platform.
+    UnknownLocation { client: Client },
+
+    /// The file could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+
+    /// The file is not JSON, or not a JSON object of servers.
+    Invalid { path: PathBuf, message: String },
+}
+
+impl fmt::Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstallError::UnknownLocation { client } => write!(
+                f,
+                "cannot tell where {} keeps its configuration; pass --client-config",
+                client
+            ),
+            InstallError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            InstallError::Invalid { path, message } => write!(
+                f,
+                "{}: {}; fix the file, or add the server by hand",
+                path.display(),
+                message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InstallError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InstallError::Io { source, .. } => Some(source),
+            InstallError::UnknownLocation { .. } | InstallError::Invalid { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Client::ClaudeDesktop => "Claude Desktop",
+            Client::Cursor => "Cursor",
+            Client::Vscode => "VS Code",
+        })
+    }
+}
+
+impl Client {
+    /// Where the client keeps its servers for the current user, found
+    /// through `var` (such as [`std::env::var_os`]).
+    pub fn config_path(&self, var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+        let home = var(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from);
+        // Where desktop applications keep their settings.
+        let settings = if cfg!(windows) {
+            var("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            home.as_ref()
+                .map(|home| home.join("Library").join("Application Support"))
+        } else {
+            var("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| home.as_ref().map(|home| home.join(".config")))
+        };
+        match self {
+            Client::ClaudeDesktop => {
+                Some(settings?.join("Claude").join("claude_desktop_config.json"))
+            }
+            Client::Cursor => Some(home?.join(".cursor").join("mcp.json")),
+            Client::Vscode => Some(settings?.join("Code").join("User").join("mcp.json")),
+        }
+    }
+
+    /// The key of the file's object of servers.
+    fn servers_key(&self) -> &'static str {
+        match self {
+            Client::ClaudeDesktop | Client::Cursor => "mcpServers",
+            Client::Vscode => "servers",
+        }
+    }
+
+    /// The fields of `entry` as the client reads them.
+    fn fields(&self, entry: &ServerEntry) -> Map<String, Value> {
+        let mut fields = Map::new();
+        if *self == Client::Vscode {
+            fields.insert("type".to_string(), json!("stdio"));
+        }
+        fields.insert(
+            "command".to_string(),
+            json!(entry.command.to_string_lossy()),
+        );
+        fields.insert("args".to_string(), json!(entry.args));
+        fields
+    }
+}
+
+/// Add `entry` to the client configuration `config` (an empty object if
+/// there is none yet), returning the configuration to write.
+pub fn merge(
+    client: Client,
+    config: Value,
+    entry: &ServerEntry,
+) -> Result<(Value, Installed), String> {
+    let Value::Object(mut config) = config else {
+        return Err("expected a JSON object".to_string());
+    };
+    let servers = config
+        .entry(client.servers_key())
+        .or_insert_with(|| json!({}));
+    let Value::Object(servers) = servers else {
+        return Err(format!(
+            "expected `{}` to be an object",
+            client.servers_key()
+        ));
+    };
+    let installed = match servers.get_mut(&entry.name) {
+        Some(Value::Object(existing)) => {
+            let mut updated = existing.clone();
+            updated.extend(client.fields(entry));
+            let changed = updated != *existing;
+            *existing = updated;
+            if changed {
+                Installed::Updated
+            } else {
+                Installed::Unchanged
+            }
+        }
+        Some(_) => {
+            return Err(format!(
+                "expected the `{}` server to be an object",
+                entry.name
+            ))
+        }
+        None => {
+            servers.insert(entry.name.clone(), Value::Object(client.fields(entry)));
+            Installed::Added
+        }
+    };
+    Ok((Value::Object(config), installed))
+}
+
+/// Add `entry` to the client configuration at `path`, creating the file
+/// if there is none. Returns the configuration written.
+pub fn install(
+    client: Client,
+    path: &Path,
+    entry: &ServerEntry,
+    dry_run: bool,
+) -> Result<(Value, Installed), InstallError> {
+    let io_error = |source| InstallError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let invalid = |message| InstallError::Invalid {
+        path: path.to_path_buf(),
+        message,
+    };
+    let existing = match fs::read_to_string(path) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(io_error(e)),
+    };
+    let config = match existing.as_deref().map(str::trim) {
+        None | Some("") => json!({}),
+        Some(text) => serde_json::from_str(text).map_err(|e| invalid(e.to_string()))?,
+    };
+    let (config, installed) = merge(client, config, entry).map_err(invalid)?;
+    if dry_run || installed == Installed::Unchanged {
+        return Ok((config, installed));
+    }
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    if let Some(existing) = &existing {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".bak");
+        fs::write(backup, existing).map_err(io_error)?;
+    }
+    let text = serde_json::to_string_pretty(&config).expect("JSON values serialize");
+    fs::write(path, text + "\n").map_err(io_error)?;
+    Ok((config, installed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry() -> ServerEntry {
+        ServerEntry {
+            name: "tools".to_string(),
+            command: PathBuf::from("/usr/local/bin/mcp-serve"),
+            args: vec!["serve".to_string(), "/srv/tools".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let (config, installed) = merge(Client::ClaudeDesktop, json!({}), &entry()).unwrap();
+        assert_eq!(installed, Installed::Added);
+        assert_eq!(
+            config,
+            json!({"mcpServers": {"tools": {
+                "command": "/usr/local/bin/mcp-serve",
+                "args": ["serve", "/srv/tools"],
+            }}})
+        );
+
+        let existing = json!({
+            "theme": "dark",
+            "servers": {
+                "other": {"command": "other"},
+                "tools": {"type": "stdio", "command": "old", "env": {"TOKEN": "x"}},
+            },
+        });
+        let (config, installed) = merge(Client::Vscode, existing, &entry()).unwrap();
+        assert_eq!(installed, Installed::Updated);
+        assert_eq!(config["theme"], "dark");
+        assert_eq!(config["servers"]["other"], json!({"command": "other"}));
+        assert_eq!(
+            config["servers"]["tools"],
+            json!({
+                "type": "stdio",
+                "command": "/usr/local/bin/mcp-serve",
+                "args": ["serve", "/srv/tools"],
+                "env": {"TOKEN": "x"},
+            })
+        );
+        let (_, installed) = merge(Client::Vscode, config, &entry()).unwrap();
+        assert_eq!(installed, Installed::Unchanged);
+
+        assert!(merge(Client::Cursor, json!([]), &entry()).is_err());
+        assert!(merge(Client::Cursor, json!({"mcpServers": []}), &entry()).is_err());
+    }
+
+    #[test]
+    fn test_install() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("Claude").join("claude_desktop_config.json");
+
+        let (_, installed) = install(Client::ClaudeDesktop, &path, &entry(), true).unwrap();
+        assert_eq!(installed, Installed::Added);
+        assert!(!path.exists());
+
+        install(Client::ClaudeDesktop, &path, &entry(), false).unwrap();
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["mcpServers"]["tools"]["args"][1], "/srv/tools");
+
+        let moved = ServerEntry {
+            args: vec!["serve".to_string(), "/srv/other".to_string()],
+            ..entry()
+        };
+        let (_, installed) = install(Client::ClaudeDesktop, &path, &moved, false).unwrap();
+        assert_eq!(installed, Installed::Updated);
+        let backup = fs::read_to_string(dir.path().join("Claude/claude_desktop_config.json.bak"));
+        assert!(backup.unwrap().contains("/srv/tools"));
+
+        fs::write(&path, "{ // comments\n}").unwrap();
+        let error = install(Client::ClaudeDesktop, &path, &entry(), false).unwrap_err();
+        assert!(matches!(error, InstallError::Invalid { .. }), "{}", error);
+    }
+
+    #[test]
+    fn test_config_path() {
+        let var = |name: &str| match name {
+            "HOME" | "USERPROFILE" => Some(OsString::from("/home/ada")),
+            "APPDATA" => Some(OsString::from("/appdata")),
+            _ => None,
+        };
+        let cursor = Client::Cursor.config_path(var).unwrap();
+        assert_eq!(cursor, Path::new("/home/ada/.cursor/mcp.json"));
+        let claude = Client::ClaudeDesktop.config_path(var).unwrap();
+        assert!(claude.ends_with("Claude/claude_desktop_config.json"));
+        let vscode = Client::Vscode.config_path(var).unwrap();
+        assert!(vscode.ends_with("Code/User/mcp.json"));
+        if cfg!(target_os = "linux") {
+            assert_eq!(vscode, Path::new("/home/ada/.config/Code/User/mcp.json"));
+        }
+
+        assert_eq!(Client::Cursor.config_path(|_| None), None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Summaries of the tools a directory would serve, for `mcp-serve list`.
+//!
+//! Each tool found is summarized as a [`ToolSummary`]: its name, where its
+//! definition comes from, its description, and the errors that keep it
+//! from being served, if any. The summaries are rendered as a table for
+//! people, or as JSON or YAML for scripts:
+//!
+//! ```text
+//! NAME           SOURCE    PATH           DESCRIPTION
+//! create_ticket  sidecar   create-ticket  Creates a new feature ticket
+//! deploy         embedded  deploy.sh
+//!   error: input.template: unknown placeholder `{{target}}`
+//! ```
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::registry::{RegisteredTool, ToolBackend};
+use crate::tool_discovery::{DiscoveredTool, MetadataSource};
+
+/// How `list` writes its summaries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListFormat {
+    /// Aligned columns, for reading
+    #[default]
+    Table,
+
+    /// A JSON array of summaries
+    Json,
+
+    /// A YAML sequence of summaries
+    Yaml,
+}
+
+/// Where a tool's definition comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefinitionSource {
+    /// A sidecar file next to the executable
+    Sidecar,
+
+    /// The executable's leading comment block
+    Embedded,
+
+    /// An entry of a `tools.yaml` manifest
+    Manifest,
+
+    /// Code embedding the server
+    Native,
+}
+
+impl DefinitionSource {
+    fn of(metadata_source: &MetadataSource) -> Self {
+        match metadata_source {
+            MetadataSource::Sidecar(_) => DefinitionSource::Sidecar,
+            MetadataSource::Embedded => DefinitionSource::Embedded,
+            MetadataSource::Manifest { .. } => DefinitionSource::Manifest,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DefinitionSource::Sidecar => "sidecar",
+            DefinitionSource::Embedded => "embedded",
+            DefinitionSource::Manifest => "manifest",
+            DefinitionSource::Native => "native",
+        }
+    }
+}
+
+/// One tool, as `list` shows it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToolSummary {
+    /// The name the tool is served under, if its definition could be read
+    pub name: Option<String>,
+
+    /// Where the definition comes from
+    pub source: DefinitionSource,
+
+    /// The executable, relative to its tools directory (none for native
+    /// tools)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+
+    /// What the tool does, if its definition could be read
+    pub description: Option<String>,
+
+    /// Why the tool cannot be served; empty for tools that can
+    pub errors: Vec<String>,
+}
+
+impl ToolSummary {
+    /// Summarize a tool that was registered.
+    pub fn registered(tool: &RegisteredTool) -> Self {
+        let (source, path) = match &tool.source {
+            ToolBackend::Executable(executable) => (
+                DefinitionSource::of(&executable.metadata_source),
+                Some(executable.relative_path.clone()),
+            ),
+            ToolBackend::Native(_) => (DefinitionSource::Native, None),
+        };
+        Self {
+            name: Some(tool.definition.name.clone()),
+            source,
+            path,
+            description: Some(tool.definition.description.clone()),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Summarize a tool that could not be loaded, named if its definition
+    /// was read.
+    pub fn broken(tool: &DiscoveredTool, name: Option<String>, errors: Vec<String>) -> Self {
+        Self {
+            name,
+            source: DefinitionSource::of(&tool.metadata_source),
+            path: Some(tool.relative_path.clone()),
+            description: None,
+            errors,
+        }
+    }
+}
+
+/// Write `summaries` in `format`.
+pub fn render(summaries: &[ToolSummary], format: ListFormat) -> String {
+    match format {
+        ListFormat::Table => table(summaries),
+        ListFormat::Json => {
+            let mut json =
+                serde_json::to_string_pretty(summaries).expect("summary serialization cannot fail");
+            json.push('\n');
+            json
+        }
+        ListFormat::Yaml => {
+            serde_yaml_ng::to_string(summaries).expect("summary serialization cannot fail")
+        }
+    }
+}
+
+/// Longest description shown in a table row; the rest is cut off.
+const TABLE_DESCRIPTION_WIDTH: usize = 60;
+
+fn table(summaries: &[ToolSummary]) -> String {
+    let rows: Vec<[String; 4]> = summaries
+        .iter()
+        .map(|summary| {
+            [
+                summary.name.clone().unwrap_or_else(|| "?".to_string()),
+                summary.source.name().to_string(),
+                summary
+                    .path
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+                summary
+                    .description
+                    .as_deref()
+                    .map(first_line)
+                    .unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let header = ["NAME", "SOURCE", "PATH", "DESCRIPTION"].map(String::from);
+    let mut widths = [0; 3];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    push_row(&mut out, &header, widths);
+    for (row, summary) in rows.iter().zip(summaries) {
+        push_row(&mut out, row, widths);
+        for error in &summary.errors {
+            // Continuation lines of an error stay under it.
+            out.push_str(&format!("  error: {}\n", error.replace('\n', "\n    ")));
+        }
+    }
+    out
+}
+
+/// Write a table row, padding all but the last cell to their column's width.
+fn push_row(out: &mut String, row: &[String; 4], widths: [usize; 3]) {
+    let mut line = String::new();
+    for (cell, width) in row.iter().zip(widths) {
+        line.push_str(&format!("{:<width$}  ", cell, width = width));
+    }
+    line.push_str(&row[3]);
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+/// The first line of `text`, shortened to fit a table row.
+fn first_line(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default().trim();
+    if line.chars().count() <= TABLE_DESCRIPTION_WIDTH {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(TABLE_DESCRIPTION_WIDTH - 3).collect();
+    format!("{}...", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{DuplicatePolicy, ToolRegistry};
+    use crate::tool_discovery::ToolDefinition;
+    use serde_json::json;
+    use std::path::Path;
+
+    fn discovered(name: &str, metadata_source: MetadataSource) -> DiscoveredTool {
+        DiscoveredTool {
+            path: Path::new("/tools").join(name),
+            relative_path: name.into(),
+            metadata_source,
+            namespace: None,
+            working_dir: None,
+            docs: None,
+            defaults: Box::default(),
+            directory_configs: Box::default(),
+        }
+    }
+
+    fn summaries() -> Vec<ToolSummary> {
+        let definition = ToolDefinition::from_yaml(
+            r#"
+name: create_ticket
+description: "Creates a new feature ticket\n\nThe ticket is filed in the default project."
+input:
+  template: ""
+  schema: { type: object }
+output:
+  template: ""
+  schema: { type: object }
+"#,
+        )
+        .unwrap();
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register(
+                definition,
+                discovered(
+                    "create-ticket",
+                    MetadataSource::Sidecar("/tools/create-ticket.yaml".into()),
+                ),
+            )
+            .unwrap();
+        vec![
+            ToolSummary::registered(&registry.tools()[0]),
+            ToolSummary::broken(
+                &discovered("deploy.sh", MetadataSource::Embedded),
+                Some("deploy".to_string()),
+                vec!["input.template: unknown placeholder `{{target}}`".to_string()],
+            ),
+            ToolSummary::broken(
+                &discovered("cleanup", MetadataSource::Embedded),
+                None,
+                vec!["invalid YAML\nat line 3".to_string()],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            render(&summaries(), ListFormat::Table),
+            concat!(
+                "NAME           SOURCE    PATH           DESCRIPTION\n",
+                "create_ticket  sidecar   create-ticket  Creates a new feature ticket\n",
+                "deploy         embedded  deploy.sh\n",
+                "  error: input.template: unknown placeholder `{{target}}`\n",
+                "?              embedded  cleanup\n",
+                "  error: invalid YAML\n",
+                "    at line 3\n",
+            )
+        );
+        let shortened = first_line(&"word ".repeat(20));
+        assert_eq!(shortened.chars().count(), TABLE_DESCRIPTION_WIDTH);
+        assert!(shortened.ends_with("wo..."));
+    }
+
+    #[test]
+    fn test_structured_formats() {
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&summaries(), ListFormat::Json)).unwrap();
+        assert_eq!(
+            json[0],
+            json!({
+                "name": "create_ticket",
+                "source": "sidecar",
+                "path": "create-ticket",
+                "description": "Creates a new feature ticket\n\nThe ticket is filed in the default project.",
+                "errors": []
+            })
+        );
+        assert_eq!(json[2]["name"], serde_json::Value::Null);
+
+        let yaml: serde_json::Value =
+            serde_yaml_ng::from_str(&render(&summaries(), ListFormat::Yaml)).unwrap();
+        assert_eq!(yaml, json);
+    }
+}
@@ -0,0 +1,127 @@
+//! What mcp-serve says about its work, on stderr.
+//!
+//! Messages are [`tracing`] events, written for people to stderr: over the
+//! stdio transport, standard output carries JSON-RPC alone. How much is
+//! written is set by [`Verbosity`]:
+//!
+//! - `-q`: errors only
+//! - default: progress and warnings
+//! - `-v`: also each request, tool run, and exit status
+//! - `-vv`: also every message exchanged with clients
+//!
+//! `RUST_LOG` refines that per module, in `tracing_subscriber`'s
+//! [`EnvFilter`] syntax, e.g. `RUST_LOG=mcp_serve::executor=trace`. The
+//! level can change while the server runs ([`set_verbosity`]), when its
+//! configuration is reloaded; the format of messages stays as it started.
+
+use std::io::{self, IsTerminal};
+use std::sync::OnceLock;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Layer, Registry};
+
+/// Changes the filter of the subscriber [`init`] set, if it set one.
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// How much mcp-serve logs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::Args)]
+pub struct Verbosity {
+    /// Log more: each request and tool run (-v), and every message (-vv)
+    #[arg(
+        long,
+        short,
+        action = clap::ArgAction::Count,
+        global = true,
+        env = "MCP_SERVE_VERBOSE"
+    )]
+    pub verbose: u8,
+
+    /// Log errors only
+    #[arg(
+        long,
+        short,
+        global = true,
+        conflicts_with = "verbose",
+        env = "MCP_SERVE_QUIET"
+    )]
+    pub quiet: bool,
+}
+
+impl Verbosity {
+    /// The most detailed level logged, unless `RUST_LOG` says otherwise.
+    pub fn level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::ERROR,
+            (false, 0) => LevelFilter::INFO,
+            (false, 1) => LevelFilter::DEBUG,
+            (false, _) => LevelFilter::TRACE,
+        }
+    }
+
+    /// The filter of logged events: [`level`](Self::level), refined by the
+    /// directives of `RUST_LOG`.
+    pub fn filter(&self) -> EnvFilter {
+        EnvFilter::builder()
+            .with_default_directive(self.level().into())
+            .from_env_lossy()
+    }
+}
+
+/// Write the events `verbosity` selects to stderr, for the rest of the
+/// process. Detailed logs are timestamped and name the module they come
+/// from.
+pub fn init(verbosity: Verbosity) {
+    let (filter, handle) = reload::Layer::new(verbosity.filter());
+    let logs = fmt::layer()
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal());
+    let logs = if verbosity.verbose > 0 {
+        logs.boxed()
+    } else {
+        logs.without_time().with_target(false).boxed()
+    };
+    // Another subscriber may already be set, as in tests.
+    if tracing_subscriber::registry()
+        .with(filter)
+        .with(logs)
+        .try_init()
+        .is_ok()
+    {
+        let _ = FILTER.set(handle);
+    }
+}
+
+/// Log the events `verbosity` selects from now on.
+pub fn set_verbosity(verbosity: Verbosity) {
+    if let Some(filter) = FILTER.get() {
+        let _ = filter.reload(verbosity.filter());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        verbosity: Verbosity,
+    }
+
+    fn level(args: &[&str]) -> LevelFilter {
+        let args = std::iter::once("mcp-serve").chain(args.iter().copied());
+        Cli::parse_from(args).verbosity.level()
+    }
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(&[]), LevelFilter::INFO);
+        assert_eq!(level(&["-q"]), LevelFilter::ERROR);
+        assert_eq!(level(&["-v"]), LevelFilter::DEBUG);
+        assert_eq!(level(&["-vv"]), LevelFilter::TRACE);
+        assert_eq!(level(&["-vvv"]), LevelFilter::TRACE);
+        assert!(Cli::try_parse_from(["mcp-serve", "-q", "-v"]).is_err());
+    }
+}
@@ -1,24 +1,2157 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use minisign_verify::PublicKey;
+use std::fmt;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
+pub mod bench;
+pub mod config;
+pub mod confine;
+pub mod confirm;
+pub mod convert;
+pub mod daemon;
+pub mod definitions;
+pub mod doctor;
+pub mod executor;
+pub mod filter;
+pub mod handler;
+pub mod init;
+pub mod install;
+pub mod list;
+pub mod logging;
+pub mod openapi;
+pub mod output;
+pub mod preview;
+pub mod quote;
+pub mod redact;
+pub mod registry;
+pub mod reload;
+pub mod repl;
+pub mod run;
+pub mod server;
+pub mod shutdown;
+pub mod template;
+pub mod testing;
 pub mod tool_discovery;
+pub mod transport;
+pub mod validate;
+
+use bench::{BenchReport, CallBench, Samples};
+use config::Config;
+use confirm::ConfirmPolicy;
+use convert::ConvertFormat;
+use daemon::{Detached, PidFile, Readiness};
+use definitions::{SchemaDraft, Severity, Validator};
+use executor::Executor;
+use filter::{NamePattern, ToolFilter, ToolPolicy};
+use init::HelpDraft;
+use install::{Client, Installed, ServerEntry};
+use list::{ListFormat, ToolSummary};
+use logging::Verbosity;
+use openapi::OpenApiImport;
+use registry::{DuplicatePolicy, ToolRegistry};
+use reload::Reload;
+use server::McpServer;
+use shutdown::Shutdown;
+use tool_discovery::execution::parse_duration;
+use tool_discovery::{permissions, pin, signature};
+use tool_discovery::{
+    DirectoryScanner, DiscoveredTool, ExecutionConfig, Loader, Lockfile, MetadataSource, ScanIndex,
+    ScanReport, SourceCache, ToolDefinition, ToolEvent, ToolRoot, ToolWatcher,
+};
+use transport::{HttpPolicy, HttpTransport, Transport, DEFAULT_HTTP_ADDRESS};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Directory to discover tools from
-    #[arg(default_value = ".")]
-    tools_dir: PathBuf,
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[command(flatten)]
+    verbosity: Verbosity,
+
+    /// Read options from this YAML file; options given as flags or
+    /// environment variables take precedence
+    #[arg(long, global = true, value_name = "FILE", env = "MCP_SERVE_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Keep running and report tools as they are added, changed, or removed
+    #[arg(long, env = "MCP_SERVE_WATCH")]
+    watch: bool,
+
+    /// Check every tool definition, report problems, and exit with failure
+    /// if any tool is broken (implies --strict)
+    #[arg(long, env = "MCP_SERVE_LINT")]
+    lint: bool,
+
+    /// List the files that were skipped during scanning, and why
+    #[arg(long, env = "MCP_SERVE_SHOW_SKIPPED")]
+    show_skipped: bool,
+}
+
+/// Where tools are discovered, and which of them are loaded.
+#[derive(Args, Clone, PartialEq)]
+struct ScanArgs {
+    /// Directories to discover tools from, each optionally prefixed with a
+    /// namespace as `NAMESPACE=DIR` (with --from-path, the first directory
+    /// holds their definitions). A git repository URL, or a .tar.gz or .zip
+    /// archive (path or URL, optionally suffixed with `#sha256=HEX`), may be
+    /// given instead of a directory.
+    #[arg(
+        default_value = ".",
+        value_delimiter = ',',
+        value_name = "DIR",
+        env = "MCP_SERVE_DIRS"
+    )]
+    tools_dirs: Vec<ToolRoot>,
+
+    /// Prefix tool names with the name of their directory (`ops/restart`),
+    /// for directories without an explicit namespace
+    #[arg(long, env = "MCP_SERVE_NAMESPACE_DIRS")]
+    namespace_dirs: bool,
+
+    /// Resolve these comma-separated tool names on $PATH instead of scanning
+    /// the directory
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "NAMES",
+        env = "MCP_SERVE_FROM_PATH"
+    )]
+    from_path: Vec<String>,
+
+    /// Also treat files starting with a `#!` shebang as tools, even without
+    /// the executable bit
+    #[arg(long, env = "MCP_SERVE_DETECT_SHEBANG")]
+    detect_shebang: bool,
+
+    /// Only scan the top level of each directory
+    #[arg(long, env = "MCP_SERVE_NO_RECURSIVE")]
+    no_recursive: bool,
+
+    /// Descend into symlinked directories
+    #[arg(long, env = "MCP_SERVE_FOLLOW_SYMLINKS")]
+    follow_symlinks: bool,
+
+    /// Also scan dotfiles and dot-directories such as .git
+    #[arg(long, env = "MCP_SERVE_INCLUDE_HIDDEN")]
+    include_hidden: bool,
+
+    /// Maximum number of directory levels to descend into
+    #[arg(long, value_name = "DEPTH", env = "MCP_SERVE_MAX_DEPTH")]
+    max_depth: Option<usize>,
+
+    /// Directory to cache fetched git repositories and tarballs in
+    #[arg(long, value_name = "DIR", env = "MCP_SERVE_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// What to do when two tools declare the same name
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = DuplicatePolicy::Error,
+        value_name = "POLICY",
+        env = "MCP_SERVE_ON_DUPLICATE",
+    )]
+    on_duplicate: DuplicatePolicy,
+
+    /// Cache scan results in this file, and reuse them while the tool
+    /// directories are unchanged
+    #[arg(long, value_name = "FILE", env = "MCP_SERVE_INDEX")]
+    index: Option<PathBuf>,
+
+    /// JSON Schema draft that tool schemas must conform to
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SchemaDraft::Draft202012,
+        value_name = "DRAFT",
+        env = "MCP_SERVE_SCHEMA_DRAFT",
+    )]
+    schema_draft: SchemaDraft,
+
+    /// Leave out tools marked as deprecated
+    #[arg(long, env = "MCP_SERVE_HIDE_DEPRECATED")]
+    hide_deprecated: bool,
+
+    /// Only serve tools with at least one of these comma-separated tags (or
+    /// categories)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "TAGS",
+        env = "MCP_SERVE_TAGS"
+    )]
+    tags: Vec<String>,
+
+    /// Only serve the tools with these comma-separated names, which may use
+    /// `*` and `?` wildcards (`deploy,ops/*`)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "NAMES",
+        env = "MCP_SERVE_ONLY"
+    )]
+    only: Vec<NamePattern>,
+
+    /// Leave out the tools with these comma-separated names, which may use
+    /// `*` and `?` wildcards
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "PATTERNS",
+        env = "MCP_SERVE_EXCLUDE"
+    )]
+    exclude: Vec<NamePattern>,
+
+    /// Reject definitions with fields the format does not define, such as
+    /// misspelled keys, instead of ignoring them
+    #[arg(long, env = "MCP_SERVE_STRICT")]
+    strict: bool,
+
+    /// Run tools in a clean environment, keeping only the variables with
+    /// these comma-separated names, which may use `*` and `?` wildcards
+    /// (`HOME,PATH,AWS_*`)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "NAMES",
+        env = "MCP_SERVE_ENV_PASSTHROUGH"
+    )]
+    env_passthrough: Option<Vec<NamePattern>>,
+
+    /// Pin executables to the SHA-256 digests this file lists, in the
+    /// format `sha256sum` writes, and refuse those it does not list
+    #[arg(long, value_name = "FILE", env = "MCP_SERVE_LOCKFILE")]
+    lockfile: Option<PathBuf>,
+
+    /// Serve and run tools whose executable does not match its pinned
+    /// digest, or is not pinned by the --lockfile, with a warning
+    #[arg(long, env = "MCP_SERVE_ALLOW_UNPINNED")]
+    allow_unpinned: bool,
+
+    /// Which tools the configuration file permits to be served at all
+    #[arg(skip)]
+    policy: ToolPolicy,
+
+    /// Keys every file of a tool must be signed with, from the
+    /// configuration file
+    #[arg(skip)]
+    trusted_keys: Option<Vec<PublicKey>>,
+
+    /// Refuse executables others can change, or that are setuid, from the
+    /// configuration file
+    #[arg(skip)]
+    strict_permissions: bool,
+
+    /// Execution settings of every tool, from the configuration file
+    #[arg(skip)]
+    defaults: ExecutionConfig,
+
+    /// Only serve tools declared read-only, for `serve --read-only`
+    #[arg(skip)]
+    read_only: bool,
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum Commands {
+    /// Serve the discovered tools to MCP clients
+    Serve(ServeArgs),
+
+    /// Show the tools that would be served, and those that cannot be
+    List(ListArgs),
+
+    /// Check every tool definition and report problems by file and line,
+    /// exiting with failure if any tool is broken
+    Validate(ValidateArgs),
+
+    /// Print a tool exactly as it is sent to MCP clients
+    Inspect(InspectArgs),
+
+    /// Call a tool with JSON arguments and print its result
+    Run(RunArgs),
+
+    /// Call tools interactively, prompting for each of their inputs
+    Repl(ReplArgs),
+
+    /// Run the test cases declared in tool definitions, exiting with
+    /// failure if any fails
+    Test(TestArgs),
+
+    /// Measure how long scanning and parsing the tools takes, and
+    /// optionally calling one, over several runs
+    Bench(BenchArgs),
+
+    /// Check that the tools directories can be read and their tools can run
+    Doctor(DoctorArgs),
+
+    /// Print the JSON Schema of tool definitions, for editor validation and
+    /// completion
+    Schema(SchemaArgs),
+
+    /// Create an example tools directory, or a definition for an existing
+    /// executable
+    Init(InitArgs),
+
+    /// Generate tools for the operations of an OpenAPI 3 document
+    Import(ImportArgs),
+
+    /// Convert a sidecar to another format (or to the MCP tool clients
+    /// receive), or rewrite every sidecar of a directory
+    Convert(ConvertArgs),
+
+    /// Add this server to the configuration of an MCP client, such as
+    /// Claude Desktop
+    Install(InstallArgs),
+
+    /// Check a configuration file, or print the JSON Schema of the format
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Args, Clone, PartialEq)]
+struct ServeArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// How clients connect: over standard input and output, or over HTTP
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Transport::Stdio,
+        value_name = "TRANSPORT",
+        env = "MCP_SERVE_TRANSPORT",
+    )]
+    transport: Transport,
+
+    /// Address the HTTP transport listens on
+    #[arg(long, default_value = DEFAULT_HTTP_ADDRESS, value_name = "ADDR", env = "MCP_SERVE_BIND")]
+    bind: String,
+
+    /// Run at most this many tool calls at once, across all tools
+    #[arg(long, value_name = "N", env = "MCP_SERVE_MAX_CONCURRENCY")]
+    max_concurrency: Option<NonZeroUsize>,
+
+    /// Refuse HTTP requests with a larger body, in bytes
+    #[arg(long, value_name = "BYTES", env = "MCP_SERVE_MAX_REQUEST_SIZE")]
+    max_request_size: Option<u64>,
+
+    /// Accept HTTP requests from web pages on this origin too
+    /// (`https://app.example.com`); may be repeated, or comma-separated
+    #[arg(
+        long = "allow-origin",
+        value_delimiter = ',',
+        value_name = "ORIGIN",
+        env = "MCP_SERVE_ALLOW_ORIGIN"
+    )]
+    allowed_origins: Vec<String>,
+
+    /// When stopped, wait this long for running tool calls to finish
+    /// before killing them (`30s`, `2m`)
+    #[arg(
+        long,
+        value_parser = parse_duration,
+        default_value = "10s",
+        value_name = "DURATION",
+        env = "MCP_SERVE_DRAIN_TIMEOUT",
+    )]
+    drain_timeout: Duration,
+
+    /// Run in the background once listening (HTTP transport only; Unix)
+    #[arg(long, env = "MCP_SERVE_DAEMON")]
+    daemon: bool,
+
+    /// Write the server's process ID to this file while it runs
+    #[arg(long, value_name = "FILE", env = "MCP_SERVE_PID_FILE")]
+    pid_file: Option<PathBuf>,
+
+    /// Where the daemon writes its logs (default: nowhere)
+    #[arg(long, value_name = "FILE", env = "MCP_SERVE_LOG_FILE")]
+    log_file: Option<PathBuf>,
+
+    /// Ask the user before running a tool marked `destructiveHint: true`:
+    /// through the client if it supports elicitation, or else on the
+    /// server's terminal; calls nobody confirms are refused
+    #[arg(long, env = "MCP_SERVE_CONFIRM_DESTRUCTIVE")]
+    confirm_destructive: bool,
+
+    /// Run these tools (names or patterns) without confirmation, even if
+    /// destructive; comma-separated
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "NAMES",
+        env = "MCP_SERVE_NO_CONFIRM"
+    )]
+    no_confirm: Vec<NamePattern>,
+
+    /// Serve only tools annotated `readOnlyHint: true`; calls of any other
+    /// tool are refused as calls of unknown tools
+    #[arg(long, env = "MCP_SERVE_READ_ONLY")]
+    read_only: bool,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// How to write the list
+    #[arg(long, value_enum, default_value_t = ListFormat::Table, value_name = "FORMAT")]
+    format: ListFormat,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// How to write the report
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, value_name = "FORMAT")]
+    format: OutputFormat,
+}
+
+/// How a command writes its results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// For reading
+    #[default]
+    Text,
+
+    /// A JSON document, for scripts
+    Json,
+}
+
+#[derive(Args)]
+struct InspectArgs {
+    /// The name (or alias) clients call the tool by
+    #[arg(value_name = "TOOL")]
+    name: String,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// The name (or alias) clients call the tool by
+    #[arg(value_name = "TOOL")]
+    name: String,
+
+    /// The arguments of the call, as a JSON object
+    #[arg(long, default_value = "{}", value_name = "JSON")]
+    args: String,
+
+    /// How to write the result: as text, or as the `tools/call` result
+    /// clients receive
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, value_name = "FORMAT")]
+    format: OutputFormat,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Args)]
+struct ReplArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Args)]
+struct TestArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// How many times to measure each step
+    #[arg(long, default_value = "10", value_name = "N")]
+    runs: NonZeroUsize,
+
+    /// Also call this tool once per run
+    #[arg(long, value_name = "TOOL")]
+    call: Option<String>,
+
+    /// The arguments of the calls, as a JSON object
+    #[arg(long, default_value = "{}", value_name = "JSON", requires = "call")]
+    args: String,
+
+    /// How to write the report
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, value_name = "FORMAT")]
+    format: OutputFormat,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Args)]
+struct DoctorArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+}
 
+#[derive(Args)]
+struct SchemaArgs {
+    /// Describe `tools.yaml` manifests instead of sidecar files and embedded
+    /// definitions
+    #[arg(long)]
+    manifest: bool,
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct InitArgs {
+    #[command(subcommand)]
+    command: Option<InitCommand>,
+
+    /// The directory to create the example tools in
+    #[arg(default_value = ".", value_name = "DIR")]
+    dir: PathBuf,
+
+    /// Instead, run this executable with --help and draft a sidecar
+    /// definition from its output
+    #[arg(long, value_name = "EXECUTABLE")]
+    from_help: Option<PathBuf>,
+
+    /// Where to write the --from-help definition (default: next to the
+    /// executable, with `.yaml` appended)
+    #[arg(long, short, value_name = "FILE", requires = "from_help")]
+    output: Option<PathBuf>,
+
+    /// Replace files that already exist
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Subcommand)]
+enum InitCommand {
+    /// Write a commented definition skeleton next to an existing executable
+    Tool(InitToolArgs),
+}
+
+#[derive(Args)]
+struct InitToolArgs {
+    /// The executable to define
+    #[arg(value_name = "EXECUTABLE")]
+    executable: PathBuf,
+
+    /// Where to write the definition (default: next to the executable, with
+    /// `.yaml` appended)
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Replace the definition file if it already exists
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// The OpenAPI document (YAML or JSON)
+    #[arg(value_name = "SPEC")]
+    spec: PathBuf,
+
+    /// The directory to write the wrapper script and its sidecar to
+    #[arg(long, short, value_name = "DIR", default_value = ".")]
+    output: PathBuf,
+
+    /// Send requests here instead of the document's first server
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Replace the generated files if they already exist
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// A sidecar file, or a directory whose sidecars to rewrite in place
+    #[arg(value_name = "PATH")]
+    path: PathBuf,
+
+    /// The format to write
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    to: ConvertFormat,
+
+    /// Write the converted sidecar here instead of to standard output
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Replace files that already exist
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct InstallArgs {
+    /// The client to register the server with
+    #[arg(long, value_enum, value_name = "CLIENT")]
+    client: Client,
+
+    /// The tools directories the client serves
+    #[arg(default_value = ".", value_name = "DIR")]
+    tools_dirs: Vec<PathBuf>,
+
+    /// The name the client lists the server under
+    #[arg(long, default_value = "mcp-serve")]
+    name: String,
+
+    /// Update this client configuration file instead of the client's
+    /// default one
+    #[arg(long, value_name = "FILE")]
+    client_config: Option<PathBuf>,
+
+    /// Print the updated client configuration instead of writing it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Report unknown options, options that contradict each other, and
+    /// tools directories that do not exist, exiting with failure on errors
+    Check(ConfigCheckArgs),
+
+    /// Print the JSON Schema of configuration files, for editor validation
+    /// and completion
+    Schema,
+}
+
+#[derive(Args)]
+struct ConfigCheckArgs {
+    /// The configuration file to check (default: the --config file)
+    #[arg(value_name = "FILE")]
+    file: Option<PathBuf>,
+}
+
+/// Set each of `fields` of `args` to the value `config` gives it, unless it
+/// was given on the command line.
+macro_rules! configure {
+    ($args:expr, $config:expr, $matches:expr, [$($field:ident),* $(,)?]) => {
+        $(
+            if let Some(value) = &$config.$field {
+                if !given($matches, stringify!($field)) {
+                    $args.$field = value.clone();
+                }
+            }
+        )*
+    };
+}
+
+/// Whether the option `id` was given, rather than left to its default.
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+impl Cli {
+    /// Take the options `config` sets that were not given as flags.
+    fn configure(&mut self, config: &Config, matches: &ArgMatches) {
+        let matches = matches.subcommand().map_or(matches, |(_, matches)| matches);
+        // -v and -q given as flags replace both settings of the file.
+        if !given(matches, "verbose") && !given(matches, "quiet") {
+            configure!(self.verbosity, config, matches, [verbose, quiet]);
+        }
+        let scan = match &mut self.command {
+            None => &mut self.scan,
+            Some(Commands::Serve(args)) => {
+                args.configure(config, matches);
+                &mut args.scan
+            }
+            Some(Commands::List(args)) => &mut args.scan,
+            Some(Commands::Validate(args)) => &mut args.scan,
+            Some(Commands::Inspect(args)) => &mut args.scan,
+            Some(Commands::Run(args)) => &mut args.scan,
+            Some(Commands::Repl(args)) => &mut args.scan,
+            Some(Commands::Test(args)) => &mut args.scan,
+            Some(Commands::Bench(args)) => &mut args.scan,
+            Some(Commands::Doctor(args)) => &mut args.scan,
+            Some(
+                Commands::Schema(_)
+                | Commands::Init(_)
+                | Commands::Import(_)
+                | Commands::Convert(_)
+                | Commands::Install(_)
+                | Commands::Config(_),
+            ) => return,
+        };
+        scan.configure(config, matches);
+    }
+}
+
+impl ScanArgs {
+    /// Which of the tools found are served.
+    fn filter(&self) -> ToolFilter {
+        ToolFilter {
+            only: self.only.clone(),
+            exclude: self.exclude.clone(),
+            tags: self.tags.clone(),
+            hide_deprecated: self.hide_deprecated,
+            read_only: self.read_only,
+        }
+    }
+
+    fn configure(&mut self, config: &Config, matches: &ArgMatches) {
+        configure!(
+            self,
+            config,
+            matches,
+            [
+                namespace_dirs,
+                from_path,
+                detect_shebang,
+                follow_symlinks,
+                include_hidden,
+                on_duplicate,
+                schema_draft,
+                hide_deprecated,
+                tags,
+                only,
+                exclude,
+                strict,
+                allow_unpinned,
+            ]
+        );
+        if let Some(dirs) = &config.dirs {
+            if !given(matches, "tools_dirs") {
+                self.tools_dirs = dirs.clone();
+            }
+        }
+        if let Some(recursive) = config.recursive {
+            if !given(matches, "no_recursive") {
+                self.no_recursive = !recursive;
+            }
+        }
+        self.max_depth = self.max_depth.or(config.max_depth);
+        self.cache_dir = self.cache_dir.take().or_else(|| config.cache_dir.clone());
+        self.index = self.index.take().or_else(|| config.index.clone());
+        self.lockfile = self.lockfile.take().or_else(|| config.lockfile.clone());
+        self.env_passthrough = self
+            .env_passthrough
+            .take()
+            .or_else(|| config.env_passthrough.clone());
+        self.policy = config.policy();
+        self.trusted_keys.clone_from(&config.security.trusted_keys);
+        self.strict_permissions = config.security.strict == Some(true);
+        self.defaults = config.defaults.execution();
+    }
+}
+
+impl ServeArgs {
+    fn configure(&mut self, config: &Config, matches: &ArgMatches) {
+        configure!(
+            self,
+            config,
+            matches,
+            [
+                transport,
+                bind,
+                drain_timeout,
+                daemon,
+                confirm_destructive,
+                no_confirm,
+                read_only
+            ]
+        );
+        configure!(self, config.security, matches, [allowed_origins]);
+        self.max_concurrency = self.max_concurrency.or(config.limits.max_concurrency);
+        self.max_request_size = self.max_request_size.or(config.limits.max_request_size);
+        self.pid_file = self.pid_file.take().or_else(|| config.pid_file.clone());
+        self.log_file = self.log_file.take().or_else(|| config.log_file.clone());
+    }
+
+    /// Continue as a daemon, if asked to. Returns the daemon's side of
+    /// detaching, or the status the command exits with in the process that
+    /// was started.
+    fn detach(&self) -> Result<Option<Readiness>, ExitCode> {
+        if !self.daemon {
+            return Ok(None);
+        }
+        if self.transport != Transport::Http {
+            eprintln!("Error: --daemon needs --transport http, as a daemon has no standard input");
+            return Err(ExitCode::FAILURE);
+        }
+        match daemon::detach(self.log_file.as_deref()) {
+            Ok(Detached::Daemon(readiness)) => Ok(Some(readiness)),
+            Ok(Detached::Parent { pid, serving: true }) => {
+                println!("Serving in the background (pid {})", pid);
+                Err(ExitCode::SUCCESS)
+            }
+            Ok(Detached::Parent { serving: false, .. }) => {
+                match &self.log_file {
+                    Some(path) => eprintln!(
+                        "Error: the daemon exited before serving; see {}",
+                        path.display()
+                    ),
+                    None => eprintln!(
+                        "Error: the daemon exited before serving; pass --log-file to see why"
+                    ),
+                }
+                Err(ExitCode::FAILURE)
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                Err(ExitCode::FAILURE)
+            }
+        }
+    }
+}
+
+/// Options every subcommand takes, wherever they are given.
+const GLOBAL_OPTIONS: &[&str] = &["verbose", "quiet", "config"];
+
+/// Parse the command line `args`. Global options may come before a
+/// subcommand (`mcp-serve -v list`); the options of the top-level scan may
+/// not, as the subcommand would ignore them.
+fn parse<I, T>(args: I) -> Result<(Cli, ArgMatches), clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let mut command = Cli::command();
+    let matches = command.try_get_matches_from_mut(args)?;
+    if let Some((name, _)) = matches.subcommand() {
+        let misplaced = command.get_arguments().find(|arg| {
+            let id = arg.get_id().as_str();
+            !GLOBAL_OPTIONS.contains(&id)
+                && matches.value_source(id) == Some(ValueSource::CommandLine)
+        });
+        if let Some(arg) = misplaced {
+            let option = match arg.get_long() {
+                Some(long) => format!("--{}", long),
+                None => arg.to_string(),
+            };
+            return Err(command.error(
+                clap::error::ErrorKind::ArgumentConflict,
+                format!(
+                    "{} cannot be used with the `{}` subcommand (give the subcommand's options after its name)",
+                    option, name
+                ),
+            ));
+        }
+    }
+    let cli = Cli::from_arg_matches(&matches)?;
+    Ok((cli, matches))
+}
+
+fn main() -> ExitCode {
+    let (mut cli, matches) = parse(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    // Checking a configuration reports its problems, rather than failing on
+    // them before it starts.
+    if let Some(Commands::Config(command)) = cli.command {
+        return config(command, cli.config.as_deref());
+    }
+    let mut unknown_keys = Vec::new();
+    if let Some(path) = &cli.config {
+        let config = match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        unknown_keys = config.unknown_keys.clone();
+        cli.configure(&config, &matches);
+    }
+    let reconfigure = match (&cli.command, &cli.config) {
+        (Some(Commands::Serve(_)), Some(path)) => Some(Reconfigure {
+            path: path.clone(),
+            matches: matches.clone(),
+            verbosity: cli.verbosity,
+        }),
+        _ => None,
+    };
+    // A daemon detaches before logging starts, so that its logs are written
+    // where it was asked to write them.
+    let mut readiness = None;
+    if let Some(Commands::Serve(args)) = &cli.command {
+        readiness = match args.detach() {
+            Ok(readiness) => readiness,
+            Err(code) => return code,
+        };
+    }
+    logging::init(cli.verbosity);
+    if let Some(path) = &cli.config {
+        for key in &unknown_keys {
+            tracing::warn!("{}: unknown option `{}`", path.display(), key);
+        }
+    }
+    match cli.command {
+        Some(Commands::Serve(args)) => return serve(args, readiness, reconfigure),
+        Some(Commands::List(args)) => return list(args),
+        Some(Commands::Validate(args)) => return validate(args),
+        Some(Commands::Inspect(args)) => return inspect(args),
+        Some(Commands::Run(args)) => return run(args),
+        Some(Commands::Repl(args)) => return repl(args),
+        Some(Commands::Test(args)) => return test(args),
+        Some(Commands::Bench(args)) => return bench(args),
+        Some(Commands::Doctor(args)) => return doctor(args),
+        Some(Commands::Schema(args)) => return schema(args),
+        Some(Commands::Init(args)) => return init(args),
+        Some(Commands::Import(args)) => return import(args),
+        Some(Commands::Convert(args)) => return convert(args),
+        Some(Commands::Install(args)) => return install(args, cli.config.as_deref()),
+        Some(Commands::Config(_)) => unreachable!("configuration commands run before loading"),
+        None => {}
+    }
+    let Discovery {
+        registry,
+        report,
+        failures,
+        scanner,
+        roots,
+    } = match discover(&cli.scan, cli.lint, |message| println!("{}", message)) {
+        Ok(discovery) => discovery,
+        Err(code) => return code,
+    };
+    report_failures(&failures);
+    let failed = failures.len();
+
+    for registered in registry.tools() {
+        let name = match &registered.definition.version {
+            Some(version) => format!("{}@{}", registered.definition.name, version),
+            None => registered.definition.name.clone(),
+        };
+        let Some(tool) = registered.executable() else {
+            println!("  {} (native)", name);
+            continue;
+        };
+        let location = tool.relative_path.display();
+        match &tool.metadata_source {
+            MetadataSource::Sidecar(sidecar) => {
+                println!("  {} {} (sidecar: {})", name, location, sidecar.display())
+            }
+            MetadataSource::Embedded => println!("  {} {} (embedded)", name, location),
+            MetadataSource::Manifest { path, .. } => {
+                println!("  {} {} (manifest: {})", name, location, path.display())
+            }
+        }
+    }
+    if cli.show_skipped {
+        for entry in &report.skipped {
+            println!("  skipped {} ({})", entry.path.display(), entry.reason);
+        }
+    }
+    println!("{}", report);
+    if failed > 0 {
+        println!("{} tool(s) could not be loaded", failed);
+    }
+
+    if cli.lint {
+        return if failed > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        };
+    }
+
+    if cli.watch {
+        return watch(scanner, roots);
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// The tools found and loaded for a command.
+struct Discovery {
+    registry: ToolRegistry,
+    report: ScanReport,
+    failures: Vec<LoadFailure>,
+    scanner: DirectoryScanner,
+    roots: Vec<ToolRoot>,
+}
+
+/// A tool that was found but could not be loaded.
+struct LoadFailure {
+    tool: DiscoveredTool,
+    /// The tool in messages: its path, and its name if the file declares
+    /// several tools
+    label: String,
+    /// The tool's name, if its definition could be read
+    name: Option<String>,
+    errors: Vec<String>,
+}
+
+/// Log why each of `failures` is skipped.
+fn report_failures(failures: &[LoadFailure]) {
+    for failure in failures {
+        for error in &failure.errors {
+            tracing::warn!("skipping {}: {}", failure.label, error);
+        }
+    }
+}
+
+/// The tools found for a command, not yet loaded.
+struct Scan {
+    /// Each tool, with its definitions if the index already holds them
+    tools: Vec<(DiscoveredTool, Option<Vec<ToolDefinition>>)>,
+    report: ScanReport,
+    scanner: DirectoryScanner,
+    roots: Vec<ToolRoot>,
+}
+
+/// Find the tools `args` select, reporting progress through `progress` and
+/// errors on stderr.
+fn scan(args: &ScanArgs, progress: fn(fmt::Arguments)) -> Result<Scan, ExitCode> {
+    let scanner = DirectoryScanner::builder()
+        .recursive(!args.no_recursive)
+        .follow_symlinks(args.follow_symlinks)
+        .include_hidden(args.include_hidden)
+        .detect_shebangs(args.detect_shebang)
+        .defaults(ExecutionConfig {
+            env_passthrough: args.env_passthrough.clone(),
+            ..args.defaults.clone()
+        });
+    let scanner = match args.max_depth {
+        Some(depth) => scanner.max_depth(depth),
+        None => scanner,
+    }
+    .build();
+    let cache = args
+        .cache_dir
+        .clone()
+        .map(SourceCache::new)
+        .unwrap_or_default();
+
+    let mut tools = Vec::new();
+    let mut report = ScanReport::default();
+    let mut roots = Vec::new();
+    if args.from_path.is_empty() {
+        for root in &args.tools_dirs {
+            let root = if args.namespace_dirs {
+                root.clone().with_derived_namespace()
+            } else {
+                root.clone()
+            };
+            let source = root.source();
+            if source.is_remote() {
+                progress(format_args!("Fetching tools from: {}", source));
+            }
+            let root = match root.fetch(&cache) {
+                Ok(root) => root,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return Err(ExitCode::FAILURE);
+                }
+            };
+            roots.push(root);
+        }
+
+        if let Some(index_path) = &args.index {
+            match ScanIndex::load_or_build(index_path, &scanner, &roots) {
+                Ok((index, rebuilt)) => {
+                    if rebuilt {
+                        progress(format_args!("Rebuilt tool index: {}", index_path.display()));
+                    } else {
+                        progress(format_args!("Loaded tool index: {}", index_path.display()));
+                    }
+                    report = index.report();
+                    tools.extend(
+                        index
+                            .tools()
+                            .iter()
+                            .map(|indexed| (indexed.tool.clone(), indexed.definitions.clone())),
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return Err(ExitCode::FAILURE);
+                }
+            }
+        } else {
+            for root in &roots {
+                progress(format_args!(
+                    "Discovering tools from directory: {}",
+                    root.path.display()
+                ));
+                match scanner.scan_root_with_report(root) {
+                    Ok((found, root_report)) => {
+                        tools.extend(found.into_iter().map(|tool| (tool, None)));
+                        report.merge(root_report);
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        return Err(ExitCode::FAILURE);
+                    }
+                }
+            }
+        }
+    } else {
+        let definitions_dir = match args.tools_dirs[0].clone().fetch(&cache) {
+            Ok(root) => root.path,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return Err(ExitCode::FAILURE);
+            }
+        };
+        let definitions_dir = &definitions_dir;
+        progress(format_args!(
+            "Resolving tools on PATH with definitions from: {}",
+            definitions_dir.display()
+        ));
+        match scanner.resolve_on_path(&args.from_path, definitions_dir) {
+            Ok(found) => {
+                report.count_tools(&found);
+                tools.extend(found.into_iter().map(|tool| (tool, None)));
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return Err(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    Ok(Scan {
+        tools,
+        report,
+        scanner,
+        roots,
+    })
+}
+
+/// Discover, load, and register the tools `args` select, reporting progress
+/// through `progress`, warnings to the log, and errors on stderr. With
+/// `lint`, definitions are read strictly and their warnings are reported
+/// too.
+fn discover(
+    args: &ScanArgs,
+    lint: bool,
+    progress: fn(fmt::Arguments),
+) -> Result<Discovery, ExitCode> {
+    let Scan {
+        tools,
+        report,
+        scanner,
+        roots,
+    } = scan(args, progress)?;
+    let lockfile = match args.lockfile.as_deref().map(Lockfile::load).transpose() {
+        Ok(lockfile) => lockfile,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return Err(ExitCode::FAILURE);
+        }
+    };
+    let strict = args.strict || lint;
+    let mut loader = Loader::new().with_strict(strict);
+    if let Some(keys) = &args.trusted_keys {
+        loader = loader.with_trusted_keys(keys.clone());
+    }
+    let validator = Validator::new().with_draft(args.schema_draft);
+    let mut registry = ToolRegistry::new(args.on_duplicate);
+    let filter = args.filter();
+    let mut failures = Vec::new();
+    // Definitions already parsed into the index are reused, unless they
+    // must be re-read strictly.
+    for (tool, indexed) in tools {
+        // Whatever the index says, the files are authenticated again.
+        if let Some(keys) = &args.trusted_keys {
+            if let Err(e) = signature::verify_tool(&tool, keys) {
+                failures.push(LoadFailure {
+                    label: tool.path.display().to_string(),
+                    name: None,
+                    errors: vec![e.to_string()],
+                    tool,
+                });
+                continue;
+            }
+        }
+        if let Err(e) = permissions::check(&tool.path) {
+            if args.strict_permissions {
+                failures.push(LoadFailure {
+                    label: tool.path.display().to_string(),
+                    name: None,
+                    errors: vec![e.to_string()],
+                    tool,
+                });
+                continue;
+            }
+            tracing::warn!("{}; serving it anyway (`security.strict` refuses it)", e);
+        }
+        // The index does not record which schema files a definition refers
+        // to, so their signatures are only checked by loading it again.
+        let indexed = indexed.filter(|_| !strict && args.trusted_keys.is_none());
+        let definitions = match indexed.map_or_else(|| loader.load(&tool), Ok) {
+            Ok(definitions) => definitions,
+            Err(e) => {
+                failures.push(LoadFailure {
+                    label: tool.path.display().to_string(),
+                    name: None,
+                    errors: vec![e.to_string()],
+                    tool,
+                });
+                continue;
+            }
+        };
+        // Name the tool in messages when its file declares several.
+        let several = definitions.len() > 1;
+        for mut definition in definitions {
+            // Tools left out are not checked either.
+            let name = tool.qualified_name(&definition.name);
+            if !args.policy.permits(&name) {
+                tracing::info!("Not serving {}: not permitted by the configuration", name);
+                continue;
+            }
+            if !filter.admits(&name, &definition) {
+                continue;
+            }
+            let label = if several {
+                format!("{} ({})", tool.path.display(), definition.name)
+            } else {
+                tool.path.display().to_string()
+            };
+            let issues = validator.validate(&definition);
+            let (errors, warnings): (Vec<_>, Vec<_>) =
+                issues.iter().partition(|issue| issue.is_error());
+            if lint {
+                for issue in warnings {
+                    eprintln!("Warning: {}: {}", label, issue);
+                }
+            }
+            if !errors.is_empty() {
+                failures.push(LoadFailure {
+                    label,
+                    name: Some(name),
+                    errors: errors.iter().map(ToString::to_string).collect(),
+                    tool: tool.clone(),
+                });
+                continue;
+            }
+            if let Err(e) = pin::check(&tool.path, &mut definition, lockfile.as_ref()) {
+                if !args.allow_unpinned {
+                    failures.push(LoadFailure {
+                        label,
+                        name: Some(name),
+                        errors: vec![e.to_string()],
+                        tool: tool.clone(),
+                    });
+                    continue;
+                }
+                tracing::warn!("{}; serving it anyway (--allow-unpinned)", e);
+            }
+            // Nor is the executable checked again before it runs.
+            if args.allow_unpinned {
+                definition.sha256 = None;
+            }
+            let path = tool.path.clone();
+            match registry.register(definition, tool.clone()) {
+                Ok(registration) => {
+                    if let Some(warning) = registration.warning(&path) {
+                        tracing::warn!("{}", warning);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return Err(ExitCode::FAILURE);
+                }
+            }
+        }
+    }
+
+    Ok(Discovery {
+        registry,
+        report,
+        failures,
+        scanner,
+        roots,
+    })
+}
+
+/// Serve the discovered tools until the client disconnects (over stdio) or
+/// the server is stopped, then let the calls in progress finish.
+/// `readiness` is told once the daemon is serving.
+fn serve(
+    mut args: ServeArgs,
+    readiness: Option<Readiness>,
+    mut reconfigure: Option<Reconfigure>,
+) -> ExitCode {
+    if args.log_file.is_some() && !args.daemon {
+        tracing::warn!("--log-file only applies with --daemon; logging to stderr");
+    }
+    let _pid_file = match args.pid_file.as_deref().map(PidFile::create).transpose() {
+        Ok(pid_file) => pid_file,
+        Err(e) => {
+            eprintln!("Error: cannot write the pid file {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let shutdown = Shutdown::on_signals().unwrap_or_else(|e| {
+        tracing::warn!(
+            "cannot handle signals, so stopping will not wait for tools: {}",
+            e
+        );
+        Shutdown::default()
+    });
+    let mut reload = reconfigure.as_ref().map(|reconfigure| {
+        Reload::on_signal(&reconfigure.path).unwrap_or_else(|e| {
+            tracing::warn!(
+                "cannot handle SIGHUP, so the configuration is only reloaded when it changes: {}",
+                e
+            );
+            Reload::watch(&reconfigure.path)
+        })
+    });
+    args.scan.read_only = args.read_only;
+    if args.read_only {
+        tracing::info!("Serving only tools marked readOnlyHint (--read-only)");
+    }
+    // Over stdio, standard output carries the protocol alone; logs go to
+    // stderr.
+    let discovery = match discover(&args.scan, false, |message| tracing::info!("{}", message)) {
+        Ok(discovery) => discovery,
+        Err(code) => return code,
+    };
+    report_failures(&discovery.failures);
+    if !discovery.failures.is_empty() {
+        tracing::warn!("{} tool(s) could not be loaded", discovery.failures.len());
+    }
+    let count = discovery.registry.len();
+    let mut executor = Executor::new();
+    if let Some(limit) = args.max_concurrency {
+        executor = executor.with_max_concurrency(limit);
+    }
+    let mut server = McpServer::new(discovery.registry).with_executor(executor);
+    if args.confirm_destructive {
+        if args.daemon {
+            tracing::warn!(
+                "a daemon has no terminal to ask on, and HTTP clients cannot be asked, so calls of destructive tools will be refused"
+            );
+        }
+        server = server.with_confirmation(ConfirmPolicy {
+            skip: args.no_confirm.clone(),
+        });
+    }
+    let server = Arc::new(server);
+    let mut http_policy = None;
+    let stop = {
+        let shutdown = shutdown.clone();
+        move || shutdown.is_requested()
+    };
+    let serving = match args.transport {
+        Transport::Stdio => {
+            tracing::info!("Serving {} tool(s) over stdio", count);
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                let input = io::BufReader::new(io::stdin());
+                transport::serve_stdio_until(&server, input, io::stdout(), stop)
+            })
+        }
+        Transport::Http => {
+            let http = match HttpTransport::bind(&args.bind) {
+                Ok(http) => http.with_allowed_origins(args.allowed_origins.clone()),
+                Err(e) => {
+                    eprintln!("Error: cannot listen on {}: {}", args.bind, e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let http = match args.max_request_size {
+                Some(bytes) => http.with_max_request_size(bytes),
+                None => http,
+            };
+            let address = http
+                .local_addr()
+                .map_or(args.bind.clone(), |address| address.to_string());
+            tracing::info!(
+                "Serving {} tool(s) at http://{}{}",
+                count,
+                address,
+                transport::HTTP_ENDPOINT
+            );
+            http_policy = Some(http.policy());
+            let server = Arc::clone(&server);
+            thread::spawn(move || http.serve_until(server, stop))
+        }
+    };
+    if let Some(readiness) = readiness {
+        readiness.serving();
+    }
+
+    loop {
+        let mut reloading = false;
+        shutdown.wait(|| {
+            reloading = reload.as_mut().is_some_and(Reload::is_requested);
+            reloading || serving.is_finished()
+        });
+        match &mut reconfigure {
+            Some(reconfigure) if reloading => {
+                reconfigure.apply(&mut args, &server, http_policy.as_ref())
+            }
+            _ => break,
+        }
+    }
+    if shutdown.is_requested() {
+        let running = server.executor().running();
+        if running > 0 {
+            tracing::info!(
+                "Shutting down: waiting up to {:?} for {} tool call(s)",
+                args.drain_timeout,
+                running
+            );
+        } else {
+            tracing::info!("Shutting down");
+        }
+        let stopped = server.executor().shut_down(args.drain_timeout);
+        if stopped > 0 {
+            tracing::warn!(
+                "Stopped {} tool call(s) still running after {:?}",
+                stopped,
+                args.drain_timeout
+            );
+        }
+    }
+    match serving.join() {
+        Ok(Ok(())) => ExitCode::SUCCESS,
+        Ok(Err(e)) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+        Err(_) => ExitCode::FAILURE,
+    }
+}
+
+/// The configuration file a server reloads, with the command line that
+/// still overrides it.
+struct Reconfigure {
+    path: PathBuf,
+    matches: ArgMatches,
+    /// What is logged, as last configured
+    verbosity: Verbosity,
+}
+
+impl Reconfigure {
+    /// Load the file again, and apply to `args` and the running `server`
+    /// what can change while it serves: what is logged, the `allow` and
+    /// `deny` lists, `read_only`, trusted keys and `security.strict`,
+    /// limits, allowed origins, and the drain timeout. Other
+    /// changes only take effect on a restart, and are reported as such.
+    fn apply(&mut self, args: &mut ServeArgs, server: &McpServer, http: Option<&HttpPolicy>) {
+        let config = match Config::load(&self.path) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::error!("{}; keeping the current configuration", e);
+                return;
+            }
+        };
+        tracing::info!("Reloading the configuration: {}", self.path.display());
+        for key in &config.unknown_keys {
+            tracing::warn!("{}: unknown option `{}`", self.path.display(), key);
+        }
+        let mut cli = Cli::from_arg_matches(&self.matches).expect("parsed at startup");
+        cli.configure(&config, &self.matches);
+        let Some(Commands::Serve(new)) = cli.command else {
+            unreachable!("only serve reloads its configuration");
+        };
+
+        if cli.verbosity != self.verbosity {
+            logging::set_verbosity(cli.verbosity);
+            self.verbosity = cli.verbosity;
+        }
+        if new.scan.policy != args.scan.policy
+            || new.scan.trusted_keys != args.scan.trusted_keys
+            || new.scan.strict_permissions != args.scan.strict_permissions
+            || new.read_only != args.read_only
+        {
+            let mut scan = args.scan.clone();
+            scan.policy = new.scan.policy.clone();
+            scan.trusted_keys.clone_from(&new.scan.trusted_keys);
+            scan.strict_permissions = new.scan.strict_permissions;
+            scan.read_only = new.read_only;
+            match discover(&scan, false, |message| tracing::info!("{}", message)) {
+                Ok(discovery) => {
+                    report_failures(&discovery.failures);
+                    tracing::info!("Serving {} tool(s)", discovery.registry.len());
+                    server.replace_registry(discovery.registry);
+                    args.scan.policy = scan.policy;
+                    args.scan.trusted_keys = scan.trusted_keys;
+                    args.scan.strict_permissions = scan.strict_permissions;
+                    args.scan.read_only = scan.read_only;
+                    args.read_only = scan.read_only;
+                }
+                Err(_) => tracing::error!("keeping the tools served so far"),
+            }
+        }
+        if new.max_concurrency != args.max_concurrency {
+            server.executor().set_max_concurrency(new.max_concurrency);
+            args.max_concurrency = new.max_concurrency;
+        }
+        if let Some(http) = http {
+            http.set_allowed_origins(new.allowed_origins.clone());
+            http.set_max_request_size(new.max_request_size);
+        }
+        args.allowed_origins.clone_from(&new.allowed_origins);
+        args.max_request_size = new.max_request_size;
+        args.drain_timeout = new.drain_timeout;
+
+        let mut scan = new.scan.clone();
+        scan.policy = args.scan.policy.clone();
+        scan.trusted_keys.clone_from(&args.scan.trusted_keys);
+        scan.strict_permissions = args.scan.strict_permissions;
+        scan.read_only = args.scan.read_only;
+        let restart: Vec<&str> = [
+            ("tool directories and scanning", scan != args.scan),
+            ("transport", new.transport != args.transport),
+            ("bind", new.bind != args.bind),
+            ("daemon", new.daemon != args.daemon),
+            ("pid_file", new.pid_file != args.pid_file),
+            ("log_file", new.log_file != args.log_file),
+            (
+                "confirm_destructive",
+                new.confirm_destructive != args.confirm_destructive
+                    || new.no_confirm != args.no_confirm,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(option, changed)| changed.then_some(option))
+        .collect();
+        if !restart.is_empty() {
+            tracing::warn!(
+                "restart the server to apply the changes to: {}",
+                restart.join(", ")
+            );
+        }
+    }
+}
+
+/// Print a summary of each tool found, including those that cannot be
+/// loaded.
+fn list(args: ListArgs) -> ExitCode {
+    // Progress is logged to stderr, leaving stdout to the list.
+    let discovery = match discover(&args.scan, false, |message| tracing::info!("{}", message)) {
+        Ok(discovery) => discovery,
+        Err(code) => return code,
+    };
+    let summaries: Vec<ToolSummary> = discovery
+        .registry
+        .tools()
+        .iter()
+        .map(ToolSummary::registered)
+        .chain(
+            discovery
+                .failures
+                .into_iter()
+                .map(|failure| ToolSummary::broken(&failure.tool, failure.name, failure.errors)),
+        )
+        .collect();
+    print!("{}", list::render(&summaries, args.format));
+    ExitCode::SUCCESS
+}
+
+/// Check the definitions of the discovered tools, printing every problem.
+fn validate(args: ValidateArgs) -> ExitCode {
+    let scan = match scan(&args.scan, |message| tracing::info!("{}", message)) {
+        Ok(scan) => scan,
+        Err(code) => return code,
+    };
+    let validator = Validator::new().with_draft(args.scan.schema_draft);
+    let report = validate::check(
+        scan.tools.into_iter().map(|(tool, _)| tool),
+        &validator,
+        args.scan.on_duplicate,
+    );
+    match args.format {
+        OutputFormat::Text => println!("{}", report),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&report.to_json())
+                .expect("report serialization cannot fail")
+        ),
+    }
+    if report.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Print the `tools/list` entry of one tool.
+fn inspect(args: InspectArgs) -> ExitCode {
+    let discovery = match discover(&args.scan, false, |message| tracing::info!("{}", message)) {
+        Ok(discovery) => discovery,
+        Err(code) => return code,
+    };
+    if let Some(tool) = discovery.registry.get(&args.name) {
+        let json = serde_json::to_string_pretty(&tool.to_wire_json())
+            .expect("tool serialization cannot fail");
+        println!("{}", json);
+        return ExitCode::SUCCESS;
+    }
+    let failures: Vec<&LoadFailure> = discovery
+        .failures
+        .iter()
+        .filter(|failure| failure.name.as_deref() == Some(args.name.as_str()))
+        .collect();
+    if failures.is_empty() {
+        eprintln!("Error: no tool named `{}` was found", args.name);
+    } else {
+        eprintln!("Error: tool `{}` cannot be served:", args.name);
+        for failure in failures {
+            for error in &failure.errors {
+                eprintln!("  {}", error.replace('\n', "\n  "));
+            }
+        }
+    }
+    ExitCode::FAILURE
+}
+
+/// Print the JSON Schema of sidecar files, or of manifests.
+fn schema(args: SchemaArgs) -> ExitCode {
+    let schema = if args.manifest {
+        tool_discovery::schema::manifest_schema()
+    } else {
+        tool_discovery::schema::sidecar_schema()
+    };
+    let json = serde_json::to_string_pretty(&schema).expect("schema serialization cannot fail");
+    println!("{}", json);
+    ExitCode::SUCCESS
+}
+
+/// Call one tool and print its result, failing if the call does.
+fn run(args: RunArgs) -> ExitCode {
+    let arguments = match run::parse_arguments(&args.args) {
+        Ok(arguments) => arguments,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let discovery = match discover(&args.scan, false, |message| tracing::info!("{}", message)) {
+        Ok(discovery) => discovery,
+        Err(code) => return code,
+    };
+    let Some(tool) = discovery.registry.get(&args.name) else {
+        eprintln!("Error: no tool named `{}` can be served", args.name);
+        return ExitCode::FAILURE;
+    };
+    let result = Executor::new().call(tool, &arguments);
+    match args.format {
+        OutputFormat::Text if result.is_error => {
+            eprintln!("Error: {}", run::describe(&result))
+        }
+        OutputFormat::Text => println!("{}", run::describe(&result)),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&result).expect("result serialization cannot fail")
+        ),
+    }
+    if result.is_error {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Prompt for tools to call and their arguments, printing each result.
+fn repl(args: ReplArgs) -> ExitCode {
+    let discovery = match discover(&args.scan, false, |message| tracing::info!("{}", message)) {
+        Ok(discovery) => discovery,
+        Err(code) => return code,
+    };
+    match repl::interact(&discovery.registry, &Executor::new()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the test cases of every tool, and of tools that cannot be loaded
+/// report a failure each.
+fn test(args: TestArgs) -> ExitCode {
+    let discovery = match discover(&args.scan, false, |message| tracing::info!("{}", message)) {
+        Ok(discovery) => discovery,
+        Err(code) => return code,
+    };
+    let executor = Executor::new();
+    let mut report = testing::TestReport::default();
+    for tool in discovery.registry.tools() {
+        report.outcomes.extend(testing::run_cases(tool, &executor));
+    }
+    for failure in discovery.failures {
+        report.outcomes.push(testing::CaseOutcome {
+            tool: failure.name.unwrap_or(failure.label),
+            case: "definition".to_string(),
+            failures: failure.errors,
+        });
+    }
+    println!("{}", report);
+    if report.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Time scanning, parsing each definition, and calling a tool, over several
+/// runs.
+fn bench(args: BenchArgs) -> ExitCode {
+    let arguments = match run::parse_arguments(&args.args) {
+        Ok(arguments) => arguments,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let runs = args.runs.get();
+    let mut report = BenchReport {
+        runs,
+        ..BenchReport::default()
+    };
+
+    tracing::info!("Scanning and parsing {} time(s)", runs);
+    let mut tools = Vec::new();
+    for _ in 0..runs {
+        let started = Instant::now();
+        // As at startup: remote roots are fetched (from the cache, after the
+        // first run), and an index is read if one is given.
+        tools = match scan(&args.scan, |_| {}) {
+            Ok(scan) => scan.tools,
+            Err(code) => return code,
+        };
+        report.scan.push(started.elapsed());
+    }
+    report.tools = tools.len();
+    let mut definitions = vec![Samples::default(); tools.len()];
+    for _ in 0..runs {
+        let mut loader = Loader::new().with_strict(args.scan.strict);
+        let started = Instant::now();
+        report.failures = 0;
+        for ((tool, _), samples) in tools.iter().zip(&mut definitions) {
+            let loading = Instant::now();
+            if loader.load(tool).is_err() {
+                report.failures += 1;
+            }
+            samples.push(loading.elapsed());
+        }
+        report.parse.push(started.elapsed());
+    }
+    report.definitions = tools
+        .iter()
+        .map(|(tool, _)| tool.path.display().to_string())
+        .zip(definitions)
+        .collect();
+
+    if let Some(name) = &args.call {
+        let discovery = match discover(&args.scan, false, |_| {}) {
+            Ok(discovery) => discovery,
+            Err(code) => return code,
+        };
+        let Some(tool) = discovery.registry.get(name) else {
+            eprintln!("Error: no tool named `{}` can be served", name);
+            return ExitCode::FAILURE;
+        };
+        tracing::info!("Calling {} {} time(s)", name, runs);
+        let executor = Executor::new();
+        let mut call = CallBench {
+            tool: name.clone(),
+            ..CallBench::default()
+        };
+        for _ in 0..runs {
+            let started = Instant::now();
+            let result = executor.call(tool, &arguments);
+            call.samples.push(started.elapsed());
+            if result.is_error {
+                call.errors += 1;
+            }
+        }
+        report.call = Some(call);
+    }
+
+    match args.format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&report.to_json())
+                .expect("report serialization cannot fail")
+        ),
+    }
+    ExitCode::SUCCESS
+}
+
+/// Check the environment of the tools, printing each problem with how to
+/// fix it.
+fn doctor(args: DoctorArgs) -> ExitCode {
+    let mut findings: Vec<doctor::Finding> = args
+        .scan
+        .tools_dirs
+        .iter()
+        .filter(|root| !root.source().is_remote())
+        .flat_map(|root| doctor::check_directory(&root.path))
+        .collect();
+    // Scanning fails on the first directory it cannot read.
+    if findings.is_empty() {
+        let scan = match scan(&args.scan, |message| tracing::info!("{}", message)) {
+            Ok(scan) => scan,
+            Err(code) => return code,
+        };
+        let tools: Vec<DiscoveredTool> = scan.tools.into_iter().map(|(tool, _)| tool).collect();
+        findings = doctor::examine(&scan.scanner, &tools, &scan.report);
+    }
+
+    for finding in &findings {
+        println!("{}\n", finding);
+    }
+    if findings.is_empty() {
+        println!("No problems found");
+    } else {
+        println!("Found {} problem(s)", findings.len());
+    }
+    if findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error)
+    {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Create an example tools directory, or, with `--from-help`, draft a
+/// sidecar definition for an executable from its --help output.
+fn init(args: InitArgs) -> ExitCode {
+    if let Some(InitCommand::Tool(args)) = args.command {
+        return init_tool(args);
+    }
+    let Some(from_help) = args.from_help else {
+        return match init::scaffold(&args.dir, args.force) {
+            Ok(paths) => {
+                println!("Created an example tools directory:");
+                for path in paths {
+                    println!("  {}", path.display());
+                }
+                println!("Try it with: mcp-serve list {}", args.dir.display());
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    };
+    let draft = match HelpDraft::from_executable(&from_help) {
+        Ok(draft) => draft,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = args.output.unwrap_or_else(|| sidecar_path(&from_help));
+    let yaml = draft.to_yaml(&from_help.display().to_string());
+    if let Err(e) = init::write_definition(&output, &yaml, args.force) {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let inputs = draft.definition.input.schema["properties"]
+        .as_object()
+        .map_or(0, |properties| properties.len());
+    println!(
+        "Wrote draft definition for {} ({} input(s)): {}",
+        draft.definition.name,
+        inputs,
+        output.display()
+    );
+    ExitCode::SUCCESS
+}
+
+/// Write a definition skeleton for an existing executable.
+fn init_tool(args: InitToolArgs) -> ExitCode {
+    if !args.executable.is_file() {
+        eprintln!(
+            "Error: {} is not an executable file",
+            args.executable.display()
+        );
+        return ExitCode::FAILURE;
+    }
+    let output = args
+        .output
+        .unwrap_or_else(|| sidecar_path(&args.executable));
+    let skeleton = init::definition_skeleton(&args.executable);
+    if let Err(e) = init::write_definition(&output, &skeleton, args.force) {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+    println!("Wrote definition skeleton: {}", output.display());
+    ExitCode::SUCCESS
+}
+
+/// The sidecar path of `executable`: its path with `.yaml` appended.
+fn sidecar_path(executable: &Path) -> PathBuf {
+    let mut path = executable.as_os_str().to_owned();
+    path.push(".yaml");
+    PathBuf::from(path)
+}
+
+/// Write a curl wrapper and a sidecar declaring one tool per operation of
+/// an OpenAPI document.
+fn import(args: ImportArgs) -> ExitCode {
+    let api = match OpenApiImport::load(&args.spec, args.base_url.as_deref()) {
+        Ok(api) => api,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let wrapper = args.output.join(&api.name);
+    let sidecar = sidecar_path(&wrapper);
+
+    let written = init::write_definition(&wrapper, &api.wrapper_script(), args.force)
+        .and_then(|()| init::make_executable(&wrapper))
+        .and_then(|()| {
+            let yaml = api.sidecar_yaml(&args.spec.display().to_string());
+            init::write_definition(&sidecar, &yaml, args.force)
+        });
+    if let Err(e) = written {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    println!(
+        "Imported {} operation(s) from {} ({}): {}",
+        api.tools.len(),
+        args.spec.display(),
+        api.base_url,
+        sidecar.display()
+    );
+    ExitCode::SUCCESS
+}
+
+/// Convert a sidecar, or rewrite every sidecar of a directory.
+fn convert(args: ConvertArgs) -> ExitCode {
+    if args.path.is_dir() {
+        return convert_dir(args);
+    }
+    let contents = match convert::convert_file(&args.path, args.to) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let Some(output) = args.output else {
+        print!("{}", contents);
+        return ExitCode::SUCCESS;
+    };
+    if let Err(e) = init::write_definition(&output, &contents, args.force) {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "Converted {} to {}: {}",
+        args.path.display(),
+        args.to,
+        output.display()
+    );
+    ExitCode::SUCCESS
+}
+
+/// Check a configuration file, or print the schema of the format.
+fn config(command: ConfigCommand, config: Option<&Path>) -> ExitCode {
+    let args = match command {
+        ConfigCommand::Check(args) => args,
+        ConfigCommand::Schema => {
+            let json = serde_json::to_string_pretty(&config::schema())
+                .expect("schema serialization cannot fail");
+            println!("{}", json);
+            return ExitCode::SUCCESS;
+        }
+    };
+    let Some(path) = args.file.as_deref().or(config) else {
+        eprintln!("Error: no configuration file to check; pass FILE or --config");
+        return ExitCode::FAILURE;
+    };
+    let findings = match Config::load(path) {
+        Ok(loaded) => loaded.check(path),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for finding in &findings {
+        println!("{}\n", finding);
+    }
+    if findings.is_empty() {
+        println!("No problems found");
+    } else {
+        println!("Found {} problem(s)", findings.len());
+    }
+    if findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error)
+    {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Register the server with an MCP client, serving the given directories
+/// with the same configuration file.
+fn install(args: InstallArgs, config: Option<&Path>) -> ExitCode {
+    let Some(path) = args
+        .client_config
+        .clone()
+        .or_else(|| args.client.config_path(|name| std::env::var_os(name)))
+    else {
+        let e = install::InstallError::UnknownLocation {
+            client: args.client,
+        };
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    };
+    let entry = match server_entry(&args, config) {
+        Ok(entry) => entry,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (updated, installed) = match install::install(args.client, &path, &entry, args.dry_run) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.dry_run {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&updated).expect("JSON values serialize")
+        );
+        return ExitCode::SUCCESS;
+    }
+    let done = match installed {
+        Installed::Added => "Added",
+        Installed::Updated => "Updated",
+        Installed::Unchanged => "Already registered",
+    };
     println!(
-        "Discovering tools from directory: {}",
-        cli.tools_dir.display()
+        "{} {} in {}: {}",
+        done,
+        entry.name,
+        args.client,
+        path.display()
     );
-    println!("Tools functionality working");
+    if installed != Installed::Unchanged {
+        println!("Restart {} to start the server", args.client);
+    }
+    ExitCode::SUCCESS
+}
+
+/// How a client starts this executable to serve the directories of `args`.
+/// Paths are made absolute, as clients start servers from a directory of
+/// their own choosing.
+fn server_entry(args: &InstallArgs, config: Option<&Path>) -> io::Result<ServerEntry> {
+    let absolute = |path: &Path| -> io::Result<String> {
+        Ok(std::path::absolute(path)?.to_string_lossy().into_owned())
+    };
+    let mut serve = vec!["serve".to_string()];
+    for dir in &args.tools_dirs {
+        serve.push(absolute(dir)?);
+    }
+    if let Some(config) = config {
+        serve.push("--config".to_string());
+        serve.push(absolute(config)?);
+    }
+    Ok(ServerEntry {
+        name: args.name.clone(),
+        command: std::env::current_exe()?,
+        args: serve,
+    })
+}
+
+/// Rewrite the sidecar of every tool in a directory in another format, or
+/// in the current definition format.
+fn convert_dir(args: ConvertArgs) -> ExitCode {
+    let Some(format) = args.to.sidecar_format() else {
+        eprintln!("Error: a directory can only be converted to yaml, json, or toml sidecars");
+        return ExitCode::FAILURE;
+    };
+    if args.output.is_some() {
+        eprintln!("Error: --output takes a single sidecar, not a directory");
+        return ExitCode::FAILURE;
+    }
+    let tools = match DirectoryScanner::new().scan_directory(&args.path) {
+        Ok(tools) => tools,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // Tools sharing a sidecar list it once each.
+    let sidecars: std::collections::BTreeSet<_> = tools
+        .into_iter()
+        .filter_map(|tool| match tool.metadata_source {
+            MetadataSource::Sidecar(path) => Some(path),
+            _ => None,
+        })
+        .collect();
+
+    let mut failed = false;
+    for sidecar in &sidecars {
+        match convert::migrate(sidecar, format, args.force) {
+            Ok(Some(target)) if &target == sidecar => println!("Rewrote {}", sidecar.display()),
+            Ok(Some(target)) => println!("Converted {} -> {}", sidecar.display(), target.display()),
+            Ok(None) => println!(
+                "Kept {}: tool directories are only recognized by it",
+                sidecar.display()
+            ),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// How often watch mode rescans the tool directories.
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Report changes to the tools in `roots` until interrupted.
+fn watch(scanner: DirectoryScanner, roots: Vec<ToolRoot>) -> ExitCode {
+    let mut watcher = ToolWatcher::new(scanner, roots);
+    // The initial tools were already listed; start from them.
+    if let Err(e) = watcher.poll() {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+    println!("Watching for changes...");
+
+    loop {
+        std::thread::sleep(WATCH_INTERVAL);
+        match watcher.poll() {
+            Ok(events) => {
+                for event in events {
+                    let (marker, tool) = match &event {
+                        ToolEvent::ToolAdded(tool) => ("added", tool),
+                        ToolEvent::ToolRemoved(tool) => ("removed", tool),
+                        ToolEvent::ToolChanged { current, .. } => ("changed", &**current),
+                    };
+                    println!("  {} {}", marker, tool.path.display());
+                }
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -98,4 +2231,51 @@ invalid_yaml: [unclosed_list
         let error_str = error.to_string();
         assert!(!error_str.is_empty(), "Error message should not be empty");
     }
+
+    #[test]
+    fn test_global_options_before_subcommand() {
+        use super::{parse, Commands};
+
+        let (cli, _) = parse(["mcp-serve", "-v", "list", "."]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::List(_))));
+        assert_eq!(cli.verbosity.verbose, 1);
+        let (cli, _) = parse(["mcp-serve", "--config", "f.yaml", "serve"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Serve(_))));
+        assert_eq!(cli.config, Some(PathBuf::from("f.yaml")));
+        let (cli, _) = parse(["mcp-serve", "-q", "serve", "--config", "f.yaml"]).unwrap();
+        assert!(cli.verbosity.quiet);
+        assert_eq!(cli.config, Some(PathBuf::from("f.yaml")));
+
+        // Without a subcommand, the tools directories are scanned and listed.
+        let (cli, _) = parse(["mcp-serve", "--watch", "./tools"]).unwrap();
+        assert!(cli.command.is_none());
+        assert!(cli.watch);
+
+        let error = parse(["mcp-serve", "--watch", "list"]).err().unwrap();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(error
+            .to_string()
+            .contains("--watch cannot be used with the `list` subcommand"));
+    }
+
+    #[test]
+    fn test_environment_variables() {
+        use clap::CommandFactory;
+
+        // Every option of the server can be set from the environment, as
+        // MCP_SERVE_ and its flag's name.
+        let cli = super::Cli::command();
+        let serve = cli.find_subcommand("serve").unwrap();
+        for arg in cli.get_arguments().chain(serve.get_arguments()) {
+            if matches!(arg.get_id().as_str(), "help" | "version") {
+                continue;
+            }
+            let env = arg.get_env().and_then(|env| env.to_str());
+            let expected = match arg.get_long() {
+                Some(long) => format!("MCP_SERVE_{}", long.to_uppercase().replace('-', "_")),
+                None => "MCP_SERVE_DIRS".to_string(),
+            };
+            assert_eq!(env, Some(expected.as_str()), "{}", arg.get_id());
+        }
+    }
 }
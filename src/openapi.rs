@@ -0,0 +1,488 @@
+//! Importing HTTP APIs described by OpenAPI 3 documents.
+//!
+//! Each operation in the document becomes a [`ToolDefinition`] whose input
+//! template builds a `curl` command line: path parameters are substituted
+//! into the URL, query parameters become `--url-query` options, and a JSON
+//! request body is passed with `--json`. `mcp-serve import` writes these
+//! definitions as one sidecar next to a small wrapper script that runs
+//! `curl`, so the API is then served like any other tool directory.
+
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::template::TemplateEngine;
+use crate::tool_discovery::{
+    ExecutionConfig, ToolAnnotations, ToolDefinition, ToolInput, ToolOutput,
+};
+
+/// HTTP methods that OpenAPI path items may define operations for.
+const METHODS: [&str; 7] = ["get", "put", "post", "delete", "patch", "head", "options"];
+
+/// Limit on nested `$ref`s, which also stops recursive schemas.
+const MAX_REF_DEPTH: usize = 16;
+
+/// An OpenAPI document and the tools generated from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApiImport {
+    /// A name for the API derived from its title, such as `pet-store`
+    pub name: String,
+
+    /// The URL requests are sent to
+    pub base_url: String,
+
+    /// One definition per operation, in document order
+    pub tools: Vec<ToolDefinition>,
+}
+
+impl OpenApiImport {
+    /// Read an OpenAPI document (YAML or JSON) from `path`.
+    pub fn load(path: &Path, base_url: Option<&str>) -> Result<Self, OpenApiError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| OpenApiError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        // YAML is a superset of JSON, so one parser reads both.
+        let document: Value =
+            serde_yaml_ng::from_str(&contents).map_err(|source| OpenApiError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Self::from_document(&document, base_url)
+    }
+
+    /// Generate tools for every operation in `document`. `base_url`
+    /// overrides the document's first server.
+    pub fn from_document(document: &Value, base_url: Option<&str>) -> Result<Self, OpenApiError> {
+        let version = document
+            .get("openapi")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if !version.starts_with("3.") {
+            return Err(OpenApiError::UnsupportedVersion {
+                version: version.to_string(),
+            });
+        }
+        let base_url = match base_url {
+            Some(url) => url.to_string(),
+            None => document
+                .pointer("/servers/0/url")
+                .and_then(Value::as_str)
+                .ok_or(OpenApiError::MissingServer)?
+                .to_string(),
+        };
+        let title = document
+            .pointer("/info/title")
+            .and_then(Value::as_str)
+            .unwrap_or("api");
+
+        let mut tools = Vec::new();
+        let paths = document.get("paths").and_then(Value::as_object);
+        for (path, item) in paths.into_iter().flatten() {
+            let item = inline_refs(document, item, 0);
+            for method in METHODS {
+                if let Some(operation) = item.get(method) {
+                    tools.push(operation_tool(
+                        document, &base_url, path, &item, method, operation,
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            name: api_name(title),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            tools,
+        })
+    }
+
+    /// The sidecar declaring every generated tool.
+    pub fn sidecar_yaml(&self, source: &str) -> String {
+        #[derive(serde::Serialize)]
+        struct ToolList<'a> {
+            tools: &'a [ToolDefinition],
+        }
+
+        format!(
+            "# Generated by `mcp-serve import` from {}.\n{}",
+            source,
+            serde_yaml_ng::to_string(&ToolList { tools: &self.tools })
+                .expect("definition serialization cannot fail")
+        )
+    }
+
+    /// The wrapper script the generated tools run: `curl`, failing on HTTP
+    /// errors while still printing the response body.
+    pub fn wrapper_script(&self) -> String {
+        "#!/bin/sh\n\
+         # Generated by `mcp-serve import`; runs curl with the tool's arguments.\n\
+         exec curl --silent --show-error --fail-with-body \"$@\"\n"
+            .to_string()
+    }
+}
+
+/// Build the tool for one operation.
+fn operation_tool(
+    document: &Value,
+    base_url: &str,
+    path: &str,
+    item: &Value,
+    method: &str,
+    operation: &Value,
+) -> ToolDefinition {
+    let operation = inline_refs(document, operation, 0);
+    let name = operation
+        .get("operationId")
+        .and_then(Value::as_str)
+        .map(tool_name)
+        .unwrap_or_else(|| tool_name(&format!("{}_{}", method, path)));
+    let description = ["summary", "description"]
+        .iter()
+        .find_map(|key| operation.get(*key).and_then(Value::as_str))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{} {}", method.to_ascii_uppercase(), path));
+
+    // Operation parameters override path-level ones with the same name.
+    let mut parameters: Vec<&Value> = Vec::new();
+    for parameter in [item.get("parameters"), operation.get("parameters")]
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_array)
+        .flatten()
+    {
+        let key = |p: &Value| (p.get("name").cloned(), p.get("in").cloned());
+        parameters.retain(|existing| key(existing) != key(parameter));
+        parameters.push(parameter);
+    }
+
+    let mut url = format!("{}{}", base_url.trim_end_matches('/'), path);
+    let mut options = Vec::new();
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for parameter in parameters {
+        let (Some(name), Some(location)) = (
+            parameter.get("name").and_then(Value::as_str),
+            parameter.get("in").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        let property = tool_name(name);
+        match location {
+            "path" => url = url.replace(&format!("{{{}}}", name), &format!("{{{{{}}}}}", property)),
+            "query" => options.push(format!("[--url-query {}={{{{{}}}}}]", name, property)),
+            // Headers and cookies are left to the author.
+            _ => continue,
+        }
+        let mut schema = parameter
+            .get("schema")
+            .cloned()
+            .unwrap_or_else(|| json!({"type": "string"}));
+        if let (Some(text), Value::Object(schema)) = (parameter.get("description"), &mut schema) {
+            schema.insert("description".to_string(), text.clone());
+        }
+        properties.insert(property.clone(), schema);
+        if location == "path" || parameter.get("required") == Some(&Value::Bool(true)) {
+            required.push(json!(property));
+        }
+    }
+
+    if let Some(body) = operation.get("requestBody") {
+        if let Some(schema) = body.pointer("/content/application~1json/schema") {
+            properties.insert("body".to_string(), schema.clone());
+            options.push("[--json {{body}}]".to_string());
+            if body.get("required") == Some(&Value::Bool(true)) {
+                required.push(json!("body"));
+            }
+        }
+    }
+
+    let mut schema = json!({"type": "object", "properties": properties});
+    if !required.is_empty() {
+        schema["required"] = Value::Array(required);
+    }
+    let mut template = vec![format!("--request {}", method.to_ascii_uppercase()), url];
+    template.extend(options);
+
+    ToolDefinition {
+        name,
+        aliases: Vec::new(),
+        title: None,
+        version: None,
+        description,
+        input: ToolInput {
+            template: template.join(" ").into(),
+            stdin_template: None,
+            env_template: BTreeMap::new(),
+            engine: TemplateEngine::Builtin,
+            schema,
+        },
+        output: ToolOutput {
+            template: r"(?<body>[\s\S]*)".into(),
+            stderr_template: Default::default(),
+            extract: None,
+            capture: None,
+            encoding: Default::default(),
+            schema: json!({"type": "object", "properties": {"body": {"type": "string"}}}),
+            content: Vec::new(),
+        },
+        annotations: Some(method_annotations(method)),
+        execution: ExecutionConfig::default(),
+        sha256: None,
+        deprecated: None,
+        category: None,
+        tags: Vec::new(),
+        icon: None,
+        meta: None,
+        tests: Vec::new(),
+    }
+}
+
+/// Behavior hints implied by an HTTP method's semantics.
+fn method_annotations(method: &str) -> ToolAnnotations {
+    let read_only = matches!(method, "get" | "head" | "options");
+    ToolAnnotations {
+        read_only_hint: Some(read_only),
+        destructive_hint: (!read_only).then_some(method == "delete"),
+        idempotent_hint: (!read_only).then_some(matches!(method, "put" | "delete")),
+        open_world_hint: Some(true),
+        ..ToolAnnotations::default()
+    }
+}
+
+/// Copy `value` with every `#/...` reference into `document` replaced by
+/// what it points to. References nested more than [`MAX_REF_DEPTH`] deep
+/// (such as recursive schemas) are left in place.
+fn inline_refs(document: &Value, value: &Value, depth: usize) -> Value {
+    match value {
+        Value::Object(object) => {
+            let target = object
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| reference.strip_prefix('#'))
+                .and_then(|pointer| document.pointer(pointer));
+            match target {
+                Some(target) if depth < MAX_REF_DEPTH => inline_refs(document, target, depth + 1),
+                _ => Value::Object(
+                    object
+                        .iter()
+                        .map(|(key, value)| (key.clone(), inline_refs(document, value, depth)))
+                        .collect(),
+                ),
+            }
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| inline_refs(document, value, depth))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// An MCP-compatible tool name: runs of invalid characters become a single
+/// `_`.
+fn tool_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// A file name for an API: its title in lowercase, words joined by `-`.
+fn api_name(title: &str) -> String {
+    let words: Vec<String> = title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        "api".to_string()
+    } else {
+        words.join("-")
+    }
+}
+
+/// Errors that can occur while importing an OpenAPI document.
+#[derive(Debug)]
+pub enum OpenApiError {
+    /// The document could not be read.
+    Io { path: PathBuf, source: io::Error },
+
+    /// The document is not valid YAML or JSON.
+    Parse {
+        path: PathBuf,
+        source: serde_yaml_ng::Error,
+    },
+
+    /// The document is not OpenAPI 3 (Swagger 2 documents must be converted
+    /// first).
+    UnsupportedVersion { version: String },
+
+    /// The document lists no server and no base URL was given.
+    MissingServer,
+}
+
+impl fmt::Display for OpenApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenApiError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            OpenApiError::Parse { path, source } => {
+                write!(f, "invalid OpenAPI document {}: {}", path.display(), source)
+            }
+            OpenApiError::UnsupportedVersion { version } if version.is_empty() => {
+                write!(f, "not an OpenAPI 3 document")
+            }
+            OpenApiError::UnsupportedVersion { version } => {
+                write!(f, "unsupported OpenAPI version {}; expected 3.x", version)
+            }
+            OpenApiError::MissingServer => {
+                write!(f, "the document lists no servers; pass --base-url")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OpenApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OpenApiError::Io { source, .. } => Some(source),
+            OpenApiError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::CommandTemplate;
+
+    fn petstore() -> Value {
+        serde_yaml_ng::from_str(
+            r##"
+openapi: 3.0.3
+info: { title: Pet Store, version: 1.0.0 }
+servers: [{ url: "https://pets.example.com/v1/" }]
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      summary: List all pets
+      parameters:
+        - { name: limit, in: query, schema: { type: integer }, description: How many }
+        - { name: X-Trace, in: header, schema: { type: string } }
+    post:
+      operationId: createPet
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema: { $ref: "#/components/schemas/Pet" }
+  /pets/{petId}:
+    parameters:
+      - { name: petId, in: path, required: true, schema: { type: string } }
+    delete:
+      description: Removes a pet
+components:
+  schemas:
+    Pet:
+      type: object
+      properties: { name: { type: string } }
+"##,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_import_operations() {
+        let import = OpenApiImport::from_document(&petstore(), None).unwrap();
+
+        assert_eq!(import.name, "pet-store");
+        assert_eq!(import.base_url, "https://pets.example.com/v1");
+        let names: Vec<&str> = import.tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["listPets", "createPet", "delete_pets_petId"]);
+
+        let list = &import.tools[0];
+        assert_eq!(list.description, "List all pets");
+        assert_eq!(
+            list.input.template,
+            "--request GET https://pets.example.com/v1/pets [--url-query limit={{limit}}]"
+        );
+        assert_eq!(
+            list.input.schema,
+            json!({
+                "type": "object",
+                "properties": {"limit": {"type": "integer", "description": "How many"}}
+            })
+        );
+        assert!(list.annotations.as_ref().unwrap().is_read_only());
+
+        let create = &import.tools[1];
+        assert_eq!(create.description, "POST /pets");
+        assert_eq!(
+            create.input.schema["properties"]["body"],
+            json!({"type": "object", "properties": {"name": {"type": "string"}}})
+        );
+        assert_eq!(create.input.schema["required"], json!(["body"]));
+        assert!(!create.annotations.as_ref().unwrap().is_destructive());
+
+        let delete = &import.tools[2];
+        assert_eq!(
+            delete.input.template,
+            "--request DELETE https://pets.example.com/v1/pets/{{petId}}"
+        );
+        assert_eq!(delete.input.schema["required"], json!(["petId"]));
+        assert!(delete.annotations.as_ref().unwrap().is_destructive());
+
+        for tool in &import.tools {
+            let issues = crate::definitions::validate(tool);
+            assert!(issues.iter().all(|i| !i.is_error()), "{:?}", issues);
+        }
+    }
+
+    #[test]
+    fn test_import_sidecar_round_trips() {
+        let import =
+            OpenApiImport::from_document(&petstore(), Some("http://localhost:8080")).unwrap();
+        let CommandTemplate::Text(template) = &import.tools[0].input.template else {
+            panic!("imported templates are text");
+        };
+        assert!(template.contains("http://localhost:8080/pets"));
+
+        let yaml = import.sidecar_yaml("petstore.yaml");
+        let parsed = crate::tool_discovery::SidecarFormat::Yaml
+            .parse_all(&yaml)
+            .unwrap();
+        assert_eq!(parsed, import.tools);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let swagger = json!({"swagger": "2.0", "paths": {}});
+        assert!(matches!(
+            OpenApiImport::from_document(&swagger, None),
+            Err(OpenApiError::UnsupportedVersion { .. })
+        ));
+
+        let no_servers = json!({"openapi": "3.1.0", "paths": {}});
+        assert!(matches!(
+            OpenApiImport::from_document(&no_servers, None),
+            Err(OpenApiError::MissingServer)
+        ));
+    }
+}
@@ -0,0 +1,930 @@
+//! Output templates: parsing a tool's output into a structured result.
+//!
+//! `output.template` is a regular expression whose named capture groups
+//! become the properties of the result:
+//!
+//! ```yaml
+//! output:
+//!   template: 'Ticket created: (?<url>\S+)\nID: (?<id>\d+)'
+//! ```
+//!
+//! Tools that print different things on different occasions can give a
+//! list of patterns instead. They are tried in order, and the first that
+//! matches produces the result. A pattern can `set` properties of its own,
+//! so that results of different forms can be told apart:
+//!
+//! ```yaml
+//! output:
+//!   template:
+//!     - 'Ticket created: (?<url>\S+)'
+//!     - pattern: 'Error: (?<error>.*)'
+//!       set: { ok: false }
+//! ```
+//!
+//! Output usually spans several lines. Patterns take the regular
+//! expression flags `i` (ignore case), `m` (`^` and `$` match at each
+//! line), `s` (`.` matches newlines too), and `x` (ignore whitespace and
+//! `#` comments), either inline, as in `(?ms)^ID: (?<id>\d+)$`, or in a
+//! pattern's `flags`:
+//!
+//! ```yaml
+//! output:
+//!   template:
+//!     - pattern: '^Status: (?<status>\w+)$.*^Notes:\n(?<notes>.*)'
+//!       flags: ms
+//! ```
+//!
+//! Captures are strings, unless a group names the type to convert them
+//! to: `(?<count:int>\d+)`, `(?<ok:bool>true|false)`, `(?<ratio:float>\S+)`,
+//! or `(?<meta:json>\{.*\})`. The type is removed before the pattern is
+//! compiled as a regular expression.
+//!
+//! [`OutputTemplate::compile`] turns the patterns into an [`OutputParser`].
+//!
+//! `output.stderr_template` takes patterns of the same form, matched
+//! against the tool's stderr. Captures from the first that matches are
+//! added to the result; stderr that matches none adds nothing.
+//!
+//! Tools that print JSON can instead reshape it with a jq-style
+//! [`extract`] expression, and need no template. Tools whose whole output
+//! is the result can [capture](OutputCapture) it as a single property.
+//!
+//! An output schema declaring an `exit_code` property receives the tool's
+//! exit code there, so that tools whose exit status is their answer, such
+//! as linters, need not parse anything. [`parse_result`] puts all of this
+//! together.
+//!
+//! Output is decoded as UTF-8 before it is parsed. What happens to output
+//! that is not valid UTF-8 is up to `output.encoding` ([`OutputEncoding`]):
+//! invalid bytes are replaced by default, but a tool can instead fail on
+//! them, or have all of its output encoded as base64, as for a tool that
+//! writes an image.
+
+use base64::Engine;
+use regex::{Regex, RegexBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Number, Value};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::tool_discovery::ToolOutput;
+use extract::{Extract, ExtractError};
+
+pub mod extract;
+
+/// Name of the output property that receives a tool's exit code.
+pub const EXIT_CODE_PROPERTY: &str = "exit_code";
+
+/// Whether the output schema `schema` declares the exit code property.
+pub fn declares_exit_code(schema: &Value) -> bool {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .is_some_and(|properties| properties.contains_key(EXIT_CODE_PROPERTY))
+}
+
+/// How a tool's output is decoded into text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputEncoding {
+    /// UTF-8, with invalid bytes replaced by U+FFFD
+    #[default]
+    Lossy,
+
+    /// UTF-8, failing the call when the output is not valid
+    Strict,
+
+    /// Base64, for binary output
+    Base64,
+}
+
+impl OutputEncoding {
+    /// Whether this is the default encoding.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Decode the bytes a tool wrote.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>, OutputError> {
+        match self {
+            OutputEncoding::Lossy => Ok(String::from_utf8_lossy(bytes)),
+            OutputEncoding::Strict => std::str::from_utf8(bytes).map(Cow::Borrowed).map_err(|e| {
+                OutputError::InvalidUtf8 {
+                    offset: e.valid_up_to(),
+                }
+            }),
+            OutputEncoding::Base64 => Ok(Cow::Owned(
+                base64::engine::general_purpose::STANDARD.encode(bytes),
+            )),
+        }
+    }
+}
+
+/// Make the result of a call from what the tool wrote and how it exited.
+///
+/// `stdout` is decoded by the definition's encoding, and `stderr` as UTF-8
+/// with invalid bytes replaced. `stdout` is then parsed by the definition's
+/// capture, extract expression, or template, in that order of precedence,
+/// and captures from `stderr` are added. When the schema declares
+/// [`EXIT_CODE_PROPERTY`], `exit_code` is added too, and `stdout` need not
+/// match the template: a tool reporting success by its exit status often
+/// prints nothing at all.
+pub fn parse_result(
+    output: &ToolOutput,
+    stdout: &[u8],
+    stderr: &[u8],
+    exit_code: Option<i32>,
+) -> Result<Map<String, Value>, OutputError> {
+    let stdout = &*output.encoding.decode(stdout)?;
+    let stderr = &*String::from_utf8_lossy(stderr);
+    let declares_exit_code = declares_exit_code(&output.schema);
+    let mut result = if let Some(capture) = &output.capture {
+        capture.apply(stdout)
+    } else if let Some(extract) = &output.extract {
+        let extract = Extract::parse(extract).map_err(OutputError::Extract)?;
+        match extract
+            .apply_to_output(stdout)
+            .map_err(OutputError::Extract)?
+        {
+            Value::Object(result) => result,
+            _ => {
+                return Err(OutputError::Extract(ExtractError::Type {
+                    message: "the result is not an object".to_string(),
+                }))
+            }
+        }
+    } else if output.template.is_empty() {
+        Map::new()
+    } else {
+        let parser = output.template.compile().map_err(OutputError::Pattern)?;
+        match parser.try_parse(stdout)? {
+            Some(result) => result,
+            None if declares_exit_code => Map::new(),
+            None => {
+                return Err(OutputError::NoMatch {
+                    patterns: output.template.patterns().len(),
+                })
+            }
+        }
+    };
+    if !output.stderr_template.is_empty() {
+        let parser = output
+            .stderr_template
+            .compile()
+            .map_err(OutputError::Pattern)?;
+        result.extend(parser.try_parse(stderr)?.unwrap_or_default());
+    }
+    if let Some(code) = exit_code.filter(|_| declares_exit_code) {
+        result.insert(EXIT_CODE_PROPERTY.to_string(), Value::from(code));
+    }
+    Ok(result)
+}
+
+/// The patterns that parse a tool's output, tried in order.
+#[derive(Debug, Clone, Default, PartialEq, JsonSchema)]
+#[schemars(from = "TemplateRepr")]
+pub struct OutputTemplate {
+    patterns: Vec<OutputPattern>,
+}
+
+/// One way of parsing a tool's output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct OutputPattern {
+    /// Regular expression whose named capture groups become properties
+    pub pattern: String,
+
+    /// Regular expression flags, such as `ms` (see the module
+    /// documentation)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub flags: String,
+
+    /// Properties added to the result when this pattern matches
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub set: Map<String, Value>,
+}
+
+impl OutputPattern {
+    /// A pattern that sets nothing besides its captures.
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            flags: String::new(),
+            set: Map::new(),
+        }
+    }
+
+    /// Compile the pattern with its flags, taking the types out of its
+    /// capture groups.
+    pub fn compile(&self) -> Result<CompiledPattern, String> {
+        let (pattern, types) = strip_capture_types(&self.pattern)?;
+        let mut builder = RegexBuilder::new(&pattern);
+        for flag in self.flags.chars() {
+            match flag {
+                'i' => builder.case_insensitive(true),
+                'm' => builder.multi_line(true),
+                's' => builder.dot_matches_new_line(true),
+                'x' => builder.ignore_whitespace(true),
+                flag => {
+                    return Err(format!(
+                        "unknown flag \"{}\" (expected i, m, s, or x)",
+                        flag
+                    ))
+                }
+            };
+        }
+        let regex = builder.build().map_err(|error| error.to_string())?;
+        Ok(CompiledPattern {
+            regex,
+            types,
+            set: self.set.clone(),
+        })
+    }
+
+    /// Whether the pattern is just its text, with no flags or properties.
+    fn is_plain(&self) -> bool {
+        self.flags.is_empty() && self.set.is_empty()
+    }
+}
+
+impl OutputTemplate {
+    /// A template trying `patterns` in order.
+    pub fn new(patterns: Vec<OutputPattern>) -> Self {
+        Self { patterns }
+    }
+
+    /// Whether there are no patterns, as when a definition extracts its
+    /// result from JSON instead.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// The patterns, in the order they are tried.
+    pub fn patterns(&self) -> &[OutputPattern] {
+        &self.patterns
+    }
+
+    /// Where the pattern at `index` is written in a definition, for
+    /// diagnostics, given where the template is written.
+    pub fn field(&self, base: &str, index: usize) -> String {
+        if self.patterns.len() == 1 {
+            base.to_string()
+        } else {
+            format!("{}.{}", base, index)
+        }
+    }
+
+    /// Compile the patterns.
+    pub fn compile(&self) -> Result<OutputParser, PatternError> {
+        let patterns = self
+            .patterns
+            .iter()
+            .enumerate()
+            .map(|(index, pattern)| {
+                pattern
+                    .compile()
+                    .map_err(|message| PatternError { index, message })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(OutputParser { patterns })
+    }
+}
+
+impl From<&str> for OutputTemplate {
+    fn from(pattern: &str) -> Self {
+        Self::new(vec![OutputPattern::new(pattern)])
+    }
+}
+
+impl From<String> for OutputTemplate {
+    fn from(pattern: String) -> Self {
+        Self::new(vec![OutputPattern::new(pattern)])
+    }
+}
+
+/// A template is equal to the text of its only pattern, if it has one and
+/// that pattern has no flags or properties.
+impl PartialEq<&str> for OutputTemplate {
+    fn eq(&self, other: &&str) -> bool {
+        match self.patterns.as_slice() {
+            [pattern] => pattern.is_plain() && pattern.pattern == *other,
+            _ => false,
+        }
+    }
+}
+
+/// How a template is written: a single pattern, or a list of patterns,
+/// each a string or a pattern with flags or properties to set.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum TemplateRepr {
+    Single(String),
+    List(Vec<PatternRepr>),
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum PatternRepr {
+    Plain(String),
+    Pattern(OutputPattern),
+}
+
+impl Serialize for OutputTemplate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match self.patterns.as_slice() {
+            [pattern] if pattern.is_plain() => TemplateRepr::Single(pattern.pattern.clone()),
+            patterns => TemplateRepr::List(
+                patterns
+                    .iter()
+                    .map(|pattern| {
+                        if pattern.is_plain() {
+                            PatternRepr::Plain(pattern.pattern.clone())
+                        } else {
+                            PatternRepr::Pattern(pattern.clone())
+                        }
+                    })
+                    .collect(),
+            ),
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OutputTemplate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let patterns = match TemplateRepr::deserialize(deserializer)? {
+            TemplateRepr::Single(pattern) => vec![OutputPattern::new(pattern)],
+            TemplateRepr::List(patterns) if patterns.is_empty() => {
+                return Err(serde::de::Error::custom(
+                    "output template needs at least one pattern",
+                ))
+            }
+            TemplateRepr::List(patterns) => patterns
+                .into_iter()
+                .map(|pattern| match pattern {
+                    PatternRepr::Plain(pattern) => OutputPattern::new(pattern),
+                    PatternRepr::Pattern(pattern) => pattern,
+                })
+                .collect(),
+        };
+        Ok(Self { patterns })
+    }
+}
+
+/// The type a capture is converted to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaptureType {
+    #[default]
+    String,
+    Int,
+    Float,
+    Bool,
+    Json,
+}
+
+impl CaptureType {
+    /// Every type, in the order they are listed in diagnostics.
+    pub const ALL: [CaptureType; 5] = [
+        CaptureType::String,
+        CaptureType::Int,
+        CaptureType::Float,
+        CaptureType::Bool,
+        CaptureType::Json,
+    ];
+
+    /// The type written as `name` in a capture group.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// How the type is written in a capture group.
+    pub fn name(self) -> &'static str {
+        match self {
+            CaptureType::String => "string",
+            CaptureType::Int => "int",
+            CaptureType::Float => "float",
+            CaptureType::Bool => "bool",
+            CaptureType::Json => "json",
+        }
+    }
+
+    /// The JSON Schema types a converted value can have.
+    pub fn schema_types(self) -> &'static [&'static str] {
+        match self {
+            CaptureType::String => &["string"],
+            CaptureType::Int => &["integer", "number"],
+            CaptureType::Float => &["number"],
+            CaptureType::Bool => &["boolean"],
+            CaptureType::Json => &[
+                "string", "integer", "number", "boolean", "object", "array", "null",
+            ],
+        }
+    }
+
+    /// Convert the captured `text`.
+    fn convert(self, text: &str) -> Option<Value> {
+        match self {
+            CaptureType::String => Some(Value::String(text.to_string())),
+            CaptureType::Int => text.parse::<i64>().ok().map(Value::from),
+            CaptureType::Float => text
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            CaptureType::Bool => match text.to_ascii_lowercase().as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            CaptureType::Json => serde_json::from_str(text).ok(),
+        }
+    }
+}
+
+impl fmt::Display for CaptureType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Remove the types from the named capture groups of `pattern`, as in
+/// `(?<count:int>...)`, returning the plain regular expression and the type
+/// of each group that gave one.
+fn strip_capture_types(pattern: &str) -> Result<(String, BTreeMap<String, CaptureType>), String> {
+    let mut plain = String::with_capacity(pattern.len());
+    let mut types = BTreeMap::new();
+    let mut rest = pattern;
+    while let Some(index) = rest.find(['\\', '(']) {
+        let (before, from) = rest.split_at(index);
+        plain.push_str(before);
+        if from.starts_with('\\') {
+            let escape = from.char_indices().nth(2).map_or(from.len(), |(i, _)| i);
+            plain.push_str(&from[..escape]);
+            rest = &from[escape..];
+            continue;
+        }
+        let Some(group) = ["(?<", "(?P<"].into_iter().find(|g| from.starts_with(g)) else {
+            plain.push('(');
+            rest = &from[1..];
+            continue;
+        };
+        let Some(end) = from.find('>') else {
+            // Left for the regular expression to report.
+            plain.push_str(from);
+            rest = "";
+            break;
+        };
+        match from[group.len()..end].split_once(':') {
+            Some((name, type_name)) => {
+                let capture_type = CaptureType::from_name(type_name).ok_or_else(|| {
+                    format!(
+                        "unknown type \"{}\" for capture \"{}\" (expected one of: {})",
+                        type_name,
+                        name,
+                        CaptureType::ALL.map(CaptureType::name).join(", ")
+                    )
+                })?;
+                types.insert(name.to_string(), capture_type);
+                plain.push_str(group);
+                plain.push_str(name);
+                plain.push('>');
+            }
+            None => plain.push_str(&from[..=end]),
+        }
+        rest = &from[end + 1..];
+    }
+    plain.push_str(rest);
+    Ok((plain, types))
+}
+
+/// An output pattern ready to match output.
+#[derive(Debug, Clone)]
+pub struct CompiledPattern {
+    /// The regular expression, without capture types
+    pub regex: Regex,
+
+    /// The types captures are converted to, by group name; other captures
+    /// are strings
+    pub types: BTreeMap<String, CaptureType>,
+
+    /// Properties added to the result when the pattern matches
+    pub set: Map<String, Value>,
+}
+
+impl CompiledPattern {
+    /// The type of the capture group `name`.
+    pub fn capture_type(&self, name: &str) -> CaptureType {
+        self.types.get(name).copied().unwrap_or_default()
+    }
+}
+
+/// A result made from the whole output, instead of from patterns.
+///
+/// `capture: full` returns the output as the `result` property, and
+/// `capture: { full: text }` as the `text` property.
+#[derive(Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[schemars(from = "CaptureRepr")]
+pub enum OutputCapture {
+    /// The whole output, without its trailing newlines, is one property.
+    Full { property: String },
+}
+
+impl OutputCapture {
+    /// The property the output is captured as, unless one is named.
+    pub const DEFAULT_PROPERTY: &'static str = "result";
+
+    /// The property the output is captured as.
+    pub fn property(&self) -> &str {
+        match self {
+            OutputCapture::Full { property } => property,
+        }
+    }
+
+    /// Make a result from a tool's output.
+    pub fn apply(&self, output: &str) -> Map<String, Value> {
+        let text = output.trim_end_matches(['\n', '\r']);
+        let mut result = Map::new();
+        result.insert(self.property().to_string(), Value::String(text.to_string()));
+        result
+    }
+}
+
+/// How a capture is written: `full`, or `{ full: property }`.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum CaptureRepr {
+    Full,
+    #[serde(untagged)]
+    Named {
+        full: String,
+    },
+}
+
+impl Serialize for OutputCapture {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match self {
+            OutputCapture::Full { property } if property == Self::DEFAULT_PROPERTY => {
+                CaptureRepr::Full
+            }
+            OutputCapture::Full { property } => CaptureRepr::Named {
+                full: property.clone(),
+            },
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OutputCapture {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let property = match CaptureRepr::deserialize(deserializer)? {
+            CaptureRepr::Full => Self::DEFAULT_PROPERTY.to_string(),
+            CaptureRepr::Named { full } => full,
+        };
+        Ok(OutputCapture::Full { property })
+    }
+}
+
+/// Compiled output patterns.
+#[derive(Debug, Clone)]
+pub struct OutputParser {
+    patterns: Vec<CompiledPattern>,
+}
+
+impl OutputParser {
+    /// Parse a tool's output with the first pattern that matches it.
+    ///
+    /// Each named capture group that took part in the match becomes a
+    /// property, converted to the group's type; the pattern's `set`
+    /// properties are added after them.
+    pub fn parse(&self, output: &str) -> Result<Map<String, Value>, OutputError> {
+        self.try_parse(output)?.ok_or(OutputError::NoMatch {
+            patterns: self.patterns.len(),
+        })
+    }
+
+    /// Parse text that need not match, as stderr, returning `None` when no
+    /// pattern matches it.
+    pub fn try_parse(&self, output: &str) -> Result<Option<Map<String, Value>>, OutputError> {
+        for pattern in &self.patterns {
+            let Some(captures) = pattern.regex.captures(output) else {
+                continue;
+            };
+            let mut result = Map::new();
+            for name in pattern.regex.capture_names().flatten() {
+                let Some(text) = captures.name(name) else {
+                    continue;
+                };
+                let capture_type = pattern.capture_type(name);
+                let value = capture_type.convert(text.as_str()).ok_or_else(|| {
+                    OutputError::InvalidCapture {
+                        name: name.to_string(),
+                        text: text.as_str().to_string(),
+                        capture_type,
+                    }
+                })?;
+                result.insert(name.to_string(), value);
+            }
+            result.extend(pattern.set.clone());
+            return Ok(Some(result));
+        }
+        Ok(None)
+    }
+}
+
+/// An output pattern that is not a valid regular expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    /// Position of the pattern in the template
+    pub index: usize,
+
+    /// Why it does not compile
+    pub message: String,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "output pattern {} is not a valid regular expression: {}",
+            self.index, self.message
+        )
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// Output that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputError {
+    /// A pattern of the definition does not compile.
+    Pattern(PatternError),
+
+    /// The extract expression does not parse, or failed on the output.
+    Extract(ExtractError),
+
+    /// No pattern matched the output.
+    NoMatch { patterns: usize },
+
+    /// The output is not valid UTF-8, from byte `offset` on.
+    InvalidUtf8 { offset: usize },
+
+    /// A capture could not be converted to its type.
+    InvalidCapture {
+        name: String,
+        text: String,
+        capture_type: CaptureType,
+    },
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputError::Pattern(error) => write!(f, "{}", error),
+            OutputError::Extract(error) => write!(f, "{}", error),
+            OutputError::NoMatch { patterns: 1 } => {
+                write!(f, "output does not match the output template")
+            }
+            OutputError::NoMatch { patterns } => {
+                write!(f, "output matches none of the {} output patterns", patterns)
+            }
+            OutputError::InvalidUtf8 { offset } => {
+                write!(f, "output is not valid UTF-8 (at byte {})", offset)
+            }
+            OutputError::InvalidCapture {
+                name,
+                text,
+                capture_type,
+            } => write!(
+                f,
+                "captured \"{}\" as \"{}\", which is not a valid {}",
+                text, name, capture_type
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_template() {
+        let single: OutputTemplate = serde_yaml_ng::from_str(r"'ID: (?<id>\d+)'").unwrap();
+        assert_eq!(single, r"ID: (?<id>\d+)");
+        assert_eq!(
+            serde_json::to_value(&single).unwrap(),
+            json!(r"ID: (?<id>\d+)")
+        );
+
+        let list: OutputTemplate = serde_yaml_ng::from_str(
+            r"
+- 'Created: (?<url>\S+)'
+- pattern: 'Error: (?<error>.*)'
+  set: { ok: false }
+",
+        )
+        .unwrap();
+        assert_eq!(list.patterns().len(), 2);
+        assert_eq!(list.patterns()[1].set["ok"], false);
+        assert_eq!(list.field("output.template", 1), "output.template.1");
+        let yaml = serde_yaml_ng::to_string(&list).unwrap();
+        assert_eq!(
+            serde_yaml_ng::from_str::<OutputTemplate>(&yaml).unwrap(),
+            list
+        );
+
+        assert!(serde_yaml_ng::from_str::<OutputTemplate>("[]").is_err());
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let template = OutputTemplate::new(vec![
+            OutputPattern::new(r"Created: (?<url>\S+)(?: as (?<id>\d+))?"),
+            OutputPattern {
+                set: json!({"ok": false}).as_object().unwrap().clone(),
+                ..OutputPattern::new("Error: (?<error>.*)")
+            },
+        ]);
+        let parser = template.compile().unwrap();
+
+        assert_eq!(
+            Value::Object(parser.parse("Created: https://x/1").unwrap()),
+            json!({"url": "https://x/1"})
+        );
+        assert_eq!(
+            Value::Object(parser.parse("Error: no access").unwrap()),
+            json!({"error": "no access", "ok": false})
+        );
+        assert_eq!(
+            parser.parse("???").unwrap_err().to_string(),
+            "output matches none of the 2 output patterns"
+        );
+
+        assert_eq!(parser.try_parse("???"), Ok(None));
+
+        let error = OutputTemplate::from("(?<a>").compile().unwrap_err();
+        assert_eq!(error.index, 0);
+    }
+
+    #[test]
+    fn test_flags() {
+        let output = "Status: done\nTook 3s\nNotes:\nAll good.\nNo retries.";
+        let parse = |template: &str| {
+            let template: OutputTemplate = serde_yaml_ng::from_str(template).unwrap();
+            template.compile().unwrap().parse(output).map(Value::Object)
+        };
+        let expected = json!({"status": "done", "notes": "All good.\nNo retries."});
+
+        assert_eq!(
+            parse(r"'(?ms)^Status: (?<status>\w+)$.*^Notes:\n(?<notes>.*)'").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse(r"[{pattern: '^Status: (?<status>\w+)$.*^Notes:\n(?<notes>.*)', flags: ms}]")
+                .unwrap(),
+            expected
+        );
+        // Without flags, `$` only matches at the end, and `.` not newlines.
+        assert!(parse(r"'^Status: (?<status>\w+)$'").is_err());
+        assert_eq!(
+            parse("[{pattern: 'STATUS: (?<status>DONE)', flags: i}]").unwrap(),
+            json!({"status": "done"})
+        );
+        assert_eq!(
+            OutputPattern {
+                flags: "g".to_string(),
+                ..OutputPattern::new("x")
+            }
+            .compile()
+            .unwrap_err(),
+            "unknown flag \"g\" (expected i, m, s, or x)"
+        );
+    }
+
+    #[test]
+    fn test_capture_types() {
+        let parser = OutputTemplate::from(
+            r"(?<n:int>-?\d+) (?P<ok:bool>\w+) (?<r:float>\S+) (?<meta:json>\{.*\}) \((?<s:string>\w+)\) \\(?<x>\w)",
+        )
+        .compile()
+        .unwrap();
+        assert_eq!(
+            Value::Object(parser.parse(r#"-3 True 0.5 {"a":[1]} (id) \q"#).unwrap()),
+            json!({"n": -3, "ok": true, "r": 0.5, "meta": {"a": [1]}, "s": "id", "x": "q"})
+        );
+        assert_eq!(
+            parser
+                .parse(r#"3 maybe 0.5 {} (id) \q"#)
+                .unwrap_err()
+                .to_string(),
+            r#"captured "maybe" as "ok", which is not a valid bool"#
+        );
+
+        assert_eq!(
+            strip_capture_types(r"\(?<a:int>x\) (?:y) (?<b>z)").unwrap(),
+            (r"\(?<a:int>x\) (?:y) (?<b>z)".to_string(), BTreeMap::new())
+        );
+        assert_eq!(
+            OutputTemplate::from("(?<a:date>x)")
+                .compile()
+                .unwrap_err()
+                .message,
+            r#"unknown type "date" for capture "a" (expected one of: string, int, float, bool, json)"#
+        );
+    }
+
+    #[test]
+    fn test_capture() {
+        let full: OutputCapture = serde_yaml_ng::from_str("full").unwrap();
+        assert_eq!(full.property(), "result");
+        assert_eq!(
+            Value::Object(full.apply("line 1\nline 2\n\n")),
+            json!({"result": "line 1\nline 2"})
+        );
+        assert_eq!(serde_json::to_value(&full).unwrap(), json!("full"));
+
+        let named: OutputCapture = serde_yaml_ng::from_str("{full: text}").unwrap();
+        assert_eq!(named.property(), "text");
+        assert_eq!(
+            serde_json::to_value(&named).unwrap(),
+            json!({"full": "text"})
+        );
+
+        assert!(serde_yaml_ng::from_str::<OutputCapture>("partial").is_err());
+    }
+
+    #[test]
+    fn test_parse_result() {
+        let mut output: ToolOutput = serde_yaml_ng::from_str(
+            r"
+template: '(?<errors:int>\d+) errors'
+stderr_template: 'took (?<seconds:float>\S+)s'
+schema:
+  type: object
+  properties:
+    errors: { type: integer }
+    exit_code: { type: integer }
+",
+        )
+        .unwrap();
+        assert!(declares_exit_code(&output.schema));
+
+        assert_eq!(
+            Value::Object(parse_result(&output, b"2 errors\n", b"took 0.5s", Some(1)).unwrap()),
+            json!({"errors": 2, "seconds": 0.5, "exit_code": 1})
+        );
+        // With an exit code, output need not match.
+        assert_eq!(
+            Value::Object(parse_result(&output, b"", b"", Some(0)).unwrap()),
+            json!({"exit_code": 0})
+        );
+
+        output.schema = json!({"type": "object"});
+        assert_eq!(
+            parse_result(&output, b"", b"", Some(0)).unwrap_err(),
+            OutputError::NoMatch { patterns: 1 }
+        );
+
+        output.extract = Some(".items[0]".to_string());
+        assert_eq!(
+            Value::Object(parse_result(&output, br#"{"items": [{"id": 7}]}"#, b"", None).unwrap()),
+            json!({"id": 7})
+        );
+        output.extract = Some(".items[0].id".to_string());
+        assert!(matches!(
+            parse_result(&output, br#"{"items": [{"id": 7}]}"#, b"", None),
+            Err(OutputError::Extract(_))
+        ));
+    }
+
+    #[test]
+    fn test_encoding() {
+        let bytes = b"caf\xe9 \x89PNG";
+        assert_eq!(
+            OutputEncoding::Lossy.decode(bytes).unwrap(),
+            "caf\u{FFFD} \u{FFFD}PNG"
+        );
+        assert_eq!(
+            OutputEncoding::Strict.decode(bytes).unwrap_err(),
+            OutputError::InvalidUtf8 { offset: 3 }
+        );
+        assert_eq!(OutputEncoding::Strict.decode(b"ok").unwrap(), "ok");
+        assert_eq!(
+            OutputEncoding::Base64.decode(bytes).unwrap(),
+            "Y2Fm6SCJUE5H"
+        );
+
+        let output: ToolOutput = serde_yaml_ng::from_str(
+            "{capture: {full: png}, encoding: base64, schema: {type: object}}",
+        )
+        .unwrap();
+        assert_eq!(
+            Value::Object(parse_result(&output, b"\x89PNG\r\n", b"", None).unwrap()),
+            json!({"png": "iVBORw0K"})
+        );
+    }
+}
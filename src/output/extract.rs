@@ -0,0 +1,416 @@
+//! jq-style extraction from JSON output (`output.extract`).
+//!
+//! Tools that already print JSON need no regular expression: an extract
+//! expression reshapes their output into the declared result.
+//!
+//! ```yaml
+//! output:
+//!   extract: '{items: [.items[] | {name, id}], total: .meta.count}'
+//! ```
+//!
+//! The supported subset of jq:
+//!
+//! - `.` is the whole input, `.name` (or `."some name"`) a field, `.[2]` (or
+//!   `.[-1]`) an array item, and `.[]` each item (or value) in turn. They
+//!   combine into paths such as `.items[0].id`.
+//! - `a | b` runs `b` on each result of `a`.
+//! - `{name, id: .meta.id}` builds an object; `name` alone is short for
+//!   `name: .name`.
+//! - `[...]` collects every result into an array.
+//!
+//! An expression with several results (such as `.items[]`) produces an
+//! array of them, as if wrapped in `[...]`.
+
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// A parsed extract expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extract {
+    filter: Filter,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    /// A path from the input; an empty path is the input itself.
+    Path(Vec<Segment>),
+
+    /// `a | b`
+    Pipe(Box<Filter>, Box<Filter>),
+
+    /// `{key: value, ...}`
+    Object(Vec<(String, Filter)>),
+
+    /// `[...]`
+    Collect(Box<Filter>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// `.name`
+    Field(String),
+
+    /// `[N]`, counting from the end when negative
+    Index(i64),
+
+    /// `[]`
+    Iterate,
+}
+
+impl Extract {
+    /// Parse the expression `source`.
+    pub fn parse(source: &str) -> Result<Self, ExtractError> {
+        let mut parser = Parser { source, offset: 0 };
+        let filter = parser.pipeline()?;
+        parser.skip_whitespace();
+        if parser.offset < source.len() {
+            return Err(parser.expected("\"|\" or the end of the expression"));
+        }
+        Ok(Self { filter })
+    }
+
+    /// Apply the expression to a JSON value.
+    pub fn apply(&self, input: &Value) -> Result<Value, ExtractError> {
+        let mut results = eval(&self.filter, input)?;
+        Ok(match results.len() {
+            1 => results.remove(0),
+            _ => Value::Array(results),
+        })
+    }
+
+    /// Parse a tool's output as JSON and apply the expression to it.
+    pub fn apply_to_output(&self, output: &str) -> Result<Value, ExtractError> {
+        let input = serde_json::from_str(output).map_err(|error| ExtractError::InvalidJson {
+            message: error.to_string(),
+        })?;
+        self.apply(&input)
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `c`, after any whitespace, if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.offset += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expected(&self, what: &str) -> ExtractError {
+        ExtractError::Syntax {
+            expected: what.to_string(),
+            offset: self.offset,
+        }
+    }
+
+    fn pipeline(&mut self) -> Result<Filter, ExtractError> {
+        let mut filter = self.term()?;
+        while self.eat('|') {
+            filter = Filter::Pipe(Box::new(filter), Box::new(self.term()?));
+        }
+        Ok(filter)
+    }
+
+    fn term(&mut self) -> Result<Filter, ExtractError> {
+        if self.eat('.') {
+            let mut segments = Vec::new();
+            if let Some(name) = self.key()? {
+                segments.push(Segment::Field(name));
+            }
+            self.suffixes(&mut segments)?;
+            Ok(Filter::Path(segments))
+        } else if self.eat('{') {
+            self.object()
+        } else if self.eat('[') {
+            let filter = self.pipeline()?;
+            if !self.eat(']') {
+                return Err(self.expected("\"]\""));
+            }
+            Ok(Filter::Collect(Box::new(filter)))
+        } else {
+            Err(self.expected("\".\", \"{\", or \"[\""))
+        }
+    }
+
+    /// Parse the `.name`, `[N]`, and `[]` following a path's start.
+    fn suffixes(&mut self, segments: &mut Vec<Segment>) -> Result<(), ExtractError> {
+        loop {
+            if self.rest().starts_with('.') {
+                self.offset += 1;
+                let name = self.key()?.ok_or_else(|| self.expected("a field name"))?;
+                segments.push(Segment::Field(name));
+            } else if self.rest().starts_with('[') {
+                self.offset += 1;
+                if self.eat(']') {
+                    segments.push(Segment::Iterate);
+                    continue;
+                }
+                self.skip_whitespace();
+                let rest = self.rest();
+                let len = rest
+                    .char_indices()
+                    .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+                    .map_or(rest.len(), |(i, _)| i);
+                let index = rest[..len]
+                    .parse()
+                    .map_err(|_| self.expected("an array index"))?;
+                self.offset += len;
+                if !self.eat(']') {
+                    return Err(self.expected("\"]\""));
+                }
+                segments.push(Segment::Index(index));
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Parse a field name, bare or quoted, if one comes next.
+    fn key(&mut self) -> Result<Option<String>, ExtractError> {
+        let rest = self.rest();
+        if rest.starts_with('"') {
+            let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<String>();
+            let name = match stream.next() {
+                Some(Ok(name)) => name,
+                _ => return Err(self.expected("a closing quote")),
+            };
+            self.offset += stream.byte_offset();
+            return Ok(Some(name));
+        }
+        let len = rest
+            .char_indices()
+            .find(|&(i, c)| !(c == '_' || c.is_alphabetic() || (i > 0 && c.is_alphanumeric())))
+            .map_or(rest.len(), |(i, _)| i);
+        self.offset += len;
+        Ok((len > 0).then(|| rest[..len].to_string()))
+    }
+
+    fn object(&mut self) -> Result<Filter, ExtractError> {
+        let mut entries = Vec::new();
+        if self.eat('}') {
+            return Ok(Filter::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.key()?.ok_or_else(|| self.expected("a key"))?;
+            let value = if self.eat(':') {
+                self.term()?
+            } else {
+                Filter::Path(vec![Segment::Field(key.clone())])
+            };
+            entries.push((key, value));
+            if self.eat('}') {
+                return Ok(Filter::Object(entries));
+            }
+            if !self.eat(',') {
+                return Err(self.expected("\",\" or \"}\""));
+            }
+        }
+    }
+}
+
+fn eval(filter: &Filter, input: &Value) -> Result<Vec<Value>, ExtractError> {
+    match filter {
+        Filter::Path(segments) => {
+            let mut values = vec![input.clone()];
+            for segment in segments {
+                let mut next = Vec::new();
+                for value in &values {
+                    step(segment, value, &mut next)?;
+                }
+                values = next;
+            }
+            Ok(values)
+        }
+        Filter::Pipe(first, second) => {
+            let mut results = Vec::new();
+            for value in eval(first, input)? {
+                results.extend(eval(second, &value)?);
+            }
+            Ok(results)
+        }
+        Filter::Object(entries) => {
+            // Like jq, a value with several results gives an object for
+            // each combination.
+            let mut objects = vec![Map::new()];
+            for (key, value) in entries {
+                let values = eval(value, input)?;
+                objects = objects
+                    .iter()
+                    .flat_map(|object| {
+                        values.iter().map(move |value| {
+                            let mut object = object.clone();
+                            object.insert(key.clone(), value.clone());
+                            object
+                        })
+                    })
+                    .collect();
+            }
+            Ok(objects.into_iter().map(Value::Object).collect())
+        }
+        Filter::Collect(filter) => Ok(vec![Value::Array(eval(filter, input)?)]),
+    }
+}
+
+fn step(segment: &Segment, value: &Value, next: &mut Vec<Value>) -> Result<(), ExtractError> {
+    match (segment, value) {
+        (Segment::Field(_) | Segment::Index(_), Value::Null) => next.push(Value::Null),
+        (Segment::Field(name), Value::Object(object)) => {
+            next.push(object.get(name).cloned().unwrap_or(Value::Null))
+        }
+        (Segment::Index(index), Value::Array(items)) => {
+            let index = if *index < 0 {
+                items.len().checked_sub(index.unsigned_abs() as usize)
+            } else {
+                Some(*index as usize)
+            };
+            next.push(
+                index
+                    .and_then(|i| items.get(i))
+                    .cloned()
+                    .unwrap_or(Value::Null),
+            );
+        }
+        (Segment::Iterate, Value::Array(items)) => next.extend(items.iter().cloned()),
+        (Segment::Iterate, Value::Object(object)) => next.extend(object.values().cloned()),
+        (segment, value) => {
+            return Err(ExtractError::Type {
+                message: format!("cannot {} {}", segment.describe(), kind(value)),
+            })
+        }
+    }
+    Ok(())
+}
+
+impl Segment {
+    fn describe(&self) -> String {
+        match self {
+            Segment::Field(name) => format!("get field \"{}\" of", name),
+            Segment::Index(index) => format!("get item {} of", index),
+            Segment::Iterate => "iterate over".to_string(),
+        }
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// An extract expression that could not be parsed or applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractError {
+    /// The expression is malformed.
+    Syntax { expected: String, offset: usize },
+
+    /// The tool's output is not JSON.
+    InvalidJson { message: String },
+
+    /// The expression does not fit the shape of the output.
+    Type { message: String },
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Syntax { expected, offset } => {
+                write!(f, "expected {} at byte {}", expected, offset)
+            }
+            ExtractError::InvalidJson { message } => {
+                write!(f, "output is not valid JSON: {}", message)
+            }
+            ExtractError::Type { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn extract(expression: &str, input: Value) -> Result<Value, ExtractError> {
+        Extract::parse(expression).unwrap().apply(&input)
+    }
+
+    #[test]
+    fn test_extract() {
+        let input = json!({
+            "items": [{"name": "a", "id": 1, "x": 0}, {"name": "b", "id": 2}],
+            "meta": {"count": 2, "next page": null}
+        });
+
+        assert_eq!(
+            extract(".items[] | {name, id}", input.clone()).unwrap(),
+            json!([{"name": "a", "id": 1}, {"name": "b", "id": 2}])
+        );
+        assert_eq!(
+            extract(
+                "{items: [.items[].name], total: .meta.count, next: .meta.\"next page\"}",
+                input.clone()
+            )
+            .unwrap(),
+            json!({"items": ["a", "b"], "total": 2, "next": null})
+        );
+        assert_eq!(extract(".items[-1].id", input.clone()).unwrap(), json!(2));
+        assert_eq!(extract(".", json!(3)).unwrap(), json!(3));
+        assert_eq!(
+            extract(".missing.deeper", input.clone()).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            extract(".items.name", input).unwrap_err().to_string(),
+            "cannot get field \"name\" of an array"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Extract::parse("items").unwrap_err(),
+            ExtractError::Syntax {
+                expected: "\".\", \"{\", or \"[\"".to_string(),
+                offset: 0
+            }
+        );
+        assert!(Extract::parse(".a | ").is_err());
+        assert!(Extract::parse("{a: .b").is_err());
+        assert!(Extract::parse(".[x]").is_err());
+        assert!(Extract::parse(".a .b").is_err());
+
+        let extract = Extract::parse(".a").unwrap();
+        assert!(matches!(
+            extract.apply_to_output("not json"),
+            Err(ExtractError::InvalidJson { .. })
+        ));
+        assert_eq!(
+            extract.apply_to_output("{\"a\": [1]}\n").unwrap(),
+            json!([1])
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Previewing tool calls without running the tool.
+//!
+//! [`invocation`] computes what a call with some arguments would run, and
+//! [`round_trip`] also parses sample output the way the tool's real output
+//! would be. Together they let tool authors, and the `test` command, check
+//! a definition's templates against known arguments and output:
+//!
+//! ```ignore
+//! let trip = preview::round_trip(&definition, &arguments, &SampleOutput::stdout("ID: 42\n"))?;
+//! assert_eq!(trip.invocation.command, CommandLine::Args(vec!["--title".into(), "Fix".into()]));
+//! assert_eq!(trip.result["id"], "42");
+//! ```
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::output::{parse_result, OutputError};
+use crate::quote::QuoteStyle;
+use crate::redact::Redactor;
+use crate::template::shell::ShellTemplate;
+use crate::template::{EnvTemplate, ExpandError, InputTemplate, TemplateError};
+use crate::tool_discovery::ToolDefinition;
+
+/// How the tool would be run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandLine {
+    /// The executable's arguments
+    Args(Vec<String>),
+
+    /// A script for the platform shell (`execution.shell`)
+    Script(String),
+}
+
+/// What a call would run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// The arguments or shell script
+    pub command: CommandLine,
+
+    /// Text written to the tool's standard input, if any
+    pub stdin: Option<String>,
+
+    /// Environment variables set for the tool, from `execution.env` and
+    /// `input.env_template`
+    pub env: BTreeMap<String, String>,
+}
+
+impl Invocation {
+    /// The invocation as it may be shown, with the sensitive values of
+    /// `arguments` hidden by `redactor`.
+    pub fn redact(&self, redactor: &Redactor, arguments: &Value) -> Invocation {
+        let redact = |text: &String| redactor.redact_text(text, arguments);
+        Invocation {
+            command: match &self.command {
+                CommandLine::Args(args) => CommandLine::Args(args.iter().map(redact).collect()),
+                CommandLine::Script(script) => CommandLine::Script(redact(script)),
+            },
+            stdin: self.stdin.as_ref().map(redact),
+            env: self
+                .env
+                .iter()
+                .map(|(name, value)| (name.clone(), redact(value)))
+                .collect(),
+        }
+    }
+}
+
+/// Output to parse as if a tool had written it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SampleOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: Option<i32>,
+}
+
+impl SampleOutput {
+    /// Output written to stdout by a tool that exited successfully.
+    pub fn stdout(stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            stdout: stdout.into(),
+            stderr: Vec::new(),
+            exit_code: Some(0),
+        }
+    }
+}
+
+/// A call previewed from its arguments to its result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundTrip {
+    /// What the call would run
+    pub invocation: Invocation,
+
+    /// The result parsed from the sample output
+    pub result: Map<String, Value>,
+}
+
+/// Compute what a call of `definition` with `arguments` would run.
+pub fn invocation(
+    definition: &ToolDefinition,
+    arguments: &Map<String, Value>,
+) -> Result<Invocation, PreviewError> {
+    let input = &definition.input;
+    let template_error = |field: &str| {
+        let field = field.to_string();
+        move |error| PreviewError::Template { field, error }
+    };
+
+    let command = if definition.execution.shell {
+        let template =
+            ShellTemplate::parse(&input.template, input.engine, QuoteStyle::native_shell())
+                .map_err(template_error("input.template"))?;
+        CommandLine::Script(template.render(arguments)?)
+    } else {
+        let template = InputTemplate::parse_command(&input.template, input.engine)
+            .map_err(template_error("input.template"))?;
+        CommandLine::Args(template.expand(arguments)?)
+    };
+    let stdin = match &input.stdin_template {
+        Some(source) => Some(
+            InputTemplate::parse_text(source, input.engine)
+                .map_err(template_error("input.stdin_template"))?
+                .render(arguments)?,
+        ),
+        None => None,
+    };
+    let mut env = definition.execution.env.clone();
+    env.extend(
+        EnvTemplate::parse(&input.env_template, input.engine)
+            .map_err(template_error("input.env_template"))?
+            .render(arguments)?,
+    );
+    Ok(Invocation {
+        command,
+        stdin,
+        env,
+    })
+}
+
+/// Compute what a call of `definition` with `arguments` would run, and
+/// parse `output` as its result.
+pub fn round_trip(
+    definition: &ToolDefinition,
+    arguments: &Map<String, Value>,
+    output: &SampleOutput,
+) -> Result<RoundTrip, PreviewError> {
+    let invocation = invocation(definition, arguments)?;
+    let result = parse_result(
+        &definition.output,
+        &output.stdout,
+        &output.stderr,
+        output.exit_code,
+    )?;
+    Ok(RoundTrip { invocation, result })
+}
+
+/// A call that could not be previewed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreviewError {
+    /// An input template of the definition does not parse.
+    Template { field: String, error: TemplateError },
+
+    /// The arguments do not fit the input templates.
+    Expand(ExpandError),
+
+    /// The output does not fit the output definition.
+    Output(OutputError),
+}
+
+impl fmt::Display for PreviewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreviewError::Template { field, error } => write!(f, "{}: {}", field, error),
+            PreviewError::Expand(error) => write!(f, "{}", error),
+            PreviewError::Output(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for PreviewError {}
+
+impl From<ExpandError> for PreviewError {
+    fn from(error: ExpandError) -> Self {
+        PreviewError::Expand(error)
+    }
+}
+
+impl From<OutputError> for PreviewError {
+    fn from(error: OutputError) -> Self {
+        PreviewError::Output(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn definition(yaml: &str) -> ToolDefinition {
+        ToolDefinition::from_yaml(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let tool = definition(
+            r#"
+name: create_ticket
+description: Create a ticket
+input:
+  template: "--title {{title}} [--label {{label}}...]"
+  stdin_template: "{{body}}"
+  env_template: { TICKET_PRIORITY: "[{{priority}}]" }
+  schema:
+    type: object
+    properties:
+      title: { type: string }
+      label: { type: array, items: { type: string } }
+      body: { type: string }
+      priority: { type: string }
+output:
+  template: 'ID: (?<id:int>\d+)'
+  schema: { type: object, properties: { id: { type: integer } } }
+execution:
+  env: { LOG_FORMAT: json }
+"#,
+        );
+        let arguments = json!({"title": "Fix it", "label": ["ux", "api"], "body": "Details"});
+
+        let trip = round_trip(
+            &tool,
+            arguments.as_object().unwrap(),
+            &SampleOutput::stdout("ID: 42\n"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            trip.invocation.command,
+            CommandLine::Args(
+                ["--title", "Fix it", "--label", "ux", "--label", "api"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+        assert_eq!(trip.invocation.stdin.as_deref(), Some("Details"));
+        assert_eq!(
+            trip.invocation.env,
+            BTreeMap::from([("LOG_FORMAT".to_string(), "json".to_string())])
+        );
+        assert_eq!(Value::Object(trip.result), json!({"id": 42}));
+
+        let error = round_trip(
+            &tool,
+            arguments.as_object().unwrap(),
+            &SampleOutput::stdout("nothing"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "output does not match the output template"
+        );
+        assert!(matches!(
+            invocation(&tool, json!({}).as_object().unwrap()),
+            Err(PreviewError::Expand(ExpandError::Missing { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_shell_invocation() {
+        let tool = definition(
+            r#"
+name: count
+description: Count matches
+input:
+  template: "grep -c {{pattern}} *.txt"
+  schema: { type: object, properties: { pattern: { type: string } } }
+output:
+  template: '(?<count>\d+)'
+  schema: { type: object }
+execution:
+  shell: true
+"#,
+        );
+        let invocation = invocation(&tool, json!({"pattern": "a b"}).as_object().unwrap()).unwrap();
+        let quoted = QuoteStyle::native_shell().quote("a b").into_owned();
+        assert_eq!(
+            invocation.command,
+            CommandLine::Script(format!("grep -c {} *.txt", quoted))
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Quoting arguments into a command line.
+//!
+//! Tools are run with their arguments passed directly, so quoting matters
+//! only where a command line is written out as text: when it is shown or
+//! logged, and when a tool runs through a shell (`execution.shell`). The
+//! rules differ by platform:
+//!
+//! - [`QuoteStyle::Posix`]: `sh` and its relatives. Arguments with special
+//!   characters are single-quoted, and a single quote is written `'\''`.
+//! - [`QuoteStyle::Msvcrt`]: how a Windows program splits its command line
+//!   into arguments (`CommandLineToArgvW` and the C runtime). Arguments
+//!   with spaces or quotes are double-quoted; quotes inside are escaped
+//!   with a backslash, and backslashes are doubled only where they precede
+//!   a quote.
+//! - [`QuoteStyle::Cmd`]: a command line that `cmd.exe` reads first, as
+//!   with `cmd /C`. Arguments are quoted for the C runtime, and then every
+//!   character `cmd.exe` would interpret is escaped with `^`.
+
+use std::borrow::Cow;
+
+/// The quoting rules of a shell or platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// POSIX shells
+    Posix,
+
+    /// Windows programs using the C runtime's argument splitting
+    Msvcrt,
+
+    /// `cmd.exe`
+    Cmd,
+}
+
+impl QuoteStyle {
+    /// The style for showing command lines on this platform.
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            QuoteStyle::Msvcrt
+        } else {
+            QuoteStyle::Posix
+        }
+    }
+
+    /// The style of this platform's shell, as run by `execution.shell`.
+    pub fn native_shell() -> Self {
+        if cfg!(windows) {
+            QuoteStyle::Cmd
+        } else {
+            QuoteStyle::Posix
+        }
+    }
+
+    /// Quote `arg` so that it is read back as exactly one argument. Plain
+    /// arguments are left as they are.
+    pub fn quote<'a>(&self, arg: &'a str) -> Cow<'a, str> {
+        match self {
+            QuoteStyle::Posix => quote_posix(arg),
+            QuoteStyle::Msvcrt => quote_msvcrt(arg),
+            QuoteStyle::Cmd => Cow::Owned(escape_cmd(&quote_msvcrt(arg))),
+        }
+    }
+
+    /// Join `args` into a command line, quoting each.
+    pub fn join<S: AsRef<str>>(&self, args: &[S]) -> String {
+        args.iter()
+            .map(|arg| self.quote(arg.as_ref()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Characters a POSIX shell gives no meaning to, anywhere in a word.
+fn is_posix_plain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_./:,+=@%".contains(c)
+}
+
+fn quote_posix(arg: &str) -> Cow<'_, str> {
+    if !arg.is_empty() && arg.chars().all(is_posix_plain) {
+        return Cow::Borrowed(arg);
+    }
+    Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+}
+
+fn quote_msvcrt(arg: &str) -> Cow<'_, str> {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return Cow::Borrowed(arg);
+    }
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote escape each other, and one
+                // more escapes the quote.
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            c => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // Trailing backslashes precede the closing quote.
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// Escape every character `cmd.exe` interprets, quotes included, so that
+/// it passes the text on unchanged whatever its quoting state.
+fn escape_cmd(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "()%!^\"<>&|".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_posix() {
+        let quote = |arg| QuoteStyle::Posix.quote(arg).into_owned();
+        assert_eq!(quote("--name=a.txt"), "--name=a.txt");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("two words"), "'two words'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote(r#"say "hi""#), r#"'say "hi"'"#);
+        assert_eq!(quote(r"C:\tools"), r"'C:\tools'");
+        assert_eq!(quote("$HOME *.rs"), "'$HOME *.rs'");
+        assert_eq!(
+            QuoteStyle::Posix.join(&["git", "commit", "-m", "fix: it's done"]),
+            r"git commit -m 'fix: it'\''s done'"
+        );
+    }
+
+    #[test]
+    fn test_msvcrt() {
+        let quote = |arg| QuoteStyle::Msvcrt.quote(arg).into_owned();
+        assert_eq!(quote(r"C:\tools\a.exe"), r"C:\tools\a.exe");
+        assert_eq!(quote(""), r#""""#);
+        assert_eq!(quote("two words"), r#""two words""#);
+        assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(quote(r"C:\Program Files\"), r#""C:\Program Files\\""#);
+        assert_eq!(quote(r"a\\b c"), r#""a\\b c""#);
+        assert_eq!(
+            QuoteStyle::Msvcrt.join(&["tool.exe", "it's", "a b"]),
+            r#"tool.exe it's "a b""#
+        );
+    }
+
+    #[test]
+    fn test_cmd() {
+        let quote = |arg| QuoteStyle::Cmd.quote(arg).into_owned();
+        assert_eq!(quote("plain"), "plain");
+        assert_eq!(quote("a&b"), "a^&b");
+        assert_eq!(quote("two words"), r#"^"two words^""#);
+        assert_eq!(quote(r#"say "hi" | more"#), r#"^"say \^"hi\^" ^| more^""#);
+        assert_eq!(quote("100%"), "100^%");
+    }
+}
@@ -0,0 +1,249 @@
+//! Redaction of sensitive tool arguments.
+//!
+//! A schema property marked `x-sensitive: true` holds a secret such as a
+//! token or password. Its value must never appear in logs, error messages,
+//! confirmation questions, or previews of a call; a [`Redactor`] built from
+//! the definition replaces it with [`REDACTED`] wherever the server would
+//! show it:
+//!
+//! - the command line and environment the executor logs, and the errors
+//!   of a call (see [`executor`](crate::executor)), including the
+//!   schema violations [`check_arguments`](crate::executor::check_arguments)
+//!   describes and what a failed tool wrote;
+//! - `tools/call` requests traced by the transports
+//!   ([`McpServer::redact_message`](crate::server::McpServer::redact_message));
+//! - the question asked to confirm a destructive call
+//!   ([`confirm::question`](crate::confirm::question));
+//! - the failures `mcp-serve test` reports ([`testing`](crate::testing)).
+//!
+//! ```yaml
+//! input:
+//!   template: "--token {{token}} {{repo}}"
+//!   schema:
+//!     type: object
+//!     properties:
+//!       token: { type: string, x-sensitive: true }
+//!       repo: { type: string }
+//! ```
+
+use serde_json::{Map, Value};
+
+use crate::tool_discovery::ToolDefinition;
+
+/// The schema keyword marking a property as sensitive.
+pub const SENSITIVE_KEYWORD: &str = "x-sensitive";
+
+/// What sensitive values are replaced with.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A property path within the arguments object; `None` steps into every
+/// item of an array.
+type FieldPath = Vec<Option<String>>;
+
+/// Hides the values of sensitive properties.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redactor {
+    fields: Vec<FieldPath>,
+}
+
+impl Redactor {
+    /// A redactor for the sensitive properties of `schema`, including those
+    /// of nested objects and array items.
+    pub fn from_schema(schema: &Value) -> Self {
+        let mut fields = Vec::new();
+        collect(schema, &mut Vec::new(), &mut fields);
+        Self { fields }
+    }
+
+    /// A redactor for the arguments of `definition`.
+    pub fn for_definition(definition: &ToolDefinition) -> Self {
+        Self::from_schema(&definition.input.schema)
+    }
+
+    /// Whether the schema marks nothing as sensitive.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// A copy of `arguments` with every sensitive value replaced.
+    pub fn redact_arguments(&self, arguments: &Value) -> Value {
+        let mut arguments = arguments.clone();
+        for path in &self.fields {
+            replace(&mut arguments, path);
+        }
+        arguments
+    }
+
+    /// `text` (such as a command line, an error message, or a tool's
+    /// stderr) with every sensitive value from `arguments` replaced.
+    pub fn redact_text(&self, text: &str, arguments: &Value) -> String {
+        let mut secrets = Vec::new();
+        for path in &self.fields {
+            gather(arguments, path, &mut secrets);
+        }
+        // Longer secrets first, so one containing another is fully hidden.
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        secrets
+            .iter()
+            .filter(|secret| !secret.is_empty())
+            .fold(text.to_string(), |text, secret| {
+                text.replace(secret.as_str(), REDACTED)
+            })
+    }
+}
+
+/// Whether a (sub)schema is marked sensitive.
+pub fn is_sensitive(schema: &Value) -> bool {
+    schema.get(SENSITIVE_KEYWORD) == Some(&Value::Bool(true))
+}
+
+fn collect(schema: &Value, path: &mut FieldPath, fields: &mut Vec<FieldPath>) {
+    if is_sensitive(schema) {
+        fields.push(path.clone());
+        return;
+    }
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (name, property) in properties {
+            path.push(Some(name.clone()));
+            collect(property, path, fields);
+            path.pop();
+        }
+    }
+    if let Some(items) = schema.get("items") {
+        path.push(None);
+        collect(items, path, fields);
+        path.pop();
+    }
+}
+
+fn replace(value: &mut Value, path: &[Option<String>]) {
+    match (path.split_first(), value) {
+        (None, value) => *value = Value::String(REDACTED.to_string()),
+        (Some((Some(name), rest)), Value::Object(object)) => {
+            if let Some(value) = object.get_mut(name) {
+                replace(value, rest);
+            }
+        }
+        (Some((None, rest)), Value::Array(items)) => {
+            for item in items {
+                replace(item, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collect the text forms of the values at `path`, as they would appear in
+/// a command line or message.
+fn gather(value: &Value, path: &[Option<String>], secrets: &mut Vec<String>) {
+    match (path.split_first(), value) {
+        (None, Value::String(text)) => secrets.push(text.clone()),
+        (None, Value::Array(items)) => {
+            for item in items {
+                gather(item, &[], secrets);
+            }
+        }
+        (None, Value::Object(object)) => {
+            secrets.push(Value::Object(object.clone()).to_string());
+            for item in object.values() {
+                gather(item, &[], secrets);
+            }
+        }
+        (None, Value::Null) => {}
+        (None, other) => secrets.push(other.to_string()),
+        (Some((Some(name), rest)), Value::Object(object)) => {
+            if let Some(value) = object.get(name) {
+                gather(value, rest, secrets);
+            }
+        }
+        (Some((None, rest)), Value::Array(items)) => {
+            for item in items {
+                gather(item, rest, secrets);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collect the fields of `schema` where the sensitive-property keyword is
+/// set to something other than a boolean.
+pub(crate) fn invalid_markers(schema: &Value, field: &str, found: &mut Vec<String>) {
+    let Value::Object(object) = schema else {
+        return;
+    };
+    if object
+        .get(SENSITIVE_KEYWORD)
+        .is_some_and(|marker| !marker.is_boolean())
+    {
+        found.push(format!("{}.{}", field, SENSITIVE_KEYWORD));
+    }
+    let properties = object.get("properties").and_then(Value::as_object);
+    for (name, property) in properties.into_iter().flat_map(Map::iter) {
+        invalid_markers(property, &format!("{}.properties.{}", field, name), found);
+    }
+    if let Some(items) = object.get("items") {
+        invalid_markers(items, &format!("{}.items", field), found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn redactor() -> Redactor {
+        Redactor::from_schema(&json!({
+            "type": "object",
+            "properties": {
+                "token": {"type": "string", "x-sensitive": true},
+                "repo": {"type": "string"},
+                "auth": {
+                    "type": "object",
+                    "properties": {"pin": {"type": "integer", "x-sensitive": true}}
+                },
+                "keys": {"type": "array", "items": {"type": "string", "x-sensitive": true}}
+            }
+        }))
+    }
+
+    #[test]
+    fn test_redact_arguments() {
+        let arguments = json!({
+            "token": "s3cret",
+            "repo": "nevir/mcp-serve",
+            "auth": {"pin": 1234},
+            "keys": ["k1", "k2"]
+        });
+
+        assert_eq!(
+            redactor().redact_arguments(&arguments),
+            json!({
+                "token": "[REDACTED]",
+                "repo": "nevir/mcp-serve",
+                "auth": {"pin": "[REDACTED]"},
+                "keys": ["[REDACTED]", "[REDACTED]"]
+            })
+        );
+        assert!(Redactor::from_schema(&json!({"type": "object"})).is_empty());
+    }
+
+    #[test]
+    fn test_redact_text() {
+        let arguments = json!({"token": "s3cret", "repo": "r", "auth": {"pin": 1234}});
+
+        assert_eq!(
+            redactor().redact_text("clone --token s3cret r (pin 1234) failed", &arguments),
+            "clone --token [REDACTED] r (pin [REDACTED]) failed"
+        );
+    }
+
+    #[test]
+    fn test_invalid_markers() {
+        let schema = json!({
+            "properties": {"a": {"x-sensitive": "yes"}, "b": {"x-sensitive": false}}
+        });
+        let mut found = Vec::new();
+        invalid_markers(&schema, "input.schema", &mut found);
+        assert_eq!(found, ["input.schema.properties.a.x-sensitive"]);
+    }
+}
@@ -0,0 +1,628 @@
+//! In-memory catalog of loaded tool definitions.
+//!
+//! The registry is where duplicate tool names are caught: MCP clients address
+//! tools purely by name, so two definitions sharing a name would make calls
+//! ambiguous. What happens on a conflict is decided by a [`DuplicatePolicy`].
+//! A tool's aliases are reserved like its name, so they count as conflicts
+//! too; under the lenient policies a taken alias is simply dropped.
+//! Discovered executables and in-process [`ToolHandler`]s share one
+//! namespace. Tools with Markdown documentation also offer it as a resource.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::handler::ToolHandler;
+use crate::tool_discovery::docs::{docs_resource, docs_uri};
+use crate::tool_discovery::wire::{Resource, ResourceContents};
+use crate::tool_discovery::{DiscoveredTool, ToolDefinition, ToolDocs};
+
+/// How to resolve two definitions that declare the same tool name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DuplicatePolicy {
+    /// Refuse to register the duplicate.
+    #[default]
+    Error,
+
+    /// Keep the first definition and skip later ones.
+    FirstWins,
+
+    /// Rename the duplicate by appending a numeric suffix (`name_2`, `name_3`, ...).
+    AutoSuffix,
+}
+
+/// What implements a registered tool.
+#[derive(Clone)]
+pub enum ToolBackend {
+    /// An executable found during discovery.
+    Executable(DiscoveredTool),
+
+    /// A handler registered by code embedding the server.
+    Native(Arc<dyn ToolHandler>),
+}
+
+impl ToolBackend {
+    /// Where the tool comes from, for messages: the executable's path, or
+    /// `(native)` for handlers.
+    pub fn location(&self) -> PathBuf {
+        match self {
+            ToolBackend::Executable(tool) => tool.path.clone(),
+            ToolBackend::Native(_) => PathBuf::from("(native)"),
+        }
+    }
+}
+
+impl fmt::Debug for ToolBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolBackend::Executable(tool) => f.debug_tuple("Executable").field(tool).finish(),
+            ToolBackend::Native(_) => f.write_str("Native"),
+        }
+    }
+}
+
+impl PartialEq for ToolBackend {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ToolBackend::Executable(a), ToolBackend::Executable(b)) => a == b,
+            (ToolBackend::Native(a), ToolBackend::Native(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// A tool definition together with what implements it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisteredTool {
+    /// The tool's definition, with its final (possibly suffixed) name
+    pub definition: ToolDefinition,
+
+    /// The executable or handler behind the tool
+    pub source: ToolBackend,
+}
+
+impl RegisteredTool {
+    /// The executable behind the tool, unless it is native.
+    pub fn executable(&self) -> Option<&DiscoveredTool> {
+        match &self.source {
+            ToolBackend::Executable(tool) => Some(tool),
+            ToolBackend::Native(_) => None,
+        }
+    }
+
+    /// The handler behind the tool, if it is native.
+    pub fn handler(&self) -> Option<&dyn ToolHandler> {
+        match &self.source {
+            ToolBackend::Executable(_) => None,
+            ToolBackend::Native(handler) => Some(handler.as_ref()),
+        }
+    }
+
+    /// The tool's Markdown documentation, if it has any.
+    pub fn docs(&self) -> Option<&Path> {
+        self.executable()?.docs.as_deref()
+    }
+
+    /// The tool as `tools/list` results send it to clients.
+    pub fn to_wire_json(&self) -> serde_json::Value {
+        self.definition.to_mcp_tool().to_json()
+    }
+}
+
+/// The result of registering a tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Registration {
+    /// The tool was registered under its declared name.
+    Added,
+
+    /// The tool was skipped because its name was already registered
+    /// ([`DuplicatePolicy::FirstWins`]).
+    Skipped { name: String, existing: PathBuf },
+
+    /// The tool was registered under a new name
+    /// ([`DuplicatePolicy::AutoSuffix`]).
+    Renamed { from: String, to: String },
+}
+
+impl Registration {
+    /// A human-readable warning for outcomes other than [`Registration::Added`].
+    pub fn warning(&self, path: &Path) -> Option<String> {
+        match self {
+            Registration::Added => None,
+            Registration::Skipped { name, existing } => Some(format!(
+                "skipping {}: tool name `{}` is already defined by {}",
+                path.display(),
+                name,
+                existing.display()
+            )),
+            Registration::Renamed { from, to } => Some(format!(
+                "renaming tool `{}` from {} to `{}` to avoid a name conflict",
+                from,
+                path.display(),
+                to
+            )),
+        }
+    }
+}
+
+/// Two definitions declared the same tool name under [`DuplicatePolicy::Error`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateToolError {
+    /// The conflicting tool name
+    pub name: String,
+
+    /// Location of the already-registered tool
+    pub existing: PathBuf,
+
+    /// Location of the tool that was rejected
+    pub duplicate: PathBuf,
+}
+
+impl fmt::Display for DuplicateToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tool name `{}` is defined by both {} and {}",
+            self.name,
+            self.existing.display(),
+            self.duplicate.display()
+        )
+    }
+}
+
+impl std::error::Error for DuplicateToolError {}
+
+/// Registry of loaded tools, keyed by name.
+#[derive(Debug, Clone, Default)]
+pub struct ToolRegistry {
+    policy: DuplicatePolicy,
+    tools: Vec<RegisteredTool>,
+    index: HashMap<String, usize>,
+}
+
+impl ToolRegistry {
+    /// Create an empty registry that resolves name conflicts with `policy`.
+    pub fn new(policy: DuplicatePolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Register a tool, applying the duplicate policy if its name is taken.
+    ///
+    /// If the tool was discovered in a namespaced [`ToolRoot`], it is
+    /// registered under its qualified name (`namespace/name`) and qualified
+    /// aliases, and conflicts are checked against those names.
+    ///
+    /// [`ToolRoot`]: crate::tool_discovery::ToolRoot
+    pub fn register(
+        &mut self,
+        mut definition: ToolDefinition,
+        source: DiscoveredTool,
+    ) -> Result<Registration, DuplicateToolError> {
+        definition.name = source.qualified_name(&definition.name);
+        for alias in &mut definition.aliases {
+            *alias = source.qualified_name(alias);
+        }
+        self.add(definition, ToolBackend::Executable(source))
+    }
+
+    /// Register a tool implemented in-process by `handler`, applying the
+    /// duplicate policy if its name is taken.
+    pub fn register_handler(
+        &mut self,
+        definition: ToolDefinition,
+        handler: impl ToolHandler + 'static,
+    ) -> Result<Registration, DuplicateToolError> {
+        self.add(definition, ToolBackend::Native(Arc::new(handler)))
+    }
+
+    fn add(
+        &mut self,
+        mut definition: ToolDefinition,
+        source: ToolBackend,
+    ) -> Result<Registration, DuplicateToolError> {
+        let Some(&existing) = self.index.get(&definition.name) else {
+            self.claim_aliases(&mut definition, &source)?;
+            self.insert(definition, source);
+            return Ok(Registration::Added);
+        };
+        let existing = self.tools[existing].source.location();
+
+        match self.policy {
+            DuplicatePolicy::Error => Err(DuplicateToolError {
+                name: definition.name,
+                existing,
+                duplicate: source.location(),
+            }),
+            DuplicatePolicy::FirstWins => Ok(Registration::Skipped {
+                name: definition.name,
+                existing,
+            }),
+            DuplicatePolicy::AutoSuffix => {
+                let from = definition.name.clone();
+                let to = (2..)
+                    .map(|n| format!("{}_{}", from, n))
+                    .find(|candidate| !self.index.contains_key(candidate))
+                    .expect("unbounded suffix search always finds a free name");
+                definition.name = to.clone();
+                self.claim_aliases(&mut definition, &source)?;
+                self.insert(definition, source);
+                Ok(Registration::Renamed { from, to })
+            }
+        }
+    }
+
+    /// Look up a tool by name or alias.
+    pub fn get(&self, name: &str) -> Option<&RegisteredTool> {
+        self.index.get(name).map(|&i| &self.tools[i])
+    }
+
+    /// All registered tools, in registration order; aliases are not listed
+    /// separately.
+    pub fn tools(&self) -> &[RegisteredTool] {
+        &self.tools
+    }
+
+    /// The registered tools having any of `tags` as a tag or category, for
+    /// serving a subset of the registry to a client.
+    pub fn tagged<'a>(&'a self, tags: &'a [String]) -> impl Iterator<Item = &'a RegisteredTool> {
+        self.tools
+            .iter()
+            .filter(move |tool| tool.definition.matches_tags(tags))
+    }
+
+    /// The documentation resources of the registered tools.
+    pub fn resources(&self) -> Vec<Resource> {
+        self.tools
+            .iter()
+            .filter(|tool| tool.docs().is_some())
+            .map(|tool| docs_resource(&tool.definition.name))
+            .collect()
+    }
+
+    /// Read the resource at `uri`, or `None` if no tool offers it.
+    pub fn read_resource(&self, uri: &str) -> Option<io::Result<ResourceContents>> {
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| docs_uri(&tool.definition.name) == uri)?;
+        let path = tool.docs()?;
+        Some(ToolDocs::load(path).map(|docs| docs.contents(&tool.definition.name)))
+    }
+
+    /// Number of registered tools.
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    /// Whether no tools are registered.
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Keep the aliases of `definition` that are free, refusing taken ones
+    /// under [`DuplicatePolicy::Error`] and dropping them otherwise.
+    fn claim_aliases(
+        &self,
+        definition: &mut ToolDefinition,
+        source: &ToolBackend,
+    ) -> Result<(), DuplicateToolError> {
+        let mut claimed: Vec<String> = Vec::new();
+        for alias in std::mem::take(&mut definition.aliases) {
+            if alias == definition.name || claimed.contains(&alias) {
+                continue;
+            }
+            if let Some(&existing) = self.index.get(&alias) {
+                if self.policy == DuplicatePolicy::Error {
+                    return Err(DuplicateToolError {
+                        name: alias,
+                        existing: self.tools[existing].source.location(),
+                        duplicate: source.location(),
+                    });
+                }
+                continue;
+            }
+            claimed.push(alias);
+        }
+        definition.aliases = claimed;
+        Ok(())
+    }
+
+    fn insert(&mut self, definition: ToolDefinition, source: ToolBackend) {
+        for name in std::iter::once(&definition.name).chain(&definition.aliases) {
+            self.index.insert(name.clone(), self.tools.len());
+        }
+        self.tools.push(RegisteredTool { definition, source });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::ToolError;
+    use crate::tool_discovery::MetadataSource;
+    use serde_json::{json, Map, Value};
+
+    fn definition(name: &str) -> ToolDefinition {
+        ToolDefinition::from_yaml(&format!(
+            r#"
+name: {}
+description: Test tool
+input:
+  template: ""
+  schema:
+    type: object
+output:
+  template: "(?<result>.*)"
+  schema:
+    type: object
+"#,
+            name
+        ))
+        .unwrap()
+    }
+
+    fn discovered(path: &str) -> DiscoveredTool {
+        DiscoveredTool {
+            path: PathBuf::from(path),
+            relative_path: PathBuf::from(path),
+            metadata_source: MetadataSource::Embedded,
+            namespace: None,
+            working_dir: None,
+            docs: None,
+            defaults: Box::default(),
+            directory_configs: Box::default(),
+        }
+    }
+
+    #[test]
+    fn test_tagged() {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        let mut deploy = definition("deploy");
+        deploy.tags = vec!["ops".to_string()];
+        registry.register(deploy, discovered("deploy")).unwrap();
+        registry
+            .register(definition("status"), discovered("status"))
+            .unwrap();
+
+        let tags = ["ops".to_string()];
+        let names: Vec<&str> = registry
+            .tagged(&tags)
+            .map(|tool| tool.definition.name.as_str())
+            .collect();
+        assert_eq!(names, ["deploy"]);
+    }
+
+    #[test]
+    fn test_docs_resources() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let docs = dir.path().join("deploy.md");
+        std::fs::write(&docs, "# deploy\n").unwrap();
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        let mut documented = discovered("deploy");
+        documented.docs = Some(docs);
+        registry.register(definition("deploy"), documented).unwrap();
+        registry
+            .register(definition("status"), discovered("status"))
+            .unwrap();
+
+        let resources = registry.resources();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].uri, "mcp-serve://docs/deploy");
+        let contents = registry.read_resource(&resources[0].uri).unwrap().unwrap();
+        assert_eq!(contents.text, "# deploy\n");
+        assert!(registry.read_resource("mcp-serve://docs/status").is_none());
+    }
+
+    fn aliased(name: &str, aliases: &[&str]) -> ToolDefinition {
+        let mut definition = definition(name);
+        definition.aliases = aliases.iter().map(|alias| alias.to_string()).collect();
+        definition
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register(aliased("create_issue", &["create_ticket"]), discovered("a"))
+            .unwrap();
+
+        assert_eq!(
+            registry.get("create_ticket").unwrap().definition.name,
+            "create_issue"
+        );
+        assert_eq!(registry.len(), 1);
+
+        let error = registry
+            .register(definition("create_ticket"), discovered("b"))
+            .unwrap_err();
+        assert_eq!(error.name, "create_ticket");
+        let error = registry
+            .register(aliased("open_issue", &["create_ticket"]), discovered("b"))
+            .unwrap_err();
+        assert_eq!(error.name, "create_ticket");
+        assert!(registry.get("open_issue").is_none());
+
+        let mut registry = ToolRegistry::new(DuplicatePolicy::FirstWins);
+        registry
+            .register(aliased("create_issue", &["create_ticket"]), discovered("a"))
+            .unwrap();
+        registry
+            .register(
+                aliased("open_issue", &["create_ticket", "new_issue"]),
+                discovered("b"),
+            )
+            .unwrap();
+        assert_eq!(
+            registry.get("open_issue").unwrap().definition.aliases,
+            ["new_issue"]
+        );
+        assert_eq!(
+            registry.get("create_ticket").unwrap().definition.name,
+            "create_issue"
+        );
+    }
+
+    #[test]
+    fn test_register_unique_names() {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+
+        assert_eq!(
+            registry.register(definition("a"), discovered("a")),
+            Ok(Registration::Added)
+        );
+        assert_eq!(
+            registry.register(definition("b"), discovered("b")),
+            Ok(Registration::Added)
+        );
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(
+            registry.get("b").unwrap().executable().unwrap().path,
+            PathBuf::from("b")
+        );
+        assert!(registry.get("c").is_none());
+    }
+
+    #[test]
+    fn test_duplicate_policy_error() {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register(definition("deploy"), discovered("one"))
+            .unwrap();
+
+        let error = registry
+            .register(definition("deploy"), discovered("two"))
+            .unwrap_err();
+
+        assert_eq!(error.name, "deploy");
+        assert_eq!(error.existing, PathBuf::from("one"));
+        assert_eq!(error.duplicate, PathBuf::from("two"));
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_policy_first_wins() {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::FirstWins);
+        registry
+            .register(definition("deploy"), discovered("one"))
+            .unwrap();
+
+        let outcome = registry
+            .register(definition("deploy"), discovered("two"))
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            Registration::Skipped {
+                name: "deploy".to_string(),
+                existing: PathBuf::from("one")
+            }
+        );
+        assert!(outcome
+            .warning(Path::new("two"))
+            .unwrap()
+            .contains("already defined by one"));
+        assert_eq!(registry.len(), 1);
+        assert_eq!(
+            registry.get("deploy").unwrap().executable().unwrap().path,
+            PathBuf::from("one")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_policy_auto_suffix() {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::AutoSuffix);
+        registry
+            .register(definition("deploy"), discovered("one"))
+            .unwrap();
+        registry
+            .register(definition("deploy_2"), discovered("two"))
+            .unwrap();
+
+        let outcome = registry
+            .register(definition("deploy"), discovered("three"))
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            Registration::Renamed {
+                from: "deploy".to_string(),
+                to: "deploy_3".to_string()
+            }
+        );
+        assert_eq!(registry.len(), 3);
+        assert_eq!(
+            registry.get("deploy_3").unwrap().executable().unwrap().path,
+            PathBuf::from("three")
+        );
+        assert_eq!(
+            registry.get("deploy_3").unwrap().definition.name,
+            "deploy_3"
+        );
+    }
+
+    #[test]
+    fn test_register_namespaced_tools() {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        let mut ops = discovered("ops/restart");
+        ops.namespace = Some("ops".to_string());
+        let mut dev = discovered("dev/restart");
+        dev.namespace = Some("dev".to_string());
+
+        registry.register(definition("restart"), ops).unwrap();
+        registry.register(definition("restart"), dev).unwrap();
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(
+            registry.get("ops/restart").unwrap().definition.name,
+            "ops/restart"
+        );
+        assert!(registry.get("dev/restart").is_some());
+        assert!(registry.get("restart").is_none());
+        assert_eq!(
+            registry.get("ops/restart").unwrap().to_wire_json()["name"],
+            "ops/restart"
+        );
+    }
+
+    #[test]
+    fn test_register_handler() {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register(definition("deploy"), discovered("deploy"))
+            .unwrap();
+        registry
+            .register_handler(definition("echo"), |arguments: &Map<String, Value>| {
+                arguments
+                    .get("text")
+                    .map(|text| json!({ "result": text }))
+                    .ok_or_else(|| ToolError::new("text is required"))
+            })
+            .unwrap();
+
+        let echo = registry.get("echo").unwrap();
+        assert!(echo.executable().is_none());
+        let handler = echo.handler().unwrap();
+        let mut arguments = Map::new();
+        assert_eq!(
+            handler.call(&arguments),
+            Err(ToolError::new("text is required"))
+        );
+        arguments.insert("text".to_string(), json!("hi"));
+        assert_eq!(handler.call(&arguments), Ok(json!({"result": "hi"})));
+        assert!(registry.get("deploy").unwrap().handler().is_none());
+
+        let error = registry
+            .register_handler(definition("deploy"), |_: &Map<String, Value>| {
+                Ok(Value::Null)
+            })
+            .unwrap_err();
+        assert_eq!(error.existing, PathBuf::from("deploy"));
+        assert_eq!(error.duplicate, PathBuf::from("(native)"));
+    }
+}
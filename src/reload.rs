@@ -0,0 +1,109 @@
+//! Noticing when the configuration file should be read again.
+//!
+//! A running server reloads its `--config` file on SIGHUP (on Unix), or
+//! when the file changes. [`Reload::is_requested`] is polled by the loop
+//! that waits for the server to stop; what a reload applies is up to the
+//! caller.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Whether the configuration file should be reloaded.
+#[derive(Debug)]
+pub struct Reload {
+    path: PathBuf,
+    /// The file's modification time and size when last seen
+    seen: Option<(SystemTime, u64)>,
+    /// Whether the file changed, and is waited on to stay as it is
+    settling: bool,
+    signalled: Arc<AtomicBool>,
+}
+
+impl Reload {
+    /// Watch the file at `path` for changes, and request a reload on SIGHUP.
+    pub fn on_signal(path: &Path) -> io::Result<Self> {
+        let reload = Self::watch(path);
+        #[cfg(unix)]
+        signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&reload.signalled))?;
+        Ok(reload)
+    }
+
+    /// Watch the file at `path` for changes only.
+    pub fn watch(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            seen: stamp(path),
+            settling: false,
+            signalled: Arc::default(),
+        }
+    }
+
+    /// Ask for a reload, as a signal would.
+    pub fn request(&self) {
+        self.signalled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether a reload was asked for since the last call, or the file
+    /// changed and then stayed the same since then, so that a file being
+    /// written is not read half-way. A file that is briefly missing, as
+    /// while an editor replaces it, has not changed.
+    pub fn is_requested(&mut self) -> bool {
+        let signalled = self.signalled.swap(false, Ordering::Relaxed);
+        let settled = match stamp(&self.path) {
+            None => false,
+            stamp if stamp != self.seen => {
+                self.seen = stamp;
+                self.settling = true;
+                false
+            }
+            _ => std::mem::take(&mut self.settling),
+        };
+        signalled || settled
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reload() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("mcp-serve.yaml");
+        fs::write(&path, "transport: stdio\n").unwrap();
+        let mut reload = Reload::watch(&path);
+        assert!(!reload.is_requested());
+
+        reload.request();
+        assert!(reload.is_requested());
+        assert!(!reload.is_requested());
+
+        fs::write(&path, "transport: http\nbind: 0.0.0.0:8080\n").unwrap();
+        assert!(!reload.is_requested());
+        assert!(reload.is_requested());
+        assert!(!reload.is_requested());
+
+        fs::remove_file(&path).unwrap();
+        assert!(!reload.is_requested());
+        assert!(!reload.is_requested());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_on_signal() {
+        let dir = TempDir::new().unwrap();
+        let mut reload = Reload::on_signal(&dir.path().join("mcp-serve.yaml")).unwrap();
+        signal_hook::low_level::raise(signal_hook::consts::SIGHUP).unwrap();
+        assert!(reload.is_requested());
+    }
+}
@@ -0,0 +1,470 @@
+//! An interactive prompt calling tools, for `mcp-serve repl`.
+//!
+//! Tool authors get a fast feedback loop: type a tool's name (completed
+//! with Tab), answer a prompt for each property of its input schema, and
+//! see the parsed result, without a client or a JSON argument string:
+//!
+//! ```text
+//! mcp-serve> greet
+//! name (string, required): Ada
+//! {
+//!   "greeting": "Hello, Ada!"
+//! }
+//! ```
+//!
+//! A call can also be typed with its arguments as a JSON object
+//! (`greet {"name": "Ada"}`), which skips the prompts. Lines starting with
+//! `:` are commands of the prompt itself (see [`HELP`]).
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use serde_json::{Map, Value};
+use std::time::Instant;
+
+use crate::executor::Executor;
+use crate::registry::ToolRegistry;
+use crate::run;
+
+/// The prompt for calls and commands.
+const PROMPT: &str = "mcp-serve> ";
+
+/// What `:help` prints.
+pub const HELP: &str = "\
+<tool>              call a tool, prompting for each of its inputs
+<tool> {...}        call a tool with arguments given as a JSON object
+:list               list the tools
+:help               show this help
+:quit               leave (or press Ctrl-D)
+
+While answering a prompt, an empty answer leaves an optional input out,
+and Ctrl-C cancels the call.";
+
+/// A line typed at the prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Call a tool, with the arguments given on the line, if any.
+    Call {
+        name: String,
+        arguments: Option<Map<String, Value>>,
+    },
+
+    /// List the tools.
+    List,
+
+    /// Show the commands.
+    Help,
+
+    /// Leave the prompt.
+    Quit,
+
+    /// Nothing was typed.
+    Empty,
+}
+
+/// The commands of the prompt, as completed.
+const COMMANDS: [&str; 3] = [":list", ":help", ":quit"];
+
+/// Read a line typed at the prompt.
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    match line {
+        "" => return Ok(Command::Empty),
+        ":list" | ":ls" => return Ok(Command::List),
+        ":help" | ":h" | "?" => return Ok(Command::Help),
+        ":quit" | ":q" | ":exit" => return Ok(Command::Quit),
+        _ if line.starts_with(':') => {
+            return Err(format!("unknown command `{}`; see :help", line));
+        }
+        _ => {}
+    }
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    let arguments = match rest {
+        "" => None,
+        _ => Some(run::parse_arguments(rest)?),
+    };
+    Ok(Command::Call {
+        name: name.to_string(),
+        arguments,
+    })
+}
+
+/// A property of a tool's input schema, as prompted for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property<'a> {
+    /// The property's name
+    pub name: &'a str,
+
+    /// The property's own schema
+    pub schema: &'a Value,
+
+    /// Whether the input schema requires the property
+    pub required: bool,
+}
+
+impl Property<'_> {
+    /// The JSON type of the property's values, if the schema gives one;
+    /// of a nullable type, the type of its other values.
+    pub fn kind(&self) -> Option<&str> {
+        match self.schema.get("type")? {
+            Value::String(kind) => Some(kind),
+            Value::Array(kinds) => kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|&kind| kind != "null"),
+            _ => None,
+        }
+    }
+
+    /// The string values the property is limited to, which are completed.
+    pub fn choices(&self) -> Vec<String> {
+        let values = self.schema.get("enum").and_then(Value::as_array);
+        values
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value.as_str().map(str::to_string))
+            .collect()
+    }
+
+    /// The prompt for the property's value, e.g.
+    /// `count (integer, default 1): `.
+    pub fn prompt(&self) -> String {
+        let mut notes = vec![self.kind().unwrap_or("JSON").to_string()];
+        if self.required {
+            notes.push("required".to_string());
+        }
+        if let Some(default) = self.schema.get("default") {
+            notes.push(format!("default {}", default));
+        }
+        let choices = self.choices();
+        if !choices.is_empty() {
+            notes.push(choices.join("|"));
+        }
+        format!("{} ({}): ", self.name, notes.join(", "))
+    }
+
+    /// Read the value typed for the property: the text itself for a string,
+    /// JSON otherwise. Values of properties without a type are read as JSON
+    /// if they can be, and as text if not.
+    pub fn parse(&self, text: &str) -> Result<Value, String> {
+        match self.kind() {
+            Some("string") => Ok(Value::String(text.to_string())),
+            Some("boolean") => match text.trim() {
+                "true" | "yes" | "y" => Ok(Value::Bool(true)),
+                "false" | "no" | "n" => Ok(Value::Bool(false)),
+                _ => Err("expected true or false".to_string()),
+            },
+            Some(kind) => match serde_json::from_str::<Value>(text) {
+                Ok(value) if matches_kind(&value, kind) => Ok(value),
+                Ok(_) => Err(format!("expected {} {}", article(kind), kind)),
+                Err(e) => Err(format!("not valid JSON: {}", e)),
+            },
+            None => {
+                Ok(serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string())))
+            }
+        }
+    }
+}
+
+/// Whether `value` is of the JSON Schema type `kind`.
+fn matches_kind(value: &Value, kind: &str) -> bool {
+    match kind {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        // Unknown types are left to the validation of the call.
+        _ => true,
+    }
+}
+
+fn article(kind: &str) -> &'static str {
+    match kind.chars().next() {
+        Some('a' | 'e' | 'i' | 'o' | 'u') => "an",
+        _ => "a",
+    }
+}
+
+/// The properties of an input schema, the required ones first.
+pub fn properties(schema: &Value) -> Vec<Property<'_>> {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    let mut properties: Vec<Property> = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(name, schema)| Property {
+            name,
+            schema,
+            required: required.contains(&name.as_str()),
+        })
+        .collect();
+    properties.sort_by_key(|property| !property.required);
+    properties
+}
+
+/// Completes the word being typed from a list of candidates: tool names and
+/// commands at the prompt, or the choices of a property.
+#[derive(Debug, Default)]
+pub struct Completions {
+    candidates: Vec<String>,
+}
+
+impl Completions {
+    /// The candidates starting with `word`, sorted.
+    pub fn matching(&self, word: &str) -> Vec<String> {
+        let mut matching: Vec<String> = self
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .cloned()
+            .collect();
+        matching.sort();
+        matching
+    }
+}
+
+impl Completer for Completions {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        // Only the first word is completed: the arguments are free-form.
+        let typed = &line[..pos];
+        if typed.contains(char::is_whitespace) {
+            return Ok((pos, Vec::new()));
+        }
+        Ok((0, self.matching(typed)))
+    }
+}
+
+impl Hinter for Completions {
+    type Hint = String;
+}
+
+impl Highlighter for Completions {}
+
+impl Validator for Completions {}
+
+impl Helper for Completions {}
+
+/// Read calls and commands until the user leaves, calling the tools of
+/// `registry` with `executor`.
+pub fn interact(registry: &ToolRegistry, executor: &Executor) -> rustyline::Result<()> {
+    let mut names: Vec<String> = COMMANDS.iter().map(|command| command.to_string()).collect();
+    for tool in registry.tools() {
+        names.push(tool.definition.name.clone());
+        names.extend(tool.definition.aliases.iter().cloned());
+    }
+    let mut editor: Editor<Completions, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(Completions::default()));
+
+    println!(
+        "{} tool(s); Tab completes their names, :help shows the commands",
+        registry.len()
+    );
+    loop {
+        set_candidates(&mut editor, names.clone());
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            // Like a shell, Ctrl-C abandons the line, not the prompt.
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let (name, arguments) = match parse_command(&line) {
+            Ok(Command::Empty) => continue,
+            Ok(Command::Quit) => return Ok(()),
+            Ok(Command::Help) => {
+                println!("{}", HELP);
+                continue;
+            }
+            Ok(Command::List) => {
+                for tool in registry.tools() {
+                    println!(
+                        "{:24} {}",
+                        tool.definition.name, tool.definition.description
+                    );
+                }
+                continue;
+            }
+            Ok(Command::Call { name, arguments }) => (name, arguments),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+        editor.add_history_entry(line.trim())?;
+
+        let Some(tool) = registry.get(&name) else {
+            eprintln!("Error: no tool named `{}`; :list shows them", name);
+            continue;
+        };
+        let arguments = match arguments {
+            Some(arguments) => arguments,
+            None => match read_arguments(&mut editor, &tool.definition.input.schema)? {
+                Some(arguments) => arguments,
+                None => continue,
+            },
+        };
+
+        let started = Instant::now();
+        let result = executor.call(tool, &arguments);
+        let elapsed = started.elapsed();
+        if result.is_error {
+            eprintln!("Error: {}", run::describe(&result));
+        } else {
+            println!("{}", run::describe(&result));
+        }
+        tracing::info!("{} finished in {:.2?}", name, elapsed);
+    }
+}
+
+fn set_candidates(editor: &mut Editor<Completions, DefaultHistory>, candidates: Vec<String>) {
+    if let Some(completions) = editor.helper_mut() {
+        completions.candidates = candidates;
+    }
+}
+
+/// Prompt for each property of `schema`, until it is given a valid value or
+/// left out; `None` if the call is cancelled with Ctrl-C.
+fn read_arguments(
+    editor: &mut Editor<Completions, DefaultHistory>,
+    schema: &Value,
+) -> rustyline::Result<Option<Map<String, Value>>> {
+    let mut arguments = Map::new();
+    for property in properties(schema) {
+        if let Some(description) = property.schema.get("description").and_then(Value::as_str) {
+            println!("  {}", description);
+        }
+        set_candidates(editor, property.choices());
+        let prompt = property.prompt();
+        loop {
+            let text = match editor.readline(&prompt) {
+                Ok(text) => text,
+                Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            if text.is_empty() {
+                if property.required && property.schema.get("default").is_none() {
+                    eprintln!("Error: {} is required", property.name);
+                    continue;
+                }
+                break;
+            }
+            match property.parse(&text) {
+                Ok(value) => {
+                    arguments.insert(property.name.to_string(), value);
+                    break;
+                }
+                Err(e) => eprintln!("Error: {}", e),
+            }
+        }
+    }
+    Ok(Some(arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("  ").unwrap(), Command::Empty);
+        assert_eq!(parse_command(":q").unwrap(), Command::Quit);
+        assert_eq!(parse_command(":list").unwrap(), Command::List);
+        assert!(parse_command(":nope").unwrap_err().contains(":help"));
+        assert_eq!(
+            parse_command("greet").unwrap(),
+            Command::Call {
+                name: "greet".to_string(),
+                arguments: None
+            }
+        );
+        assert_eq!(
+            parse_command(r#"greet {"name": "Ada"}"#).unwrap(),
+            Command::Call {
+                name: "greet".to_string(),
+                arguments: run::parse_arguments(r#"{"name": "Ada"}"#).ok()
+            }
+        );
+        assert!(parse_command("greet Ada")
+            .unwrap_err()
+            .contains("not valid JSON"));
+    }
+
+    #[test]
+    fn test_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "count": {"type": "integer", "default": 1},
+                "level": {"type": "string", "enum": ["info", "debug"]},
+                "name": {"type": "string"},
+                "tags": {"type": ["array", "null"]},
+                "extra": {}
+            },
+            "required": ["name"]
+        });
+        let properties = properties(&schema);
+        let names: Vec<&str> = properties.iter().map(|property| property.name).collect();
+        assert_eq!(names, ["name", "count", "extra", "level", "tags"]);
+
+        let [name, count, extra, level, tags] = &properties[..] else {
+            unreachable!()
+        };
+        assert_eq!(name.prompt(), "name (string, required): ");
+        assert_eq!(count.prompt(), "count (integer, default 1): ");
+        assert_eq!(level.prompt(), "level (string, info|debug): ");
+        assert_eq!(level.choices(), ["info", "debug"]);
+        assert_eq!(tags.kind(), Some("array"));
+
+        assert_eq!(name.parse(" Ada ").unwrap(), " Ada ");
+        assert_eq!(count.parse("3").unwrap(), 3);
+        assert_eq!(count.parse("3.5").unwrap_err(), "expected an integer");
+        assert!(count.parse("three").unwrap_err().contains("not valid JSON"));
+        assert_eq!(tags.parse(r#"["a"]"#).unwrap(), json!(["a"]));
+        assert_eq!(tags.parse("{}").unwrap_err(), "expected an array");
+        assert_eq!(extra.parse("[1]").unwrap(), json!([1]));
+        assert_eq!(extra.parse("plain text").unwrap(), "plain text");
+        let flag = Property {
+            name: "force",
+            schema: &json!({"type": "boolean"}),
+            required: false,
+        };
+        assert_eq!(flag.parse("yes").unwrap(), true);
+        assert!(flag.parse("maybe").is_err());
+    }
+
+    #[test]
+    fn test_completions() {
+        let completions = Completions {
+            candidates: vec![
+                "greet".to_string(),
+                "deploy".to_string(),
+                "grep".to_string(),
+                ":list".to_string(),
+            ],
+        };
+        assert_eq!(completions.matching("gr"), ["greet", "grep"]);
+        assert_eq!(completions.matching(":"), [":list"]);
+        assert!(completions.matching("x").is_empty());
+    }
+}
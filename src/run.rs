@@ -0,0 +1,84 @@
+//! Calling a tool from the command line, for `mcp-serve run`.
+//!
+//! A tool runs exactly as it would for a `tools/call` request, and its
+//! result is shown for people by [`describe`]: the structured result when
+//! there is one, its text otherwise. The result can also be written as the
+//! JSON a client would receive.
+
+use serde_json::{Map, Value};
+
+use crate::tool_discovery::wire::{CallToolResult, ContentBlock};
+
+/// Read the arguments of a call, given as a JSON object.
+pub fn parse_arguments(text: &str) -> Result<Map<String, Value>, String> {
+    match serde_json::from_str(text) {
+        Ok(Value::Object(arguments)) => Ok(arguments),
+        Ok(_) => Err("the arguments must be a JSON object".to_string()),
+        Err(e) => Err(format!("the arguments are not valid JSON: {}", e)),
+    }
+}
+
+/// Describe `result` for people: the structured result, pretty-printed, or
+/// else its text, followed by any resource links.
+pub fn describe(result: &CallToolResult) -> String {
+    let mut lines = Vec::new();
+    match &result.structured_content {
+        // The text of a structured result is the same JSON, unformatted.
+        Some(structured) if !result.is_error => lines.push(
+            serde_json::to_string_pretty(structured).expect("JSON serialization cannot fail"),
+        ),
+        _ => lines.extend(result.content.iter().filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.trim_end().to_string()),
+            ContentBlock::ResourceLink { .. } => None,
+        })),
+    }
+    for block in &result.content {
+        if let ContentBlock::ResourceLink { uri, name, .. } = block {
+            lines.push(format!("link: {} <{}>", name, uri));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(
+            parse_arguments(r#"{"title": "Fix"}"#).unwrap()["title"],
+            "Fix"
+        );
+        assert!(parse_arguments("[1]").unwrap_err().contains("JSON object"));
+        assert!(parse_arguments("{").unwrap_err().contains("not valid JSON"));
+    }
+
+    #[test]
+    fn test_describe() {
+        let result = CallToolResult {
+            content: vec![
+                ContentBlock::Text {
+                    text: r#"{"id":"T-1"}"#.to_string(),
+                },
+                ContentBlock::ResourceLink {
+                    uri: "ticket://T-1".to_string(),
+                    name: "ticket".to_string(),
+                    mime_type: None,
+                    description: None,
+                },
+            ],
+            structured_content: Some(json!({"id": "T-1"})),
+            is_error: false,
+        };
+        assert_eq!(
+            describe(&result),
+            "{\n  \"id\": \"T-1\"\n}\nlink: ticket <ticket://T-1>"
+        );
+        assert_eq!(
+            describe(&CallToolResult::error("deploy exited with 1\n")),
+            "deploy exited with 1"
+        );
+    }
+}
@@ -0,0 +1,671 @@
+//! The MCP protocol, independent of how messages are carried.
+//!
+//! An [`McpServer`] answers JSON-RPC messages for a [`ToolRegistry`]: it
+//! negotiates the protocol in `initialize`, lists and calls tools
+//! (`tools/list`, `tools/call`), and offers tool documentation as resources
+//! (`resources/list`, `resources/read`). Transports (see
+//! [`transport`](crate::transport)) only move text in and out, and, if they
+//! can, carry the server's own requests to the client as a [`Peer`]:
+//!
+//! ```ignore
+//! let server = McpServer::new(registry);
+//! if let Some(reply) = server.handle_text(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#) {
+//!     println!("{}", reply);
+//! }
+//! ```
+
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::confirm::{self, Answer, ConfirmPolicy};
+use crate::executor::{check_arguments, Executor};
+use crate::redact::Redactor;
+use crate::registry::{RegisteredTool, ToolRegistry};
+use crate::tool_discovery::wire::CallToolResult;
+
+/// Protocol revisions the server speaks, newest first.
+pub const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC error codes.
+pub mod error_code {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+    /// MCP's code for a resource that does not exist
+    pub const RESOURCE_NOT_FOUND: i64 = -32002;
+}
+
+/// Answers MCP requests for the tools of a registry.
+#[derive(Debug)]
+pub struct McpServer {
+    registry: RwLock<Arc<ToolRegistry>>,
+    executor: Executor,
+    /// Which calls are confirmed before they run, if any are
+    confirm: Option<ConfirmPolicy>,
+    /// Whether the client declared in `initialize` that it can ask its
+    /// user for input. Only stdio, with a single client, carries requests.
+    elicitation: AtomicBool,
+}
+
+/// The client at the other end of a transport that carries requests from
+/// the server, as stdio does.
+pub trait Peer {
+    /// Send the request `method` with `params` to the client, and wait for
+    /// its reply: the result, or the error it answered with.
+    fn request(&mut self, method: &str, params: Value) -> io::Result<Result<Value, Value>>;
+}
+
+/// A request that could not be answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(error_code::INVALID_PARAMS, message)
+    }
+}
+
+impl McpServer {
+    /// Create a server for the tools in `registry`.
+    pub fn new(registry: ToolRegistry) -> Self {
+        Self {
+            registry: RwLock::new(Arc::new(registry)),
+            executor: Executor::new(),
+            confirm: None,
+            elicitation: AtomicBool::new(false),
+        }
+    }
+
+    /// Run tools with `executor` instead of one without limits of its own.
+    pub fn with_executor(mut self, executor: Executor) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Have calls of destructive tools confirmed before they run, as
+    /// `policy` requires (see [`confirm`]).
+    pub fn with_confirmation(mut self, policy: ConfirmPolicy) -> Self {
+        self.confirm = Some(policy);
+        self
+    }
+
+    /// The tools being served.
+    pub fn registry(&self) -> Arc<ToolRegistry> {
+        Arc::clone(&self.registry.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Serve the tools of `registry` from now on, as when the configuration
+    /// is reloaded. Requests already being answered keep the tools they
+    /// started with.
+    pub fn replace_registry(&self, registry: ToolRegistry) {
+        *self
+            .registry
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(registry);
+    }
+
+    /// What runs the tools, for shutting down.
+    pub fn executor(&self) -> &Executor {
+        &self.executor
+    }
+
+    /// The message `text` as it may be logged: the arguments of any
+    /// `tools/call` request in it with the values its tool marks sensitive
+    /// hidden (see [`redact`](crate::redact)).
+    pub fn redact_message<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let Ok(mut message) = serde_json::from_str::<Value>(text) else {
+            return Cow::Borrowed(text);
+        };
+        let registry = self.registry();
+        let mut redacted = false;
+        let messages = match &mut message {
+            Value::Array(batch) => batch.iter_mut().collect(),
+            message => vec![message],
+        };
+        for message in messages {
+            if message["method"] != "tools/call" {
+                continue;
+            }
+            let tool = message["params"]["name"]
+                .as_str()
+                .and_then(|name| registry.get(name));
+            let redactor = tool.map(|tool| Redactor::for_definition(&tool.definition));
+            let arguments = message.pointer_mut("/params/arguments");
+            if let (Some(redactor), Some(arguments)) = (redactor, arguments) {
+                if !redactor.is_empty() {
+                    *arguments = redactor.redact_arguments(arguments);
+                    redacted = true;
+                }
+            }
+        }
+        if redacted {
+            Cow::Owned(message.to_string())
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// Answer a message given as JSON text, returning the reply's text.
+    /// Notifications, and batches of them, get no reply.
+    pub fn handle_text(&self, text: &str) -> Option<String> {
+        self.handle_text_with(text, None)
+    }
+
+    /// Like [`handle_text`](Self::handle_text), sending any requests of the
+    /// server's own to `peer`.
+    pub fn handle_text_with(&self, text: &str, peer: Option<&mut dyn Peer>) -> Option<String> {
+        let reply = match serde_json::from_str::<Value>(text) {
+            Ok(message) => self.handle_with(message, peer)?,
+            Err(e) => error_response(
+                Value::Null,
+                RpcError::new(error_code::PARSE_ERROR, e.to_string()),
+            ),
+        };
+        Some(reply.to_string())
+    }
+
+    /// Answer a message, or a batch of messages, returning the reply.
+    /// Notifications, and batches of them, get no reply.
+    pub fn handle(&self, message: Value) -> Option<Value> {
+        self.handle_with(message, None)
+    }
+
+    /// Like [`handle`](Self::handle), sending any requests of the server's
+    /// own to `peer`.
+    pub fn handle_with(&self, message: Value, mut peer: Option<&mut dyn Peer>) -> Option<Value> {
+        match message {
+            Value::Array(batch) if batch.is_empty() => Some(error_response(
+                Value::Null,
+                RpcError::new(error_code::INVALID_REQUEST, "empty batch"),
+            )),
+            Value::Array(batch) => {
+                let replies: Vec<Value> = batch
+                    .into_iter()
+                    .filter_map(|message| self.handle_one(message, peer.as_deref_mut()))
+                    .collect();
+                (!replies.is_empty()).then_some(Value::Array(replies))
+            }
+            message => self.handle_one(message, peer),
+        }
+    }
+
+    fn handle_one(&self, message: Value, peer: Option<&mut (dyn Peer + '_)>) -> Option<Value> {
+        let Value::Object(mut message) = message else {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(error_code::INVALID_REQUEST, "expected a JSON-RPC object"),
+            ));
+        };
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // Responses need no reply; anything else without a method is
+            // malformed.
+            if message.contains_key("result") || message.contains_key("error") {
+                return None;
+            }
+            return Some(error_response(
+                message.remove("id").unwrap_or(Value::Null),
+                RpcError::new(error_code::INVALID_REQUEST, "missing method"),
+            ));
+        };
+        // Notifications carry no id and get no reply.
+        let id = message.get("id").cloned()?;
+        let params = match message.get("params") {
+            Some(Value::Object(params)) => params.clone(),
+            None | Some(Value::Null) => Map::new(),
+            Some(_) => {
+                return Some(error_response(
+                    id,
+                    RpcError::invalid_params("params must be an object"),
+                ))
+            }
+        };
+        Some(match self.dispatch(method, &params, peer) {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn dispatch(
+        &self,
+        method: &str,
+        params: &Map<String, Value>,
+        peer: Option<&mut (dyn Peer + '_)>,
+    ) -> Result<Value, RpcError> {
+        tracing::debug!("handling {}", method);
+        match method {
+            "initialize" => Ok(self.initialize(params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools()),
+            "tools/call" => self.call_tool(params, peer),
+            "resources/list" => Ok(json!({"resources": self.registry().resources()})),
+            "resources/read" => self.read_resource(params),
+            _ => Err(RpcError::new(
+                error_code::METHOD_NOT_FOUND,
+                format!("unknown method `{}`", method),
+            )),
+        }
+    }
+
+    /// Agree on the client's protocol revision if the server speaks it, and
+    /// offer the newest one otherwise.
+    fn initialize(&self, params: &Map<String, Value>) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str);
+        let version = requested
+            .filter(|version| PROTOCOL_VERSIONS.contains(version))
+            .unwrap_or(PROTOCOL_VERSIONS[0]);
+        let elicitation = params
+            .get("capabilities")
+            .is_some_and(|capabilities| capabilities["elicitation"].is_object());
+        self.elicitation.store(elicitation, Ordering::Relaxed);
+        let mut capabilities = json!({"tools": {"listChanged": false}});
+        if !self.registry().resources().is_empty() {
+            capabilities["resources"] = json!({"listChanged": false});
+        }
+        json!({
+            "protocolVersion": version,
+            "capabilities": capabilities,
+            "serverInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        })
+    }
+
+    fn list_tools(&self) -> Value {
+        let tools: Vec<Value> = self
+            .registry()
+            .tools()
+            .iter()
+            .map(RegisteredTool::to_wire_json)
+            .collect();
+        json!({ "tools": tools })
+    }
+
+    fn call_tool(
+        &self,
+        params: &Map<String, Value>,
+        peer: Option<&mut (dyn Peer + '_)>,
+    ) -> Result<Value, RpcError> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::invalid_params("missing tool name"))?;
+        let registry = self.registry();
+        let tool = registry
+            .get(name)
+            .ok_or_else(|| RpcError::invalid_params(format!("unknown tool `{}`", name)))?;
+        let arguments = match params.get("arguments") {
+            Some(Value::Object(arguments)) => arguments.clone(),
+            None | Some(Value::Null) => Map::new(),
+            Some(_) => return Err(RpcError::invalid_params("arguments must be an object")),
+        };
+        let result = match self.refusal(tool, &arguments, peer) {
+            Some(message) => CallToolResult::error(message),
+            None => self.executor.call(tool, &arguments),
+        };
+        serde_json::to_value(result)
+            .map_err(|e| RpcError::new(error_code::INTERNAL_ERROR, e.to_string()))
+    }
+
+    /// Why the call of `tool` with `arguments` is not run, if it must be
+    /// confirmed and is not: the client is asked through `peer` if it can
+    /// be, and the server's terminal otherwise.
+    fn refusal(
+        &self,
+        tool: &RegisteredTool,
+        arguments: &Map<String, Value>,
+        peer: Option<&mut (dyn Peer + '_)>,
+    ) -> Option<String> {
+        let definition = &tool.definition;
+        if !self
+            .confirm
+            .as_ref()
+            .is_some_and(|policy| policy.requires(definition))
+        {
+            return None;
+        }
+        // Nobody is asked about a call that would fail anyway.
+        if let Err(message) = check_arguments(definition, arguments) {
+            return Some(message);
+        }
+        let question = confirm::question(definition, arguments);
+        let peer = peer.filter(|_| self.elicitation.load(Ordering::Relaxed));
+        let answer = match peer {
+            Some(peer) => match peer.request("elicitation/create", confirm::elicitation(&question))
+            {
+                Ok(Ok(result)) => confirm::read_elicitation(&result),
+                Ok(Err(error)) => Answer::Unavailable(format!(
+                    "the client could not ask: {}",
+                    error["message"].as_str().unwrap_or("no reason given")
+                )),
+                Err(e) => Answer::Unavailable(format!("the client could not be asked: {}", e)),
+            },
+            None => confirm::ask_terminal(&question),
+        };
+        match answer {
+            Answer::Confirmed => {
+                tracing::info!("Running {}, as the user confirmed", definition.name);
+                None
+            }
+            Answer::Declined => Some(format!(
+                "The user declined to run {}; do not retry it unless asked to.",
+                definition.name
+            )),
+            Answer::Unavailable(reason) => Some(format!(
+                "{} must be confirmed before it runs, and nobody could be asked: {}",
+                definition.name, reason
+            )),
+        }
+    }
+
+    fn read_resource(&self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let uri = params
+            .get("uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::invalid_params("missing resource uri"))?;
+        match self.registry().read_resource(uri) {
+            Some(Ok(contents)) => Ok(json!({ "contents": [contents] })),
+            Some(Err(e)) => Err(RpcError::new(
+                error_code::INTERNAL_ERROR,
+                format!("could not read {}: {}", uri, e),
+            )),
+            None => Err(RpcError::new(
+                error_code::RESOURCE_NOT_FOUND,
+                format!("unknown resource `{}`", uri),
+            )),
+        }
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": error.code, "message": error.message},
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::ToolError;
+    use crate::registry::DuplicatePolicy;
+    use crate::tool_discovery::ToolDefinition;
+
+    fn server() -> McpServer {
+        let definition = ToolDefinition::from_yaml(
+            r#"
+name: greet
+description: Greet someone
+input:
+  template: ""
+  schema: { type: object, properties: { name: { type: string } }, required: [name] }
+output:
+  template: ""
+  schema: { type: object, properties: { greeting: { type: string } } }
+"#,
+        )
+        .unwrap();
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register_handler(definition, |arguments: &Map<String, Value>| {
+                let name = arguments["name"]
+                    .as_str()
+                    .ok_or_else(|| ToolError::new("name must be a string"))?;
+                Ok(json!({"greeting": format!("Hello, {}!", name)}))
+            })
+            .unwrap();
+        McpServer::new(registry)
+    }
+
+    fn request(server: &McpServer, method: &str, params: Value) -> Value {
+        server
+            .handle(json!({"jsonrpc": "2.0", "id": 7, "method": method, "params": params}))
+            .expect("requests are answered")
+    }
+
+    #[test]
+    fn test_initialize() {
+        let server = server();
+
+        let reply = request(
+            &server,
+            "initialize",
+            json!({"protocolVersion": "2025-03-26"}),
+        );
+        assert_eq!(reply["id"], 7);
+        assert_eq!(reply["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(reply["result"]["serverInfo"]["name"], "mcp-serve");
+        assert!(reply["result"]["capabilities"]["tools"].is_object());
+        assert!(reply["result"]["capabilities"].get("resources").is_none());
+
+        let reply = request(
+            &server,
+            "initialize",
+            json!({"protocolVersion": "1999-01-01"}),
+        );
+        assert_eq!(reply["result"]["protocolVersion"], PROTOCOL_VERSIONS[0]);
+        assert_eq!(request(&server, "ping", Value::Null)["result"], json!({}));
+    }
+
+    #[test]
+    fn test_tools() {
+        let server = server();
+
+        let reply = request(&server, "tools/list", json!({}));
+        assert_eq!(reply["result"]["tools"][0]["name"], "greet");
+        assert!(reply["result"]["tools"][0]["inputSchema"].is_object());
+
+        let reply = request(
+            &server,
+            "tools/call",
+            json!({"name": "greet", "arguments": {"name": "Ada"}}),
+        );
+        assert_eq!(
+            reply["result"]["structuredContent"],
+            json!({"greeting": "Hello, Ada!"})
+        );
+
+        // Failed calls are results the model can read...
+        let reply = request(&server, "tools/call", json!({"name": "greet"}));
+        assert_eq!(reply["result"]["isError"], true);
+
+        // ...but calls of unknown tools are protocol errors.
+        let reply = request(&server, "tools/call", json!({"name": "wave"}));
+        assert_eq!(reply["error"]["code"], error_code::INVALID_PARAMS);
+        assert_eq!(reply["error"]["message"], "unknown tool `wave`");
+
+        // Tools can be replaced while serving.
+        server.replace_registry(ToolRegistry::new(DuplicatePolicy::Error));
+        let reply = request(&server, "tools/list", json!({}));
+        assert_eq!(reply["result"]["tools"], json!([]));
+        let reply = request(&server, "tools/call", json!({"name": "greet"}));
+        assert_eq!(reply["error"]["message"], "unknown tool `greet`");
+    }
+
+    /// A client answering every request with `reply`, recording the requests.
+    struct Client {
+        reply: Result<Value, Value>,
+        requests: Vec<(String, Value)>,
+    }
+
+    impl Peer for Client {
+        fn request(&mut self, method: &str, params: Value) -> io::Result<Result<Value, Value>> {
+            self.requests.push((method.to_string(), params));
+            Ok(self.reply.clone())
+        }
+    }
+
+    #[test]
+    fn test_confirmation() {
+        let definition = ToolDefinition::from_yaml(
+            r#"
+name: wipe
+description: Wipe the disk
+annotations: { destructiveHint: true }
+input: { template: "", schema: { type: object, properties: { disk: { type: string } } } }
+output: { template: "", schema: { type: object } }
+"#,
+        )
+        .unwrap();
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register_handler(definition, |_: &Map<String, Value>| {
+                Ok(json!({"wiped": true}))
+            })
+            .unwrap();
+        let server = McpServer::new(registry).with_confirmation(ConfirmPolicy::default());
+        let initialize = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+            "protocolVersion": "2025-06-18",
+            "capabilities": {"elicitation": {}},
+        }});
+        server.handle(initialize).unwrap();
+        let call = json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {
+            "name": "wipe",
+            "arguments": {"disk": "sda"},
+        }});
+
+        let mut client = Client {
+            reply: Ok(json!({"action": "accept", "content": {"confirm": true}})),
+            requests: Vec::new(),
+        };
+        let reply = server.handle_with(call.clone(), Some(&mut client)).unwrap();
+        assert_eq!(reply["result"]["structuredContent"], json!({"wiped": true}));
+        let (method, params) = &client.requests[0];
+        assert_eq!(method, "elicitation/create");
+        assert!(params["message"].as_str().unwrap().contains("\"sda\""));
+
+        client.reply = Ok(json!({"action": "decline"}));
+        let reply = server.handle_with(call.clone(), Some(&mut client)).unwrap();
+        assert_eq!(reply["result"]["isError"], true);
+        assert_eq!(
+            reply["result"]["content"][0]["text"],
+            "The user declined to run wipe; do not retry it unless asked to."
+        );
+
+        client.reply = Err(json!({"code": -1, "message": "no user"}));
+        let reply = server.handle_with(call, Some(&mut client)).unwrap();
+        let text = reply["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(
+            text.ends_with("the client could not ask: no user"),
+            "{}",
+            text
+        );
+
+        // Calls that would fail are not asked about.
+        let invalid = json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {
+            "name": "wipe",
+            "arguments": {"disk": 1},
+        }});
+        let reply = server.handle_with(invalid, Some(&mut client)).unwrap();
+        assert_eq!(reply["result"]["isError"], true);
+        assert_eq!(client.requests.len(), 3);
+    }
+
+    #[test]
+    fn test_redact_message() {
+        let definition = ToolDefinition::from_yaml(
+            r#"
+name: login
+description: Log in
+input:
+  template: ""
+  schema:
+    type: object
+    properties: { user: { type: string }, password: { type: string, x-sensitive: true } }
+output: { template: "", schema: { type: object } }
+"#,
+        )
+        .unwrap();
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register_handler(definition, |arguments: &Map<String, Value>| {
+                Err(ToolError::new(format!(
+                    "wrong password {}",
+                    arguments["password"]
+                )))
+            })
+            .unwrap();
+        let server = McpServer::new(registry);
+        let call = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {
+            "name": "login",
+            "arguments": {"user": "ada", "password": "hunter2"},
+        }});
+
+        let text = call.to_string();
+        let logged = server.redact_message(&text);
+        assert!(logged.contains("[REDACTED]") && logged.contains("ada"));
+        assert!(!logged.contains("hunter2"), "{}", logged);
+        let batch = json!([call, {"jsonrpc": "2.0", "id": 2, "method": "ping"}]).to_string();
+        assert!(!server.redact_message(&batch).contains("hunter2"));
+        let list = r#"{"jsonrpc":"2.0","id":3,"method":"tools/list"}"#;
+        assert!(matches!(server.redact_message(list), Cow::Borrowed(_)));
+
+        let reply = server.handle(call).unwrap();
+        let text = reply["result"]["content"][0]["text"].as_str().unwrap();
+        assert_eq!(text, "wrong password \"[REDACTED]\"");
+    }
+
+    #[test]
+    fn test_resources() {
+        let server = server();
+
+        let reply = request(&server, "resources/list", json!({}));
+        assert_eq!(reply["result"], json!({"resources": []}));
+        let reply = request(
+            &server,
+            "resources/read",
+            json!({"uri": "mcp-serve://docs/greet"}),
+        );
+        assert_eq!(reply["error"]["code"], error_code::RESOURCE_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_messages() {
+        let server = server();
+
+        assert_eq!(
+            server.handle(json!({"jsonrpc": "2.0", "method": "notifications/initialized"})),
+            None
+        );
+        assert_eq!(
+            server.handle(json!({"jsonrpc": "2.0", "id": 1, "result": {}})),
+            None
+        );
+
+        let reply = request(&server, "tools/run", json!({}));
+        assert_eq!(reply["error"]["code"], error_code::METHOD_NOT_FOUND);
+        let reply = request(&server, "tools/call", json!(["greet"]));
+        assert_eq!(reply["error"]["code"], error_code::INVALID_PARAMS);
+
+        let reply: Value = serde_json::from_str(&server.handle_text("{not json").unwrap()).unwrap();
+        assert_eq!(reply["error"]["code"], error_code::PARSE_ERROR);
+        assert_eq!(reply["id"], Value::Null);
+
+        let reply = server
+            .handle(json!([
+                {"jsonrpc": "2.0", "method": "notifications/initialized"},
+                {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+                {"jsonrpc": "2.0", "id": 2}
+            ]))
+            .unwrap();
+        assert_eq!(reply[0], json!({"jsonrpc": "2.0", "id": 1, "result": {}}));
+        assert_eq!(reply[1]["error"]["code"], error_code::INVALID_REQUEST);
+        assert_eq!(reply[1]["id"], 2);
+    }
+}
@@ -0,0 +1,89 @@
+//! Stopping the server gracefully on SIGTERM or SIGINT (Ctrl-C).
+//!
+//! The first signal asks the server to stop ([`Shutdown::is_requested`]):
+//! it stops taking requests, and lets the tool calls in progress finish up
+//! to a drain timeout (see [`Executor::shut_down`]). A second SIGINT exits
+//! at once, for an operator who will not wait.
+//!
+//! [`Executor::shut_down`]: crate::executor::Executor::shut_down
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How often [`Shutdown::wait`] checks for a request.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The exit status of a process interrupted by SIGINT, as shells report it.
+const INTERRUPTED: i32 = 130;
+
+/// Whether the server was asked to stop; clones share the request.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    /// Request shutdown on SIGTERM or SIGINT, and exit on a second SIGINT.
+    pub fn on_signals() -> io::Result<Self> {
+        let shutdown = Self::default();
+        // Registered first, so that it sees whether an earlier signal was
+        // received before this one is recorded.
+        signal_hook::flag::register_conditional_shutdown(
+            SIGINT,
+            INTERRUPTED,
+            Arc::clone(&shutdown.requested),
+        )?;
+        for signal in [SIGTERM, SIGINT] {
+            signal_hook::flag::register(signal, Arc::clone(&shutdown.requested))?;
+        }
+        Ok(shutdown)
+    }
+
+    /// Ask the server to stop, as a signal would.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the server was asked to stop.
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Wait until the server is asked to stop, or `done` returns true.
+    pub fn wait(&self, mut done: impl FnMut() -> bool) {
+        while !self.is_requested() && !done() {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shutdown() {
+        let shutdown = Shutdown::default();
+        shutdown.wait(|| true);
+        assert!(!shutdown.is_requested());
+
+        let signalled = shutdown.clone();
+        let waiter = thread::spawn(move || signalled.wait(|| false));
+        shutdown.request();
+        waiter.join().unwrap();
+        assert!(shutdown.is_requested());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_on_signals() {
+        let shutdown = Shutdown::on_signals().unwrap();
+        assert!(!shutdown.is_requested());
+        signal_hook::low_level::raise(SIGTERM).unwrap();
+        assert!(shutdown.is_requested());
+    }
+}
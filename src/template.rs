@@ -0,0 +1,1752 @@
+//! Input templates: parsing and expansion.
+//!
+//! An input template turns a tool call's JSON arguments into the command
+//! line of the tool's executable:
+//!
+//! ```text
+//! --title {{title}} [--parent {{parent_id}}] [--label {{label}}...] {{body}}
+//! ```
+//!
+//! - `{{name}}` is replaced by the value of the argument `name`, and
+//!   `{{name|filter}}` by the value passed through a [`Filter`], such as
+//!   `{{payload|json}}` or `{{name|upper}}`. Filters can be chained:
+//!   `{{name|trim|lower}}`.
+//! - `[...]` is an optional section, left out unless every argument it uses
+//!   has a value. Sections may be nested: `[--parent {{parent_id}}
+//!   [--inherit {{inherit}}]]` passes `--inherit` only along with
+//!   `--parent`.
+//! - `[......]` (a section ending in `...`) is a repetition, expanded once
+//!   for each item of the arrays it uses: `[--label {{label}}...]` gives
+//!   `--label ux --label api`.
+//! - `[...SEP]`, where a separator of punctuation follows the `...`,
+//!   expands the section once, with the items of each array joined by the
+//!   separator into one value: `[--labels {{label}}...,]` gives
+//!   `--labels ux,api`.
+//! - In a repetition, `{{name.key}}` and `{{name.value}}` stand for each
+//!   entry of the object argument `name`: `[--{{env.key}} {{env.value}}...]`
+//!   gives `--HOME /root --TERM xterm`.
+//! - `{{name@N}}` makes the value the `N`th positional argument. However
+//!   they are written, positionals come after every other argument, in
+//!   order of `N`, so `{{path@1}} [--depth {{depth}}]` gives
+//!   `--depth 2 src`. `{{--}}` adds a `--` before the positionals (when
+//!   there are any), so values starting with `-` are not taken for flags.
+//! - `[?name:...]` is a conditional section, included when the argument
+//!   `name` is true (or another non-empty value), so a boolean can toggle a
+//!   literal flag: `[?verbose:--verbose]`. `[?!name:...]` is included when
+//!   it is not.
+//!
+//! A backslash makes the syntax literal: `\{{` writes `{{`, `\[` and `\]`
+//! write brackets, and `\\` a backslash. Any other backslash is kept, so
+//! `C:\tools` needs no escaping.
+//!
+//! [`Template::parse`] turns the text into a tree of [`Node`]s, reporting
+//! malformed templates with the byte offset of the problem, and
+//! [`Template::expand`] turns the tree and a call's arguments into the
+//! executable's arguments. Literal text is split into arguments at
+//! whitespace; a substituted value is never split, so `{{title}}` is one
+//! argument however many spaces the title contains.
+//!
+//! `input.template` can also be a list of tokens ([`CommandTemplate`]).
+//! Each token is expanded on its own, and its literal text is not split, so
+//! `["--message={{message}} (automated)", "{{path}}"]` makes two arguments
+//! however the message is written. A token left out entirely, as by an
+//! optional section, makes no argument, and a repetition in a token makes
+//! one argument for each item.
+//!
+//! The same syntax also writes text, such as the `input.stdin_template`
+//! written to a tool's standard input, or the variables of
+//! `input.env_template` ([`EnvTemplate`]): [`Template::render`] keeps
+//! literal text as it is, whitespace included, and puts positionals where
+//! they are written.
+//!
+//! A tool with `execution.shell` runs its template as a [shell](shell)
+//! script instead, with the values substituted into it quoted.
+//!
+//! Definitions can opt into [MiniJinja](jinja) instead with
+//! `input.engine: jinja`; [`InputTemplate`] parses and expands a template
+//! with either engine.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+pub mod jinja;
+pub mod shell;
+
+use crate::quote::QuoteStyle;
+use jinja::JinjaTemplate;
+
+/// Marks the end of a repetition section.
+const REPEAT_MARKER: &str = "...";
+
+/// Opens a conditional section, after the `[`.
+const CONDITION_MARKER: char = '?';
+
+/// Negates a condition.
+const NEGATION_MARKER: char = '!';
+
+/// Ends a condition, before the section's contents.
+const CONDITION_END: char = ':';
+
+/// Makes the next character literal, if it is one of [`ESCAPED`].
+const ESCAPE: char = '\\';
+
+/// The characters an [`ESCAPE`] applies to.
+const ESCAPED: [char; 4] = ['{', '[', ']', ESCAPE];
+
+/// Separates a placeholder's filters from its name and each other.
+const FILTER_SEPARATOR: char = '|';
+
+/// Separates a placeholder's name from its position, as in `{{path@1}}`.
+const POSITION_SEPARATOR: char = '@';
+
+/// The placeholder text of the end-of-options directive, `{{--}}`.
+const END_OF_OPTIONS: &str = "--";
+
+/// Separates an object argument's name from the part of its entries used,
+/// as in `{{env.key}}`.
+const ENTRY_SEPARATOR: char = '.';
+
+/// Byte offsets of a part of the template text.
+pub type Span = Range<usize>;
+
+/// Which engine expands a definition's input template.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateEngine {
+    /// The built-in syntax of placeholders and sections
+    #[default]
+    Builtin,
+
+    /// MiniJinja, for loops, conditionals, and filters
+    Jinja,
+}
+
+impl TemplateEngine {
+    /// Whether this is the default engine.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How `input.template` is written: one text, split into arguments at
+/// whitespace, or a list of tokens, each expanded without splitting.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum CommandTemplate {
+    /// `--title {{title}} {{body}}`
+    Text(String),
+
+    /// `["--title={{title}}", "{{body}}"]`
+    Tokens(Vec<String>),
+}
+
+impl From<&str> for CommandTemplate {
+    fn from(text: &str) -> Self {
+        CommandTemplate::Text(text.to_string())
+    }
+}
+
+impl From<String> for CommandTemplate {
+    fn from(text: String) -> Self {
+        CommandTemplate::Text(text)
+    }
+}
+
+/// A template is equal to its text, if it is written as text.
+impl PartialEq<&str> for CommandTemplate {
+    fn eq(&self, other: &&str) -> bool {
+        matches!(self, CommandTemplate::Text(text) if text == other)
+    }
+}
+
+/// An input template parsed for its engine.
+#[derive(Debug, Clone)]
+pub enum InputTemplate {
+    /// A template in the built-in syntax
+    Builtin(Template),
+
+    /// A MiniJinja template
+    Jinja(JinjaTemplate),
+
+    /// Tokens of a [`CommandTemplate`], each expanded on its own
+    Tokens(Vec<InputTemplate>),
+}
+
+impl InputTemplate {
+    /// Parse `input.template` for `engine`, in either of its forms.
+    pub fn parse_command(
+        template: &CommandTemplate,
+        engine: TemplateEngine,
+    ) -> Result<Self, TemplateError> {
+        match template {
+            CommandTemplate::Text(source) => Self::parse(source, engine),
+            // A token is never split, so it is parsed as text.
+            CommandTemplate::Tokens(tokens) => tokens
+                .iter()
+                .map(|token| Self::parse_text(token, engine))
+                .collect::<Result<_, _>>()
+                .map(InputTemplate::Tokens),
+        }
+    }
+
+    /// Parse the template text `source` for `engine`.
+    pub fn parse(source: &str, engine: TemplateEngine) -> Result<Self, TemplateError> {
+        match engine {
+            TemplateEngine::Builtin => Template::parse(source).map(InputTemplate::Builtin),
+            TemplateEngine::Jinja => JinjaTemplate::parse(source).map(InputTemplate::Jinja),
+        }
+    }
+
+    /// Parse the template text `source` for `engine`, to be rendered as
+    /// text rather than arguments.
+    pub fn parse_text(source: &str, engine: TemplateEngine) -> Result<Self, TemplateError> {
+        match engine {
+            TemplateEngine::Builtin => Template::parse(source).map(InputTemplate::Builtin),
+            TemplateEngine::Jinja => JinjaTemplate::parse_text(source).map(InputTemplate::Jinja),
+        }
+    }
+
+    /// Build the executable's arguments from a call's `arguments`.
+    pub fn expand(&self, arguments: &Map<String, Value>) -> Result<Vec<String>, ExpandError> {
+        match self {
+            InputTemplate::Builtin(template) => template.expand(arguments),
+            InputTemplate::Jinja(template) => template.expand(arguments),
+            InputTemplate::Tokens(tokens) => {
+                let mut argv = Argv {
+                    mode: ArgvMode::Tokens,
+                    ..Argv::default()
+                };
+                expand_tokens(tokens, arguments, &mut argv)?;
+                Ok(argv.finish())
+            }
+        }
+    }
+
+    /// Write text from a call's `arguments`.
+    pub fn render(&self, arguments: &Map<String, Value>) -> Result<String, ExpandError> {
+        match self {
+            InputTemplate::Builtin(template) => template.render(arguments),
+            InputTemplate::Jinja(template) => template.render(arguments),
+            InputTemplate::Tokens(_) => Ok(self.expand(arguments)?.join(" ")),
+        }
+    }
+}
+
+/// Expand each of `tokens` into arguments of its own.
+fn expand_tokens(
+    tokens: &[InputTemplate],
+    arguments: &Map<String, Value>,
+    argv: &mut Argv,
+) -> Result<(), ExpandError> {
+    for token in tokens {
+        match token {
+            InputTemplate::Builtin(template) => expand(&template.nodes, arguments, argv)?,
+            InputTemplate::Jinja(template) => {
+                // MiniJinja cannot tell an empty value from none, so a
+                // token rendering nothing makes no argument.
+                let text = template.render(arguments)?;
+                if !text.is_empty() {
+                    argv.push_value(&text);
+                }
+            }
+            InputTemplate::Tokens(tokens) => expand_tokens(tokens, arguments, argv)?,
+        }
+        argv.word_break();
+    }
+    Ok(())
+}
+
+/// Templates for the environment variables a tool runs with, such as
+/// `TICKET_TITLE: "{{title}}"`.
+#[derive(Debug, Clone)]
+pub struct EnvTemplate {
+    variables: Vec<(String, InputTemplate)>,
+}
+
+impl EnvTemplate {
+    /// Parse the template of each variable in `templates` for `engine`.
+    pub fn parse(
+        templates: &BTreeMap<String, String>,
+        engine: TemplateEngine,
+    ) -> Result<Self, TemplateError> {
+        let variables = templates
+            .iter()
+            .map(|(name, source)| Ok((name.clone(), InputTemplate::parse_text(source, engine)?)))
+            .collect::<Result<_, TemplateError>>()?;
+        Ok(Self { variables })
+    }
+
+    /// The variables for a call's `arguments`. A variable whose template
+    /// writes nothing, such as one left out by an optional section, is not
+    /// set.
+    pub fn render(
+        &self,
+        arguments: &Map<String, Value>,
+    ) -> Result<BTreeMap<String, String>, ExpandError> {
+        let mut env = BTreeMap::new();
+        for (name, template) in &self.variables {
+            let value = template.render(arguments)?;
+            if !value.is_empty() {
+                env.insert(name.clone(), value);
+            }
+        }
+        Ok(env)
+    }
+}
+
+/// A parsed input template in the built-in syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+/// A part of a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// Text passed through as-is (apart from splitting at whitespace)
+    Literal { text: String, span: Span },
+
+    /// `{{name}}`: the value of an argument, or with `{{name.key}}` or
+    /// `{{name.value}}`, a part of each of its entries; passed through any
+    /// filters
+    Placeholder {
+        name: String,
+        entry: Option<EntryPart>,
+        position: Option<usize>,
+        filters: Vec<Filter>,
+        span: Span,
+    },
+
+    /// `{{--}}`: a `--` between the other arguments and the positionals
+    EndOfOptions { span: Span },
+
+    /// `[...]`: included only when its arguments have values
+    Optional { nodes: Vec<Node>, span: Span },
+
+    /// `[......]`: included once per item of its array arguments, or, with
+    /// a separator (`[...,]`), once with the items joined by it
+    Repeat {
+        nodes: Vec<Node>,
+        separator: Option<String>,
+        span: Span,
+    },
+
+    /// `[?name:...]`: included when the argument `name` is truthy, or with
+    /// `[?!name:...]`, when it is not
+    Conditional {
+        name: String,
+        negated: bool,
+        nodes: Vec<Node>,
+        span: Span,
+    },
+}
+
+impl Node {
+    /// Where the node appears in the template text.
+    pub fn span(&self) -> &Span {
+        match self {
+            Node::Literal { span, .. }
+            | Node::Placeholder { span, .. }
+            | Node::Optional { span, .. }
+            | Node::Repeat { span, .. }
+            | Node::Conditional { span, .. }
+            | Node::EndOfOptions { span } => span,
+        }
+    }
+}
+
+/// The part of an object argument's entries a placeholder stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryPart {
+    /// `{{name.key}}`
+    Key,
+
+    /// `{{name.value}}`
+    Value,
+}
+
+impl EntryPart {
+    /// The part written as `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "key" => Some(EntryPart::Key),
+            "value" => Some(EntryPart::Value),
+            _ => None,
+        }
+    }
+
+    /// How the part is written in templates, and the field of an entry
+    /// holding it while a repetition is expanded.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryPart::Key => "key",
+            EntryPart::Value => "value",
+        }
+    }
+}
+
+/// A transformation of a placeholder's value, as in `{{name|upper}}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// `json`: the value as JSON, so strings are quoted and arrays can be
+    /// passed outside a repetition
+    Json,
+
+    /// `shell-quote`: the text quoted for a POSIX shell
+    ShellQuote,
+
+    /// `upper`: the text in upper case
+    Upper,
+
+    /// `lower`: the text in lower case
+    Lower,
+
+    /// `trim`: the text without leading and trailing whitespace
+    Trim,
+}
+
+impl Filter {
+    /// Every filter, in the order they are documented.
+    pub const ALL: [Filter; 5] = [
+        Filter::Json,
+        Filter::ShellQuote,
+        Filter::Upper,
+        Filter::Lower,
+        Filter::Trim,
+    ];
+
+    /// The filter written as `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|filter| filter.name() == name)
+    }
+
+    /// How the filter is written in templates.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filter::Json => "json",
+            Filter::ShellQuote => "shell-quote",
+            Filter::Upper => "upper",
+            Filter::Lower => "lower",
+            Filter::Trim => "trim",
+        }
+    }
+
+    /// Apply the filter to the value of the argument `name`.
+    fn apply(&self, name: &str, value: &Value) -> Result<Value, ExpandError> {
+        let text = match self {
+            Filter::Json => value.to_string(),
+            Filter::ShellQuote => shell_quote(&scalar(name, value)?),
+            Filter::Upper => scalar(name, value)?.to_uppercase(),
+            Filter::Lower => scalar(name, value)?.to_lowercase(),
+            Filter::Trim => scalar(name, value)?.trim().to_string(),
+        };
+        Ok(Value::String(text))
+    }
+}
+
+/// A use of an argument in a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaceholderUse<'a> {
+    /// The argument's name
+    pub name: &'a str,
+
+    /// Where the placeholder appears
+    pub span: &'a Span,
+
+    /// Inside an optional or repetition section
+    pub optional: bool,
+
+    /// Inside a repetition section
+    pub repeated: bool,
+
+    /// The part of the argument's entries used, for an object argument
+    pub entry: Option<EntryPart>,
+
+    /// The positional argument the value is passed as, counting from 1
+    pub position: Option<usize>,
+
+    /// The filters applied to the value
+    pub filters: &'a [Filter],
+
+    /// The condition of a conditional section, rather than a placeholder
+    pub condition: bool,
+}
+
+impl Template {
+    /// Parse the template text `source`.
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut parser = Parser { source, offset: 0 };
+        let nodes = parser.nodes(None)?;
+        Ok(Self { nodes })
+    }
+
+    /// The template's top-level nodes.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Every placeholder, in template order.
+    pub fn placeholders(&self) -> Vec<PlaceholderUse<'_>> {
+        let mut found = Vec::new();
+        collect(&self.nodes, false, false, &mut found);
+        found
+    }
+
+    /// The spans of optional sections that use no arguments at all, and so
+    /// are always included.
+    pub fn constant_sections(&self) -> Vec<&Span> {
+        let mut found = Vec::new();
+        constant_sections(&self.nodes, &mut found);
+        found
+    }
+
+    /// Build the executable's arguments from a call's `arguments`.
+    pub fn expand(&self, arguments: &Map<String, Value>) -> Result<Vec<String>, ExpandError> {
+        let mut argv = Argv::default();
+        expand(&self.nodes, arguments, &mut argv)?;
+        Ok(argv.finish())
+    }
+
+    /// Write text from a call's `arguments`, keeping literal text as it is.
+    pub fn render(&self, arguments: &Map<String, Value>) -> Result<String, ExpandError> {
+        let mut text = Argv {
+            mode: ArgvMode::Text,
+            ..Argv::default()
+        };
+        expand(&self.nodes, arguments, &mut text)?;
+        Ok(text.finish().concat())
+    }
+}
+
+fn collect<'a>(
+    nodes: &'a [Node],
+    optional: bool,
+    repeated: bool,
+    found: &mut Vec<PlaceholderUse<'a>>,
+) {
+    for node in nodes {
+        match node {
+            Node::Literal { .. } | Node::EndOfOptions { .. } => {}
+            Node::Placeholder {
+                name,
+                entry,
+                position,
+                filters,
+                span,
+            } => found.push(PlaceholderUse {
+                name,
+                span,
+                optional,
+                repeated,
+                entry: *entry,
+                position: *position,
+                filters,
+                condition: false,
+            }),
+            Node::Optional { nodes, .. } => collect(nodes, true, repeated, found),
+            Node::Conditional {
+                name, nodes, span, ..
+            } => {
+                found.push(PlaceholderUse {
+                    name,
+                    span,
+                    optional,
+                    repeated,
+                    entry: None,
+                    position: None,
+                    filters: &[],
+                    condition: true,
+                });
+                collect(nodes, true, repeated, found);
+            }
+            Node::Repeat { nodes, .. } => collect(nodes, true, true, found),
+        }
+    }
+}
+
+fn constant_sections<'a>(nodes: &'a [Node], found: &mut Vec<&'a Span>) {
+    for node in nodes {
+        if let Node::Optional { nodes, span } | Node::Repeat { nodes, span, .. } = node {
+            let mut names = Vec::new();
+            collect_names(nodes, &mut names);
+            if names.is_empty() {
+                found.push(span);
+            } else {
+                constant_sections(nodes, found);
+            }
+        } else if let Node::Conditional { nodes, .. } = node {
+            constant_sections(nodes, found);
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    /// Parse nodes up to the end of the text, or up to the `]` closing the
+    /// section opened at `open`.
+    fn nodes(&mut self, open: Option<usize>) -> Result<Vec<Node>, TemplateError> {
+        let mut nodes = Vec::new();
+        let mut literal_start = self.offset;
+        loop {
+            let rest = &self.source[self.offset..];
+            let start = self.offset;
+            let Some(c) = rest.chars().next() else {
+                return match open {
+                    Some(open) => Err(TemplateError::new(TemplateErrorKind::UnclosedSection, open)),
+                    None => {
+                        push_literal(&mut nodes, self.source, literal_start..start);
+                        Ok(nodes)
+                    }
+                };
+            };
+            if let Some(inner) = rest.strip_prefix("{{") {
+                push_literal(&mut nodes, self.source, literal_start..start);
+                let len = inner.find("}}").ok_or_else(|| {
+                    TemplateError::new(TemplateErrorKind::UnclosedPlaceholder, start)
+                })?;
+                if inner[..len].trim() == END_OF_OPTIONS {
+                    self.offset += len + 4;
+                    nodes.push(Node::EndOfOptions {
+                        span: start..self.offset,
+                    });
+                    literal_start = self.offset;
+                    continue;
+                }
+                let mut parts = inner[..len].split(FILTER_SEPARATOR).map(str::trim);
+                let mut name = parts.next().unwrap_or_default();
+                let mut position = None;
+                if let Some((before, index)) = name.rsplit_once(POSITION_SEPARATOR) {
+                    if !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()) {
+                        let index =
+                            index
+                                .parse()
+                                .ok()
+                                .filter(|&index| index > 0)
+                                .ok_or_else(|| {
+                                    TemplateError::new(TemplateErrorKind::InvalidPosition, start)
+                                })?;
+                        (name, position) = (before.trim_end(), Some(index));
+                    }
+                }
+                let mut entry = None;
+                if let Some((object, part)) = name.rsplit_once(ENTRY_SEPARATOR) {
+                    if let Some(part) = EntryPart::from_name(part) {
+                        (name, entry) = (object, Some(part));
+                    }
+                }
+                if name.is_empty() {
+                    return Err(TemplateError::new(
+                        TemplateErrorKind::EmptyPlaceholder,
+                        start,
+                    ));
+                }
+                let filters = parts
+                    .map(|filter| {
+                        Filter::from_name(filter).ok_or_else(|| {
+                            TemplateError::new(
+                                TemplateErrorKind::UnknownFilter(filter.to_string()),
+                                start,
+                            )
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+                self.offset += len + 4;
+                nodes.push(Node::Placeholder {
+                    name: name.to_string(),
+                    entry,
+                    position,
+                    filters,
+                    span: start..self.offset,
+                });
+                literal_start = self.offset;
+                continue;
+            }
+            match c {
+                '[' if rest[1..].starts_with(CONDITION_MARKER) => {
+                    push_literal(&mut nodes, self.source, literal_start..start);
+                    let condition = &rest[2..];
+                    let (negated, condition) = match condition.strip_prefix(NEGATION_MARKER) {
+                        Some(condition) => (true, condition),
+                        None => (false, condition),
+                    };
+                    let name = condition
+                        .find(CONDITION_END)
+                        .map(|end| &condition[..end])
+                        .filter(|name| !name.is_empty() && name.chars().all(is_name_char))
+                        .ok_or_else(|| {
+                            TemplateError::new(TemplateErrorKind::InvalidCondition, start)
+                        })?;
+                    self.offset = self.source.len() - condition.len() + name.len() + 1;
+                    let inner = self.nodes(Some(start))?;
+                    nodes.push(Node::Conditional {
+                        name: name.to_string(),
+                        negated,
+                        nodes: inner,
+                        span: start..self.offset,
+                    });
+                    literal_start = self.offset;
+                }
+                '[' => {
+                    push_literal(&mut nodes, self.source, literal_start..start);
+                    self.offset += 1;
+                    let mut inner = self.nodes(Some(start))?;
+                    let span = start..self.offset;
+                    nodes.push(match strip_repeat_marker(&mut inner) {
+                        Some(separator) => Node::Repeat {
+                            nodes: inner,
+                            separator,
+                            span,
+                        },
+                        None => Node::Optional { nodes: inner, span },
+                    });
+                    literal_start = self.offset;
+                }
+                ']' => {
+                    if open.is_none() {
+                        return Err(TemplateError::new(TemplateErrorKind::UnmatchedClose, start));
+                    }
+                    push_literal(&mut nodes, self.source, literal_start..start);
+                    self.offset += 1;
+                    return Ok(nodes);
+                }
+                ESCAPE if rest[1..].starts_with(ESCAPED) => {
+                    push_literal(&mut nodes, self.source, literal_start..start);
+                    self.offset += 2;
+                    push_text(&mut nodes, &rest[1..2], start..self.offset);
+                    literal_start = self.offset;
+                }
+                _ => self.offset += c.len_utf8(),
+            }
+        }
+    }
+}
+
+fn push_literal(nodes: &mut Vec<Node>, source: &str, span: Span) {
+    push_text(nodes, &source[span.clone()], span);
+}
+
+/// Add literal text, joining it to the literal before it (which it follows
+/// when an escape splits the text).
+fn push_text(nodes: &mut Vec<Node>, text: &str, span: Span) {
+    if span.is_empty() {
+        return;
+    }
+    match nodes.last_mut() {
+        Some(Node::Literal {
+            text: last,
+            span: last_span,
+        }) if last_span.end == span.start => {
+            last.push_str(text);
+            last_span.end = span.end;
+        }
+        _ => nodes.push(Node::Literal {
+            text: text.to_string(),
+            span,
+        }),
+    }
+}
+
+/// Remove the `...` (and any separator after it) ending a section's
+/// contents. Returns `None` for a section without one, and otherwise the
+/// separator, if any.
+fn strip_repeat_marker(nodes: &mut Vec<Node>) -> Option<Option<String>> {
+    let Some(Node::Literal { text, span }) = nodes.last_mut() else {
+        return None;
+    };
+    let marker = text.rfind(REPEAT_MARKER)?;
+    let separator = &text[marker + REPEAT_MARKER.len()..];
+    if !separator.chars().all(is_separator_char) {
+        return None;
+    }
+    let separator = (!separator.is_empty()).then(|| separator.to_string());
+    if marker == 0 {
+        nodes.pop();
+    } else {
+        span.end -= text.len() - marker;
+        text.truncate(marker);
+    }
+    Some(separator)
+}
+
+/// Quote `text` as a single word for a POSIX shell.
+pub(crate) fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Condition names are argument names, which cannot contain the
+/// template's own syntax.
+fn is_name_char(c: char) -> bool {
+    !c.is_whitespace() && !"[]{}".contains(c)
+}
+
+/// Separators are punctuation, so that `...` followed by a word is still
+/// literal text.
+fn is_separator_char(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+/// How [`Argv`] treats literal text and values.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum ArgvMode {
+    /// Split literal text into arguments at whitespace.
+    #[default]
+    Words,
+
+    /// Keep literal text whole: each token of a [`CommandTemplate`] is its
+    /// own argument (or one per repetition).
+    Tokens,
+
+    /// Build one text, without setting positionals aside.
+    Text,
+
+    /// Build a shell script: like `Text`, but with values quoted and
+    /// repetitions separated by spaces.
+    Shell(QuoteStyle),
+}
+
+/// Arguments being built from expanded text.
+#[derive(Default)]
+struct Argv {
+    args: Vec<String>,
+    current: Option<String>,
+    positionals: Vec<(usize, String)>,
+    end_of_options: bool,
+    mode: ArgvMode,
+}
+
+impl Argv {
+    /// Add literal text, starting a new argument at each run of whitespace
+    /// when splitting words.
+    fn push_literal(&mut self, text: &str) {
+        if self.mode != ArgvMode::Words {
+            return self.push_raw(text);
+        }
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.args.extend(self.current.take());
+            } else {
+                self.current.get_or_insert_with(String::new).push(c);
+            }
+        }
+    }
+
+    /// Add a substituted value to the current argument. Even an empty value
+    /// makes an argument.
+    fn push_value(&mut self, value: &str) {
+        match self.mode {
+            ArgvMode::Shell(style) => self.push_raw(&style.quote(value)),
+            _ => self.push_raw(value),
+        }
+    }
+
+    fn push_raw(&mut self, text: &str) {
+        self.current.get_or_insert_with(String::new).push_str(text);
+    }
+
+    /// End the current argument. Text is left as the template writes it,
+    /// and a shell script separates words with a space.
+    fn word_break(&mut self) {
+        match self.mode {
+            ArgvMode::Text => {}
+            ArgvMode::Shell(_) => self.push_raw(" "),
+            ArgvMode::Words | ArgvMode::Tokens => self.args.extend(self.current.take()),
+        }
+    }
+
+    /// Set aside a value for the positional argument `position`.
+    fn push_positional(&mut self, position: usize, value: String) {
+        if matches!(self.mode, ArgvMode::Text | ArgvMode::Shell(_)) {
+            return self.push_value(&value);
+        }
+        self.positionals.push((position, value));
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.args.extend(self.current.take());
+        if self.end_of_options && !self.positionals.is_empty() {
+            self.args.push(END_OF_OPTIONS.to_string());
+        }
+        // A stable sort keeps the items of a repeated positional in order.
+        self.positionals.sort_by_key(|(position, _)| *position);
+        self.args
+            .extend(self.positionals.into_iter().map(|(_, value)| value));
+        self.args
+    }
+}
+
+fn expand(
+    nodes: &[Node],
+    arguments: &Map<String, Value>,
+    argv: &mut Argv,
+) -> Result<(), ExpandError> {
+    for node in nodes {
+        match node {
+            Node::Literal { text, .. } => argv.push_literal(text),
+            Node::EndOfOptions { .. } => argv.end_of_options = true,
+            Node::Placeholder {
+                name,
+                entry,
+                position,
+                filters,
+                ..
+            } => {
+                let mut value = arguments
+                    .get(name)
+                    .filter(|value| !value.is_null())
+                    .ok_or_else(|| ExpandError::Missing { name: name.clone() })?;
+                if let Some(part) = entry {
+                    // In a repetition, the argument is one entry, as an
+                    // object of its parts (see `repeated_values`).
+                    value = match value {
+                        Value::Object(entry)
+                            if entry.len() == 2 && entry.contains_key(part.name()) =>
+                        {
+                            &entry[part.name()]
+                        }
+                        _ => {
+                            return Err(ExpandError::EntryOutsideRepetition { name: name.clone() })
+                        }
+                    };
+                }
+                let mut value = value.clone();
+                for filter in filters {
+                    value = filter.apply(name, &value)?;
+                }
+                let value = scalar(name, &value)?;
+                match position {
+                    Some(position) => argv.push_positional(*position, value),
+                    None => argv.push_value(&value),
+                }
+            }
+            Node::Optional { nodes, .. } => {
+                if is_included(nodes, arguments) {
+                    expand(nodes, arguments, argv)?;
+                }
+            }
+            Node::Conditional {
+                name,
+                negated,
+                nodes,
+                ..
+            } => {
+                if is_truthy(arguments.get(name)) != *negated && has_own_values(nodes, arguments) {
+                    expand(nodes, arguments, argv)?;
+                }
+            }
+            Node::Repeat {
+                nodes, separator, ..
+            } => {
+                let arrays = repeated_values(nodes, arguments);
+                if let Some(separator) = separator {
+                    // Empty arrays leave the section out, like zero
+                    // repetitions would.
+                    if arrays.iter().any(|(_, items)| items.is_empty()) {
+                        continue;
+                    }
+                    let mut joined = arguments.clone();
+                    for (name, items) in &arrays {
+                        let join = |part: Option<EntryPart>| {
+                            items
+                                .iter()
+                                .map(|item| match part {
+                                    Some(part) => scalar(name, &item[part.name()]),
+                                    None => scalar(name, item),
+                                })
+                                .collect::<Result<Vec<_>, _>>()
+                                .map(|values| Value::from(values.join(separator)))
+                        };
+                        let value = match arguments.get(*name) {
+                            Some(Value::Object(_)) => json!({
+                                "key": join(Some(EntryPart::Key))?,
+                                "value": join(Some(EntryPart::Value))?,
+                            }),
+                            _ => join(None)?,
+                        };
+                        joined.insert(name.to_string(), value);
+                    }
+                    if is_included(nodes, &joined) {
+                        expand(nodes, &joined, argv)?;
+                    }
+                    continue;
+                }
+                let Some((first, items)) = arrays.first() else {
+                    // Without arrays, a repetition is an optional section.
+                    if is_included(nodes, arguments) {
+                        expand(nodes, arguments, argv)?;
+                    }
+                    continue;
+                };
+                if let Some(&(other, _)) =
+                    arrays.iter().find(|(_, other)| other.len() != items.len())
+                {
+                    return Err(ExpandError::LengthMismatch {
+                        first: first.to_string(),
+                        second: other.to_string(),
+                    });
+                }
+                for index in 0..items.len() {
+                    // Repetitions are separate arguments, as if written
+                    // out one after another.
+                    if index > 0 {
+                        argv.word_break();
+                    }
+                    let mut item = arguments.clone();
+                    for (name, values) in &arrays {
+                        item.insert(name.to_string(), values[index].clone());
+                    }
+                    if is_included(nodes, &item) {
+                        expand(nodes, &item, argv)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a section with contents `nodes` is included.
+///
+/// A section is included when every placeholder of its own has a value.
+/// Placeholders in nested sections are left to those sections, so in
+/// `[--parent {{parent_id}} [--inherit {{inherit}}]]` the outer section
+/// needs only `parent_id`, and the inner one is added when `inherit` is also
+/// given. A section with no placeholders of its own is included when a
+/// section nested in it is (or always, if it has none). A conditional
+/// section counts as nested, and is included when its condition holds and
+/// its own placeholders have values.
+fn is_included(nodes: &[Node], arguments: &Map<String, Value>) -> bool {
+    if nodes
+        .iter()
+        .any(|node| matches!(node, Node::Placeholder { .. }))
+    {
+        return has_own_values(nodes, arguments);
+    }
+    let mut nested = nodes
+        .iter()
+        .filter(|node| {
+            matches!(
+                node,
+                Node::Optional { .. } | Node::Repeat { .. } | Node::Conditional { .. }
+            )
+        })
+        .peekable();
+    nested.peek().is_none()
+        || nested.any(|node| match node {
+            Node::Optional { nodes, .. } => is_included(nodes, arguments),
+            Node::Repeat { nodes, .. } => match repeated_values(nodes, arguments).first() {
+                Some((_, items)) => !items.is_empty(),
+                None => is_included(nodes, arguments),
+            },
+            Node::Conditional {
+                name,
+                negated,
+                nodes,
+                ..
+            } => is_truthy(arguments.get(name)) != *negated && has_own_values(nodes, arguments),
+            _ => false,
+        })
+}
+
+/// Whether every placeholder directly in `nodes` (not in nested sections)
+/// has a value.
+fn has_own_values(nodes: &[Node], arguments: &Map<String, Value>) -> bool {
+    nodes.iter().all(|node| match node {
+        Node::Placeholder { name, .. } => has_value(arguments, name),
+        _ => true,
+    })
+}
+
+/// The values a repetition with contents `nodes` iterates over, by
+/// argument: the items of arrays, and the entries of objects used as
+/// `{{name.key}}` or `{{name.value}}`, each as an object of the two parts.
+fn repeated_values<'a>(
+    nodes: &'a [Node],
+    arguments: &'a Map<String, Value>,
+) -> Vec<(&'a str, Cow<'a, [Value]>)> {
+    let mut uses = Vec::new();
+    collect(nodes, true, true, &mut uses);
+    uses.iter()
+        .filter_map(|placeholder| match arguments.get(placeholder.name) {
+            Some(Value::Array(items)) => Some((placeholder.name, Cow::Borrowed(items.as_slice()))),
+            Some(Value::Object(object)) if placeholder.entry.is_some() => {
+                let entries = object
+                    .iter()
+                    .map(|(key, value)| json!({"key": key, "value": value}))
+                    .collect();
+                Some((placeholder.name, Cow::Owned(entries)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn has_value(arguments: &Map<String, Value>, name: &str) -> bool {
+    arguments.get(name).is_some_and(|value| !value.is_null())
+}
+
+/// Whether a condition's value holds: anything but a missing value, `null`,
+/// `false`, zero, or an empty string, array, or object.
+fn is_truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(value)) => *value,
+        Some(Value::Number(number)) => number.as_f64() != Some(0.0),
+        Some(Value::String(text)) => !text.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Object(object)) => !object.is_empty(),
+    }
+}
+
+fn collect_names<'a>(nodes: &'a [Node], names: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Literal { .. } | Node::EndOfOptions { .. } => {}
+            Node::Placeholder { name, .. } => names.push(name),
+            Node::Optional { nodes, .. } | Node::Repeat { nodes, .. } => {
+                collect_names(nodes, names)
+            }
+            Node::Conditional { name, nodes, .. } => {
+                names.push(name);
+                collect_names(nodes, names);
+            }
+        }
+    }
+}
+
+/// The text of a single value: strings as-is, objects as JSON, other
+/// scalars in their JSON form.
+fn scalar(name: &str, value: &Value) -> Result<String, ExpandError> {
+    match value {
+        Value::String(text) => Ok(text.clone()),
+        Value::Array(_) => Err(ExpandError::UnexpectedArray {
+            name: name.to_string(),
+        }),
+        other => Ok(other.to_string()),
+    }
+}
+
+/// What is wrong with a malformed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateErrorKind {
+    /// A `{{` without a matching `}}`
+    UnclosedPlaceholder,
+
+    /// A `{{}}` without an argument name
+    EmptyPlaceholder,
+
+    /// A `[` without a matching `]`
+    UnclosedSection,
+
+    /// A `]` without a matching `[`
+    UnmatchedClose,
+
+    /// A `[?` not followed by an argument name and `:`
+    InvalidCondition,
+
+    /// A placeholder's filter that does not exist
+    UnknownFilter(String),
+
+    /// A placeholder's position that is not a number from 1 up
+    InvalidPosition,
+
+    /// A MiniJinja syntax error, described by the message
+    Jinja(String),
+
+    /// A placeholder of a shell script where the shell would interpret its
+    /// quoted value, such as `"inside double quotes"`
+    ShellValue(&'static str),
+
+    /// A section or block tag of a shell script that starts or ends inside
+    /// quotes or other shell syntax
+    ShellSection(&'static str),
+}
+
+/// A template that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    /// What is wrong
+    pub kind: TemplateErrorKind,
+
+    /// Byte offset of the problem in the template text
+    pub offset: usize,
+}
+
+impl TemplateError {
+    fn new(kind: TemplateErrorKind, offset: usize) -> Self {
+        Self { kind, offset }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offset = self.offset;
+        match &self.kind {
+            TemplateErrorKind::UnclosedPlaceholder => {
+                write!(f, "\"{{{{\" at byte {} is never closed", offset)
+            }
+            TemplateErrorKind::EmptyPlaceholder => {
+                write!(f, "placeholder at byte {} has no name", offset)
+            }
+            TemplateErrorKind::UnclosedSection => {
+                write!(f, "\"[\" at byte {} is never closed", offset)
+            }
+            TemplateErrorKind::UnmatchedClose => {
+                write!(f, "\"]\" at byte {} has no matching \"[\"", offset)
+            }
+            TemplateErrorKind::InvalidCondition => write!(
+                f,
+                "condition at byte {} needs an argument name and \":\", as in \"[?verbose:--verbose]\"",
+                offset
+            ),
+            TemplateErrorKind::InvalidPosition => write!(
+                f,
+                "placeholder at byte {} has position 0, but positions count from 1",
+                offset
+            ),
+            TemplateErrorKind::UnknownFilter(filter) => {
+                let known: Vec<&str> = Filter::ALL.iter().map(Filter::name).collect();
+                write!(
+                    f,
+                    "placeholder at byte {} uses unknown filter \"{}\" (expected one of {})",
+                    offset,
+                    filter,
+                    known.join(", ")
+                )
+            }
+            TemplateErrorKind::Jinja(message) => write!(f, "{} at byte {}", message, offset),
+            TemplateErrorKind::ShellValue(context) => write!(
+                f,
+                "placeholder at byte {} is {}, where the shell would interpret its value; \
+                 values are quoted already, so write it outside quotes and other shell syntax",
+                offset, context
+            ),
+            TemplateErrorKind::ShellSection(context) => write!(
+                f,
+                "section at byte {} starts or ends {}, so leaving it out would change how the \
+                 shell reads the rest of the script",
+                offset, context
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Arguments that do not fit a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpandError {
+    /// A placeholder outside optional sections has no value.
+    Missing { name: String },
+
+    /// An array was used outside a repetition section.
+    UnexpectedArray { name: String },
+
+    /// Two arrays expanded by the same repetition have different lengths.
+    LengthMismatch { first: String, second: String },
+
+    /// An object's entries were used outside a repetition section.
+    EntryOutsideRepetition { name: String },
+
+    /// A MiniJinja template failed to render, or rendered unbalanced quotes.
+    Render { message: String },
+}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpandError::Missing { name } => write!(f, "missing argument `{}`", name),
+            ExpandError::UnexpectedArray { name } => write!(
+                f,
+                "argument `{}` is an array, but is not in a repetition section",
+                name
+            ),
+            ExpandError::LengthMismatch { first, second } => write!(
+                f,
+                "arguments `{}` and `{}` are repeated together but have different lengths",
+                first, second
+            ),
+            ExpandError::EntryOutsideRepetition { name } => write!(
+                f,
+                "the entries of argument `{}` are used, but not in a repetition section",
+                name
+            ),
+            ExpandError::Render { message } => write!(f, "cannot render template: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ExpandError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn expand(template: &str, arguments: Value) -> Result<Vec<String>, ExpandError> {
+        Template::parse(template)
+            .unwrap()
+            .expand(arguments.as_object().unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let template = Template::parse("-t {{ title }} [--label {{label}}...]").unwrap();
+
+        assert_eq!(
+            template.nodes(),
+            [
+                Node::Literal {
+                    text: "-t ".to_string(),
+                    span: 0..3
+                },
+                Node::Placeholder {
+                    name: "title".to_string(),
+                    entry: None,
+                    position: None,
+                    filters: Vec::new(),
+                    span: 3..14
+                },
+                Node::Literal {
+                    text: " ".to_string(),
+                    span: 14..15
+                },
+                Node::Repeat {
+                    separator: None,
+                    nodes: vec![
+                        Node::Literal {
+                            text: "--label ".to_string(),
+                            span: 16..24
+                        },
+                        Node::Placeholder {
+                            name: "label".to_string(),
+                            entry: None,
+                            position: None,
+                            filters: Vec::new(),
+                            span: 24..33
+                        },
+                    ],
+                    span: 15..37
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |template: &str| Template::parse(template).unwrap_err();
+
+        assert_eq!(
+            error("a {{b"),
+            TemplateError::new(TemplateErrorKind::UnclosedPlaceholder, 2)
+        );
+        assert_eq!(error("{{ }}").kind, TemplateErrorKind::EmptyPlaceholder);
+        assert_eq!(
+            error("x [a [b]"),
+            TemplateError::new(TemplateErrorKind::UnclosedSection, 2)
+        );
+        assert_eq!(
+            error("{{a}}]").to_string(),
+            "\"]\" at byte 5 has no matching \"[\""
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        let template = "--title {{title}} [--parent {{parent}}] [--label {{label}}...] {{body}}";
+
+        assert_eq!(
+            expand(
+                template,
+                json!({"title": "My Ticket", "body": "", "label": ["ux", "api"]})
+            )
+            .unwrap(),
+            [
+                "--title",
+                "My Ticket",
+                "--label",
+                "ux",
+                "--label",
+                "api",
+                ""
+            ]
+        );
+        assert_eq!(
+            expand(template, json!({"title": "T", "parent": 12, "body": "b"})).unwrap(),
+            ["--title", "T", "--parent", "12", "b"]
+        );
+        assert_eq!(
+            expand("--limit={{n}}", json!({"n": 5})).unwrap(),
+            ["--limit=5"]
+        );
+        assert_eq!(
+            expand(template, json!({"title": "T"})),
+            Err(ExpandError::Missing {
+                name: "body".to_string()
+            })
+        );
+        assert_eq!(
+            expand("{{tags}}", json!({"tags": ["a"]})),
+            Err(ExpandError::UnexpectedArray {
+                name: "tags".to_string()
+            })
+        );
+        assert!(matches!(
+            expand("[{{a}}={{b}}...]", json!({"a": [1, 2], "b": [3]})),
+            Err(ExpandError::LengthMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_filters() {
+        let template = Template::parse("{{ name | trim | upper }}").unwrap();
+        assert!(matches!(
+            &template.nodes()[0],
+            Node::Placeholder { filters, .. } if filters == &[Filter::Trim, Filter::Upper]
+        ));
+
+        assert_eq!(
+            expand(
+                "{{name|trim|upper}} {{payload|json}} {{title|shell-quote}} {{n|json}}",
+                json!({
+                    "name": " ux ",
+                    "payload": {"ids": [1, 2]},
+                    "title": "it's",
+                    "n": "5"
+                })
+            )
+            .unwrap(),
+            ["UX", r#"{"ids":[1,2]}"#, r"'it'\''s'", r#""5""#]
+        );
+        assert_eq!(
+            expand("{{tags|json}}", json!({"tags": ["a", "b"]})).unwrap(),
+            [r#"["a","b"]"#]
+        );
+        assert_eq!(
+            expand("[-l {{label|lower}}...]", json!({"label": ["A", "B"]})).unwrap(),
+            ["-l", "a", "-l", "b"]
+        );
+
+        let error = Template::parse("x {{name|shout}}").unwrap_err();
+        assert_eq!(
+            error.kind,
+            TemplateErrorKind::UnknownFilter("shout".to_string())
+        );
+        assert_eq!(
+            error.to_string(),
+            "placeholder at byte 2 uses unknown filter \"shout\" \
+             (expected one of json, shell-quote, upper, lower, trim)"
+        );
+    }
+
+    #[test]
+    fn test_object_entries() {
+        let template = "run [--{{opt.key}} {{opt.value}}...] [-D{{def.key}}={{def.value}}...]";
+        assert_eq!(
+            expand(
+                template,
+                json!({"opt": {"depth": 2, "format": "json"}, "def": {}})
+            )
+            .unwrap(),
+            ["run", "--depth", "2", "--format", "json"]
+        );
+        assert_eq!(
+            expand(
+                "[--set {{kv.key}}={{kv.value}}...,]",
+                json!({"kv": {"a": 1, "b": "x"}})
+            )
+            .unwrap(),
+            ["--set", "a,b=1,x"]
+        );
+        assert_eq!(
+            expand("{{opt.key}}", json!({"opt": {"a": 1}})),
+            Err(ExpandError::EntryOutsideRepetition {
+                name: "opt".to_string()
+            })
+        );
+
+        let template = Template::parse("[{{opt.key|upper}}...] {{a.b}}").unwrap();
+        let uses: Vec<(&str, Option<EntryPart>)> = template
+            .placeholders()
+            .iter()
+            .map(|p| (p.name, p.entry))
+            .collect();
+        assert_eq!(uses, [("opt", Some(EntryPart::Key)), ("a.b", None)]);
+    }
+
+    #[test]
+    fn test_positionals() {
+        let template = "{{dest@2}} {{src@1}} [--depth {{depth}}] [{{extra@3}}...] {{--}}";
+        assert_eq!(
+            expand(
+                template,
+                json!({"src": "-a", "dest": "b", "depth": 2, "extra": ["c", "d"]})
+            )
+            .unwrap(),
+            ["--depth", "2", "--", "-a", "b", "c", "d"]
+        );
+        assert_eq!(expand("-v [{{path@1}}] {{--}}", json!({})).unwrap(), ["-v"]);
+        assert_eq!(expand("-- {{a}}", json!({"a": "x"})).unwrap(), ["--", "x"]);
+
+        let template = Template::parse("{{ files@2 | upper }} {{ -- }}").unwrap();
+        assert!(matches!(
+            &template.nodes()[0],
+            Node::Placeholder { name, position: Some(2), .. } if name == "files"
+        ));
+        assert_eq!(template.nodes()[2], Node::EndOfOptions { span: 22..30 });
+        assert_eq!(
+            Template::parse("{{a@0}}").unwrap_err().kind,
+            TemplateErrorKind::InvalidPosition
+        );
+    }
+
+    #[test]
+    fn test_tokens() {
+        let template: CommandTemplate = serde_yaml_ng::from_str(
+            r#"["--message={{message}} (automated)", "[--label {{label}}...]", "[--parent={{parent}}]", "{{path@1}}", "{{--}}"]"#,
+        )
+        .unwrap();
+        let template = InputTemplate::parse_command(&template, TemplateEngine::Builtin).unwrap();
+        let arguments = json!({"message": "fix  it", "label": ["a b", "c"], "path": "-x"});
+
+        assert_eq!(
+            template.expand(arguments.as_object().unwrap()).unwrap(),
+            [
+                "--message=fix  it (automated)",
+                "--label a b",
+                "--label c",
+                "--",
+                "-x"
+            ]
+        );
+
+        let template = InputTemplate::parse_command(
+            &CommandTemplate::Tokens(vec![
+                "--title={{ title }}".to_string(),
+                "{% if draft %}--draft{% endif %}".to_string(),
+            ]),
+            TemplateEngine::Jinja,
+        )
+        .unwrap();
+        assert_eq!(
+            template
+                .expand(
+                    json!({"title": "It's done", "draft": false})
+                        .as_object()
+                        .unwrap()
+                )
+                .unwrap(),
+            ["--title=It's done"]
+        );
+        assert_eq!(CommandTemplate::from("{{a}}"), "{{a}}");
+    }
+
+    #[test]
+    fn test_render() {
+        let render = |template: &str, arguments: Value| {
+            Template::parse(template)
+                .unwrap()
+                .render(arguments.as_object().unwrap())
+        };
+
+        assert_eq!(
+            render(
+                "# {{title}}\n\n{{body}}\n[Labels:[ {{label}}...]]\n{{--}}{{n@1}}",
+                json!({"title": "A  B", "body": "x\ny", "label": ["ux", "api"], "n": 1})
+            )
+            .unwrap(),
+            "# A  B\n\nx\ny\nLabels: ux api\n1"
+        );
+        assert_eq!(
+            render("{{body}}", json!({})),
+            Err(ExpandError::Missing {
+                name: "body".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_env_template() {
+        let templates = BTreeMap::from([
+            ("TICKET_TITLE".to_string(), "{{title}}".to_string()),
+            ("TICKET_PARENT".to_string(), "[{{parent}}]".to_string()),
+            ("TICKET_LABELS".to_string(), "[{{label}}...,]".to_string()),
+        ]);
+        let env = EnvTemplate::parse(&templates, TemplateEngine::Builtin).unwrap();
+
+        let arguments = json!({"title": "A  title", "label": ["ux", "api"]});
+        assert_eq!(
+            env.render(arguments.as_object().unwrap()).unwrap(),
+            BTreeMap::from([
+                ("TICKET_LABELS".to_string(), "ux,api".to_string()),
+                ("TICKET_TITLE".to_string(), "A  title".to_string()),
+            ])
+        );
+        assert!(env.render(&Map::new()).is_err());
+    }
+
+    #[test]
+    fn test_escapes() {
+        let template = Template::parse(r"echo \{{name}} \[{{x}}\] C:\tools \\{{y}}").unwrap();
+        assert_eq!(
+            template.nodes()[0],
+            Node::Literal {
+                text: "echo {{name}} [".to_string(),
+                span: 0..17
+            }
+        );
+        assert_eq!(
+            template
+                .placeholders()
+                .iter()
+                .map(|p| p.name)
+                .collect::<Vec<_>>(),
+            ["x", "y"]
+        );
+        assert_eq!(
+            expand(
+                r"echo \{{name}} \[{{x}}\] C:\tools \\{{y}}",
+                json!({"x": 1, "y": 2})
+            )
+            .unwrap(),
+            ["echo", "{{name}}", "[1]", r"C:\tools", r"\2"]
+        );
+        assert_eq!(
+            expand(r"[--glob \[a-z\]* {{glob}}]", json!({"glob": "g"})).unwrap(),
+            ["--glob", "[a-z]*", "g"]
+        );
+    }
+
+    #[test]
+    fn test_joined_repetition() {
+        let template = Template::parse("[--labels={{label}}...,] [-x {{x}}...]").unwrap();
+        let Node::Repeat { separator, .. } = &template.nodes()[0] else {
+            panic!("expected a repetition");
+        };
+        assert_eq!(separator.as_deref(), Some(","));
+        assert!(matches!(
+            &template.nodes()[2],
+            Node::Repeat {
+                separator: None,
+                ..
+            }
+        ));
+
+        assert_eq!(
+            expand(
+                "[--labels {{label}}...,]",
+                json!({"label": ["ux", "api", 3]})
+            )
+            .unwrap(),
+            ["--labels", "ux,api,3"]
+        );
+        assert_eq!(
+            expand("[--path {{dir}}...:] x", json!({"dir": []})).unwrap(),
+            ["x"]
+        );
+        // A word after `...` is text, not a separator.
+        assert!(matches!(
+            Template::parse("[wait...more]").unwrap().nodes()[0],
+            Node::Optional { .. }
+        ));
+    }
+
+    #[test]
+    fn test_nested_optionals() {
+        let template = "x [--parent {{parent}} [--inherit {{inherit}}]]";
+
+        assert_eq!(expand(template, json!({})).unwrap(), ["x"]);
+        assert_eq!(expand(template, json!({"inherit": true})).unwrap(), ["x"]);
+        assert_eq!(
+            expand(template, json!({"parent": "p"})).unwrap(),
+            ["x", "--parent", "p"]
+        );
+        assert_eq!(
+            expand(template, json!({"parent": "p", "inherit": null})).unwrap(),
+            ["x", "--parent", "p"]
+        );
+        assert_eq!(
+            expand(template, json!({"parent": "p", "inherit": true})).unwrap(),
+            ["x", "--parent", "p", "--inherit", "true"]
+        );
+
+        // A section without placeholders of its own follows its nested ones.
+        let template = "[--filter [--name {{name}}] [--tag {{tag}}...]]";
+        assert!(expand(template, json!({"tag": []})).unwrap().is_empty());
+        assert_eq!(
+            expand(template, json!({"tag": ["a"]})).unwrap(),
+            ["--filter", "--tag", "a"]
+        );
+        assert_eq!(
+            expand(template, json!({"name": "n"})).unwrap(),
+            ["--filter", "--name", "n"]
+        );
+
+        let template = Template::parse("[--verbose] [--a [{{a}}]] [-x [-y]]").unwrap();
+        assert_eq!(template.constant_sections(), [&(0..11), &(26..35)]);
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let template = Template::parse("run [?verbose:--verbose] [?!color:--no-color]").unwrap();
+        assert_eq!(
+            template.nodes()[1],
+            Node::Conditional {
+                name: "verbose".to_string(),
+                negated: false,
+                nodes: vec![Node::Literal {
+                    text: "--verbose".to_string(),
+                    span: 14..23
+                }],
+                span: 4..24
+            }
+        );
+        let template = "run [?verbose:--verbose] [?!color:--no-color]";
+        assert_eq!(
+            expand(template, json!({"verbose": true, "color": true})).unwrap(),
+            ["run", "--verbose"]
+        );
+        assert_eq!(
+            expand(template, json!({"verbose": false})).unwrap(),
+            ["run", "--no-color"]
+        );
+        assert_eq!(
+            expand(template, json!({"verbose": "", "color": 0})).unwrap(),
+            ["run", "--no-color"]
+        );
+
+        // The contents are still an optional section.
+        let template = "[?dry_run:--dry-run [--level {{level}}]] [?tag:--tag {{tag}}]";
+        assert_eq!(
+            expand(template, json!({"dry_run": true, "tag": ""})).unwrap(),
+            ["--dry-run"]
+        );
+        assert_eq!(
+            expand(template, json!({"tag": "v1"})).unwrap(),
+            ["--tag", "v1"]
+        );
+
+        let uses = Template::parse("[?v:-v {{n}}]").unwrap();
+        let uses: Vec<(&str, bool)> = uses
+            .placeholders()
+            .iter()
+            .map(|p| (p.name, p.condition))
+            .collect();
+        assert_eq!(uses, [("v", true), ("n", false)]);
+
+        for invalid in ["[?verbose]", "[?:-v]", "[?a b:-v]"] {
+            assert_eq!(
+                Template::parse(invalid).unwrap_err(),
+                TemplateError::new(TemplateErrorKind::InvalidCondition, 0)
+            );
+        }
+    }
+}
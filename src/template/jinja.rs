@@ -0,0 +1,250 @@
+//! The MiniJinja engine for input templates (`input.engine: jinja`).
+//!
+//! For templates that need loops, conditionals, or filters:
+//!
+//! ```yaml
+//! input:
+//!   engine: jinja
+//!   template: >-
+//!     --title {{ title }}
+//!     {% if draft %}--draft{% endif %}
+//!     {% for label in labels %}--label {{ label | lower }} {% endfor %}
+//! ```
+//!
+//! The rendered text is split into arguments at whitespace, as with the
+//! built-in engine, and every `{{ ... }}` value is quoted so that it stays a
+//! single argument. Values marked `| safe` are inserted unquoted, and may
+//! span several arguments. Undefined and `none` values insert nothing.
+//!
+//! Templates for text, such as `input.stdin_template`, are parsed with
+//! [`JinjaTemplate::parse_text`] and [rendered](JinjaTemplate::render)
+//! without quoting or splitting. Shell scripts are parsed with
+//! [`JinjaTemplate::parse_shell`], and rendered with values quoted for the
+//! shell but not split.
+
+use minijinja::value::{Value, ValueKind};
+use minijinja::{Environment, Error, ErrorKind, Output};
+use serde_json::Map;
+use std::collections::BTreeSet;
+
+use super::{shell_quote, ExpandError, TemplateError, TemplateErrorKind};
+use crate::quote::QuoteStyle;
+
+/// Name the template is registered under in its environment.
+const TEMPLATE_NAME: &str = "input";
+
+/// A parsed MiniJinja input template.
+#[derive(Debug, Clone)]
+pub struct JinjaTemplate {
+    environment: Environment<'static>,
+}
+
+impl JinjaTemplate {
+    /// Parse the template text `source`, to be expanded into arguments.
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut environment = Environment::new();
+        environment.set_formatter(|out, _state, value| write_value(out, value, shell_quote));
+        Self::add(environment, source)
+    }
+
+    /// Parse the template text `source`, to be rendered as a script for a
+    /// shell quoting in `style`.
+    pub fn parse_shell(source: &str, style: QuoteStyle) -> Result<Self, TemplateError> {
+        let mut environment = Environment::new();
+        environment.set_formatter(move |out, _state, value| {
+            write_value(out, value, |text| style.quote(text).into_owned())
+        });
+        Self::add(environment, source)
+    }
+
+    /// Parse the template text `source`, to be rendered as text.
+    pub fn parse_text(source: &str) -> Result<Self, TemplateError> {
+        Self::add(Environment::new(), source)
+    }
+
+    fn add(mut environment: Environment<'static>, source: &str) -> Result<Self, TemplateError> {
+        environment
+            .add_template_owned(TEMPLATE_NAME, source.to_string())
+            .map_err(|error| {
+                TemplateError::new(
+                    TemplateErrorKind::Jinja(error.detail().unwrap_or("syntax error").to_string()),
+                    error.range().map_or(0, |range| range.start),
+                )
+            })?;
+        Ok(Self { environment })
+    }
+
+    /// The arguments the template refers to.
+    pub fn variables(&self) -> BTreeSet<String> {
+        self.template()
+            .undeclared_variables(false)
+            .into_iter()
+            .collect()
+    }
+
+    /// Build the executable's arguments from a call's `arguments`.
+    pub fn expand(
+        &self,
+        arguments: &Map<String, serde_json::Value>,
+    ) -> Result<Vec<String>, ExpandError> {
+        let rendered = self.render(arguments)?;
+        split_words(&rendered).map_err(|message| ExpandError::Render { message })
+    }
+
+    /// Render the template with a call's `arguments`.
+    pub fn render(
+        &self,
+        arguments: &Map<String, serde_json::Value>,
+    ) -> Result<String, ExpandError> {
+        self.template()
+            .render(arguments)
+            .map_err(|error| ExpandError::Render {
+                message: error.to_string(),
+            })
+    }
+
+    fn template(&self) -> minijinja::Template<'_, '_> {
+        self.environment
+            .get_template(TEMPLATE_NAME)
+            .expect("the template was added when parsed")
+    }
+}
+
+/// Write an interpolated value as one word, quoted by `quote`.
+fn write_value(
+    out: &mut Output,
+    value: &Value,
+    quote: impl Fn(&str) -> String,
+) -> Result<(), Error> {
+    if value.is_undefined() || value.is_none() {
+        return Ok(());
+    }
+    let text = match value.kind() {
+        ValueKind::String => value.as_str().unwrap_or_default().to_string(),
+        ValueKind::Number | ValueKind::Bool => value.to_string(),
+        _ => serde_json::to_string(value)
+            .map_err(|error| Error::new(ErrorKind::BadSerialization, error.to_string()))?,
+    };
+    let text = if value.is_safe() { text } else { quote(&text) };
+    out.write_str(&text).map_err(Error::from)
+}
+
+/// Split rendered text into words at whitespace, honoring single quotes,
+/// double quotes, and backslash escapes as a POSIX shell does.
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(current.take()),
+            '\'' => {
+                let word = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    current.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(current);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn expand(template: &str, arguments: serde_json::Value) -> Vec<String> {
+        JinjaTemplate::parse(template)
+            .unwrap()
+            .expand(arguments.as_object().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_expand() {
+        let template = "--title {{ title }} {% if draft %}--draft{% endif %} \
+                        {% for label in labels %}--label {{ label | upper }} {% endfor %}\
+                        {{ parent }}";
+
+        assert_eq!(
+            expand(
+                template,
+                json!({"title": "It's done", "draft": true, "labels": ["ux", "api"]})
+            ),
+            [
+                "--title",
+                "It's done",
+                "--draft",
+                "--label",
+                "UX",
+                "--label",
+                "API"
+            ]
+        );
+        assert_eq!(
+            expand(
+                "{{ n }} {{ flags | safe }} {{ opts }}",
+                json!({"n": 3, "flags": "-a -b", "opts": {"k": 1}})
+            ),
+            ["3", "-a", "-b", r#"{"k":1}"#]
+        );
+        assert_eq!(
+            JinjaTemplate::parse("{{ a }} {% for x in b %}{{ c }}{% endfor %}")
+                .unwrap()
+                .variables(),
+            BTreeSet::from(["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_render_text() {
+        let template = JinjaTemplate::parse_text("# {{ title }}\n\n{{ body }}").unwrap();
+        let arguments = json!({"title": "It's", "body": "a  b"});
+        assert_eq!(
+            template.render(arguments.as_object().unwrap()).unwrap(),
+            "# It's\n\na  b"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = JinjaTemplate::parse("--a {% if x %}").unwrap_err();
+        assert!(matches!(error.kind, TemplateErrorKind::Jinja(_)));
+
+        assert_eq!(
+            split_words(r#"a 'b c'd "e \"f\"" g\ h"#).unwrap(),
+            ["a", "b cd", r#"e "f""#, "g h"]
+        );
+        assert!(split_words("'open").is_err());
+    }
+}
@@ -0,0 +1,631 @@
+//! Input templates run as shell scripts (`execution.shell: true`).
+//!
+//! A few tools need what only a shell provides, such as pipes, globs, or
+//! redirection. With `execution.shell`, the expanded template is not split
+//! into arguments but run as a script, by `sh -c` or `cmd /C`:
+//!
+//! ```yaml
+//! input:
+//!   template: "grep -rn {{pattern}} src | head -n {{limit}}"
+//! execution:
+//!   shell: true
+//! ```
+//!
+//! The template's own text is the script, and reaches the shell as written.
+//! Every substituted value is quoted for the shell ([`QuoteStyle`]), so a
+//! value is always one word and never runs as a command, and a repetition
+//! separates its items with spaces.
+//!
+//! Quoting protects a value only where the shell reads plain words. Inside
+//! quotes, `$(...)`, backquotes, `${...}`, a comment, or a here-document,
+//! or right after a `\` or `$`, the quotes around a value are themselves
+//! text, and `echo "{{x}}"` would run whatever command `x` holds. Such
+//! templates are refused when parsed, as are sections that start or end
+//! inside quotes, since leaving one out would change the quoting of the
+//! text after it. For `cmd.exe`, a placeholder may not be inside double
+//! quotes or follow `^`, `%`, or `!`.
+//!
+//! The risk that remains is in the script itself, which is why validation
+//! warns about every tool using the shell.
+
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+
+use super::jinja::JinjaTemplate;
+use super::{
+    expand, Argv, ArgvMode, CommandTemplate, ExpandError, InputTemplate, Node, Template,
+    TemplateEngine, TemplateError, TemplateErrorKind,
+};
+use crate::quote::QuoteStyle;
+
+/// An input template parsed to be run as a shell script.
+#[derive(Debug, Clone)]
+pub struct ShellTemplate {
+    template: InputTemplate,
+    style: QuoteStyle,
+}
+
+impl ShellTemplate {
+    /// Parse `input.template` for `engine`, for the shell whose quoting is
+    /// `style` ([`QuoteStyle::Posix`] for `sh`, [`QuoteStyle::Cmd`] for
+    /// `cmd.exe`).
+    ///
+    /// A placeholder or section where the shell would not read a quoted
+    /// value as one word is an error. Tokens are read as the script they
+    /// make, joined with spaces, and an error's offset is within its token.
+    pub fn parse(
+        template: &CommandTemplate,
+        engine: TemplateEngine,
+        style: QuoteStyle,
+    ) -> Result<Self, TemplateError> {
+        let mut script = Script::new(style);
+        let template = match template {
+            CommandTemplate::Text(source) => parse(source, engine, &mut script)?,
+            CommandTemplate::Tokens(tokens) => {
+                let mut parsed = Vec::with_capacity(tokens.len());
+                for token in tokens {
+                    parsed.push(parse(token, engine, &mut script)?);
+                    script.text(" ");
+                }
+                InputTemplate::Tokens(parsed)
+            }
+        };
+        Ok(Self { template, style })
+    }
+
+    /// Write the script for a call's `arguments`.
+    pub fn render(&self, arguments: &Map<String, Value>) -> Result<String, ExpandError> {
+        render(&self.template, arguments, self.style)
+    }
+
+    /// The command running the script for a call's `arguments`: the shell,
+    /// its option, and the script.
+    ///
+    /// On Windows, the script must reach `cmd.exe` exactly as written,
+    /// rather than quoted again as an argument (see
+    /// `std::os::windows::process::CommandExt::raw_arg`).
+    pub fn command(&self, arguments: &Map<String, Value>) -> Result<Vec<String>, ExpandError> {
+        let (shell, option) = match self.style {
+            QuoteStyle::Posix => ("sh", "-c"),
+            QuoteStyle::Msvcrt | QuoteStyle::Cmd => ("cmd", "/C"),
+        };
+        Ok(vec![
+            shell.to_string(),
+            option.to_string(),
+            self.render(arguments)?,
+        ])
+    }
+}
+
+/// Parse one template text, and follow it through `script`.
+fn parse(
+    source: &str,
+    engine: TemplateEngine,
+    script: &mut Script,
+) -> Result<InputTemplate, TemplateError> {
+    match engine {
+        TemplateEngine::Builtin => {
+            let template = Template::parse(source)?;
+            script.nodes(template.nodes())?;
+            Ok(InputTemplate::Builtin(template))
+        }
+        TemplateEngine::Jinja => {
+            let template = JinjaTemplate::parse_shell(source, script.style)?;
+            script.jinja(source)?;
+            Ok(InputTemplate::Jinja(template))
+        }
+    }
+}
+
+/// Shell syntax a value or a section's edge may not be inside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// `'...'`
+    Single,
+
+    /// `$'...'`
+    Ansi,
+
+    /// `"..."`
+    Double,
+
+    /// `` `...` ``
+    Backquote,
+
+    /// `$(...)` or `$((...))`, with the number of parentheses open
+    Command(usize),
+
+    /// `${...}`
+    Parameter,
+
+    /// `#` up to the end of the line
+    Comment,
+}
+
+impl Context {
+    fn describe(self) -> &'static str {
+        match self {
+            Context::Single => "inside single quotes",
+            Context::Ansi => "inside $'...' quotes",
+            Context::Double => "inside double quotes",
+            Context::Backquote => "inside backquotes",
+            Context::Command(_) => "inside $(...)",
+            Context::Parameter => "inside ${...}",
+            Context::Comment => "in a comment",
+        }
+    }
+}
+
+/// Stands for the character before when it was escaped or a value: a
+/// plain part of a word.
+const PLAIN: Option<char> = Some('_');
+
+/// Follows a script's literal text closely enough to tell whether a value
+/// put between it would be read as one word: through quotes, substitutions,
+/// comments, and here-documents for `sh`, and through quotes and `^` for
+/// `cmd.exe`.
+struct Script {
+    style: QuoteStyle,
+    contexts: Vec<Context>,
+    /// The character before
+    last: Option<char>,
+    /// Whether the next character is escaped
+    escaped: bool,
+    /// The delimiter of a here-document being read after `<<`, and whether
+    /// `<<-` strips tabs from its lines
+    delimiter: Option<(String, bool)>,
+    /// Here-documents whose bodies follow, in order, from the next line or,
+    /// when `in_body`, from this one
+    heredocs: VecDeque<(String, bool)>,
+    in_body: bool,
+    /// The here-document line read so far
+    line: String,
+}
+
+impl Script {
+    fn new(style: QuoteStyle) -> Self {
+        Self {
+            style,
+            contexts: Vec::new(),
+            last: None,
+            escaped: false,
+            delimiter: None,
+            heredocs: VecDeque::new(),
+            in_body: false,
+            line: String::new(),
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[Node]) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Literal { text, .. } => self.text(text),
+                Node::Placeholder { span, .. } => self.value(span.start)?,
+                Node::EndOfOptions { .. } => {}
+                Node::Optional { nodes, span }
+                | Node::Repeat { nodes, span, .. }
+                | Node::Conditional { nodes, span, .. } => {
+                    self.section(span.start)?;
+                    self.nodes(nodes)?;
+                    self.section(span.end - 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Follow a MiniJinja template: `{{ ... }}` is a value, and a block
+    /// tag, which may leave out or repeat what follows, is a section edge.
+    fn jinja(&mut self, source: &str) -> Result<(), TemplateError> {
+        let (mut offset, mut text_start) = (0, 0);
+        while let Some(found) = source[offset..].find('{') {
+            let start = offset + found;
+            let close = match source[start + 1..].chars().next() {
+                Some('{') => "}}",
+                Some('%') => "%}",
+                Some('#') => "#}",
+                _ => {
+                    offset = start + 1;
+                    continue;
+                }
+            };
+            self.text(&source[text_start..start]);
+            match close {
+                "}}" => self.value(start)?,
+                "%}" => self.section(start)?,
+                _ => {}
+            }
+            offset = source[start + 2..]
+                .find(close)
+                .map_or(source.len(), |end| start + 2 + end + close.len());
+            text_start = offset;
+        }
+        self.text(&source[text_start..]);
+        Ok(())
+    }
+
+    fn value(&mut self, offset: usize) -> Result<(), TemplateError> {
+        if let Some(context) = self.context() {
+            return Err(TemplateError::new(
+                TemplateErrorKind::ShellValue(context),
+                offset,
+            ));
+        }
+        self.last = PLAIN;
+        Ok(())
+    }
+
+    fn section(&mut self, offset: usize) -> Result<(), TemplateError> {
+        match self.context() {
+            Some(context) => Err(TemplateError::new(
+                TemplateErrorKind::ShellSection(context),
+                offset,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Where the script is, unless it is reading plain words.
+    fn context(&self) -> Option<&'static str> {
+        if self.in_body {
+            return Some("in a here-document");
+        }
+        if self.delimiter.is_some() {
+            return Some("in a here-document's delimiter");
+        }
+        if let Some(context) = self.contexts.last() {
+            return Some(context.describe());
+        }
+        match (self.style, self.escaped, self.last) {
+            (QuoteStyle::Posix, true, _) => Some("after a backslash"),
+            (QuoteStyle::Posix, false, Some('$')) => Some("after \"$\""),
+            (QuoteStyle::Posix, false, _) => None,
+            (_, true, _) => Some("after \"^\""),
+            (_, false, Some('%')) => Some("after \"%\""),
+            (_, false, Some('!')) => Some("after \"!\""),
+            _ => None,
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            if self.in_body {
+                self.body(c);
+            } else if self.delimiter.is_some() && self.read_delimiter(c) {
+                continue;
+            } else if self.style == QuoteStyle::Posix {
+                self.posix(c);
+            } else {
+                self.cmd(c);
+            }
+        }
+    }
+
+    fn posix(&mut self, c: char) {
+        if self.escaped {
+            self.escaped = false;
+            self.last = PLAIN;
+            return;
+        }
+        let last = self.last.replace(c);
+        match (self.contexts.last().copied(), c) {
+            (Some(Context::Single), '\'') => self.close(),
+            (Some(Context::Single), _) => {}
+            (Some(Context::Comment), '\n') => {
+                self.close();
+                self.newline();
+            }
+            (Some(Context::Comment), _) => {}
+            (Some(Context::Ansi), '\'') => self.close(),
+            (Some(Context::Ansi), '\\') => self.escaped = true,
+            (Some(Context::Ansi), _) => {}
+            (_, '\\') => self.escaped = true,
+            (Some(Context::Backquote), '`') => self.close(),
+            (Some(Context::Double), '"') => self.close(),
+            (Some(Context::Parameter), '}') => self.close(),
+            (Some(Context::Command(1)), ')') => self.close(),
+            (Some(Context::Command(open)), ')') => self.open(Context::Command(open - 1), true),
+            (Some(Context::Command(open)), '(') => self.open(Context::Command(open + 1), true),
+            (_, '(') if last == Some('$') => self.open(Context::Command(1), false),
+            (_, '{') if last == Some('$') => self.open(Context::Parameter, false),
+            (_, '`') => self.open(Context::Backquote, false),
+            (Some(Context::Double), _) => {}
+            (_, '"') => self.open(Context::Double, false),
+            (_, '\'') if last == Some('$') => self.open(Context::Ansi, false),
+            (_, '\'') => self.open(Context::Single, false),
+            (None, '#') if last.is_none_or(|c| c.is_whitespace() || ";&|()<>".contains(c)) => {
+                self.open(Context::Comment, false)
+            }
+            (None, '\n') => self.newline(),
+            (None, '<') if last == Some('<') => self.delimiter = Some((String::new(), false)),
+            _ => {}
+        }
+    }
+
+    fn cmd(&mut self, c: char) {
+        if self.escaped {
+            self.escaped = false;
+            self.last = PLAIN;
+            return;
+        }
+        self.last = Some(c);
+        match (self.contexts.last(), c) {
+            (Some(_), '"') => self.close(),
+            (Some(_), _) => {}
+            (None, '^') => self.escaped = true,
+            (None, '"') => self.open(Context::Double, false),
+            _ => {}
+        }
+    }
+
+    /// Enter `context`, or with `replace`, change the current one to it.
+    fn open(&mut self, context: Context, replace: bool) {
+        if replace {
+            self.contexts.pop();
+        }
+        self.contexts.push(context);
+    }
+
+    fn close(&mut self) {
+        self.contexts.pop();
+    }
+
+    /// Read a character of a here-document's delimiter, returning whether
+    /// it was part of it.
+    fn read_delimiter(&mut self, c: char) -> bool {
+        let Some((word, strip)) = &mut self.delimiter else {
+            return false;
+        };
+        match c {
+            // `<<<` is a here-string.
+            '<' if word.is_empty() && !*strip => self.delimiter = None,
+            '-' if word.is_empty() && !*strip => *strip = true,
+            ' ' | '\t' if word.is_empty() => {}
+            c if c.is_whitespace() || ";&|<>()".contains(c) => {
+                if let Some(delimiter) = self.delimiter.take().filter(|(word, _)| !word.is_empty())
+                {
+                    self.heredocs.push_back(delimiter);
+                }
+                return false;
+            }
+            // Quoting the delimiter only keeps the body from being
+            // expanded.
+            '\'' | '"' | '\\' => {}
+            c => word.push(c),
+        }
+        true
+    }
+
+    /// Start reading the bodies of any here-documents on the line ended.
+    fn newline(&mut self) {
+        self.in_body = !self.heredocs.is_empty();
+    }
+
+    fn body(&mut self, c: char) {
+        if c != '\n' {
+            return self.line.push(c);
+        }
+        if let Some((delimiter, strip)) = self.heredocs.front() {
+            let line = if *strip {
+                self.line.trim_start_matches('\t')
+            } else {
+                &self.line
+            };
+            if line == delimiter {
+                self.heredocs.pop_front();
+            }
+        }
+        self.line.clear();
+        self.in_body = !self.heredocs.is_empty();
+    }
+}
+
+fn render(
+    template: &InputTemplate,
+    arguments: &Map<String, Value>,
+    style: QuoteStyle,
+) -> Result<String, ExpandError> {
+    match template {
+        InputTemplate::Builtin(template) => {
+            let mut script = Argv {
+                mode: ArgvMode::Shell(style),
+                ..Argv::default()
+            };
+            expand(&template.nodes, arguments, &mut script)?;
+            Ok(script.finish().concat())
+        }
+        InputTemplate::Jinja(template) => template.render(arguments),
+        InputTemplate::Tokens(tokens) => {
+            let words = tokens
+                .iter()
+                .map(|token| render(token, arguments, style))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(words
+                .into_iter()
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(
+        template: &str,
+        engine: TemplateEngine,
+        style: QuoteStyle,
+        arguments: Value,
+    ) -> String {
+        ShellTemplate::parse(&template.into(), engine, style)
+            .unwrap()
+            .render(arguments.as_object().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let arguments = json!({"pattern": "it's; rm -rf /", "label": ["a b", "c"], "limit": 5});
+        assert_eq!(
+            render(
+                "grep -rn {{pattern}} src [--label {{label}}...] | head -n {{limit}}",
+                TemplateEngine::Builtin,
+                QuoteStyle::Posix,
+                arguments.clone()
+            ),
+            r"grep -rn 'it'\''s; rm -rf /' src --label 'a b' --label c | head -n 5"
+        );
+        assert_eq!(
+            render(
+                "findstr {{pattern}} *.txt",
+                TemplateEngine::Builtin,
+                QuoteStyle::Cmd,
+                arguments.clone()
+            ),
+            r#"findstr ^"it's; rm -rf /^" *.txt"#
+        );
+        assert_eq!(
+            render(
+                "grep {{ pattern }} src{% for l in label %} -e {{ l }}{% endfor %} | wc -l",
+                TemplateEngine::Jinja,
+                QuoteStyle::Posix,
+                arguments.clone()
+            ),
+            r"grep 'it'\''s; rm -rf /' src -e 'a b' -e c | wc -l"
+        );
+
+        let template = ShellTemplate::parse(
+            &CommandTemplate::Tokens(vec![
+                "ls {{path}}".to_string(),
+                "[--sort={{sort}}]".to_string(),
+                "| wc -l".to_string(),
+            ]),
+            TemplateEngine::Builtin,
+            QuoteStyle::Posix,
+        )
+        .unwrap();
+        assert_eq!(
+            template
+                .command(json!({"path": "my dir"}).as_object().unwrap())
+                .unwrap(),
+            ["sh", "-c", "ls 'my dir' | wc -l"]
+        );
+    }
+
+    #[test]
+    fn test_context() {
+        let error = |template: &str, engine, style| {
+            ShellTemplate::parse(&template.into(), engine, style)
+                .unwrap_err()
+                .kind
+        };
+        let posix = |template: &str| error(template, TemplateEngine::Builtin, QuoteStyle::Posix);
+        let accepted = [
+            "echo {{x}}",
+            "echo \"a b\" {{x}} 'c' $(date) ${HOME} # it's a comment",
+            "echo --x={{x}}[ --y {{y}}...] | tr -d \"'\"",
+            "cat <<EOF | grep {{x}}\n$(date) \"\nEOF\necho {{y}}",
+            "cat <<-'END'\n\t'\n\tEND\necho {{x}}#{{y}}",
+            "grep <<< {{x}} y",
+        ];
+        for template in accepted {
+            let parsed =
+                ShellTemplate::parse(&template.into(), TemplateEngine::Builtin, QuoteStyle::Posix);
+            assert!(parsed.is_ok(), "{}: {:?}", template, parsed.unwrap_err());
+        }
+
+        // The quotes around a value would be text, and `$(id -un)` would
+        // run.
+        assert_eq!(
+            posix("echo \"{{x}}\""),
+            TemplateErrorKind::ShellValue("inside double quotes")
+        );
+        let rejected = [
+            ("echo '{{x}}'", "inside single quotes"),
+            ("echo $'{{x}}'", "inside $'...' quotes"),
+            ("echo \"$(cat \"{{x}}\")\"", "inside double quotes"),
+            ("echo $(cat {{x}})", "inside $(...)"),
+            ("echo $((1 + ({{x}})))", "inside $(...)"),
+            ("echo `cat {{x}}`", "inside backquotes"),
+            ("echo ${HOME:-{{x}}}", "inside ${...}"),
+            ("echo ${{x}}", "after \"$\""),
+            (r"echo \\{{x}}", "after a backslash"),
+            ("echo hi # {{x}}", "in a comment"),
+            ("cat <<EOF\n{{x}}\nEOF", "in a here-document"),
+            ("cat << EOF\n\tEOF\n{{x}}\nEOF", "in a here-document"),
+            ("cat <<{{x}}", "in a here-document's delimiter"),
+        ];
+        for (template, context) in rejected {
+            assert_eq!(
+                posix(template),
+                TemplateErrorKind::ShellValue(context),
+                "{}",
+                template
+            );
+        }
+        assert_eq!(
+            posix("echo \"[--x {{x}}]\""),
+            TemplateErrorKind::ShellSection("inside double quotes")
+        );
+        assert_eq!(
+            posix("echo [\"{{x}}]\""),
+            TemplateErrorKind::ShellValue("inside double quotes")
+        );
+        assert_eq!(
+            posix("echo [?quoted:\"]{{x}}[?quoted:\"]"),
+            TemplateErrorKind::ShellSection("inside double quotes")
+        );
+
+        assert_eq!(
+            error("echo \"{{ x }}\"", TemplateEngine::Jinja, QuoteStyle::Posix),
+            TemplateErrorKind::ShellValue("inside double quotes")
+        );
+        assert_eq!(
+            error(
+                "echo {% if q %}\"{% endif %}{{ x }}",
+                TemplateEngine::Jinja,
+                QuoteStyle::Posix
+            ),
+            TemplateErrorKind::ShellSection("inside double quotes")
+        );
+        assert!(ShellTemplate::parse(
+            &"echo {# \" #}{{ x }}{% if y %} -y {{ y }}{% endif %}".into(),
+            TemplateEngine::Jinja,
+            QuoteStyle::Posix
+        )
+        .is_ok());
+
+        let tokens = CommandTemplate::Tokens(vec![
+            "echo \"".to_string(),
+            "{{x}}".to_string(),
+            "\"".to_string(),
+        ]);
+        let error = ShellTemplate::parse(&tokens, TemplateEngine::Builtin, QuoteStyle::Posix);
+        assert_eq!(
+            error.unwrap_err(),
+            TemplateError::new(TemplateErrorKind::ShellValue("inside double quotes"), 0)
+        );
+
+        let cmd = |template: &str| {
+            ShellTemplate::parse(&template.into(), TemplateEngine::Builtin, QuoteStyle::Cmd)
+        };
+        assert!(cmd("findstr {{x}} \"my file.txt\" & echo '{{y}}'").is_ok());
+        let rejected = [
+            ("echo \"{{x}}\"", "inside double quotes"),
+            ("echo ^{{x}}", "after \"^\""),
+            ("echo %{{x}}%", "after \"%\""),
+            ("echo !{{x}}!", "after \"!\""),
+        ];
+        for (template, context) in rejected {
+            assert_eq!(
+                cmd(template).unwrap_err().kind,
+                TemplateErrorKind::ShellValue(context),
+                "{}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Running the test cases definitions declare, for `mcp-serve test`.
+//!
+//! Each [`TestCase`] of a tool is checked the way its fields ask: the
+//! arguments must fit the input schema, expand to the expected command line
+//! and standard input, and the sample output must parse to the expected
+//! result; with `run`, the tool is also called for real. The outcomes are
+//! reported like a test runner's:
+//!
+//! ```text
+//! test create_ticket: files a ticket ... ok
+//! test create_ticket #2 ... FAILED
+//!
+//! failures:
+//!   create_ticket #2
+//!     argv: expected ["--title", "Fix"], got ["--title", "fix"]
+//!
+//! test result: FAILED. 1 passed; 1 failed
+//! ```
+
+use serde_json::{Map, Value};
+use std::fmt;
+
+use crate::executor::{check_arguments, Executor};
+use crate::output::parse_result;
+use crate::preview::{self, CommandLine};
+use crate::redact::Redactor;
+use crate::registry::RegisteredTool;
+use crate::tool_discovery::TestCase;
+
+/// The outcome of one test case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseOutcome {
+    /// The name of the tool tested
+    pub tool: String,
+
+    /// The case's name, or its position (`#2`)
+    pub case: String,
+
+    /// What did not go as expected; empty if the case passed
+    pub failures: Vec<String>,
+}
+
+impl CaseOutcome {
+    /// Whether everything went as expected.
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    fn label(&self) -> String {
+        if self.case.starts_with('#') {
+            format!("{} {}", self.tool, self.case)
+        } else {
+            format!("{}: {}", self.tool, self.case)
+        }
+    }
+}
+
+/// The outcomes of a test run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestReport {
+    /// Each case's outcome, in the order they ran
+    pub outcomes: Vec<CaseOutcome>,
+}
+
+impl TestReport {
+    /// How many cases passed.
+    pub fn passed(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.passed())
+            .count()
+    }
+
+    /// How many cases failed.
+    pub fn failed(&self) -> usize {
+        self.outcomes.len() - self.passed()
+    }
+
+    /// Whether every case passed.
+    pub fn is_ok(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+impl fmt::Display for TestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for outcome in &self.outcomes {
+            let status = if outcome.passed() { "ok" } else { "FAILED" };
+            writeln!(f, "test {} ... {}", outcome.label(), status)?;
+        }
+        if !self.is_ok() {
+            writeln!(f, "\nfailures:")?;
+            for outcome in self.outcomes.iter().filter(|outcome| !outcome.passed()) {
+                writeln!(f, "  {}", outcome.label())?;
+                for failure in &outcome.failures {
+                    writeln!(f, "    {}", failure.replace('\n', "\n    "))?;
+                }
+            }
+        }
+        write!(
+            f,
+            "\ntest result: {}. {} passed; {} failed",
+            if self.is_ok() { "ok" } else { "FAILED" },
+            self.passed(),
+            self.failed()
+        )
+    }
+}
+
+/// Check each test case of `tool`, calling it through `executor` for the
+/// cases that ask to run it.
+pub fn run_cases(tool: &RegisteredTool, executor: &Executor) -> Vec<CaseOutcome> {
+    tool.definition
+        .tests
+        .iter()
+        .enumerate()
+        .map(|(i, case)| CaseOutcome {
+            tool: tool.definition.name.clone(),
+            case: case.name.clone().unwrap_or_else(|| format!("#{}", i + 1)),
+            failures: check_case(tool, case, executor),
+        })
+        .collect()
+}
+
+/// Everything that does not go as `case` expects, with the sensitive values
+/// of its arguments hidden.
+fn check_case(tool: &RegisteredTool, case: &TestCase, executor: &Executor) -> Vec<String> {
+    let redactor = Redactor::for_definition(&tool.definition);
+    let arguments = Value::Object(case.arguments.clone());
+    case_failures(tool, case, executor)
+        .iter()
+        .map(|failure| redactor.redact_text(failure, &arguments))
+        .collect()
+}
+
+fn case_failures(tool: &RegisteredTool, case: &TestCase, executor: &Executor) -> Vec<String> {
+    let definition = &tool.definition;
+    let mut failures = Vec::new();
+    if let Err(message) = check_arguments(definition, &case.arguments) {
+        failures.push(message);
+        return failures;
+    }
+
+    match preview::invocation(definition, &case.arguments) {
+        Ok(invocation) => {
+            match (&invocation.command, &case.argv, &case.script) {
+                (CommandLine::Args(args), Some(expected), _) => {
+                    expect(&mut failures, "argv", expected, args)
+                }
+                (CommandLine::Script(script), _, Some(expected)) => {
+                    expect(&mut failures, "script", expected, script)
+                }
+                (CommandLine::Script(_), Some(_), None) => failures.push(
+                    "argv: the tool runs through the shell; expect a `script` instead".to_string(),
+                ),
+                (CommandLine::Args(_), None, Some(_)) => failures.push(
+                    "script: the tool does not run through the shell; expect `argv` instead"
+                        .to_string(),
+                ),
+                _ => {}
+            }
+            if let Some(expected) = &case.stdin {
+                let stdin = invocation.stdin.unwrap_or_default();
+                expect(&mut failures, "stdin", expected, &stdin);
+            }
+        }
+        Err(e) => failures.push(format!("arguments: {}", e)),
+    }
+
+    if case.has_sample_output() {
+        let parsed = parse_result(
+            &definition.output,
+            case.stdout.as_deref().unwrap_or_default().as_bytes(),
+            case.stderr.as_deref().unwrap_or_default().as_bytes(),
+            Some(case.exit_code.unwrap_or(0)),
+        );
+        match parsed {
+            Ok(result) => {
+                if let Some(expected) = &case.result {
+                    expect_result(&mut failures, "result", expected, &Value::Object(result));
+                }
+            }
+            Err(e) => failures.push(format!("output: {}", e)),
+        }
+    }
+
+    if case.run {
+        let result = executor.call(tool, &case.arguments);
+        if result.is_error {
+            failures.push(format!("run: {}", crate::run::describe(&result)));
+        } else if let (Some(expected), false) = (&case.result, case.has_sample_output()) {
+            let actual = result
+                .structured_content
+                .unwrap_or_else(|| Value::Object(Map::new()));
+            expect_result(&mut failures, "run", expected, &actual);
+        }
+    }
+    failures
+}
+
+fn expect<T: PartialEq + fmt::Debug + ?Sized>(
+    failures: &mut Vec<String>,
+    what: &str,
+    expected: &T,
+    actual: &T,
+) {
+    if expected != actual {
+        failures.push(format!(
+            "{}: expected {:?}, got {:?}",
+            what, expected, actual
+        ));
+    }
+}
+
+fn expect_result(failures: &mut Vec<String>, what: &str, expected: &Value, actual: &Value) {
+    if expected != actual {
+        failures.push(format!("{}: expected {}, got {}", what, expected, actual));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{DuplicatePolicy, ToolRegistry};
+    use crate::tool_discovery::{DiscoveredTool, MetadataSource, ToolDefinition};
+
+    fn register(yaml: &str) -> ToolRegistry {
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        let tool = DiscoveredTool {
+            path: "/tools/create-ticket".into(),
+            relative_path: "create-ticket".into(),
+            metadata_source: MetadataSource::Embedded,
+            namespace: None,
+            working_dir: None,
+            docs: None,
+            defaults: Box::default(),
+            directory_configs: Box::default(),
+        };
+        registry
+            .register(ToolDefinition::from_yaml(yaml).unwrap(), tool)
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_run_cases() {
+        let registry = register(
+            r#"
+name: create_ticket
+description: Creates a ticket
+input:
+  template: "--title {{title}}"
+  schema:
+    type: object
+    properties: { title: { type: string } }
+    required: [title]
+output:
+  template: 'ID: (?<id:int>\d+)'
+  schema: { type: object }
+tests:
+  - name: files a ticket
+    arguments: { title: Fix }
+    argv: [--title, Fix]
+    stdout: "ID: 42\n"
+    result: { id: 42 }
+  - arguments: { title: Fix }
+    argv: [--title, fix]
+    stdout: "ID: 42\n"
+    result: { id: "42" }
+  - arguments: {}
+  - arguments: { title: Fix }
+    script: "echo"
+"#,
+        );
+
+        let outcomes = run_cases(&registry.tools()[0], &Executor::new());
+
+        let failures: Vec<(&str, &[String])> = outcomes
+            .iter()
+            .map(|outcome| (outcome.case.as_str(), outcome.failures.as_slice()))
+            .collect();
+        assert_eq!(failures[0], ("files a ticket", &[][..]));
+        assert_eq!(
+            failures[1],
+            (
+                "#2",
+                &[
+                    r#"argv: expected ["--title", "fix"], got ["--title", "Fix"]"#.to_string(),
+                    r#"result: expected {"id":"42"}, got {"id":42}"#.to_string(),
+                ][..]
+            )
+        );
+        assert!(failures[2].1[0].starts_with("invalid arguments:"));
+        assert!(failures[3].1[0].starts_with("script: the tool does not run"));
+    }
+
+    #[test]
+    fn test_redaction() {
+        let registry = register(
+            r#"
+name: login
+description: Logs in
+input:
+  template: "--password {{password}}"
+  stdin_template: "{{password}}"
+  schema:
+    type: object
+    properties: { password: { type: string, x-sensitive: true } }
+output: { template: "", schema: { type: object } }
+tests:
+  - arguments: { password: hunter2 }
+    argv: [--password, other]
+    stdin: other
+"#,
+        );
+
+        let outcomes = run_cases(&registry.tools()[0], &Executor::new());
+
+        assert_eq!(
+            outcomes[0].failures,
+            [
+                r#"argv: expected ["--password", "other"], got ["--password", "[REDACTED]"]"#,
+                r#"stdin: expected "other", got "[REDACTED]""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let report = TestReport {
+            outcomes: vec![
+                CaseOutcome {
+                    tool: "create_ticket".to_string(),
+                    case: "files a ticket".to_string(),
+                    failures: Vec::new(),
+                },
+                CaseOutcome {
+                    tool: "create_ticket".to_string(),
+                    case: "#2".to_string(),
+                    failures: vec!["stdin: expected \"a\", got \"b\"".to_string()],
+                },
+            ],
+        };
+        assert_eq!(
+            report.to_string(),
+            concat!(
+                "test create_ticket: files a ticket ... ok\n",
+                "test create_ticket #2 ... FAILED\n",
+                "\n",
+                "failures:\n",
+                "  create_ticket #2\n",
+                "    stdin: expected \"a\", got \"b\"\n",
+                "\n",
+                "test result: FAILED. 1 passed; 1 failed",
+            )
+        );
+        assert!(TestReport::default().is_ok());
+    }
+}
@@ -12,14 +12,65 @@
 //! allowing for flexible schema definitions without needing to model
 //! the entire JSON Schema specification.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::output::{OutputCapture, OutputEncoding, OutputTemplate};
+use crate::template::{CommandTemplate, TemplateEngine};
+
+pub mod content;
+pub mod diagnostics;
+pub mod directory_config;
+pub mod docs;
+pub mod embedded;
+pub mod execution;
+pub mod index;
+pub mod loader;
+pub mod manifest;
+pub mod permissions;
+pub mod pin;
+pub mod refs;
+pub mod report;
+pub mod scanner;
+pub mod schema;
+pub mod signature;
+pub mod source;
+pub mod strict;
+pub mod test_case;
+pub mod watch;
+pub mod wire;
+
+pub use content::{OutputContent, ResourceLinkTemplate};
+pub use diagnostics::{DefinitionError, FieldLocator};
+pub use directory_config::{DirectoryConfig, DIRECTORY_CONFIG_FILE};
+pub use docs::ToolDocs;
+pub use execution::{ExecutionConfig, SandboxProfile};
+pub use index::{IndexError, IndexedTool, ScanIndex};
+pub use loader::{LoadError, Loader};
+pub use permissions::PermissionError;
+pub use pin::{Lockfile, PinError};
+pub use refs::RefError;
+pub use report::{ScanReport, SkipReason, SkippedEntry};
+pub use scanner::{
+    DirectoryScanner, DiscoveredTool, MetadataSource, ScanError, ScannerBuilder, ScannerConfig,
+    ToolRoot,
+};
+pub use signature::SignatureError;
+pub use source::{ArchiveFormat, SourceCache, SourceError, ToolSource};
+pub use test_case::TestCase;
+pub use watch::{ToolEvent, ToolWatcher};
 
 /// Pure MCP tool definition as specified in the Model Context Protocol.
 ///
-/// This structure represents the exact MCP specification format and is used
-/// when communicating with MCP clients. It contains no mcp-serve specific
-/// extensions.
+/// This structure represents the MCP specification's tool and is used when
+/// communicating with MCP clients. It contains no mcp-serve specific
+/// extensions. Its own serde representation uses snake_case keys; use
+/// [`McpTool::to_json`] (or [`wire::WireTool`]) for the camelCase JSON sent
+/// over the wire.
 ///
 /// JSON schemas are represented as opaque `serde_json::Value` objects that can
 /// contain any valid JSON Schema structure.
@@ -44,6 +95,8 @@ use std::collections::HashMap;
 ///     }),
 ///     output_schema: None,
 ///     annotations: None,
+///     icons: None,
+///     meta: None,
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -70,8 +123,17 @@ pub struct McpTool {
     #[serde(rename = "output_schema")]
     pub output_schema: Option<serde_json::Value>,
 
-    /// Optional metadata annotations
-    pub annotations: Option<HashMap<String, serde_yaml_ng::Value>>,
+    /// Optional behavior hints for clients
+    pub annotations: Option<ToolAnnotations>,
+
+    /// Optional icons for clients to display with the tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icons: Option<Vec<Icon>>,
+
+    /// Optional metadata for clients (the specification's `_meta`), such as
+    /// the tool's `version`
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Map<String, serde_json::Value>>,
 }
 
 /// mcp-serve tool definition with custom extensions for template-based execution.
@@ -120,14 +182,25 @@ pub struct McpTool {
 ///     .with_title("Create Ticket");
 /// assert_eq!(tool.name, "create_ticket");
 /// ```
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ToolDefinition {
     /// Unique identifier for the tool
     pub name: String,
 
+    /// Other names the tool can be called by, such as its names before a
+    /// rename; only `name` is listed to clients
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+
     /// Optional human-readable display name
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
 
+    /// Optional revision of the tool, such as `1.4.0`, passed on to clients
+    /// in the tool's `_meta` so operators can tell which version is served
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
     /// Human-readable description of the tool's functionality
     pub description: String,
 
@@ -137,14 +210,178 @@ pub struct ToolDefinition {
     /// Output specification with schema and template (required)
     pub output: ToolOutput,
 
-    /// Optional metadata annotations
-    pub annotations: Option<HashMap<String, serde_yaml_ng::Value>>,
+    /// Optional behavior hints for clients
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+
+    /// How the tool's executable is run
+    #[serde(default, skip_serializing_if = "ExecutionConfig::is_default")]
+    pub execution: ExecutionConfig,
+
+    /// SHA-256 digest (hex) the executable must have; a tool whose
+    /// executable differs is neither served nor run (see [`pin`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(pattern(r"^[0-9a-fA-F]{64}$"))]
+    pub sha256: Option<String>,
+
+    /// Marks the tool as deprecated, optionally naming its replacement
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<Deprecation>,
+
+    /// Optional group the tool belongs to, such as `deploy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+
+    /// Labels for selecting subsets of tools, such as `readonly`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Optional icon for clients to display with the tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<Icon>,
+
+    /// Arbitrary metadata forwarded to clients as the tool's `_meta`; a
+    /// `version` key is overridden by the [`version`](Self::version) field
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Map<String, serde_json::Value>>,
+
+    /// Calls and what they should do, checked by `mcp-serve test`; never
+    /// sent to clients
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestCase>,
+}
+
+/// An icon for a tool, as defined by the MCP specification.
+///
+/// In definitions, an icon may be given as just its URL.
+///
+/// # Examples
+///
+/// ```yaml
+/// icon: https://example.com/ticket.png
+/// ```
+///
+/// ```yaml
+/// icon:
+///   src: data:image/svg+xml;base64,PHN2Zy8+
+///   mimeType: image/svg+xml
+///   sizes: [any]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(from = "IconRepr")]
+pub struct Icon {
+    /// URL or `data:` URI of the image
+    pub src: String,
+
+    /// MIME type of the image, if the URL does not make it clear
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+
+    /// Sizes the image is suitable for, such as `48x48` or `any`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sizes: Vec<String>,
+}
+
+/// The accepted spellings of an [`Icon`].
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum IconRepr {
+    Src(String),
+    Icon {
+        src: String,
+        #[serde(rename = "mimeType", alias = "mime_type", default)]
+        mime_type: Option<String>,
+        #[serde(default)]
+        sizes: Vec<String>,
+    },
+}
+
+impl From<IconRepr> for Icon {
+    fn from(repr: IconRepr) -> Self {
+        match repr {
+            IconRepr::Src(src) => Icon {
+                src,
+                mime_type: None,
+                sizes: Vec::new(),
+            },
+            IconRepr::Icon {
+                src,
+                mime_type,
+                sizes,
+            } => Icon {
+                src,
+                mime_type,
+                sizes,
+            },
+        }
+    }
+}
+
+/// A tool's deprecation marker.
+///
+/// # Examples
+///
+/// ```yaml
+/// deprecated: true
+/// ```
+///
+/// ```yaml
+/// deprecated:
+///   replacement: create_issue
+///   message: Tickets moved to the new tracker.
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Deprecation {
+    /// `deprecated: true` (or `false`, which is the same as omitting it)
+    Flag(bool),
+
+    /// A deprecation with details
+    Details {
+        /// Name of the tool to use instead
+        #[serde(default)]
+        replacement: Option<String>,
+
+        /// Why the tool is deprecated, or what to do instead
+        #[serde(default)]
+        message: Option<String>,
+    },
+}
+
+impl Deprecation {
+    /// Whether the marker actually deprecates the tool.
+    pub fn is_deprecated(&self) -> bool {
+        !matches!(self, Deprecation::Flag(false))
+    }
+
+    /// The notice prefixed to a deprecated tool's description, such as
+    /// `DEPRECATED: use create_issue instead.`
+    pub fn notice(&self) -> Option<String> {
+        match self {
+            Deprecation::Flag(false) => None,
+            Deprecation::Flag(true) => Some("DEPRECATED.".to_string()),
+            Deprecation::Details {
+                replacement,
+                message,
+            } => {
+                let mut notice = match replacement {
+                    Some(replacement) => format!("DEPRECATED: use {} instead.", replacement),
+                    None => "DEPRECATED.".to_string(),
+                };
+                if let Some(message) = message {
+                    notice.push(' ');
+                    notice.push_str(message);
+                }
+                Some(notice)
+            }
+        }
+    }
 }
 
 /// Input specification for mcp-serve tools.
 ///
 /// Combines JSON Schema validation with template-based command-line generation.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ToolInput {
     /// Template for converting JSON input to command-line arguments.
     ///
@@ -156,7 +393,30 @@ pub struct ToolInput {
     /// - `"--title {{title}} {{body}}"` - Basic substitution
     /// - `"--title {{title}} [--parent {{parent_id}}]"` - Optional argument
     /// - `"[--label {{label}}...]"` - Repeated array items
-    pub template: String,
+    ///
+    /// A list of tokens, each expanded into arguments of its own, avoids
+    /// splitting at whitespace altogether (see [`CommandTemplate`]).
+    pub template: CommandTemplate,
+
+    /// Template for text written to the tool's standard input, such as a
+    /// long body that would not fit on the command line (default: nothing)
+    ///
+    /// Uses the same syntax as `template`, but keeps its text as written
+    /// rather than splitting it into arguments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdin_template: Option<String>,
+
+    /// Templates for environment variables the tool runs with, by name,
+    /// such as `TICKET_TITLE: "{{title}}"`
+    ///
+    /// Like `stdin_template`, each value keeps its text as written. A
+    /// variable whose template writes nothing is not set.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env_template: BTreeMap<String, String>,
+
+    /// The engine that expands `template` (default: the built-in syntax)
+    #[serde(default, skip_serializing_if = "TemplateEngine::is_default")]
+    pub engine: TemplateEngine,
 
     /// JSON Schema defining the input parameters
     ///
@@ -168,25 +428,142 @@ pub struct ToolInput {
 /// Output specification for mcp-serve tools.
 ///
 /// Combines JSON Schema validation with regex-based output parsing.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ToolOutput {
     /// Regex template for parsing script output into JSON.
     ///
     /// Uses named capture groups `(?<name>pattern)` to extract values
-    /// that become JSON properties.
+    /// that become JSON properties. A list of patterns is tried in order
+    /// (see [`OutputTemplate`]).
     ///
     /// # Examples
     ///
     /// ```text
     /// Ticket created: (?<url>https://.*)\nID: (?<id>\d+)
     /// ```
-    pub template: String,
+    #[serde(default, skip_serializing_if = "OutputTemplate::is_empty")]
+    pub template: OutputTemplate,
+
+    /// Patterns matched against stderr, whose captures are added to the
+    /// result (see [`OutputTemplate`])
+    #[serde(default, skip_serializing_if = "OutputTemplate::is_empty")]
+    pub stderr_template: OutputTemplate,
+
+    /// A jq-style expression reshaping JSON output into the result, used
+    /// instead of `template` (see [`extract`](crate::output::extract))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extract: Option<String>,
+
+    /// Return the whole output as a single property, used instead of
+    /// `template` (see [`OutputCapture`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture: Option<OutputCapture>,
+
+    /// How stdout is decoded into text (default: UTF-8, with invalid bytes
+    /// replaced; see [`OutputEncoding`])
+    #[serde(default, skip_serializing_if = "OutputEncoding::is_default")]
+    pub encoding: OutputEncoding,
 
     /// JSON Schema defining the output structure
     ///
     /// This is an opaque JSON Schema object that can contain any valid
     /// JSON Schema structure for result validation.
     pub schema: serde_json::Value,
+
+    /// How results are presented to clients (see [`content`]); empty means
+    /// [`content::DEFAULT_CONTENT`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content: Vec<OutputContent>,
+}
+
+/// Hints describing a tool's behavior, as defined by the MCP specification.
+///
+/// Clients use these to make safety decisions, such as asking for
+/// confirmation before running a destructive tool. They are hints only: a
+/// client must not rely on them for security.
+///
+/// Keys are written in the specification's camelCase (`readOnlyHint`);
+/// snake_case spellings (`read_only_hint`) are accepted when reading. Keys
+/// other than the standard hints are kept in [`extra`](Self::extra) and
+/// written back unchanged.
+///
+/// # Examples
+///
+/// ```yaml
+/// annotations:
+///   readOnlyHint: false
+///   destructiveHint: true
+///   idempotentHint: true
+///   openWorldHint: false
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ToolAnnotations {
+    /// The tool does not modify its environment (default: `false`)
+    #[serde(
+        rename = "readOnlyHint",
+        alias = "read_only_hint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub read_only_hint: Option<bool>,
+
+    /// The tool may perform destructive updates; only meaningful when it is
+    /// not read-only (default: `true`)
+    #[serde(
+        rename = "destructiveHint",
+        alias = "destructive_hint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub destructive_hint: Option<bool>,
+
+    /// Calling the tool repeatedly with the same arguments has no additional
+    /// effect; only meaningful when it is not read-only (default: `false`)
+    #[serde(
+        rename = "idempotentHint",
+        alias = "idempotent_hint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub idempotent_hint: Option<bool>,
+
+    /// The tool interacts with an open world of external entities, such as
+    /// the web, rather than a closed domain (default: `true`)
+    #[serde(
+        rename = "openWorldHint",
+        alias = "open_world_hint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub open_world_hint: Option<bool>,
+
+    /// Any other annotations, preserved as given
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl ToolAnnotations {
+    /// Whether the tool is declared read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only_hint.unwrap_or(false)
+    }
+
+    /// Whether the tool may be destructive, applying the specification's
+    /// defaults: tools are assumed destructive unless declared read-only or
+    /// explicitly non-destructive.
+    pub fn is_destructive(&self) -> bool {
+        !self.is_read_only() && self.destructive_hint.unwrap_or(true)
+    }
+
+    /// Whether repeated calls are declared to have no additional effect.
+    pub fn is_idempotent(&self) -> bool {
+        self.idempotent_hint.unwrap_or(false)
+    }
+
+    /// Whether the tool may reach external entities (defaults to `true`).
+    pub fn is_open_world(&self) -> bool {
+        self.open_world_hint.unwrap_or(true)
+    }
 }
 
 impl ToolDefinition {
@@ -222,14 +599,49 @@ impl ToolDefinition {
     /// let tool = ToolDefinition::from_yaml(yaml).unwrap();
     /// assert_eq!(tool.name, "example_tool");
     /// ```
-    pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml_ng::Error> {
-        serde_yaml_ng::from_str(yaml)
+    ///
+    /// Errors carry the line, column, and text of the problem, and a hint for
+    /// common mistakes such as misspelled keys.
+    pub fn from_yaml(yaml: &str) -> Result<Self, DefinitionError> {
+        serde_yaml_ng::from_str(yaml).map_err(|e| DefinitionError::from_yaml(e, yaml))
+    }
+
+    /// Parse a tool definition from a YAML string, rejecting fields the
+    /// format does not define (see [`strict`]).
+    pub fn from_yaml_strict(yaml: &str) -> Result<Self, DefinitionError> {
+        strict::deserialize(serde_yaml_ng::Deserializer::from_str(yaml))
+            .map_err(|e| DefinitionError::from_yaml(e, yaml))
+    }
+
+    /// Parse a tool definition from a JSON string, using the same fields as
+    /// the YAML format.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Parse a tool definition from a TOML string, using the same fields as
+    /// the YAML format.
+    pub fn from_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
     }
 
     /// Convert this mcp-serve tool definition to a pure MCP tool.
     ///
     /// This extracts the schema information and discards the template-specific
     /// extensions, creating a tool definition that conforms to the MCP specification.
+    /// Everything else a client can use is mapped to its MCP field:
+    ///
+    /// - `title` becomes the tool's title, and is repeated as
+    ///   `annotations.title` for clients of the 2025-03-26 revision, which
+    ///   only read it from there.
+    /// - `annotations` (the safety hints) are passed through.
+    /// - `version` becomes `_meta.version`.
+    /// - `deprecated` prefixes the description with a notice, sets
+    ///   `annotations.deprecated`, and is described in `_meta.deprecated`
+    ///   (`true`, or the replacement and message).
+    /// - `category` and `tags` become `annotations.category` and
+    ///   `annotations.tags`.
+    /// - `icon` becomes the tool's only icon, and `_meta` is passed through.
     ///
     /// # Examples
     ///
@@ -238,13 +650,21 @@ impl ToolDefinition {
     /// use serde_json::json;
     ///
     /// let input = ToolInput {
-    ///     template: "--name {{name}}".to_string(),
+    ///     template: "--name {{name}}".into(),
+    ///     stdin_template: None,
+    ///     env_template: Default::default(),
+    ///     engine: Default::default(),
     ///     schema: json!({"type": "object"}),
     /// };
     ///
     /// let output = ToolOutput {
-    ///     template: "Result: (?<value>.*)".to_string(),
+    ///     template: "Result: (?<value>.*)".into(),
+    ///     stderr_template: Default::default(),
+    ///     extract: None,
+    ///     capture: None,
+    ///     encoding: Default::default(),
     ///     schema: json!({"type": "string"}),
+    ///     content: Vec::new(),
     /// };
     ///
     /// let tool = ToolDefinition::new("test", "Test tool", input, output);
@@ -254,13 +674,291 @@ impl ToolDefinition {
     /// assert_eq!(mcp_tool.description, "Test tool");
     /// ```
     pub fn to_mcp_tool(&self) -> McpTool {
+        let notice = self.deprecated.as_ref().and_then(Deprecation::notice);
+        let description = match &notice {
+            Some(notice) => format!("{} {}", notice, self.description),
+            None => self.description.clone(),
+        };
+        let mut annotations = self.annotations.clone();
+        if let Some(title) = &self.title {
+            annotations
+                .get_or_insert_with(ToolAnnotations::default)
+                .extra
+                .entry("title".to_string())
+                .or_insert_with(|| title.clone().into());
+        }
+        if notice.is_some() {
+            annotations
+                .get_or_insert_with(ToolAnnotations::default)
+                .extra
+                .insert("deprecated".to_string(), serde_json::Value::Bool(true));
+        }
+        if let Some(category) = &self.category {
+            annotations
+                .get_or_insert_with(ToolAnnotations::default)
+                .extra
+                .insert("category".to_string(), category.clone().into());
+        }
+        if !self.tags.is_empty() {
+            annotations
+                .get_or_insert_with(ToolAnnotations::default)
+                .extra
+                .insert("tags".to_string(), self.tags.clone().into());
+        }
+
+        let mut meta = self.meta.clone().unwrap_or_default();
+        if let Some(version) = &self.version {
+            meta.insert("version".to_string(), version.clone().into());
+        }
+        if notice.is_some() {
+            let deprecation = serde_json::to_value(&self.deprecated)
+                .expect("deprecation serialization cannot fail");
+            meta.insert("deprecated".to_string(), deprecation);
+        }
+        let meta = (!meta.is_empty()).then_some(meta);
+
         McpTool {
             name: self.name.clone(),
             title: self.title.clone(),
-            description: self.description.clone(),
+            description,
             input_schema: self.input.schema.clone(),
-            output_schema: Some(self.output.schema.clone()),
-            annotations: self.annotations.clone(),
+            // Tools that do not return structured content must not
+            // advertise an output schema.
+            output_schema: content::is_structured(&self.output.content)
+                .then(|| self.output.schema.clone()),
+            annotations,
+            icons: self.icon.clone().map(|icon| vec![icon]),
+            meta,
+        }
+    }
+
+    /// Whether the tool has any of `selected` as a tag or as its category.
+    pub fn matches_tags(&self, selected: &[String]) -> bool {
+        selected
+            .iter()
+            .any(|tag| self.tags.contains(tag) || self.category.as_ref() == Some(tag))
+    }
+
+    /// Whether the tool is marked deprecated.
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated
+            .as_ref()
+            .is_some_and(Deprecation::is_deprecated)
+    }
+
+    /// Load a tool definition from a sidecar file, choosing the parser from
+    /// the file's extension (see [`SidecarFormat`]).
+    pub fn from_sidecar(path: &Path) -> Result<Self, SidecarError> {
+        let format =
+            SidecarFormat::from_path(path).ok_or_else(|| SidecarError::UnsupportedFormat {
+                path: path.to_path_buf(),
+            })?;
+        let contents = std::fs::read_to_string(path).map_err(|source| SidecarError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        format.parse(&contents).map_err(|e| e.with_path(path))
+    }
+
+    /// Load every tool definition from a sidecar file, which may declare a
+    /// single tool or a list of tools sharing one executable (see
+    /// [`SidecarFormat::parse_all`]).
+    pub fn all_from_sidecar(path: &Path) -> Result<Vec<Self>, SidecarError> {
+        Self::read_sidecar(path, false)
+    }
+
+    /// Like [`ToolDefinition::all_from_sidecar`], but rejecting fields the
+    /// format does not define (see [`strict`]).
+    pub fn all_from_sidecar_strict(path: &Path) -> Result<Vec<Self>, SidecarError> {
+        Self::read_sidecar(path, true)
+    }
+
+    fn read_sidecar(path: &Path, strict: bool) -> Result<Vec<Self>, SidecarError> {
+        let format =
+            SidecarFormat::from_path(path).ok_or_else(|| SidecarError::UnsupportedFormat {
+                path: path.to_path_buf(),
+            })?;
+        let contents = std::fs::read_to_string(path).map_err(|source| SidecarError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        format
+            .parse_list(&contents, strict)
+            .map_err(|e| e.with_path(path))
+    }
+}
+
+/// Supported sidecar definition file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarFormat {
+    /// `.yaml` or `.yml`
+    Yaml,
+    /// `.json`
+    Json,
+    /// `.toml`
+    Toml,
+}
+
+impl SidecarFormat {
+    /// Sidecar file extensions, in the order they are looked up.
+    ///
+    /// When several sidecars exist for the same executable, the first match in
+    /// this list wins: `.yaml`, then `.yml`, then `.json`, then `.toml`.
+    pub const EXTENSIONS: &'static [&'static str] = &["yaml", "yml", "json", "toml"];
+
+    /// Determine the format for a file extension (case-insensitive, without
+    /// the leading dot).
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Some(SidecarFormat::Yaml),
+            "json" => Some(SidecarFormat::Json),
+            "toml" => Some(SidecarFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Determine the format of a sidecar file from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension)
+    }
+
+    /// Parse a tool definition written in this format.
+    pub fn parse(self, contents: &str) -> Result<ToolDefinition, SidecarError> {
+        match self {
+            SidecarFormat::Yaml => ToolDefinition::from_yaml(contents).map_err(SidecarError::Yaml),
+            SidecarFormat::Json => ToolDefinition::from_json(contents).map_err(SidecarError::Json),
+            SidecarFormat::Toml => ToolDefinition::from_toml(contents).map_err(SidecarError::Toml),
+        }
+    }
+
+    /// Parse a file declaring either a single tool definition or, under a
+    /// `tools` key, a list of definitions that share one executable.
+    ///
+    /// # Examples
+    ///
+    /// ```yaml
+    /// # git-tools.yaml, next to a `git-tools` executable
+    /// tools:
+    ///   - name: git_log
+    ///     description: Shows the commit log
+    ///     input: ...
+    ///     output: ...
+    ///   - name: git_blame
+    ///     description: Shows who last changed each line of a file
+    ///     input: ...
+    ///     output: ...
+    /// ```
+    pub fn parse_all(self, contents: &str) -> Result<Vec<ToolDefinition>, SidecarError> {
+        self.parse_list(contents, false)
+    }
+
+    /// Like [`SidecarFormat::parse_all`], but rejecting fields the format
+    /// does not define (see [`strict`]).
+    pub fn parse_all_strict(self, contents: &str) -> Result<Vec<ToolDefinition>, SidecarError> {
+        self.parse_list(contents, true)
+    }
+
+    fn parse_list(self, contents: &str, strict: bool) -> Result<Vec<ToolDefinition>, SidecarError> {
+        #[derive(Deserialize)]
+        struct ToolList {
+            tools: Vec<ToolDefinition>,
+        }
+
+        let is_list = self
+            .parse_as::<serde_json::Value>(contents, false)
+            .is_ok_and(|value| value.get("tools").is_some() && value.get("name").is_none());
+        if is_list {
+            Ok(self.parse_as::<ToolList>(contents, strict)?.tools)
+        } else {
+            self.parse_as(contents, strict)
+                .map(|definition| vec![definition])
+        }
+    }
+
+    fn parse_as<T: serde::de::DeserializeOwned>(
+        self,
+        contents: &str,
+        strict: bool,
+    ) -> Result<T, SidecarError> {
+        match (self, strict) {
+            (SidecarFormat::Yaml, false) => serde_yaml_ng::from_str(contents)
+                .map_err(|e| SidecarError::Yaml(DefinitionError::from_yaml(e, contents))),
+            (SidecarFormat::Yaml, true) => {
+                strict::deserialize(serde_yaml_ng::Deserializer::from_str(contents))
+                    .map_err(|e| SidecarError::Yaml(DefinitionError::from_yaml(e, contents)))
+            }
+            (SidecarFormat::Json, false) => {
+                serde_json::from_str(contents).map_err(SidecarError::Json)
+            }
+            (SidecarFormat::Json, true) => {
+                let mut deserializer = serde_json::Deserializer::from_str(contents);
+                let value = strict::deserialize(&mut deserializer).map_err(SidecarError::Json)?;
+                deserializer.end().map_err(SidecarError::Json)?;
+                Ok(value)
+            }
+            (SidecarFormat::Toml, false) => toml::from_str(contents).map_err(SidecarError::Toml),
+            (SidecarFormat::Toml, true) => toml::Deserializer::parse(contents)
+                .and_then(strict::deserialize)
+                .map_err(SidecarError::Toml),
+        }
+    }
+}
+
+/// Errors that can occur while loading a sidecar definition file.
+#[derive(Debug)]
+pub enum SidecarError {
+    /// The sidecar file could not be read.
+    Io { path: PathBuf, source: io::Error },
+
+    /// The file extension does not correspond to a supported format.
+    UnsupportedFormat { path: PathBuf },
+
+    /// The YAML sidecar could not be parsed.
+    Yaml(DefinitionError),
+
+    /// The JSON sidecar could not be parsed.
+    Json(serde_json::Error),
+
+    /// The TOML sidecar could not be parsed.
+    Toml(toml::de::Error),
+}
+
+impl SidecarError {
+    /// Attribute a YAML parse error to the sidecar at `path`.
+    fn with_path(self, path: &Path) -> Self {
+        match self {
+            SidecarError::Yaml(e) => SidecarError::Yaml(e.with_path(path)),
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for SidecarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SidecarError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            SidecarError::UnsupportedFormat { path } => {
+                write!(f, "unsupported sidecar format: {}", path.display())
+            }
+            SidecarError::Yaml(e) => write!(f, "invalid YAML definition: {}", e),
+            SidecarError::Json(e) => write!(f, "invalid JSON definition: {}", e),
+            SidecarError::Toml(e) => write!(f, "invalid TOML definition: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SidecarError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SidecarError::Io { source, .. } => Some(source),
+            SidecarError::UnsupportedFormat { .. } => None,
+            SidecarError::Yaml(e) => Some(e),
+            SidecarError::Json(e) => Some(e),
+            SidecarError::Toml(e) => Some(e),
         }
     }
 }
@@ -326,6 +1024,58 @@ output:
         assert_eq!(tool.output.template, "Result: (?<value>.*)");
     }
 
+    #[test]
+    fn test_typed_annotations() {
+        let yaml = r#"
+name: deploy
+description: Deploys
+input:
+  template: ""
+  schema: { type: object }
+output:
+  template: ""
+  schema: { type: object }
+annotations:
+  readOnlyHint: false
+  idempotent_hint: true
+  openWorldHint: false
+  x-team: platform
+"#;
+
+        let tool = ToolDefinition::from_yaml(yaml).expect("Should parse YAML");
+        let annotations = tool.annotations.as_ref().unwrap();
+
+        assert_eq!(annotations.read_only_hint, Some(false));
+        assert_eq!(annotations.destructive_hint, None);
+        assert!(annotations.is_destructive());
+        assert!(annotations.is_idempotent());
+        assert!(!annotations.is_open_world());
+        assert_eq!(annotations.extra["x-team"], json!("platform"));
+
+        // Unknown keys round-trip; hints are written in camelCase
+        let written = serde_json::to_value(tool.to_mcp_tool().annotations).unwrap();
+        assert_eq!(
+            written,
+            json!({
+                "readOnlyHint": false,
+                "idempotentHint": true,
+                "openWorldHint": false,
+                "x-team": "platform"
+            })
+        );
+    }
+
+    #[test]
+    fn test_annotation_defaults() {
+        let read_only = ToolAnnotations {
+            read_only_hint: Some(true),
+            ..ToolAnnotations::default()
+        };
+        assert!(!read_only.is_destructive());
+        assert!(ToolAnnotations::default().is_destructive());
+        assert!(ToolAnnotations::default().is_open_world());
+    }
+
     #[test]
     fn test_mcp_tool_creation() {
         // Test McpTool via conversion from ToolDefinition
@@ -378,6 +1128,163 @@ output:
         assert_eq!(mcp_tool.description, "Conversion test");
         assert_eq!(mcp_tool.input_schema["type"], "object");
         assert_eq!(mcp_tool.output_schema.unwrap()["type"], "string");
+        assert_eq!(
+            mcp_tool.annotations.unwrap().extra["title"],
+            serde_json::json!("Convert Test")
+        );
+        assert!(mcp_tool.meta.is_none());
+    }
+
+    #[test]
+    fn test_version_in_mcp_meta() {
+        let yaml = r#"
+name: versioned
+version: 2.1.0
+description: Versioned tool
+input:
+  template: ""
+  schema:
+    type: object
+output:
+  template: ""
+  schema:
+    type: object
+"#;
+
+        let tool = ToolDefinition::from_yaml(yaml).expect("Should parse YAML");
+        assert_eq!(tool.version.as_deref(), Some("2.1.0"));
+
+        let mcp_tool = tool.to_mcp_tool();
+        assert_eq!(mcp_tool.meta.unwrap()["version"], "2.1.0");
+    }
+
+    #[test]
+    fn test_icon_and_meta_passthrough() {
+        let yaml = r#"
+name: branded
+version: 1.0.0
+description: Branded tool
+icon: https://example.com/icon.png
+_meta:
+  com.example/team: platform
+  version: ignored
+input:
+  template: ""
+  schema:
+    type: object
+output:
+  template: ""
+  schema:
+    type: object
+"#;
+
+        let tool = ToolDefinition::from_yaml(yaml).expect("Should parse YAML");
+        let json = tool.to_mcp_tool().to_json();
+
+        assert_eq!(
+            json["icons"],
+            serde_json::json!([{"src": "https://example.com/icon.png"}])
+        );
+        assert_eq!(
+            json["_meta"],
+            serde_json::json!({"com.example/team": "platform", "version": "1.0.0"})
+        );
+
+        let yaml = yaml.replace(
+            "icon: https://example.com/icon.png",
+            "icon:\n  src: data:image/svg+xml;base64,PHN2Zy8+\n  mime_type: image/svg+xml\n  sizes: [any]",
+        );
+        let tool = ToolDefinition::from_yaml(&yaml).expect("Should parse YAML");
+        assert_eq!(
+            tool.to_mcp_tool().to_json()["icons"][0],
+            serde_json::json!({
+                "src": "data:image/svg+xml;base64,PHN2Zy8+",
+                "mimeType": "image/svg+xml",
+                "sizes": ["any"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_tags_and_category() {
+        let yaml = r#"
+name: rollout
+description: Rolls out a release
+category: deploy
+tags: [production, slow]
+input:
+  template: ""
+  schema:
+    type: object
+output:
+  template: ""
+  schema:
+    type: object
+"#;
+
+        let tool = ToolDefinition::from_yaml(yaml).expect("Should parse YAML");
+        let selected = |tags: &[&str]| {
+            tool.matches_tags(&tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>())
+        };
+        assert!(selected(&["deploy"]));
+        assert!(selected(&["readonly", "slow"]));
+        assert!(!selected(&["readonly"]));
+
+        let annotations = tool.to_mcp_tool().to_json()["annotations"].clone();
+        assert_eq!(
+            annotations,
+            serde_json::json!({"category": "deploy", "tags": ["production", "slow"]})
+        );
+    }
+
+    #[test]
+    fn test_deprecation() {
+        let yaml = r#"
+name: create_ticket
+description: Creates a ticket.
+deprecated: true
+input:
+  template: ""
+  schema:
+    type: object
+output:
+  template: ""
+  schema:
+    type: object
+"#;
+
+        let tool = ToolDefinition::from_yaml(yaml).expect("Should parse YAML");
+        assert!(tool.is_deprecated());
+        let mcp_tool = tool.to_mcp_tool();
+        assert_eq!(mcp_tool.description, "DEPRECATED. Creates a ticket.");
+        assert_eq!(
+            mcp_tool.annotations.unwrap().extra["deprecated"],
+            serde_json::Value::Bool(true)
+        );
+
+        let yaml = yaml.replace(
+            "deprecated: true",
+            "deprecated:\n  replacement: create_issue\n  message: Tickets moved.",
+        );
+        let tool = ToolDefinition::from_yaml(&yaml).expect("Should parse YAML");
+        let mcp_tool = tool.to_mcp_tool();
+        assert_eq!(
+            mcp_tool.description,
+            "DEPRECATED: use create_issue instead. Tickets moved. Creates a ticket."
+        );
+        assert_eq!(
+            mcp_tool.meta.unwrap()["deprecated"],
+            serde_json::json!({"replacement": "create_issue", "message": "Tickets moved."})
+        );
+
+        let yaml = yaml.replace(
+            "deprecated:\n  replacement: create_issue\n  message: Tickets moved.",
+            "deprecated: false",
+        );
+        let tool = ToolDefinition::from_yaml(&yaml).expect("Should parse YAML");
+        assert!(!tool.is_deprecated());
+        assert_eq!(tool.to_mcp_tool().description, "Creates a ticket.");
+        assert!(tool.to_mcp_tool().annotations.is_none());
     }
 
     #[test]
@@ -479,11 +1386,12 @@ output:
         assert_eq!(tool.input.schema["required"], json!(["title", "body"]));
 
         // Verify output
-        assert!(tool
-            .output
-            .template
+        assert!(tool.output.template.patterns()[0]
+            .pattern
             .contains("Ticket created: (?<url>https://.*)"));
-        assert!(tool.output.template.contains("ID: (?<id>\\d+)"));
+        assert!(tool.output.template.patterns()[0]
+            .pattern
+            .contains("ID: (?<id>\\d+)"));
         assert_eq!(tool.output.schema["type"], "object");
     }
 
@@ -552,6 +1460,115 @@ schema:
         let _parsed2: ToolInput = serde_yaml_ng::from_str(&yaml2).unwrap();
     }
 
+    #[test]
+    fn test_sidecar_format_from_path() {
+        assert_eq!(
+            SidecarFormat::from_path(Path::new("tool.yaml")),
+            Some(SidecarFormat::Yaml)
+        );
+        assert_eq!(
+            SidecarFormat::from_path(Path::new("tool.YML")),
+            Some(SidecarFormat::Yaml)
+        );
+        assert_eq!(
+            SidecarFormat::from_path(Path::new("tool.json")),
+            Some(SidecarFormat::Json)
+        );
+        assert_eq!(
+            SidecarFormat::from_path(Path::new("tool.toml")),
+            Some(SidecarFormat::Toml)
+        );
+        assert_eq!(SidecarFormat::from_path(Path::new("tool.sh")), None);
+    }
+
+    #[test]
+    fn test_sidecar_formats_parse_equivalently() {
+        let yaml = r#"
+name: fmt_test
+description: Format test
+input:
+  template: "--name {{name}}"
+  schema:
+    type: object
+output:
+  template: "Result: (?<result>.*)"
+  schema:
+    type: string
+"#;
+
+        let json = r#"{
+  "name": "fmt_test",
+  "description": "Format test",
+  "input": {"template": "--name {{name}}", "schema": {"type": "object"}},
+  "output": {"template": "Result: (?<result>.*)", "schema": {"type": "string"}}
+}"#;
+
+        let toml = r#"
+name = "fmt_test"
+description = "Format test"
+
+[input]
+template = "--name {{name}}"
+schema = { type = "object" }
+
+[output]
+template = "Result: (?<result>.*)"
+schema = { type = "string" }
+"#;
+
+        let from_yaml = SidecarFormat::Yaml.parse(yaml).expect("Should parse YAML");
+        let from_json = SidecarFormat::Json.parse(json).expect("Should parse JSON");
+        let from_toml = SidecarFormat::Toml.parse(toml).expect("Should parse TOML");
+
+        assert_eq!(from_yaml, from_json);
+        assert_eq!(from_yaml, from_toml);
+        assert_eq!(ToolDefinition::from_json(json).unwrap(), from_yaml);
+        assert_eq!(ToolDefinition::from_toml(toml).unwrap(), from_yaml);
+    }
+
+    #[test]
+    fn test_sidecar_parse_errors_name_format() {
+        let error = SidecarFormat::Json.parse("{ not json").unwrap_err();
+        assert!(matches!(error, SidecarError::Json(_)));
+        assert!(error.to_string().starts_with("invalid JSON definition"));
+
+        let error = SidecarFormat::Toml.parse("name = ").unwrap_err();
+        assert!(matches!(error, SidecarError::Toml(_)));
+    }
+
+    #[test]
+    fn test_sidecar_parse_all() {
+        let toml = r#"
+[[tools]]
+name = "git_log"
+description = "Shows the commit log"
+input = { template = "", schema = { type = "object" } }
+output = { template = "", schema = { type = "object" } }
+
+[[tools]]
+name = "git_diff"
+description = "Shows changes"
+input = { template = "", schema = { type = "object" } }
+output = { template = "", schema = { type = "object" } }
+"#;
+        let tools = SidecarFormat::Toml.parse_all(toml).unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[1].name, "git_diff");
+
+        let json = r#"{"name": "single", "description": "One tool",
+            "input": {"template": "", "schema": {}}, "output": {"template": "", "schema": {}}}"#;
+        assert_eq!(
+            SidecarFormat::Json.parse_all(json).unwrap()[0].name,
+            "single"
+        );
+
+        // Errors in list entries are reported as such, not as a missing `name`
+        let error = SidecarFormat::Yaml
+            .parse_all("tools:\n  - name: broken\n")
+            .unwrap_err();
+        assert!(error.to_string().contains("description"));
+    }
+
     #[test]
     fn test_error_handling_malformed_yaml() {
         let malformed_yaml = r#"
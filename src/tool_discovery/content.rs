@@ -0,0 +1,259 @@
+//! How a tool's results are presented to MCP clients.
+//!
+//! A call's result can carry unstructured content blocks, a
+//! `structuredContent` object, or both. By default a tool returns its parsed
+//! output as `structuredContent` plus the same object serialized in a text
+//! block for clients that predate structured output, as the specification
+//! recommends. A definition can choose differently under `output.content`:
+//!
+//! ```yaml
+//! output:
+//!   template: "Created (?<id>\\d+) at (?<url>\\S+)"
+//!   schema: { ... }
+//!   content:
+//!     - structured        # the parsed object as structuredContent
+//!     - text              # the tool's raw output
+//!     - resource_link:    # a link built from the parsed object
+//!         uri: "{{url}}"
+//!         name: "Ticket {{id}}"
+//!         mime_type: text/html
+//! ```
+//!
+//! `json` adds the parsed object serialized as a text block.
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+
+use super::wire::{CallToolResult, ContentBlock};
+
+/// One part of a tool's result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(from = "ContentRepr", into = "ContentRepr")]
+pub enum OutputContent {
+    /// The tool's raw output, as a text block.
+    Text,
+
+    /// The parsed output serialized as JSON, as a text block.
+    Json,
+
+    /// The parsed output as the result's `structuredContent`.
+    Structured,
+
+    /// A link to a resource, built from the parsed output.
+    ResourceLink(ResourceLinkTemplate),
+}
+
+/// The written form of [`OutputContent`]: a kind's name, or a mapping with
+/// a `resource_link` key.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum ContentRepr {
+    Kind(ContentKind),
+    ResourceLink { resource_link: ResourceLinkTemplate },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum ContentKind {
+    Text,
+    Json,
+    Structured,
+}
+
+impl From<ContentRepr> for OutputContent {
+    fn from(repr: ContentRepr) -> Self {
+        match repr {
+            ContentRepr::Kind(ContentKind::Text) => OutputContent::Text,
+            ContentRepr::Kind(ContentKind::Json) => OutputContent::Json,
+            ContentRepr::Kind(ContentKind::Structured) => OutputContent::Structured,
+            ContentRepr::ResourceLink { resource_link } => {
+                OutputContent::ResourceLink(resource_link)
+            }
+        }
+    }
+}
+
+impl From<OutputContent> for ContentRepr {
+    fn from(content: OutputContent) -> Self {
+        match content {
+            OutputContent::Text => ContentRepr::Kind(ContentKind::Text),
+            OutputContent::Json => ContentRepr::Kind(ContentKind::Json),
+            OutputContent::Structured => ContentRepr::Kind(ContentKind::Structured),
+            OutputContent::ResourceLink(resource_link) => {
+                ContentRepr::ResourceLink { resource_link }
+            }
+        }
+    }
+}
+
+/// A resource link whose fields may contain `{{property}}` placeholders,
+/// filled from the parsed output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ResourceLinkTemplate {
+    /// The resource's URI
+    pub uri: String,
+
+    /// A name for the resource
+    pub name: String,
+
+    /// The resource's MIME type
+    #[serde(
+        rename = "mimeType",
+        alias = "mime_type",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mime_type: Option<String>,
+
+    /// What the resource is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl ResourceLinkTemplate {
+    /// Names of the output properties the link refers to.
+    pub fn placeholders(&self) -> Vec<&str> {
+        [Some(&self.uri), Some(&self.name), self.description.as_ref()]
+            .into_iter()
+            .flatten()
+            .flat_map(|text| placeholder_regex().captures_iter(text))
+            .map(|captures| captures.get(1).map_or("", |name| name.as_str()))
+            .collect()
+    }
+
+    fn render(&self, output: &Map<String, Value>) -> ContentBlock {
+        ContentBlock::ResourceLink {
+            uri: fill(&self.uri, output),
+            name: fill(&self.name, output),
+            mime_type: self.mime_type.clone(),
+            description: self.description.as_ref().map(|text| fill(text, output)),
+        }
+    }
+}
+
+/// The content used when a definition does not choose.
+pub const DEFAULT_CONTENT: [OutputContent; 2] = [OutputContent::Structured, OutputContent::Json];
+
+/// Build the result of a call from the tool's raw output and the object
+/// parsed from it, presenting them as `content` asks (or as
+/// [`DEFAULT_CONTENT`] when it is empty).
+pub fn render(content: &[OutputContent], raw: &str, output: &Map<String, Value>) -> CallToolResult {
+    let content = if content.is_empty() {
+        &DEFAULT_CONTENT[..]
+    } else {
+        content
+    };
+    let mut result = CallToolResult::default();
+    for part in content {
+        match part {
+            OutputContent::Text => result.content.push(ContentBlock::Text {
+                text: raw.to_string(),
+            }),
+            OutputContent::Json => result.content.push(ContentBlock::Text {
+                text: Value::Object(output.clone()).to_string(),
+            }),
+            OutputContent::Structured => {
+                result.structured_content = Some(Value::Object(output.clone()))
+            }
+            OutputContent::ResourceLink(link) => result.content.push(link.render(output)),
+        }
+    }
+    result
+}
+
+/// Whether results include `structuredContent`, in which case the tool
+/// advertises its output schema.
+pub fn is_structured(content: &[OutputContent]) -> bool {
+    content.is_empty() || content.contains(&OutputContent::Structured)
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z0-9_.-]+)\s*\}\}").expect("valid regex"))
+}
+
+/// Replace each placeholder in `text` with the named output property;
+/// strings are inserted as-is and other values as JSON.
+fn fill(text: &str, output: &Map<String, Value>) -> String {
+    placeholder_regex()
+        .replace_all(text, |captures: &regex::Captures| {
+            match output.get(&captures[1]) {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn output() -> Map<String, Value> {
+        json!({"id": 42, "url": "https://example.com/t/42"})
+            .as_object()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_default_content() {
+        let result = render(&[], "Created 42", &output());
+
+        assert_eq!(result.structured_content, Some(Value::Object(output())));
+        assert_eq!(
+            result.content,
+            [ContentBlock::Text {
+                text: r#"{"id":42,"url":"https://example.com/t/42"}"#.to_string()
+            }]
+        );
+        assert!(is_structured(&[]));
+    }
+
+    #[test]
+    fn test_declared_content() {
+        let content: Vec<OutputContent> = serde_yaml_ng::from_str(
+            r#"
+- text
+- resource_link:
+    uri: "{{url}}"
+    name: "Ticket {{ id }}"
+    mime_type: text/html
+"#,
+        )
+        .unwrap();
+        let OutputContent::ResourceLink(link) = &content[1] else {
+            panic!("expected a resource link");
+        };
+        assert_eq!(link.placeholders(), ["url", "id"]);
+        let yaml = serde_yaml_ng::to_string(&content).unwrap();
+        assert_eq!(
+            serde_yaml_ng::from_str::<Vec<OutputContent>>(&yaml).unwrap(),
+            content
+        );
+
+        let result = render(&content, "Created 42", &output());
+
+        assert_eq!(result.structured_content, None);
+        assert!(!is_structured(&content));
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            json!({
+                "content": [
+                    {"type": "text", "text": "Created 42"},
+                    {
+                        "type": "resource_link",
+                        "uri": "https://example.com/t/42",
+                        "name": "Ticket 42",
+                        "mimeType": "text/html"
+                    }
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,485 @@
+//! Terminal-friendly diagnostics for definitions that fail to parse.
+//!
+//! A [`DefinitionError`] wraps the YAML parser's error with what a person
+//! needs to fix the file: where it is, the offending line, and, for common
+//! mistakes such as a misspelled key, a hint:
+//!
+//! ```text
+//! missing field `input`
+//!   --> tools/deploy.yaml:3:1
+//!    |
+//!  3 | inptu:
+//!    | ^
+//!    = hint: did you mean `input:`?
+//! ```
+//!
+//! Problems found after parsing, such as those of
+//! [`validate`](crate::definitions::validate), name a field rather than a
+//! position; a [`FieldLocator`] finds the line of that field in the file.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Keys this far (in edits) from an expected key are taken to be typos.
+const MAX_TYPO_DISTANCE: usize = 2;
+
+/// A tool definition that could not be parsed.
+#[derive(Debug)]
+pub struct DefinitionError(Box<Details>);
+
+#[derive(Debug)]
+struct Details {
+    path: Option<PathBuf>,
+    message: String,
+    line: Option<usize>,
+    column: Option<usize>,
+    snippet: Option<String>,
+    hint: Option<String>,
+    source: serde_yaml_ng::Error,
+}
+
+impl DefinitionError {
+    /// Describe `source`, an error from parsing the YAML `contents`.
+    pub fn from_yaml(source: serde_yaml_ng::Error, contents: &str) -> Self {
+        let mut line = source.location().map(|location| location.line());
+        let mut column = source.location().map(|location| location.column());
+        let mut message = source.to_string();
+        if let (Some(l), Some(c)) = (line, column) {
+            let suffix = format!(" at line {} column {}", l, c);
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message = stripped.to_string();
+            }
+        }
+
+        let mut hint = None;
+        if let Some(field) = quoted_after(&message, "missing field ") {
+            // The parser points at the start of the mapping; a key that looks
+            // like a misspelling of the missing one is a better place.
+            let similar =
+                |key: &str| key != field && edit_distance(key, field) <= MAX_TYPO_DISTANCE;
+            if let Some((key_line, key_column)) = find_key(contents, similar) {
+                hint = Some(format!("did you mean `{}:`?", field));
+                line = Some(key_line);
+                column = Some(key_column);
+            } else {
+                line = None;
+                column = None;
+            }
+        } else if let Some(field) = quoted_after(&message, "unknown field ") {
+            let expected = message
+                .split_once("expected ")
+                .map(|(_, rest)| backticked(rest))
+                .unwrap_or_default();
+            hint = closest(field, expected.iter().copied())
+                .map(|candidate| format!("did you mean `{}:`?", candidate));
+            // Strict parsing reports unknown fields without a position.
+            if line.is_none() {
+                if let Some((key_line, key_column)) = find_key(contents, |key| key == field) {
+                    line = Some(key_line);
+                    column = Some(key_column);
+                }
+            }
+        } else if contents.lines().any(|l| l.starts_with('\t')) && message.contains("character") {
+            hint = Some("YAML does not allow tabs for indentation; use spaces".to_string());
+        }
+
+        let snippet = line
+            .and_then(|line| contents.lines().nth(line - 1))
+            .map(str::to_string);
+        Self(Box::new(Details {
+            path: None,
+            message,
+            line,
+            column,
+            snippet,
+            hint,
+            source,
+        }))
+    }
+
+    /// Attribute the error to the file at `path`.
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.0.path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Shift the reported line by `offset`, for definitions embedded partway
+    /// through a file.
+    pub fn with_line_offset(mut self, offset: usize) -> Self {
+        self.0.line = self.0.line.map(|line| line + offset);
+        self
+    }
+
+    /// The file the definition was read from, when known.
+    pub fn path(&self) -> Option<&Path> {
+        self.0.path.as_deref()
+    }
+
+    /// What is wrong, without position information.
+    pub fn message(&self) -> &str {
+        &self.0.message
+    }
+
+    /// 1-based line of the problem, when known.
+    pub fn line(&self) -> Option<usize> {
+        self.0.line
+    }
+
+    /// 1-based column of the problem, when known.
+    pub fn column(&self) -> Option<usize> {
+        self.0.column
+    }
+
+    /// The offending line of the definition.
+    pub fn snippet(&self) -> Option<&str> {
+        self.0.snippet.as_deref()
+    }
+
+    /// A suggestion for fixing the problem.
+    pub fn hint(&self) -> Option<&str> {
+        self.0.hint.as_deref()
+    }
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.message)?;
+        let position = match (self.0.line, self.0.column) {
+            (Some(line), Some(column)) => Some(format!("{}:{}", line, column)),
+            (Some(line), None) => Some(line.to_string()),
+            _ => None,
+        };
+        match (&self.0.path, &position) {
+            (Some(path), Some(position)) => write!(f, "\n  --> {}:{}", path.display(), position)?,
+            (Some(path), None) => write!(f, "\n  --> {}", path.display())?,
+            (None, Some(_)) => write!(
+                f,
+                " (line {}{})",
+                self.0.line.unwrap_or_default(),
+                self.0
+                    .column
+                    .map(|column| format!(", column {}", column))
+                    .unwrap_or_default()
+            )?,
+            (None, None) => {}
+        }
+        if let (Some(snippet), Some(line)) = (&self.0.snippet, self.0.line) {
+            let width = line.to_string().len();
+            write!(f, "\n{:width$} |", "", width = width + 1)?;
+            write!(f, "\n{:>width$} | {}", line, snippet, width = width + 1)?;
+            if let Some(column) = self.0.column {
+                write!(
+                    f,
+                    "\n{:width$} | {:>column$}",
+                    "",
+                    "^",
+                    width = width + 1,
+                    column = column
+                )?;
+            }
+        }
+        if let Some(hint) = &self.0.hint {
+            write!(f, "\n   = hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DefinitionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0.source)
+    }
+}
+
+/// Finds the lines of a definition's fields in the text it was parsed from.
+///
+/// Fields are named by dotted paths, as in
+/// [`ValidationIssue::field`](crate::definitions::ValidationIssue::field):
+/// `output.template`, or `input.template.2` for the third item of a list.
+/// The text is followed by indentation, as in block-style YAML (and
+/// pretty-printed JSON); a field inside a flow mapping (`{ ... }`) is
+/// located at the line of the mapping.
+#[derive(Debug, Clone)]
+pub struct FieldLocator {
+    contents: String,
+    line_offset: usize,
+    prefix: Vec<String>,
+}
+
+impl FieldLocator {
+    /// Locate fields in `contents`, whose first line is line
+    /// `line_offset + 1` of its file.
+    pub fn new(contents: impl Into<String>, line_offset: usize) -> Self {
+        Self {
+            contents: contents.into(),
+            line_offset,
+            prefix: Vec::new(),
+        }
+    }
+
+    /// Locate fields within the field at `path`, such as `tools.1` for the
+    /// second definition of a sidecar declaring several.
+    pub fn within(mut self, path: &str) -> Self {
+        self.prefix.extend(path.split('.').map(str::to_string));
+        self
+    }
+
+    /// The 1-based line of `field` in the file. When the field itself is
+    /// absent, the line of the closest enclosing field that is present.
+    pub fn line(&self, field: &str) -> Option<usize> {
+        let lines: Vec<Line> = self.contents.lines().map(Line::parse).collect();
+        let mut found = None;
+        // The lines of the value of the field found last.
+        let mut range = 0..lines.len();
+        let segments = self
+            .prefix
+            .iter()
+            .map(String::as_str)
+            .chain(field.split('.'));
+        for segment in segments {
+            let children: Vec<usize> = range.clone().filter(|&i| !lines[i].blank).collect();
+            let item = segment.parse::<usize>().ok();
+            let index = match item {
+                Some(n) => {
+                    let items = children.iter().filter(|&&i| lines[i].item);
+                    let column = items.clone().map(|&i| lines[i].indent).min();
+                    items
+                        .filter(|&&i| Some(lines[i].indent) == column)
+                        .nth(n)
+                        .copied()
+                }
+                None => {
+                    let level = children.first().map(|&i| lines[i].column);
+                    children
+                        .iter()
+                        .copied()
+                        .find(|&i| Some(lines[i].column) == level && lines[i].key == Some(segment))
+                }
+            };
+            let Some(index) = index else { break };
+            found = Some(index);
+
+            let parent = &lines[index];
+            let end = (index + 1..range.end)
+                .find(|&i| {
+                    let line = &lines[i];
+                    !line.blank
+                        && match (item, line.item) {
+                            // An item ends where its list, or the next
+                            // item, continues.
+                            (Some(_), _) => line.indent <= parent.indent,
+                            // A key's list may be indented as far as the
+                            // key itself.
+                            (None, true) => line.indent < parent.column,
+                            (None, false) => line.column <= parent.column,
+                        }
+                })
+                .unwrap_or(range.end);
+            // An item's first key shares the item's line.
+            range = if item.is_some() { index } else { index + 1 }..end;
+        }
+        found.map(|index| index + 1 + self.line_offset)
+    }
+}
+
+/// A line of a definition, as [`FieldLocator`] reads it.
+struct Line<'a> {
+    /// Column of the line's first character
+    indent: usize,
+    /// Column of the line's key or value, past the dash of a list item
+    column: usize,
+    /// Whether the line starts a list item (`- `)
+    item: bool,
+    /// The key the line starts with, if any
+    key: Option<&'a str>,
+    /// Whether the line is blank, a comment, or only JSON punctuation
+    blank: bool,
+}
+
+impl<'a> Line<'a> {
+    fn parse(text: &'a str) -> Self {
+        let trimmed = text.trim_start();
+        let indent = text.len() - trimmed.len();
+        let (item, content) = match trimmed.strip_prefix('-') {
+            Some(rest) if rest.is_empty() || rest.starts_with(' ') => (true, rest.trim_start()),
+            _ => (false, trimmed),
+        };
+        let key = content
+            .split_once(':')
+            .map(|(key, _)| key.trim().trim_matches('"').trim_matches('\''))
+            .filter(|key| !key.is_empty() && !key.contains(' '));
+        Self {
+            indent,
+            column: text.len() - content.len(),
+            item,
+            key,
+            blank: trimmed.starts_with('#') || trimmed.chars().all(|c| "{}[],".contains(c)),
+        }
+    }
+}
+
+/// The first backticked word after `prefix` in `message`.
+fn quoted_after<'a>(message: &'a str, prefix: &str) -> Option<&'a str> {
+    let (_, rest) = message.split_once(prefix)?;
+    backticked(rest).first().copied()
+}
+
+/// Every backticked word in `text`.
+fn backticked(text: &str) -> Vec<&str> {
+    text.split('`').skip(1).step_by(2).collect()
+}
+
+/// The 1-based line and column of the first key in `contents` matching
+/// `predicate`.
+fn find_key(contents: &str, predicate: impl Fn(&str) -> bool) -> Option<(usize, usize)> {
+    contents.lines().enumerate().find_map(|(index, line)| {
+        let trimmed = line.trim_start().trim_start_matches("- ");
+        let (key, _) = trimmed.split_once(':')?;
+        predicate(key.trim()).then(|| (index + 1, line.len() - trimmed.len() + 1))
+    })
+}
+
+/// The candidate closest to `word`, if any is close enough to be a typo.
+pub(crate) fn closest<'a>(
+    word: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    candidates
+        .map(|candidate| (edit_distance(word, candidate), candidate))
+        .filter(|&(distance, _)| distance <= MAX_TYPO_DISTANCE)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_discovery::ToolDefinition;
+
+    const MISSPELLED: &str = "\
+name: deploy
+description: Deploys
+inptu:
+  template: x
+  schema: {}
+output:
+  template: y
+  schema: {}
+";
+
+    #[test]
+    fn test_missing_field_hint() {
+        let error = ToolDefinition::from_yaml(MISSPELLED)
+            .unwrap_err()
+            .with_path(Path::new("deploy.yaml"));
+
+        assert_eq!(error.message(), "missing field `input`");
+        assert_eq!((error.line(), error.column()), (Some(3), Some(1)));
+        assert_eq!(error.hint(), Some("did you mean `input:`?"));
+        assert_eq!(
+            error.to_string(),
+            "missing field `input`\n  --> deploy.yaml:3:1\n   |\n 3 | inptu:\n   | ^\n   = hint: did you mean `input:`?"
+        );
+    }
+
+    #[test]
+    fn test_located_errors() {
+        let yaml = "name: deploy\ndescription: Deploys\ninput: 5\n";
+        let error = ToolDefinition::from_yaml(yaml).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "input: invalid type: integer `5`, expected struct ToolInput"
+        );
+        assert_eq!(error.snippet(), Some("input: 5"));
+        assert!(error.hint().is_none());
+        assert!(error.to_string().starts_with(
+            "input: invalid type: integer `5`, expected struct ToolInput (line 3, column 8)\n"
+        ));
+
+        let error = ToolDefinition::from_yaml("name: deploy\n")
+            .unwrap_err()
+            .with_line_offset(10);
+        assert_eq!(error.message(), "missing field `description`");
+        assert_eq!(error.line(), None);
+        assert_eq!(error.to_string(), "missing field `description`");
+    }
+
+    #[test]
+    fn test_field_locator() {
+        let yaml = "\
+name: deploy
+description: Deploys
+input:
+  template:
+    - --env
+    - \"{{env}}\"
+  schema: { type: object }
+
+output:
+  # The deploy's ID
+  template: 'ID: (?<id>\\d+)'
+  schema:
+    type: object
+";
+        let locator = FieldLocator::new(yaml, 0);
+        assert_eq!(locator.line("name"), Some(1));
+        assert_eq!(locator.line("input.template"), Some(4));
+        assert_eq!(locator.line("input.template.1"), Some(6));
+        assert_eq!(locator.line("input.schema.properties"), Some(7));
+        assert_eq!(locator.line("output.template"), Some(11));
+        assert_eq!(locator.line("output.schema.type"), Some(13));
+        assert_eq!(locator.line("output.extract"), Some(9));
+        assert_eq!(locator.line("version"), None);
+        assert_eq!(FieldLocator::new(yaml, 10).line("output"), Some(19));
+
+        let sidecar = "\
+tools:
+- name: log
+  input:
+    template: log
+- name: blame
+  input:
+    template: blame {{file}}
+  output:
+    template: x
+";
+        let locator = FieldLocator::new(sidecar, 0).within("tools.1");
+        assert_eq!(locator.line("name"), Some(5));
+        assert_eq!(locator.line("input.template"), Some(7));
+        assert_eq!(locator.line("output"), Some(8));
+        assert_eq!(
+            FieldLocator::new(sidecar, 0)
+                .within("tools.0")
+                .line("output.template"),
+            Some(2)
+        );
+
+        let json = "{\n  \"name\": \"deploy\",\n  \"output\": {\n    \"template\": \"x\"\n  }\n}\n";
+        assert_eq!(FieldLocator::new(json, 0).line("output.template"), Some(4));
+    }
+
+    #[test]
+    fn test_closest() {
+        assert_eq!(edit_distance("descripton", "description"), 1);
+        assert_eq!(
+            closest("descripton", ["name", "description"].into_iter()),
+            Some("description")
+        );
+        assert_eq!(closest("zzz", ["name", "input"].into_iter()), None);
+    }
+}
@@ -0,0 +1,207 @@
+//! Settings for a tools directory, kept inside it.
+//!
+//! A `.mcp-serve.yaml` file in a tools directory sets defaults for the
+//! tools in that directory and below it, so a team owning a directory does
+//! not need to touch the server's configuration:
+//!
+//! ```yaml
+//! namespace: billing      # served as billing/<name>
+//! timeout: 30s            # unless a definition sets execution.timeout
+//! env:                    # added to each tool's execution.env
+//!   BILLING_API: https://billing.internal
+//! ignore: [scratch/*, "*.bak"]
+//! env_passthrough: [HOME, PATH, AWS_*]  # and no other host variables
+//! ```
+//!
+//! The settings of a directory are merged over those of the directories
+//! above it, and over the server's configuration: the namespace replaces the
+//! one the directory is served under, `env_passthrough` replaces the list
+//! inherited, and `env` variables are added to those inherited. A tool's
+//! own definition still has the last word.
+//!
+//! `ignore` patterns are matched against paths relative to the directory of
+//! the file, with `*` and `?` wildcards; a pattern without a `/` matches
+//! the name of an entry at any depth, like a `.gitignore`.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::execution::ExecutionConfig;
+use super::DiscoveredTool;
+use crate::filter::NamePattern;
+
+/// File name of the settings of a tools directory.
+pub const DIRECTORY_CONFIG_FILE: &str = ".mcp-serve.yaml";
+
+/// The contents of a [`DIRECTORY_CONFIG_FILE`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirectoryConfig {
+    /// Namespace the directory's tools are served under
+    pub namespace: Option<String>,
+
+    /// Variables added to the environment of the directory's tools
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// How long calls of the directory's tools may run (seconds, or `30s`,
+    /// `2m`)
+    #[serde(default, deserialize_with = "super::execution::deserialize_duration")]
+    pub timeout: Option<Duration>,
+
+    /// Entries of the directory not to scan
+    #[serde(default)]
+    pub ignore: Vec<NamePattern>,
+
+    /// The only variables of the server's environment the directory's tools
+    /// see
+    pub env_passthrough: Option<Vec<NamePattern>>,
+}
+
+impl DirectoryConfig {
+    /// Parse directory settings from YAML; an empty file sets nothing.
+    pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml_ng::Error> {
+        if yaml.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_yaml_ng::from_str(yaml)
+    }
+}
+
+/// The settings in effect in a directory being scanned: those of its own
+/// [`DIRECTORY_CONFIG_FILE`] merged over its parents'.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct DirectoryScope {
+    namespace: Option<String>,
+    defaults: ExecutionConfig,
+    /// Ignore patterns, with the directory they are relative to
+    ignore: Vec<(PathBuf, NamePattern)>,
+    /// The files merged, outermost first
+    files: Vec<PathBuf>,
+}
+
+impl DirectoryScope {
+    /// The settings of a scanned root, before any directory's file: the
+    /// execution `defaults` the scanner is configured with.
+    pub(super) fn new(defaults: ExecutionConfig) -> Self {
+        Self {
+            defaults,
+            ..Self::default()
+        }
+    }
+
+    /// The settings of `dir`, whose file holds `config`.
+    pub(super) fn enter(&self, dir: &Path, config: DirectoryConfig) -> Self {
+        let mut scope = self.clone();
+        scope.files.push(dir.join(DIRECTORY_CONFIG_FILE));
+        if config.namespace.is_some() {
+            scope.namespace = config.namespace;
+        }
+        if config.timeout.is_some() {
+            scope.defaults.timeout = config.timeout;
+        }
+        if config.env_passthrough.is_some() {
+            scope.defaults.env_passthrough = config.env_passthrough;
+        }
+        scope.defaults.env.extend(config.env);
+        scope.ignore.extend(
+            config
+                .ignore
+                .into_iter()
+                .map(|pattern| (dir.to_path_buf(), pattern)),
+        );
+        scope
+    }
+
+    /// Whether an ignore pattern matches the entry at `path`.
+    pub(super) fn ignores(&self, path: &Path) -> bool {
+        self.ignore.iter().any(|(dir, pattern)| {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                return false;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if pattern.to_string().contains('/') {
+                pattern.matches(&relative)
+            } else {
+                relative
+                    .rsplit('/')
+                    .next()
+                    .is_some_and(|name| pattern.matches(name))
+            }
+        })
+    }
+
+    /// Give `tool` the namespace and execution defaults of the directory.
+    pub(super) fn apply(&self, tool: &mut DiscoveredTool) {
+        if self.namespace.is_some() {
+            tool.namespace.clone_from(&self.namespace);
+        }
+        *tool.defaults = self.defaults.clone();
+        tool.directory_configs = self.files.as_slice().into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_scope() {
+        let config = DirectoryConfig::from_yaml(
+            r#"
+namespace: billing
+timeout: 30s
+env: { BILLING_API: "https://billing.internal", REGION: eu }
+ignore: ["scratch/*", "*.bak"]
+"#,
+        )
+        .unwrap();
+        let passthrough = vec!["PATH".parse().unwrap()];
+        let root = DirectoryScope::new(ExecutionConfig {
+            env_passthrough: Some(passthrough.clone()),
+            ..ExecutionConfig::default()
+        })
+        .enter(Path::new("/tools"), config);
+        assert_eq!(root.defaults.env_passthrough, Some(passthrough));
+        assert!(root.ignores(Path::new("/tools/scratch/try.sh")));
+        assert!(root.ignores(Path::new("/tools/ops/deploy.bak")));
+        assert!(!root.ignores(Path::new("/tools/ops/scratch/try.sh")));
+        assert!(!root.ignores(Path::new("/elsewhere/deploy.bak")));
+
+        let nested = root.enter(
+            Path::new("/tools/ops"),
+            DirectoryConfig::from_yaml(
+                "namespace: ops\nenv: { REGION: us }\nenv_passthrough: [HOME]\n",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            nested.defaults.env_passthrough,
+            Some(vec!["HOME".parse().unwrap()])
+        );
+        assert_eq!(nested.namespace.as_deref(), Some("ops"));
+        assert_eq!(nested.defaults.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(nested.defaults.env["REGION"], "us");
+        assert_eq!(
+            nested.defaults.env["BILLING_API"],
+            "https://billing.internal"
+        );
+        assert!(nested.ignores(Path::new("/tools/ops/deploy.bak")));
+        assert_eq!(
+            nested.files,
+            [
+                PathBuf::from("/tools").join(DIRECTORY_CONFIG_FILE),
+                PathBuf::from("/tools/ops").join(DIRECTORY_CONFIG_FILE),
+            ]
+        );
+
+        assert_eq!(
+            DirectoryConfig::from_yaml("").unwrap(),
+            DirectoryConfig::default()
+        );
+        let error = DirectoryConfig::from_yaml("namespcae: ops\n").unwrap_err();
+        assert!(error.to_string().contains("namespcae"), "{}", error);
+    }
+}
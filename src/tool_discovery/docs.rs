@@ -0,0 +1,161 @@
+//! Markdown documentation for tools.
+//!
+//! A `toolname.md` file next to an executable documents the tool at length,
+//! without squeezing prose into a YAML string. It is found with the same
+//! naming rules as sidecars (`deploy.sh.md`, or `deploy.md` as a fallback),
+//! and as `tool.md` in a tool directory. Its first paragraph is added to the
+//! tool's description, and the whole document is offered to clients as an
+//! MCP resource.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::wire::{Resource, ResourceContents};
+
+/// Extension of documentation files.
+pub const DOCS_EXTENSION: &str = "md";
+
+/// Name of the documentation file in a tool directory.
+pub const TOOL_DIRECTORY_DOCS: &str = "tool.md";
+
+/// MIME type of documentation resources.
+pub const DOCS_MIME_TYPE: &str = "text/markdown";
+
+/// Prefix of the URIs documentation resources are offered under.
+pub const DOCS_URI_PREFIX: &str = "mcp-serve://docs/";
+
+/// Find the documentation for the executable at `path`.
+///
+/// `deploy.sh` is documented by `deploy.sh.md`, or by `deploy.md` unless an
+/// executable named `deploy` also exists and would own that file.
+pub fn find_docs(path: &Path) -> Option<PathBuf> {
+    let mut appended = path.as_os_str().to_owned();
+    appended.push(".");
+    appended.push(DOCS_EXTENSION);
+    let legacy = (path.extension().is_some() && !path.with_extension("").exists())
+        .then(|| path.with_extension(DOCS_EXTENSION));
+
+    std::iter::once(PathBuf::from(appended))
+        .chain(legacy)
+        .find(|docs| docs != path && docs.is_file())
+}
+
+/// The URI a tool's documentation is offered under.
+pub fn docs_uri(tool_name: &str) -> String {
+    format!("{}{}", DOCS_URI_PREFIX, tool_name)
+}
+
+/// The resource offering the documentation of the tool named `tool_name`.
+pub fn docs_resource(tool_name: &str) -> Resource {
+    Resource {
+        uri: docs_uri(tool_name),
+        name: format!("{} documentation", tool_name),
+        description: Some(format!("Documentation for the `{}` tool", tool_name)),
+        mime_type: Some(DOCS_MIME_TYPE.to_string()),
+    }
+}
+
+/// A tool's documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolDocs {
+    /// The documentation file
+    pub path: PathBuf,
+
+    /// The Markdown text
+    pub text: String,
+}
+
+impl ToolDocs {
+    /// Read the documentation file at `path`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            text: fs::read_to_string(path)?,
+        })
+    }
+
+    /// The first paragraph of prose, skipping headings, joined onto one
+    /// line.
+    pub fn summary(&self) -> Option<String> {
+        let lines = self
+            .text
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| line.is_empty() || line.starts_with('#'))
+            .take_while(|line| !line.is_empty() && !line.starts_with('#'));
+        let summary = lines.collect::<Vec<_>>().join(" ");
+        (!summary.is_empty()).then_some(summary)
+    }
+
+    /// Add the summary to `description`, unless it already says the same.
+    pub fn enrich(&self, description: &str) -> String {
+        match self.summary() {
+            Some(summary) if description.trim().is_empty() => summary,
+            Some(summary) if !description.contains(&summary) => {
+                format!("{}\n\n{}", description.trim_end(), summary)
+            }
+            _ => description.to_string(),
+        }
+    }
+
+    /// The contents of the tool's [`docs_resource`], for `resources/read`.
+    pub fn contents(&self, tool_name: &str) -> ResourceContents {
+        ResourceContents {
+            uri: docs_uri(tool_name),
+            mime_type: Some(DOCS_MIME_TYPE.to_string()),
+            text: self.text.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn docs(text: &str) -> ToolDocs {
+        ToolDocs {
+            path: PathBuf::from("deploy.md"),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_docs() {
+        let dir = TempDir::new().unwrap();
+        let tool = dir.path().join("deploy.sh");
+        assert_eq!(find_docs(&tool), None);
+
+        fs::write(dir.path().join("deploy.md"), "# deploy\n").unwrap();
+        assert_eq!(find_docs(&tool), Some(dir.path().join("deploy.md")));
+
+        fs::write(dir.path().join("deploy.sh.md"), "# deploy\n").unwrap();
+        assert_eq!(find_docs(&tool), Some(dir.path().join("deploy.sh.md")));
+    }
+
+    #[test]
+    fn test_summary() {
+        let docs =
+            docs("# deploy\n\nDeploys the current branch\nto staging.\n\n## Options\n\nMore.\n");
+        assert_eq!(
+            docs.summary().as_deref(),
+            Some("Deploys the current branch to staging.")
+        );
+        assert_eq!(
+            docs.enrich("Deploys code."),
+            "Deploys code.\n\nDeploys the current branch to staging."
+        );
+        assert_eq!(docs.enrich(""), "Deploys the current branch to staging.");
+        assert_eq!(
+            docs.enrich("Deploys the current branch to staging."),
+            "Deploys the current branch to staging."
+        );
+        assert_eq!(self::docs("# Only a heading\n").summary(), None);
+
+        let contents = docs.contents("deploy");
+        assert_eq!(contents.uri, "mcp-serve://docs/deploy");
+        assert_eq!(contents.mime_type.as_deref(), Some("text/markdown"));
+        assert_eq!(contents.text, docs.text);
+    }
+}
@@ -0,0 +1,761 @@
+//! Extraction of tool definitions embedded in an executable's comments.
+//!
+//! A script can carry its own definition as a YAML block written in comments,
+//! delimited by `---` marker lines:
+//!
+//! ```bash
+//! #!/bin/bash
+//! # ---
+//! # name: CreateTicket
+//! # description: Creates a new feature ticket.
+//! # ...
+//! # ---
+//! echo "Ticket created..."
+//! ```
+//!
+//! A bare `# ---` opener is only recognized in the leading comment block (the
+//! comments directly following the shebang). An explicit `# --- mcp` opener
+//! may appear anywhere in the file.
+//!
+//! The comment syntax is chosen per script (see [`CommentSyntax::detect`]), so
+//! the same convention works with `//` in JavaScript, `--` in Lua, `::` or
+//! `REM` in batch files, and inside block comments such as `/* ... */`.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use super::{DefinitionError, ToolDefinition};
+
+/// Marker line content that delimits an embedded block.
+const BLOCK_DELIMITER: &str = "---";
+
+/// Tag that may follow the opening delimiter to mark a block explicitly.
+const BLOCK_TAG: &str = "mcp";
+
+/// Errors that can occur while loading an embedded definition.
+#[derive(Debug)]
+pub enum EmbeddedError {
+    /// The executable could not be read.
+    Io { path: PathBuf, source: io::Error },
+
+    /// The executable does not contain an embedded definition block.
+    NotFound { path: PathBuf },
+
+    /// An opening delimiter was found without a matching closing delimiter.
+    Unterminated { path: PathBuf, line: usize },
+
+    /// The embedded block is not a valid tool definition.
+    Parse {
+        path: PathBuf,
+        source: DefinitionError,
+    },
+
+    /// The file looks like a binary rather than a script, so it cannot carry
+    /// an embedded definition.
+    Binary { path: PathBuf },
+
+    /// The file exceeds the configured maximum size and was not examined.
+    TooLarge {
+        path: PathBuf,
+        size: u64,
+        limit: u64,
+    },
+}
+
+impl fmt::Display for EmbeddedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbeddedError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            EmbeddedError::NotFound { path } => {
+                write!(f, "no embedded definition found in {}", path.display())
+            }
+            EmbeddedError::Unterminated { path, line } => write!(
+                f,
+                "{}:{}: embedded definition is missing its closing `{}` line",
+                path.display(),
+                line,
+                BLOCK_DELIMITER
+            ),
+            EmbeddedError::Parse { path, source } => {
+                write!(
+                    f,
+                    "invalid embedded definition in {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            EmbeddedError::Binary { path } => write!(
+                f,
+                "{} is a binary file and cannot embed a definition",
+                path.display()
+            ),
+            EmbeddedError::TooLarge { path, size, limit } => write!(
+                f,
+                "{} is {} bytes, exceeding the {} byte limit for embedded definitions",
+                path.display(),
+                size,
+                limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EmbeddedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EmbeddedError::Io { source, .. } => Some(source),
+            EmbeddedError::Parse { source, .. } => Some(source),
+            EmbeddedError::NotFound { .. }
+            | EmbeddedError::Unterminated { .. }
+            | EmbeddedError::Binary { .. }
+            | EmbeddedError::TooLarge { .. } => None,
+        }
+    }
+}
+
+/// Comment syntax used to locate an embedded block in a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentSyntax {
+    /// Prefixes that start a single-line comment (e.g. `#`, `//`, `REM`)
+    pub line: &'static [&'static str],
+
+    /// Opening and closing delimiters of block comments, if the language has
+    /// them (e.g. `/*` and `*/`)
+    pub block: Option<(&'static str, &'static str)>,
+}
+
+impl CommentSyntax {
+    /// `#` comments: shell, Python, Ruby, Perl, R, and similar.
+    pub const HASH: CommentSyntax = CommentSyntax {
+        line: &["#"],
+        block: None,
+    };
+
+    /// `//` and `/* */` comments: JavaScript, TypeScript, Go, Rust, C, and
+    /// similar.
+    pub const C_STYLE: CommentSyntax = CommentSyntax {
+        line: &["//"],
+        block: Some(("/*", "*/")),
+    };
+
+    /// `--` and `--[[ ]]` comments: Lua.
+    pub const LUA: CommentSyntax = CommentSyntax {
+        line: &["--"],
+        block: Some(("--[[", "]]")),
+    };
+
+    /// `--` comments: SQL, Haskell.
+    pub const DOUBLE_DASH: CommentSyntax = CommentSyntax {
+        line: &["--"],
+        block: None,
+    };
+
+    /// `::` and `REM` comments: Windows batch files.
+    pub const BATCH: CommentSyntax = CommentSyntax {
+        line: &["::", "@REM", "REM"],
+        block: None,
+    };
+
+    /// `#` and `<# #>` comments: PowerShell.
+    pub const POWERSHELL: CommentSyntax = CommentSyntax {
+        line: &["#"],
+        block: Some(("<#", "#>")),
+    };
+
+    /// Determine the comment syntax of a script from its file extension,
+    /// falling back to the interpreter named by its shebang, and finally to
+    /// `#` comments.
+    pub fn detect(path: &Path, contents: &str) -> CommentSyntax {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::for_extension)
+            .or_else(|| Self::for_shebang(contents))
+            .unwrap_or(Self::HASH)
+    }
+
+    /// Comment syntax for a file extension (case-insensitive, without the
+    /// leading dot), if known.
+    pub fn for_extension(extension: &str) -> Option<CommentSyntax> {
+        match extension.to_ascii_lowercase().as_str() {
+            "sh" | "bash" | "zsh" | "fish" | "py" | "rb" | "pl" | "r" | "tcl" | "awk" => {
+                Some(Self::HASH)
+            }
+            "js" | "mjs" | "cjs" | "ts" | "mts" | "go" | "rs" | "c" | "cc" | "cpp" | "java"
+            | "kt" | "kts" | "swift" | "scala" | "dart" | "php" => Some(Self::C_STYLE),
+            "lua" => Some(Self::LUA),
+            "sql" | "hs" => Some(Self::DOUBLE_DASH),
+            "bat" | "cmd" => Some(Self::BATCH),
+            "ps1" => Some(Self::POWERSHELL),
+            _ => None,
+        }
+    }
+
+    /// Comment syntax for the interpreter named by a script's `#!` line, if
+    /// known.
+    pub fn for_shebang(contents: &str) -> Option<CommentSyntax> {
+        let shebang = contents.lines().next()?.strip_prefix("#!")?;
+        let mut words = shebang.split_whitespace();
+        let mut interpreter = words.next()?.rsplit('/').next()?;
+        if interpreter == "env" {
+            // Skip `env` flags such as `-S`.
+            interpreter = words.find(|word| !word.starts_with('-'))?;
+        }
+
+        let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        match name {
+            "node" | "nodejs" | "deno" | "bun" | "ts-node" | "tsx" | "rust-script" => {
+                Some(Self::C_STYLE)
+            }
+            "lua" | "luajit" => Some(Self::LUA),
+            "pwsh" | "powershell" => Some(Self::POWERSHELL),
+            "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" | "python" | "ruby" | "perl"
+            | "Rscript" => Some(Self::HASH),
+            _ => None,
+        }
+    }
+
+    /// Extract the comment text from each line of `contents`, or `None` for
+    /// lines that are not comments.
+    fn comment_lines<'a>(&self, contents: &'a str) -> Vec<Option<&'a str>> {
+        let mut open_block: Option<&'static str> = None;
+
+        contents
+            .lines()
+            .map(|line| {
+                if let Some(close) = open_block {
+                    let text = match line.find(close) {
+                        Some(end) => {
+                            open_block = None;
+                            &line[..end]
+                        }
+                        None => line,
+                    };
+                    return Some(strip_block_decoration(text));
+                }
+
+                let trimmed = line.trim_start();
+                if let Some((open, close)) = self.block {
+                    if let Some(rest) = trimmed.strip_prefix(open) {
+                        let text = match rest.find(close) {
+                            Some(end) => &rest[..end],
+                            None => {
+                                open_block = Some(close);
+                                rest
+                            }
+                        };
+                        return Some(strip_space(text));
+                    }
+                }
+
+                self.line
+                    .iter()
+                    .find_map(|prefix| strip_line_prefix(trimmed, prefix))
+                    .map(strip_space)
+            })
+            .collect()
+    }
+}
+
+/// A YAML block extracted from an executable's comments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedBlock {
+    /// The YAML content with comment markers removed
+    pub yaml: String,
+
+    /// 1-based line number of the opening delimiter
+    pub start_line: usize,
+}
+
+/// Extract the embedded YAML block from the contents of a script written
+/// with the given comment syntax.
+///
+/// Returns `Ok(None)` when no block is present, and an error (carrying the
+/// 1-based line number of the opener) when a block is opened but never closed.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_serve::tool_discovery::embedded::{extract_block, CommentSyntax};
+///
+/// let script = "#!/usr/bin/env node\n// ---\n// name: hello\n// ---\n";
+/// let block = extract_block(script, &CommentSyntax::C_STYLE).unwrap().unwrap();
+/// assert_eq!(block.yaml, "name: hello\n");
+/// ```
+pub fn extract_block(
+    contents: &str,
+    syntax: &CommentSyntax,
+) -> Result<Option<EmbeddedBlock>, usize> {
+    let comments = syntax.comment_lines(contents);
+    let raw_lines: Vec<&str> = contents.lines().collect();
+    let mut lines = comments.iter().enumerate().peekable();
+
+    // Skip the shebang, if any.
+    if raw_lines.first().is_some_and(|line| line.starts_with("#!")) {
+        lines.next();
+    }
+
+    let mut in_header = true;
+    let mut opener = None;
+    for (index, comment) in lines.by_ref() {
+        if in_header && comment.is_none() && !raw_lines[index].trim().is_empty() {
+            in_header = false;
+        }
+
+        let Some(text) = comment else { continue };
+        let text = text.trim();
+        let is_tagged_opener = text
+            .strip_prefix(BLOCK_DELIMITER)
+            .map(|rest| rest.trim() == BLOCK_TAG)
+            .unwrap_or(false);
+        if is_tagged_opener || (in_header && text == BLOCK_DELIMITER) {
+            opener = Some(index);
+            break;
+        }
+    }
+
+    let Some(start) = opener else {
+        return Ok(None);
+    };
+
+    let mut yaml = String::new();
+    for (_, comment) in lines {
+        let Some(text) = comment else {
+            break;
+        };
+        if text.trim() == BLOCK_DELIMITER {
+            return Ok(Some(EmbeddedBlock {
+                yaml,
+                start_line: start + 1,
+            }));
+        }
+        yaml.push_str(text);
+        yaml.push('\n');
+    }
+
+    Err(start + 1)
+}
+
+/// Load the tool definition embedded in the executable at `path`, using the
+/// default [`EmbeddedLoader`] limits.
+pub fn load(path: &Path) -> Result<ToolDefinition, EmbeddedError> {
+    EmbeddedLoader::new().load(path)
+}
+
+/// Default number of bytes examined at the start of each file.
+pub const DEFAULT_MAX_READ_BYTES: usize = 64 * 1024;
+
+/// Number of leading bytes inspected for NUL bytes when sniffing for binaries.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+/// Loads embedded definitions while bounding how much of each file is read.
+///
+/// Only the first [`DEFAULT_MAX_READ_BYTES`] of a file are examined by
+/// default, so compiled binaries in a tools directory are never read into
+/// memory in full. Files containing NUL bytes near their start are treated as
+/// binaries and skipped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mcp_serve::tool_discovery::embedded::EmbeddedLoader;
+/// use std::path::Path;
+///
+/// let loader = EmbeddedLoader::new()
+///     .with_max_read_bytes(16 * 1024)
+///     .with_max_file_size(Some(10 * 1024 * 1024));
+/// let tool = loader.load(Path::new("./tools/create-ticket")).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct EmbeddedLoader {
+    max_read_bytes: usize,
+    max_file_size: Option<u64>,
+    strict: bool,
+}
+
+impl Default for EmbeddedLoader {
+    fn default() -> Self {
+        Self {
+            max_read_bytes: DEFAULT_MAX_READ_BYTES,
+            max_file_size: None,
+            strict: false,
+        }
+    }
+}
+
+impl EmbeddedLoader {
+    /// Create a loader with default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many bytes at the start of each file are examined for an
+    /// embedded block. A block extending past this limit is reported as
+    /// unterminated.
+    pub fn with_max_read_bytes(mut self, bytes: usize) -> Self {
+        self.max_read_bytes = bytes;
+        self
+    }
+
+    /// Refuse to examine files larger than `bytes` at all. Unlimited by
+    /// default.
+    pub fn with_max_file_size(mut self, bytes: Option<u64>) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Reject fields the definition format does not define (see
+    /// [`strict`](super::strict)). Off by default.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Load the tool definition embedded in the executable at `path`.
+    ///
+    /// The comment syntax is chosen by [`CommentSyntax::detect`].
+    pub fn load(&self, path: &Path) -> Result<ToolDefinition, EmbeddedError> {
+        let contents = self.read_head(path)?;
+        let syntax = CommentSyntax::detect(path, &contents);
+
+        let block = extract_block(&contents, &syntax)
+            .map_err(|line| EmbeddedError::Unterminated {
+                path: path.to_path_buf(),
+                line,
+            })?
+            .ok_or_else(|| EmbeddedError::NotFound {
+                path: path.to_path_buf(),
+            })?;
+
+        let parsed = if self.strict {
+            ToolDefinition::from_yaml_strict(&block.yaml)
+        } else {
+            ToolDefinition::from_yaml(&block.yaml)
+        };
+        parsed.map_err(|source| EmbeddedError::Parse {
+            path: path.to_path_buf(),
+            source: source.with_path(path).with_line_offset(block.start_line),
+        })
+    }
+
+    /// Read at most `max_read_bytes` from the start of the file, rejecting
+    /// oversized and binary files.
+    fn read_head(&self, path: &Path) -> Result<String, EmbeddedError> {
+        let io_error = |source| EmbeddedError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let file = File::open(path).map_err(io_error)?;
+        if let Some(limit) = self.max_file_size {
+            let size = file.metadata().map_err(io_error)?.len();
+            if size > limit {
+                return Err(EmbeddedError::TooLarge {
+                    path: path.to_path_buf(),
+                    size,
+                    limit,
+                });
+            }
+        }
+
+        let mut head = Vec::with_capacity(self.max_read_bytes.min(DEFAULT_MAX_READ_BYTES));
+        let read = file
+            .take(self.max_read_bytes as u64)
+            .read_to_end(&mut head)
+            .map_err(io_error)?;
+
+        if head[..head.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+            return Err(EmbeddedError::Binary {
+                path: path.to_path_buf(),
+            });
+        }
+
+        // Drop a trailing partial line cut off by the read limit.
+        if read == self.max_read_bytes {
+            if let Some(end) = head.iter().rposition(|&b| b == b'\n') {
+                head.truncate(end + 1);
+            }
+        }
+
+        Ok(String::from_utf8_lossy(&head).into_owned())
+    }
+}
+
+/// Strip a line-comment prefix from an already left-trimmed line.
+///
+/// Alphabetic prefixes such as `REM` match case-insensitively and must be
+/// followed by whitespace or the end of the line.
+fn strip_line_prefix<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    if !prefix.chars().any(|c| c.is_ascii_alphabetic()) {
+        return line.strip_prefix(prefix);
+    }
+
+    let head = line.get(..prefix.len())?;
+    let rest = &line[prefix.len()..];
+    if head.eq_ignore_ascii_case(prefix) && (rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+/// Strip a single space separating a comment marker from its text.
+fn strip_space(text: &str) -> &str {
+    text.strip_prefix(' ').unwrap_or(text)
+}
+
+/// Strip the ` * ` decoration conventionally used on block comment lines.
+///
+/// Undecorated lines are returned unchanged so their indentation survives.
+fn strip_block_decoration(text: &str) -> &str {
+    match text.trim_start().strip_prefix('*') {
+        Some(rest) => strip_space(rest),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DESIGN_EXAMPLE: &str = r#"#!/bin/bash
+# ---
+# # The official name for the tool, used in API calls.
+# name: CreateTicket
+#
+# # A human-friendly title.
+# title: Create Ticket
+#
+# description: Creates a new feature ticket in the project tracking system.
+#
+# input:
+#   template: '--title {{title}} [--parent {{parent_id}}] {{body}}'
+#   schema:
+#     type: object
+#     properties:
+#       title:
+#         type: string
+#       body:
+#         type: string
+#     required: [ "title", "body" ]
+#
+# output:
+#   template: |-
+#     Ticket created: (?<url>https://.*)
+#     ID: (?<id>\d+)
+#   schema:
+#     type: object
+#     properties:
+#       url: { type: string }
+#       id: { type: string }
+# ---
+
+# --- Script logic starts here ---
+echo "Ticket created: https://jira.example.com/T-1234"
+"#;
+
+    #[test]
+    fn test_extract_block_from_design_example() {
+        let block = extract_block(DESIGN_EXAMPLE, &CommentSyntax::HASH)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(block.start_line, 2);
+        let tool = ToolDefinition::from_yaml(&block.yaml).expect("Should parse YAML");
+        assert_eq!(tool.name, "CreateTicket");
+        assert_eq!(tool.title, Some("Create Ticket".to_string()));
+        assert_eq!(
+            tool.output.template,
+            "Ticket created: (?<url>https://.*)\nID: (?<id>\\d+)"
+        );
+    }
+
+    #[test]
+    fn test_extract_tagged_block_after_code() {
+        let script = "#!/bin/sh\nset -e\n\n# --- mcp\n# name: late\n# ---\necho hi\n";
+
+        let block = extract_block(script, &CommentSyntax::HASH)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(block.yaml, "name: late\n");
+        assert_eq!(block.start_line, 4);
+    }
+
+    #[test]
+    fn test_bare_delimiter_only_in_header() {
+        let script = "#!/bin/sh\nset -e\n# ---\n# name: ignored\n# ---\n";
+
+        assert_eq!(extract_block(script, &CommentSyntax::HASH), Ok(None));
+    }
+
+    #[test]
+    fn test_unterminated_block() {
+        let script = "#!/bin/sh\n# ---\n# name: broken\necho hi\n";
+
+        assert_eq!(extract_block(script, &CommentSyntax::HASH), Err(2));
+    }
+
+    fn extract_yaml(script: &str, syntax: &CommentSyntax) -> String {
+        extract_block(script, syntax).unwrap().unwrap().yaml
+    }
+
+    #[test]
+    fn test_extract_line_comment_syntaxes() {
+        let node =
+            "#!/usr/bin/env node\n// ---\n// name: js\n// input:\n//   template: x\n// ---\n";
+        assert_eq!(
+            extract_yaml(node, &CommentSyntax::C_STYLE),
+            "name: js\ninput:\n  template: x\n"
+        );
+
+        let lua = "#!/usr/bin/env lua\n-- ---\n-- name: lua\n-- ---\nprint(1)\n";
+        assert_eq!(extract_yaml(lua, &CommentSyntax::LUA), "name: lua\n");
+
+        let batch = "@echo off\n:: --- mcp\n:: name: bat\nREM title: Batch\nrem\n:: ---\n";
+        assert_eq!(
+            extract_yaml(batch, &CommentSyntax::BATCH),
+            "name: bat\ntitle: Batch\n\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_block_comment_syntaxes() {
+        let decorated = "/* ---\n * name: js\n * input:\n *   template: x\n * --- */\n";
+        assert_eq!(
+            extract_yaml(decorated, &CommentSyntax::C_STYLE),
+            "name: js\ninput:\n  template: x\n"
+        );
+
+        let bare = "#!/usr/bin/env node\n/*\n---\nname: js\ninput:\n  template: x\n---\n*/\n";
+        assert_eq!(
+            extract_yaml(bare, &CommentSyntax::C_STYLE),
+            "name: js\ninput:\n  template: x\n"
+        );
+
+        let lua = "--[[ ---\nname: lua\n--- ]]\n";
+        assert_eq!(extract_yaml(lua, &CommentSyntax::LUA), "name: lua\n");
+
+        let powershell = "<#\n---\nname: ps\n---\n#>\nWrite-Output hi\n";
+        assert_eq!(
+            extract_yaml(powershell, &CommentSyntax::POWERSHELL),
+            "name: ps\n"
+        );
+    }
+
+    #[test]
+    fn test_rem_requires_word_boundary() {
+        let batch = ":: ---\n:: name: bat\nremark\n:: ---\n";
+        assert_eq!(extract_block(batch, &CommentSyntax::BATCH), Err(1));
+    }
+
+    #[test]
+    fn test_detect_comment_syntax() {
+        let detect = |name: &str, contents: &str| CommentSyntax::detect(Path::new(name), contents);
+
+        assert_eq!(detect("tool.js", ""), CommentSyntax::C_STYLE);
+        assert_eq!(detect("tool.LUA", ""), CommentSyntax::LUA);
+        assert_eq!(detect("tool.cmd", ""), CommentSyntax::BATCH);
+        assert_eq!(detect("tool.ps1", ""), CommentSyntax::POWERSHELL);
+        assert_eq!(
+            detect("tool", "#!/usr/bin/env node\n"),
+            CommentSyntax::C_STYLE
+        );
+        assert_eq!(
+            detect("tool", "#!/usr/bin/env -S deno run\n"),
+            CommentSyntax::C_STYLE
+        );
+        assert_eq!(detect("tool", "#!/usr/bin/lua5.4\n"), CommentSyntax::LUA);
+        assert_eq!(detect("tool", "#!/usr/bin/python3\n"), CommentSyntax::HASH);
+        assert_eq!(detect("tool", "no shebang"), CommentSyntax::HASH);
+        // Extension wins over the shebang
+        assert_eq!(
+            detect("tool.py", "#!/usr/bin/env node\n"),
+            CommentSyntax::HASH
+        );
+    }
+
+    #[test]
+    fn test_load_reports_errors() {
+        let dir = TempDir::new().unwrap();
+
+        let plain = dir.path().join("plain");
+        std::fs::write(&plain, "#!/bin/sh\necho hi\n").unwrap();
+        assert!(matches!(load(&plain), Err(EmbeddedError::NotFound { .. })));
+
+        let invalid = dir.path().join("invalid");
+        std::fs::write(&invalid, "#!/bin/sh\n# ---\n# name: [unclosed\n# ---\n").unwrap();
+        assert!(matches!(load(&invalid), Err(EmbeddedError::Parse { .. })));
+
+        let valid = dir.path().join("valid");
+        std::fs::write(&valid, DESIGN_EXAMPLE).unwrap();
+        assert_eq!(load(&valid).unwrap().name, "CreateTicket");
+
+        let node = dir.path().join("node-tool");
+        std::fs::write(
+            &node,
+            "#!/usr/bin/env node\n// ---\n// name: node_tool\n// description: Node\n// input: { template: '', schema: {} }\n// output: { template: '', schema: {} }\n// ---\n",
+        )
+        .unwrap();
+        assert_eq!(load(&node).unwrap().name, "node_tool");
+    }
+
+    #[test]
+    fn test_loader_skips_binaries() {
+        let dir = TempDir::new().unwrap();
+        let binary = dir.path().join("binary");
+        std::fs::write(&binary, b"\x7fELF\x02\x01\x01\x00\x00# ---\n").unwrap();
+
+        assert!(matches!(
+            EmbeddedLoader::new().load(&binary),
+            Err(EmbeddedError::Binary { .. })
+        ));
+    }
+
+    #[test]
+    fn test_loader_rejects_oversized_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("big");
+        std::fs::write(&path, DESIGN_EXAMPLE).unwrap();
+
+        let result = EmbeddedLoader::new()
+            .with_max_file_size(Some(16))
+            .load(&path);
+
+        match result {
+            Err(EmbeddedError::TooLarge { size, limit, .. }) => {
+                assert_eq!(size, DESIGN_EXAMPLE.len() as u64);
+                assert_eq!(limit, 16);
+            }
+            other => panic!("expected TooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_loader_reads_only_file_head() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tool");
+        let mut contents = DESIGN_EXAMPLE.to_string();
+        // Trailing data after the header (with a NUL past the sniff window)
+        // must never be examined.
+        contents.push_str(&"x".repeat(BINARY_SNIFF_BYTES * 2));
+        contents.push('\0');
+        std::fs::write(&path, &contents).unwrap();
+
+        let tool = EmbeddedLoader::new()
+            .with_max_read_bytes(DESIGN_EXAMPLE.len())
+            .load(&path)
+            .expect("Header fits within the read limit");
+        assert_eq!(tool.name, "CreateTicket");
+
+        let truncated = EmbeddedLoader::new().with_max_read_bytes(64).load(&path);
+        assert!(matches!(
+            truncated,
+            Err(EmbeddedError::Unterminated { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,315 @@
+//! How a tool's executable is run.
+//!
+//! The optional `execution` section of a definition collects the settings
+//! the executor needs, rather than leaving them to ad-hoc annotations:
+//!
+//! ```yaml
+//! execution:
+//!   timeout: 30s          # or a number of seconds
+//!   env:
+//!     LOG_FORMAT: json
+//!   cwd: ./workspace      # relative to the definition file
+//!   runtime: python3      # run the executable with this interpreter
+//!   concurrency: 1        # at most one call at a time
+//!   shell: false
+//!   env_passthrough: [HOME, PATH, AWS_*]
+//!   max_output_size: 1048576  # bytes of stdout or stderr, past which it is stopped
+//!   retries: 2            # run again when it fails, up to twice
+//!   sandbox: isolated     # see SandboxProfile
+//!   allowed_roots: [./data]   # where `format: path` arguments may point
+//! ```
+//!
+//! Tools inherit the whole environment of the server unless
+//! `env_passthrough` is set, here, in a directory's `.mcp-serve.yaml`, or in
+//! the server's configuration: the tool then starts from a clean
+//! environment holding only the server's variables whose names match one of
+//! the patterns, plus its own `env`. On Windows, most programs also need
+//! `SystemRoot` passed through.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::filter::NamePattern;
+
+/// Execution settings for a tool.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ExecutionConfig {
+    /// How long a call may run before it is killed (default: no limit)
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    #[schemars(with = "Option<Timeout>")]
+    pub timeout: Option<Duration>,
+
+    /// Variables added to the tool's environment
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+
+    /// Directory the tool runs in, relative to the definition file
+    /// (default: the tool's own working directory; see
+    /// [`DiscoveredTool::working_dir`](super::DiscoveredTool::working_dir))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+
+    /// Run the template through the platform shell (`sh -c` or `cmd /C`)
+    /// instead of executing the tool directly, with substituted values
+    /// quoted (see [`ShellTemplate`](crate::template::shell::ShellTemplate));
+    /// the executable is not run, so neither `runtime` nor a `sha256` pin
+    /// may be given
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shell: bool,
+
+    /// An interpreter to run the executable with, such as `python3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+
+    /// Most calls that may run at once (default: no limit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<NonZeroUsize>,
+
+    /// Start from a clean environment, keeping only the server's variables
+    /// with a name matching one of these, which may use `*` and `?`
+    /// wildcards (default: keep the whole environment)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<Vec<String>>")]
+    pub env_passthrough: Option<Vec<NamePattern>>,
+
+    /// Most bytes the tool may write to stdout, or to stderr; a tool writing
+    /// more is stopped, and the call fails (default: no limit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_size: Option<u64>,
+
+    /// How many times a call is run again when the tool cannot be started,
+    /// times out, or exits unsuccessfully (default: 0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+
+    /// How far the tool is kept from the server's environment (default:
+    /// `none`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxProfile>,
+
+    /// Directories that arguments the input schema marks `format: path`
+    /// must lie within, relative to the definition file (default: any
+    /// path; see [`confine`](crate::confine))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_roots: Option<Vec<PathBuf>>,
+}
+
+/// How far a tool is kept from the server's environment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxProfile {
+    /// The tool runs as the server does, in its environment.
+    #[default]
+    None,
+
+    /// The tool starts from a clean environment, keeping only the variables
+    /// of `env_passthrough`, in a temporary directory removed after the call
+    /// (unless `cwd` is set). On Unix it runs in a session of its own,
+    /// without core dumps, and on Linux it cannot gain privileges, as
+    /// through setuid executables.
+    Isolated,
+}
+
+impl ExecutionConfig {
+    /// Whether no setting differs from the defaults.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Take the settings of `defaults` that are not set here: its timeout,
+    /// `env_passthrough`, output limit, retries, sandbox, and allowed roots,
+    /// unless set, and the variables of its `env` not set here.
+    pub fn inherit(&mut self, defaults: &ExecutionConfig) {
+        if self.timeout.is_none() {
+            self.timeout = defaults.timeout;
+        }
+        if self.max_output_size.is_none() {
+            self.max_output_size = defaults.max_output_size;
+        }
+        if self.retries.is_none() {
+            self.retries = defaults.retries;
+        }
+        if self.sandbox.is_none() {
+            self.sandbox = defaults.sandbox;
+        }
+        if self.env_passthrough.is_none() {
+            self.env_passthrough.clone_from(&defaults.env_passthrough);
+        }
+        if self.allowed_roots.is_none() {
+            self.allowed_roots.clone_from(&defaults.allowed_roots);
+        }
+        for (name, value) in &defaults.env {
+            self.env
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    /// The directory the tool should run in: `cwd` resolved against
+    /// `definition_dir`, if set.
+    pub fn resolve_cwd(&self, definition_dir: &Path) -> Option<PathBuf> {
+        self.cwd.as_ref().map(|cwd| definition_dir.join(cwd))
+    }
+
+    /// The directories `format: path` arguments must lie within, resolved
+    /// against `definition_dir`, if restricted.
+    pub fn resolve_allowed_roots(&self, definition_dir: &Path) -> Option<Vec<PathBuf>> {
+        self.allowed_roots
+            .as_ref()
+            .map(|roots| roots.iter().map(|root| definition_dir.join(root)).collect())
+    }
+}
+
+/// Parse a duration written as a number of seconds or a number with a unit
+/// (`ms`, `s`, `m`, or `h`), such as `500ms` or `5m`.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", text))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        unit => {
+            return Err(format!(
+                "unknown duration unit `{}` (use ms, s, m, or h)",
+                unit
+            ))
+        }
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid duration `{}`", text))
+}
+
+/// Write a duration in the largest unit that represents it exactly.
+fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    match millis {
+        0 => "0s".to_string(),
+        _ if millis.is_multiple_of(3_600_000) => format!("{}h", millis / 3_600_000),
+        _ if millis.is_multiple_of(60_000) => format!("{}m", millis / 60_000),
+        _ if millis.is_multiple_of(1000) => format!("{}s", millis / 1000),
+        _ => format!("{}ms", millis),
+    }
+}
+
+fn serialize_timeout<S: Serializer>(
+    timeout: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match timeout {
+        Some(timeout) => serializer.serialize_str(&format_duration(*timeout)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// How a timeout is written: a number of seconds, or a duration such as
+/// `30s`, `500ms`, `5m`, or `1h`.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum Timeout {
+    Seconds(f64),
+    Text(String),
+}
+
+/// Deserialize a duration written as a number of seconds or as text for
+/// [`parse_duration`].
+pub(crate) fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    match Timeout::deserialize(deserializer)? {
+        Timeout::Seconds(seconds) => Duration::try_from_secs_f64(seconds)
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("invalid timeout {}", seconds))),
+        Timeout::Text(text) => parse_duration(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_execution() {
+        let execution: ExecutionConfig = serde_yaml_ng::from_str(
+            r#"
+timeout: 1m
+env: { LOG_FORMAT: json }
+cwd: ./workspace
+runtime: python3
+concurrency: 2
+env_passthrough: [HOME, AWS_*]
+retries: 0
+sandbox: isolated
+allowed_roots: [data, /srv/shared]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(execution.timeout, Some(Duration::from_secs(60)));
+        assert_eq!(execution.env["LOG_FORMAT"], "json");
+        assert_eq!(
+            execution.resolve_cwd(Path::new("/tools")),
+            Some(PathBuf::from("/tools/./workspace"))
+        );
+        assert!(!execution.shell);
+        assert_eq!(execution.concurrency, NonZeroUsize::new(2));
+
+        let yaml = serde_yaml_ng::to_string(&execution).unwrap();
+        assert!(yaml.contains("timeout: 1m"));
+        assert_eq!(
+            serde_yaml_ng::from_str::<ExecutionConfig>(&yaml).unwrap(),
+            execution
+        );
+        assert!(ExecutionConfig::default().is_default());
+
+        let mut inherited = ExecutionConfig {
+            env: BTreeMap::from([("LOG_FORMAT".to_string(), "text".to_string())]),
+            retries: Some(3),
+            ..ExecutionConfig::default()
+        };
+        inherited.inherit(&execution);
+        assert_eq!(inherited.timeout, Some(Duration::from_secs(60)));
+        assert_eq!(inherited.env["LOG_FORMAT"], "text");
+        assert_eq!(inherited.runtime, None);
+        assert_eq!(inherited.env_passthrough, execution.env_passthrough);
+        assert_eq!(inherited.retries, Some(3));
+        assert_eq!(inherited.sandbox, Some(SandboxProfile::Isolated));
+        assert_eq!(
+            inherited
+                .resolve_allowed_roots(Path::new("/tools"))
+                .unwrap(),
+            [PathBuf::from("/tools/data"), PathBuf::from("/srv/shared")]
+        );
+        assert!(serde_yaml_ng::from_str::<ExecutionConfig>("concurrency: 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert!(parse_duration("5 days").is_err());
+        assert!(parse_duration("soon").is_err());
+
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1500ms");
+        assert_eq!(format_duration(Duration::from_secs(90)), "90s");
+    }
+}
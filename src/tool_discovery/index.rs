@@ -0,0 +1,381 @@
+//! Persisted scan results for fast startup on large tool trees.
+//!
+//! A [`ScanIndex`] records what a scan found: every discovered tool, its
+//! parsed definition, and fingerprints of the files involved. Saved to disk,
+//! it lets a later run skip scanning and parsing entirely as long as nothing
+//! changed.
+//!
+//! An index is stale when:
+//!
+//! - the roots or scanner configuration differ,
+//! - any scanned directory's modification time changed (an entry was added,
+//!   removed, or renamed), or
+//! - any tool's executable or definition file, or a directory's
+//!   `.mcp-serve.yaml`, changed. Files whose
+//!   modification time and size are unchanged are trusted; otherwise their
+//!   SHA-256 digest is compared, so touching a file without editing it does
+//!   not invalidate the index.
+//!
+//! Schema files pulled in through `$ref` are not fingerprinted; rebuild the
+//! index (delete it) after editing one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::directory_config::DIRECTORY_CONFIG_FILE;
+use super::loader::Loader;
+use super::source::sha256_file;
+use super::{
+    DirectoryScanner, DiscoveredTool, MetadataSource, ScanError, ScanReport, ScannerConfig,
+    ToolDefinition, ToolRoot,
+};
+
+/// Version of the index file format; indexes written by other versions are
+/// ignored.
+pub const INDEX_VERSION: u32 = 3;
+
+/// A persisted snapshot of one or more scans.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanIndex {
+    version: u32,
+    config: ScannerConfig,
+    roots: Vec<ToolRoot>,
+    /// Modification time of every scanned directory
+    directories: BTreeMap<PathBuf, Option<SystemTime>>,
+    /// Fingerprints of the scanned directories' settings files
+    #[serde(default)]
+    directory_configs: Vec<FileFingerprint>,
+    tools: Vec<IndexedTool>,
+}
+
+/// A tool recorded in a [`ScanIndex`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedTool {
+    /// The discovered tool
+    pub tool: DiscoveredTool,
+
+    /// The tool's parsed definitions, if they could be loaded when indexed
+    pub definitions: Option<Vec<ToolDefinition>>,
+
+    /// Fingerprints of the tool's executable, definition file, and
+    /// documentation
+    files: Vec<FileFingerprint>,
+}
+
+/// Enough about a file to tell whether it changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FileFingerprint {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    size: u64,
+    sha256: String,
+}
+
+impl FileFingerprint {
+    fn take(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+            sha256: sha256_file(path)?,
+        })
+    }
+
+    fn is_current(&self) -> bool {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return false;
+        };
+        if metadata.modified().ok() == self.modified && metadata.len() == self.size {
+            return true;
+        }
+        metadata.len() == self.size
+            && sha256_file(&self.path).is_ok_and(|sha256| sha256 == self.sha256)
+    }
+}
+
+impl ScanIndex {
+    /// Scan `roots` and record the results.
+    ///
+    /// Roots are recorded (and scanned) by their canonical paths, so an index
+    /// is not mistakenly reused from a different working directory.
+    pub fn build(scanner: &DirectoryScanner, roots: &[ToolRoot]) -> Result<Self, IndexError> {
+        let roots = canonical_roots(roots);
+        let mut directories = BTreeMap::new();
+        let mut directory_configs = Vec::new();
+        let mut tools = Vec::new();
+        let mut loader = Loader::new();
+        for root in &roots {
+            let (found, report) = scanner.scan_root_with_report(root)?;
+            for dir in report.scanned_directories {
+                let modified = fs::metadata(&dir).and_then(|m| m.modified()).ok();
+                let config = dir.join(DIRECTORY_CONFIG_FILE);
+                if config.is_file() {
+                    directory_configs.push(FileFingerprint::take(&config).map_err(|source| {
+                        IndexError::Io {
+                            path: config.clone(),
+                            source,
+                        }
+                    })?);
+                }
+                directories.insert(dir, modified);
+            }
+            for tool in found {
+                tools.push(IndexedTool::new(tool, &mut loader)?);
+            }
+        }
+
+        Ok(Self {
+            version: INDEX_VERSION,
+            config: scanner.config().clone(),
+            roots,
+            directories,
+            directory_configs,
+            tools,
+        })
+    }
+
+    /// Read an index from `path`.
+    pub fn load(path: &Path) -> Result<Self, IndexError> {
+        let contents = fs::read_to_string(path).map_err(|source| IndexError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        serde_json::from_str(&contents).map_err(|source| IndexError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Write the index to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> Result<(), IndexError> {
+        let io_error = |source| IndexError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let json = serde_json::to_string(self).expect("index serialization cannot fail");
+        fs::write(path, json).map_err(io_error)
+    }
+
+    /// Load the index at `path` if it is still fresh for `scanner` and
+    /// `roots`; otherwise rebuild and save it. Returns the index and whether
+    /// it was rebuilt.
+    ///
+    /// A missing, unreadable, or outdated index file is simply rebuilt.
+    pub fn load_or_build(
+        path: &Path,
+        scanner: &DirectoryScanner,
+        roots: &[ToolRoot],
+    ) -> Result<(Self, bool), IndexError> {
+        if let Ok(index) = Self::load(path) {
+            if index.is_fresh(scanner, roots) {
+                return Ok((index, false));
+            }
+        }
+        let index = Self::build(scanner, roots)?;
+        index.save(path)?;
+        Ok((index, true))
+    }
+
+    /// Whether the index still describes what scanning `roots` with
+    /// `scanner` would find.
+    pub fn is_fresh(&self, scanner: &DirectoryScanner, roots: &[ToolRoot]) -> bool {
+        self.version == INDEX_VERSION
+            && &self.config == scanner.config()
+            && self.roots == canonical_roots(roots)
+            && self.directories.iter().all(|(dir, modified)| {
+                fs::metadata(dir).and_then(|m| m.modified()).ok() == *modified
+            })
+            && self
+                .tools
+                .iter()
+                .flat_map(|tool| &tool.files)
+                .chain(&self.directory_configs)
+                .all(FileFingerprint::is_current)
+    }
+
+    /// The indexed tools, in scan order.
+    pub fn tools(&self) -> &[IndexedTool] {
+        &self.tools
+    }
+
+    /// Statistics for the indexed tools, as a scan would have reported them.
+    pub fn report(&self) -> ScanReport {
+        let mut report = ScanReport {
+            directories_scanned: self.directories.len(),
+            scanned_directories: self.directories.keys().cloned().collect(),
+            ..ScanReport::default()
+        };
+        let tools: Vec<DiscoveredTool> = self.tools.iter().map(|t| t.tool.clone()).collect();
+        report.count_tools(&tools);
+        report
+    }
+}
+
+impl IndexedTool {
+    fn new(tool: DiscoveredTool, loader: &mut Loader) -> Result<Self, IndexError> {
+        let fingerprint = |path: &Path| {
+            FileFingerprint::take(path).map_err(|source| IndexError::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+        };
+        let mut files = vec![fingerprint(&tool.path)?];
+        if let MetadataSource::Sidecar(path) | MetadataSource::Manifest { path, .. } =
+            &tool.metadata_source
+        {
+            files.push(fingerprint(path)?);
+        }
+        if let Some(path) = &tool.docs {
+            files.push(fingerprint(path)?);
+        }
+
+        Ok(Self {
+            definitions: loader.load(&tool).ok(),
+            tool,
+            files,
+        })
+    }
+}
+
+fn canonical_roots(roots: &[ToolRoot]) -> Vec<ToolRoot> {
+    roots
+        .iter()
+        .map(|root| ToolRoot {
+            path: root
+                .path
+                .canonicalize()
+                .unwrap_or_else(|_| root.path.clone()),
+            namespace: root.namespace.clone(),
+        })
+        .collect()
+}
+
+/// Errors that can occur while building, reading, or writing an index.
+#[derive(Debug)]
+pub enum IndexError {
+    /// An index file or indexed file could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+
+    /// An index file is not valid.
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    /// Scanning the roots failed.
+    Scan(ScanError),
+}
+
+impl From<ScanError> for IndexError {
+    fn from(error: ScanError) -> Self {
+        IndexError::Scan(error)
+    }
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::Io { path, source } => {
+                write!(f, "failed to access {}: {}", path.display(), source)
+            }
+            IndexError::Parse { path, source } => {
+                write!(f, "invalid index {}: {}", path.display(), source)
+            }
+            IndexError::Scan(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IndexError::Io { source, .. } => Some(source),
+            IndexError::Parse { source, .. } => Some(source),
+            IndexError::Scan(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    const SIDECAR: &str = r#"
+name: tool
+description: Indexed tool
+input:
+  template: ""
+  schema: { type: object }
+output:
+  template: "(?<out>.*)"
+  schema: { type: object }
+"#;
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let tool = dir.path().join("tool");
+        fs::write(&tool, "binary").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(dir.path().join("tool.yaml"), SIDECAR).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        let dir = setup();
+        let roots = vec![ToolRoot::new(dir.path())];
+        let scanner = DirectoryScanner::new();
+        let cache = TempDir::new().unwrap();
+        let index_path = cache.path().join("mcp-serve").join("index.json");
+
+        let (index, rebuilt) = ScanIndex::load_or_build(&index_path, &scanner, &roots).unwrap();
+        assert!(rebuilt);
+        assert_eq!(index.tools().len(), 1);
+        assert_eq!(
+            index.tools()[0].definitions.as_ref().unwrap()[0].description,
+            "Indexed tool"
+        );
+
+        let (reloaded, rebuilt) = ScanIndex::load_or_build(&index_path, &scanner, &roots).unwrap();
+        assert!(!rebuilt);
+        assert_eq!(reloaded, index);
+        assert_eq!(reloaded.report().executables_found, 1);
+    }
+
+    #[test]
+    fn test_index_invalidation() {
+        let dir = setup();
+        let roots = vec![ToolRoot::new(dir.path())];
+        let scanner = DirectoryScanner::new();
+        let index = ScanIndex::build(&scanner, &roots).unwrap();
+        assert!(index.is_fresh(&scanner, &roots));
+
+        // Different configuration or roots
+        let shebangs = DirectoryScanner::builder().detect_shebangs(true).build();
+        assert!(!index.is_fresh(&shebangs, &roots));
+        assert!(!index.is_fresh(&scanner, &[]));
+
+        // Rewriting a file with identical contents keeps the index fresh
+        fs::write(dir.path().join("tool.yaml"), SIDECAR).unwrap();
+        assert!(index.is_fresh(&scanner, &roots));
+
+        // Editing a definition invalidates it
+        fs::write(
+            dir.path().join("tool.yaml"),
+            SIDECAR.replace("Indexed", "Edited"),
+        )
+        .unwrap();
+        assert!(!index.is_fresh(&scanner, &roots));
+    }
+}
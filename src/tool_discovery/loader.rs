@@ -0,0 +1,430 @@
+//! Loading tool definitions for discovered tools.
+//!
+//! The scanner only decides *where* a tool's definition lives (its
+//! [`MetadataSource`]); this module reads and parses it into
+//! [`ToolDefinition`]s. A sidecar may declare several tools backed by the same
+//! executable, so loading yields a list of definitions per discovered tool.
+//! Schema `$ref`s to shared files are resolved as part of loading (see
+//! [`refs`](super::refs)), and those files must be signed if the loader has
+//! trusted keys. Descriptions are enriched from the tool's Markdown
+//! documentation, if it has any (see [`docs`](super::docs)), and the
+//! execution settings of the tool's directory fill in those its definition
+//! leaves unset (see [`directory_config`](super::directory_config)).
+
+use minisign_verify::PublicKey;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::docs::ToolDocs;
+use super::embedded::{EmbeddedError, EmbeddedLoader};
+use super::manifest::{Manifest, ManifestError};
+use super::refs::{resolve_refs, resolve_signed_refs, RefError};
+use super::{DefinitionError, DiscoveredTool, MetadataSource, SidecarError, ToolDefinition};
+
+/// Load the definitions of a discovered tool with default settings.
+///
+/// See [`Loader::load`].
+pub fn load(tool: &DiscoveredTool) -> Result<Vec<ToolDefinition>, LoadError> {
+    Loader::new().load(tool)
+}
+
+/// Loads tool definitions from sidecars, embedded blocks, and manifests.
+///
+/// A loader caches parsed manifests, so loading every tool declared by one
+/// manifest reads it only once.
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+    embedded: EmbeddedLoader,
+    manifests: HashMap<PathBuf, Manifest>,
+    strict: bool,
+    trusted_keys: Option<Vec<PublicKey>>,
+}
+
+impl Loader {
+    /// Create a loader with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refuse schema files referenced by `$ref` unless one of `keys` signed
+    /// them (see [`signature`](super::signature)). Other files of a tool
+    /// are checked by [`verify_tool`](super::signature::verify_tool).
+    pub fn with_trusted_keys(mut self, keys: Vec<PublicKey>) -> Self {
+        self.trusted_keys = Some(keys);
+        self
+    }
+
+    /// Use `embedded` to read definitions embedded in executables.
+    pub fn with_embedded_loader(mut self, embedded: EmbeddedLoader) -> Self {
+        self.embedded = embedded.with_strict(self.strict);
+        self
+    }
+
+    /// Reject fields the definition format does not define in sidecars and
+    /// embedded definitions (see [`strict`](super::strict)). Off by default.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self.embedded = self.embedded.with_strict(strict);
+        self
+    }
+
+    /// Read and parse the definitions of `tool` from its metadata source.
+    ///
+    /// Embedded definitions and manifest entries always describe one tool; a
+    /// sidecar may describe several.
+    pub fn load(&mut self, tool: &DiscoveredTool) -> Result<Vec<ToolDefinition>, LoadError> {
+        let mut definitions = self.parse(tool)?;
+        for definition in &mut definitions {
+            definition.execution.inherit(&tool.defaults);
+        }
+        let definition_file = tool.definition_file();
+        let base_dir = definition_file.parent().unwrap_or(Path::new(""));
+        for definition in &mut definitions {
+            for schema in [&mut definition.input.schema, &mut definition.output.schema] {
+                let resolved = match &self.trusted_keys {
+                    Some(keys) => resolve_signed_refs(schema, base_dir, keys),
+                    None => resolve_refs(schema, base_dir),
+                };
+                resolved.map_err(|source| LoadError::SchemaRef {
+                    path: definition_file.to_path_buf(),
+                    source,
+                })?;
+            }
+        }
+        if let Some(path) = &tool.docs {
+            let docs = ToolDocs::load(path).map_err(|source| LoadError::Docs {
+                path: path.clone(),
+                source,
+            })?;
+            for definition in &mut definitions {
+                definition.description = docs.enrich(&definition.description);
+            }
+        }
+        Ok(definitions)
+    }
+
+    fn parse(&mut self, tool: &DiscoveredTool) -> Result<Vec<ToolDefinition>, LoadError> {
+        match &tool.metadata_source {
+            MetadataSource::Sidecar(path) => {
+                let parsed = if self.strict {
+                    ToolDefinition::all_from_sidecar_strict(path)
+                } else {
+                    ToolDefinition::all_from_sidecar(path)
+                };
+                parsed.map_err(|source| LoadError::Sidecar {
+                    path: path.clone(),
+                    source,
+                })
+            }
+            MetadataSource::Embedded => self
+                .embedded
+                .load(&tool.path)
+                .map(|definition| vec![definition])
+                .map_err(LoadError::Embedded),
+            MetadataSource::Manifest { path, index } => {
+                let manifest = self.manifest(path)?;
+                manifest
+                    .tools
+                    .get(*index)
+                    .map(|entry| vec![entry.definition.clone()])
+                    .ok_or_else(|| LoadError::MissingManifestEntry {
+                        path: path.clone(),
+                        index: *index,
+                    })
+            }
+        }
+    }
+
+    fn manifest(&mut self, path: &Path) -> Result<&Manifest, LoadError> {
+        if !self.manifests.contains_key(path) {
+            let manifest = Manifest::load(path).map_err(LoadError::Manifest)?;
+            self.manifests.insert(path.to_path_buf(), manifest);
+        }
+        Ok(&self.manifests[path])
+    }
+}
+
+/// Errors that can occur while loading a tool's definition.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The tool's sidecar could not be read or parsed.
+    Sidecar { path: PathBuf, source: SidecarError },
+
+    /// The tool's embedded definition could not be extracted or parsed.
+    Embedded(EmbeddedError),
+
+    /// The manifest declaring the tool could not be loaded.
+    Manifest(ManifestError),
+
+    /// The manifest no longer has an entry at the tool's position (it changed
+    /// since the tool was discovered).
+    MissingManifestEntry { path: PathBuf, index: usize },
+
+    /// A schema `$ref` in the definition file could not be resolved.
+    SchemaRef { path: PathBuf, source: RefError },
+
+    /// The tool's documentation could not be read.
+    Docs { path: PathBuf, source: io::Error },
+}
+
+impl LoadError {
+    /// The parse error behind this error, if the definition was read but
+    /// could not be parsed; it knows where in the file the problem is.
+    pub fn definition_error(&self) -> Option<&DefinitionError> {
+        match self {
+            LoadError::Sidecar {
+                source: SidecarError::Yaml(e),
+                ..
+            } => Some(e),
+            LoadError::Embedded(EmbeddedError::Parse { source, .. }) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Sidecar { path, source } => {
+                write!(f, "invalid sidecar {}: {}", path.display(), source)
+            }
+            LoadError::Embedded(e) => write!(f, "{}", e),
+            LoadError::Manifest(e) => write!(f, "{}", e),
+            LoadError::MissingManifestEntry { path, index } => write!(
+                f,
+                "manifest {} has no tool at position {}",
+                path.display(),
+                index + 1
+            ),
+            LoadError::SchemaRef { path, source } => {
+                write!(f, "invalid definition {}: {}", path.display(), source)
+            }
+            LoadError::Docs { path, source } => {
+                write!(
+                    f,
+                    "cannot read documentation {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Sidecar { source, .. } => Some(source),
+            LoadError::Embedded(e) => Some(e),
+            LoadError::Manifest(e) => Some(e),
+            LoadError::MissingManifestEntry { .. } => None,
+            LoadError::SchemaRef { source, .. } => Some(source),
+            LoadError::Docs { source, .. } => Some(source),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const DEFINITION: &str = r#"name: greet
+description: Says hello
+input:
+  template: "{{name}}"
+  schema: { type: object }
+output:
+  template: "(?<greeting>.*)"
+  schema: { type: object }
+"#;
+
+    fn discovered(path: PathBuf, metadata_source: MetadataSource) -> DiscoveredTool {
+        DiscoveredTool {
+            relative_path: PathBuf::from(path.file_name().unwrap()),
+            path,
+            metadata_source,
+            namespace: None,
+            working_dir: None,
+            docs: None,
+            defaults: Box::default(),
+            directory_configs: Box::default(),
+        }
+    }
+
+    #[test]
+    fn test_load_sidecar() {
+        let dir = TempDir::new().unwrap();
+        let sidecar = dir.path().join("greet.yaml");
+        fs::write(&sidecar, DEFINITION).unwrap();
+
+        let tool = discovered(dir.path().join("greet"), MetadataSource::Sidecar(sidecar));
+        let definitions = load(&tool).unwrap();
+
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].name, "greet");
+    }
+
+    #[test]
+    fn test_load_enriches_description_from_docs() {
+        let dir = TempDir::new().unwrap();
+        let sidecar = dir.path().join("greet.yaml");
+        fs::write(&sidecar, DEFINITION).unwrap();
+        let docs = dir.path().join("greet.md");
+        fs::write(&docs, "# greet\n\nGreets someone by name.\n\n## Usage\n").unwrap();
+
+        let mut tool = discovered(dir.path().join("greet"), MetadataSource::Sidecar(sidecar));
+        tool.docs = Some(docs.clone());
+        let definitions = load(&tool).unwrap();
+        assert_eq!(
+            definitions[0].description,
+            "Says hello\n\nGreets someone by name."
+        );
+
+        fs::remove_file(&docs).unwrap();
+        assert!(matches!(load(&tool), Err(LoadError::Docs { path, .. }) if path == docs));
+    }
+
+    #[test]
+    fn test_load_sidecar_with_several_tools() {
+        let dir = TempDir::new().unwrap();
+        let sidecar = dir.path().join("git-tools.yaml");
+        let entries: String = ["git_log", "git_blame"]
+            .iter()
+            .map(|name| {
+                DEFINITION
+                    .replace("name: greet", name)
+                    .lines()
+                    .enumerate()
+                    .map(|(i, line)| {
+                        let prefix = if i == 0 { "  - name: " } else { "    " };
+                        format!("{}{}\n", prefix, line)
+                    })
+                    .collect::<String>()
+            })
+            .collect();
+        fs::write(&sidecar, format!("tools:\n{}", entries)).unwrap();
+
+        let tool = discovered(
+            dir.path().join("git-tools"),
+            MetadataSource::Sidecar(sidecar),
+        );
+        let names: Vec<String> = load(&tool)
+            .unwrap()
+            .into_iter()
+            .map(|definition| definition.name)
+            .collect();
+
+        assert_eq!(names, ["git_log", "git_blame"]);
+    }
+
+    #[test]
+    fn test_load_embedded() {
+        let dir = TempDir::new().unwrap();
+        let script = dir.path().join("greet.sh");
+        let embedded: String = DEFINITION
+            .lines()
+            .map(|line| format!("# {}\n", line))
+            .collect();
+        fs::write(
+            &script,
+            format!("#!/bin/sh\n# ---\n{}# ---\necho hello\n", embedded),
+        )
+        .unwrap();
+
+        let tool = discovered(script, MetadataSource::Embedded);
+        let definitions = load(&tool).unwrap();
+
+        assert_eq!(definitions[0].description, "Says hello");
+    }
+
+    #[test]
+    fn test_load_manifest_entries() {
+        let dir = TempDir::new().unwrap();
+        let manifest = dir.path().join("tools.yaml");
+        let entry: String = DEFINITION
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let prefix = if i == 0 { "  - " } else { "    " };
+                format!("{}{}\n", prefix, line)
+            })
+            .collect();
+        fs::write(
+            &manifest,
+            format!("tools:\n{}    command: ./greet\n", entry),
+        )
+        .unwrap();
+
+        let mut loader = Loader::new();
+        let tool = discovered(
+            dir.path().join("greet"),
+            MetadataSource::Manifest {
+                path: manifest.clone(),
+                index: 0,
+            },
+        );
+        assert_eq!(loader.load(&tool).unwrap()[0].name, "greet");
+
+        let missing = discovered(
+            dir.path().join("greet"),
+            MetadataSource::Manifest {
+                path: manifest,
+                index: 1,
+            },
+        );
+        assert!(matches!(
+            loader.load(&missing),
+            Err(LoadError::MissingManifestEntry { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_resolves_schema_refs() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("name.json"),
+            r#"{"type": "string", "minLength": 1}"#,
+        )
+        .unwrap();
+        let sidecar = dir.path().join("greet.yaml");
+        fs::write(
+            &sidecar,
+            DEFINITION.replace(
+                "schema: { type: object }",
+                "schema: { type: object, properties: { name: { $ref: ./name.json } } }",
+            ),
+        )
+        .unwrap();
+
+        let tool = discovered(dir.path().join("greet"), MetadataSource::Sidecar(sidecar));
+        let definitions = load(&tool).unwrap();
+
+        assert_eq!(
+            definitions[0].input.schema["properties"]["name"]["minLength"],
+            1
+        );
+
+        fs::remove_file(dir.path().join("name.json")).unwrap();
+        assert!(matches!(load(&tool), Err(LoadError::SchemaRef { .. })));
+    }
+
+    #[test]
+    fn test_load_invalid_sidecar() {
+        let dir = TempDir::new().unwrap();
+        let sidecar = dir.path().join("greet.yaml");
+        fs::write(&sidecar, "name: [unclosed").unwrap();
+
+        let tool = discovered(
+            dir.path().join("greet"),
+            MetadataSource::Sidecar(sidecar.clone()),
+        );
+        let error = load(&tool).unwrap_err();
+
+        assert!(matches!(&error, LoadError::Sidecar { path, .. } if *path == sidecar));
+        assert!(error.to_string().contains("greet.yaml"));
+    }
+}
@@ -0,0 +1,195 @@
+//! Tool manifests declaring several tools in a single file.
+//!
+//! A directory may contain a `tools.yaml` manifest listing tool definitions
+//! explicitly, each paired with the command that implements it. This suits
+//! users who prefer one curated file over a sidecar per executable.
+//!
+//! ```yaml
+//! tools:
+//!   - command: ./bin/deploy
+//!     name: deploy
+//!     description: Deploys the current branch
+//!     input:
+//!       template: "--env {{env}}"
+//!       schema:
+//!         type: object
+//!         properties:
+//!           env: { type: string }
+//!     output:
+//!       template: "Deployed: (?<url>.*)"
+//!       schema:
+//!         type: object
+//!         properties:
+//!           url: { type: string }
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::ToolDefinition;
+
+/// File name of a tool manifest within a tools directory.
+pub const MANIFEST_FILE_NAME: &str = "tools.yaml";
+
+/// A parsed `tools.yaml` manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Manifest {
+    /// Tools declared by the manifest, in declaration order
+    pub tools: Vec<ManifestEntry>,
+}
+
+/// A single tool declared in a manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ManifestEntry {
+    /// Path to the executable implementing the tool, relative to the
+    /// manifest's directory (or absolute)
+    pub command: PathBuf,
+
+    /// The tool's definition
+    #[serde(flatten)]
+    pub definition: ToolDefinition,
+}
+
+impl ManifestEntry {
+    /// Resolve the entry's command against the directory containing the
+    /// manifest.
+    pub fn command_path(&self, manifest_dir: &Path) -> PathBuf {
+        // Collecting the components drops interior `.` segments, so
+        // `./bin/deploy` resolves to the same path the scanner reports.
+        manifest_dir.join(&self.command).components().collect()
+    }
+}
+
+impl Manifest {
+    /// Parse a manifest from a YAML string.
+    pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml_ng::Error> {
+        serde_yaml_ng::from_str(yaml)
+    }
+
+    /// Read and parse the manifest at `path`.
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ManifestError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_yaml(&contents).map_err(|source| ManifestError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// Errors that can occur while loading a manifest.
+#[derive(Debug)]
+pub enum ManifestError {
+    /// The manifest could not be read.
+    Io { path: PathBuf, source: io::Error },
+
+    /// The manifest is not valid YAML or does not match the manifest format.
+    Parse {
+        path: PathBuf,
+        source: serde_yaml_ng::Error,
+    },
+
+    /// An entry's command does not exist.
+    MissingCommand { path: PathBuf, command: PathBuf },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            ManifestError::Parse { path, source } => {
+                write!(f, "invalid manifest {}: {}", path.display(), source)
+            }
+            ManifestError::MissingCommand { path, command } => write!(
+                f,
+                "manifest {} declares a command that does not exist: {}",
+                path.display(),
+                command.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ManifestError::Io { source, .. } => Some(source),
+            ManifestError::Parse { source, .. } => Some(source),
+            ManifestError::MissingCommand { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_parsing() {
+        let yaml = r#"
+tools:
+  - command: ./bin/deploy
+    name: deploy
+    description: Deploys things
+    input:
+      template: "--env {{env}}"
+      schema:
+        type: object
+    output:
+      template: "Deployed: (?<url>.*)"
+      schema:
+        type: object
+  - command: /usr/bin/uptime
+    name: uptime
+    title: Uptime
+    description: Shows uptime
+    input:
+      template: ""
+      schema:
+        type: object
+    output:
+      template: "(?<uptime>.*)"
+      schema:
+        type: object
+"#;
+
+        let manifest = Manifest::from_yaml(yaml).expect("Should parse manifest");
+
+        assert_eq!(manifest.tools.len(), 2);
+        assert_eq!(manifest.tools[0].command, PathBuf::from("./bin/deploy"));
+        assert_eq!(manifest.tools[0].definition.name, "deploy");
+        assert_eq!(manifest.tools[0].definition.input.template, "--env {{env}}");
+        assert_eq!(
+            manifest.tools[1].definition.title,
+            Some("Uptime".to_string())
+        );
+        assert_eq!(
+            manifest.tools[1].command_path(Path::new("/tools")),
+            PathBuf::from("/usr/bin/uptime")
+        );
+    }
+
+    #[test]
+    fn test_manifest_entry_requires_command() {
+        let yaml = r#"
+tools:
+  - name: deploy
+    description: Deploys things
+    input:
+      template: ""
+      schema: {}
+    output:
+      template: ""
+      schema: {}
+"#;
+
+        assert!(Manifest::from_yaml(yaml).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Checking who can change or escalate through an executable.
+//!
+//! A tool runs with the server's privileges, so whoever can change its
+//! executable can run anything as the server. Executables others may
+//! write, or that are owned by another user (other than root), are flagged,
+//! as are setuid and setgid executables, which run with privileges the
+//! server does not have. With `security.strict` such tools are refused;
+//! otherwise they are served with a warning.
+//!
+//! Permissions are only checked on Unix; elsewhere every executable passes.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An executable others could change, or that runs with other privileges.
+#[derive(Debug)]
+pub enum PermissionError {
+    /// The executable could not be inspected.
+    Io { path: PathBuf, source: io::Error },
+
+    /// Every user may write the executable.
+    WorldWritable { path: PathBuf },
+
+    /// The executable belongs to another user, who may change it.
+    ForeignOwner { path: PathBuf, uid: u32 },
+
+    /// The executable runs as its owner (setuid) or group (setgid).
+    Setuid { path: PathBuf },
+}
+
+impl fmt::Display for PermissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PermissionError::Io { path, source } => {
+                write!(f, "cannot inspect {}: {}", path.display(), source)
+            }
+            PermissionError::WorldWritable { path } => {
+                write!(f, "{} is writable by every user", path.display())
+            }
+            PermissionError::ForeignOwner { path, uid } => write!(
+                f,
+                "{} is owned by another user (uid {}), who can change it",
+                path.display(),
+                uid
+            ),
+            PermissionError::Setuid { path } => write!(
+                f,
+                "{} is setuid or setgid, so it runs with privileges of its own",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PermissionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PermissionError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Check that only its owner, the user running the server or root, can
+/// change the executable at `path`, and that it does not run with
+/// privileges of its own.
+#[cfg(unix)]
+pub fn check(path: &Path) -> Result<(), PermissionError> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::metadata(path).map_err(|source| PermissionError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    // SAFETY: geteuid cannot fail and has no preconditions.
+    let user = unsafe { libc::geteuid() };
+    inspect(path, metadata.mode(), metadata.uid(), user)
+}
+
+/// Check that only its owner, the user running the server or root, can
+/// change the executable at `path`, and that it does not run with
+/// privileges of its own.
+#[cfg(not(unix))]
+pub fn check(_path: &Path) -> Result<(), PermissionError> {
+    Ok(())
+}
+
+/// Check the `mode` and owner `uid` of the executable at `path`, for the
+/// server running as `user`.
+#[cfg(unix)]
+fn inspect(path: &Path, mode: u32, uid: u32, user: u32) -> Result<(), PermissionError> {
+    let path = path.to_path_buf();
+    if mode & 0o002 != 0 {
+        Err(PermissionError::WorldWritable { path })
+    } else if uid != user && uid != 0 {
+        Err(PermissionError::ForeignOwner { path, uid })
+    } else if mode & 0o6000 != 0 {
+        Err(PermissionError::Setuid { path })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check() {
+        let dir = TempDir::new().unwrap();
+        let greet = dir.path().join("greet");
+        fs::write(&greet, "#!/bin/sh\necho hello\n").unwrap();
+
+        fs::set_permissions(&greet, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(check(&greet).is_ok());
+        fs::set_permissions(&greet, fs::Permissions::from_mode(0o777)).unwrap();
+        let error = check(&greet).unwrap_err();
+        assert!(
+            matches!(error, PermissionError::WorldWritable { .. }),
+            "{}",
+            error
+        );
+        fs::set_permissions(&greet, fs::Permissions::from_mode(0o4755)).unwrap();
+        let error = check(&greet).unwrap_err();
+        assert!(matches!(error, PermissionError::Setuid { .. }), "{}", error);
+
+        let error = check(&dir.path().join("missing")).unwrap_err();
+        assert!(matches!(error, PermissionError::Io { .. }), "{}", error);
+
+        assert!(inspect(&greet, 0o755, 0, 1000).is_ok());
+        let error = inspect(&greet, 0o755, 1001, 1000).unwrap_err();
+        assert!(
+            matches!(error, PermissionError::ForeignOwner { uid: 1001, .. }),
+            "{}",
+            error
+        );
+        assert!(matches!(
+            inspect(&greet, 0o2755, 1000, 1000),
+            Err(PermissionError::Setuid { .. })
+        ));
+    }
+}
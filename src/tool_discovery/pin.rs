@@ -0,0 +1,268 @@
+//! Pinning executables to their SHA-256 digests.
+//!
+//! A definition may pin the executable it describes with `sha256`, or a
+//! lockfile may pin every executable of a deployment. A lockfile is what
+//! `sha256sum` writes, one executable per line, with paths relative to the
+//! lockfile's directory:
+//!
+//! ```text
+//! 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  tools/greet
+//! 60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752 *tools/deploy.exe
+//! ```
+//!
+//! A tool whose executable does not match its pin is not served, and the
+//! executor checks the digest again before every run, so a binary replaced
+//! while the server runs is refused too. With a lockfile, executables it
+//! does not list are refused as well, so that new files appearing in a
+//! tools directory are not served unvetted.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::source::sha256_file;
+use super::ToolDefinition;
+
+/// The digests a lockfile pins executables to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    path: PathBuf,
+    /// Digests (lowercase hex) by the canonical path of their executable
+    digests: HashMap<PathBuf, String>,
+}
+
+/// An executable that does not match its pin, or has none.
+#[derive(Debug)]
+pub enum PinError {
+    /// A file could not be read.
+    Io { path: PathBuf, source: io::Error },
+
+    /// A lockfile line is not a digest and a path.
+    InvalidLockfile {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    /// The executable's digest differs from the one it is pinned to.
+    Mismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    /// The lockfile does not list the executable.
+    Unpinned { path: PathBuf, lockfile: PathBuf },
+}
+
+impl fmt::Display for PinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinError::Io { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            PinError::InvalidLockfile {
+                path,
+                line,
+                message,
+            } => write!(
+                f,
+                "invalid lockfile {} (line {}): {}",
+                path.display(),
+                line,
+                message
+            ),
+            PinError::Mismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} does not match its pinned digest: expected sha256 {}, got {}",
+                path.display(),
+                expected,
+                actual
+            ),
+            PinError::Unpinned { path, lockfile } => write!(
+                f,
+                "{} is not pinned in the lockfile {}",
+                path.display(),
+                lockfile.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PinError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PinError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Lockfile {
+    /// Load the lockfile at `path`.
+    pub fn load(path: &Path) -> Result<Self, PinError> {
+        let text = fs::read_to_string(path).map_err(|source| PinError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(path, &text)
+    }
+
+    /// Parse the lockfile `text`, read from `path`.
+    pub fn parse(path: &Path, text: &str) -> Result<Self, PinError> {
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut digests = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let invalid = |message: &str| PinError::InvalidLockfile {
+                path: path.to_path_buf(),
+                line: index + 1,
+                message: message.to_string(),
+            };
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (digest, file) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid("expected a digest and a path"))?;
+            if digest.len() != 64 || !digest.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                return Err(invalid("expected a SHA-256 digest of 64 hex digits"));
+            }
+            // `sha256sum` marks files read in binary mode with `*`.
+            let file = file.strip_prefix([' ', '*']).unwrap_or(file);
+            if file.is_empty() {
+                return Err(invalid("expected a path after the digest"));
+            }
+            digests.insert(canonical(&base.join(file)), digest.to_ascii_lowercase());
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            digests,
+        })
+    }
+
+    /// The digest the lockfile pins `executable` to.
+    pub fn digest(&self, executable: &Path) -> Option<&str> {
+        self.digests.get(&canonical(executable)).map(String::as_str)
+    }
+
+    /// The path the lockfile was read from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Check `executable` against its pin: `definition`'s `sha256`, or else
+/// the digest `lockfile` lists for it. A pin from the lockfile is copied
+/// into the definition, for the executor to check again.
+pub fn check(
+    executable: &Path,
+    definition: &mut ToolDefinition,
+    lockfile: Option<&Lockfile>,
+) -> Result<(), PinError> {
+    if definition.sha256.is_none() {
+        definition.sha256 = lockfile
+            .and_then(|lockfile| lockfile.digest(executable))
+            .map(str::to_string);
+    }
+    match (&definition.sha256, lockfile) {
+        (Some(expected), _) => verify(executable, expected),
+        (None, Some(lockfile)) => Err(PinError::Unpinned {
+            path: executable.to_path_buf(),
+            lockfile: lockfile.path.clone(),
+        }),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Check that the file at `path` has the SHA-256 digest `expected` (hex).
+pub fn verify(path: &Path, expected: &str) -> Result<(), PinError> {
+    let actual = sha256_file(path).map_err(|source| PinError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(PinError::Mismatch {
+            path: path.to_path_buf(),
+            expected: expected.to_ascii_lowercase(),
+            actual,
+        })
+    }
+}
+
+/// `path` with symlinks resolved, so that an executable matches its lockfile
+/// line however either names it; paths that do not exist are kept as they
+/// are.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check() {
+        let dir = TempDir::new().unwrap();
+        let greet = dir.path().join("tools").join("greet");
+        fs::create_dir(greet.parent().unwrap()).unwrap();
+        fs::write(&greet, "#!/bin/sh\necho hello\n").unwrap();
+        let digest = sha256_file(&greet).unwrap();
+        let definition = || {
+            ToolDefinition::from_yaml(
+                "name: greet\ndescription: Greets\ninput: { template: '', schema: { type: object } }\noutput: { template: '', schema: { type: object } }\n",
+            )
+            .unwrap()
+        };
+
+        assert!(check(&greet, &mut definition(), None).is_ok());
+        let mut pinned = definition();
+        pinned.sha256 = Some(digest.to_uppercase());
+        assert!(check(&greet, &mut pinned, None).is_ok());
+        pinned.sha256 = Some("0".repeat(64));
+        let error = check(&greet, &mut pinned, None).unwrap_err();
+        assert!(matches!(error, PinError::Mismatch { .. }), "{}", error);
+
+        let path = dir.path().join("mcp-serve.lock");
+        let text = format!(
+            "# pinned\n{}  tools/greet\n{} *tools/other\n",
+            digest,
+            "a".repeat(64)
+        );
+        let lockfile = Lockfile::parse(&path, &text).unwrap();
+        assert_eq!(lockfile.digest(&greet), Some(digest.as_str()));
+        let mut locked = definition();
+        assert!(check(&greet, &mut locked, Some(&lockfile)).is_ok());
+        assert_eq!(locked.sha256.as_deref(), Some(digest.as_str()));
+
+        fs::write(&greet, "#!/bin/sh\nrm -rf ~\n").unwrap();
+        let error = check(&greet, &mut definition(), Some(&lockfile)).unwrap_err();
+        assert!(matches!(error, PinError::Mismatch { .. }), "{}", error);
+        let error = verify(&greet, &digest).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("does not match its pinned digest"));
+
+        let lockfile = Lockfile::parse(&path, "").unwrap();
+        let error = check(&greet, &mut definition(), Some(&lockfile)).unwrap_err();
+        assert!(matches!(error, PinError::Unpinned { .. }), "{}", error);
+
+        let error = Lockfile::parse(&path, "\nabc  tools/greet\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "invalid lockfile {} (line 2): expected a SHA-256 digest of 64 hex digits",
+                path.display()
+            )
+        );
+    }
+}
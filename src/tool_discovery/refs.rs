@@ -0,0 +1,282 @@
+//! Resolution of `$ref`s to schema files shared between tool definitions.
+//!
+//! A schema may reference a JSON, YAML, or TOML file by a path relative to
+//! the file the reference appears in, optionally followed by a JSON pointer:
+//!
+//! ```yaml
+//! input:
+//!   schema:
+//!     type: object
+//!     properties:
+//!       ticket: { $ref: ./schemas/ticket.json }
+//!       owner: { $ref: "./schemas/common.yaml#/$defs/user" }
+//! ```
+//!
+//! Each such reference is replaced by the referenced schema when the
+//! definition is loaded; keywords next to the `$ref` (such as a
+//! `description`) are kept and take precedence. References within the
+//! document (`#/...`) and to URLs are left for the JSON Schema validator.
+//! Note that `#/...` references inside a referenced file are not rewritten,
+//! so shared files should be self-contained.
+//!
+//! With trusted keys configured, [`resolve_signed_refs`] refuses any
+//! referenced file that is not signed by one of them, as the schemas it
+//! holds are part of what the model is shown.
+
+use minisign_verify::PublicKey;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::signature::{verify_file, SignatureError};
+
+/// Replace every local-file `$ref` in `schema` with the schema it refers to.
+///
+/// `base_dir` is the directory relative paths are resolved against: that of
+/// the file `schema` was read from.
+pub fn resolve_refs(schema: &mut Value, base_dir: &Path) -> Result<(), RefError> {
+    Resolver::default().resolve(schema, base_dir)
+}
+
+/// Like [`resolve_refs`], but require every file referenced to be signed by
+/// one of `keys` (see [`signature`](super::signature)).
+pub fn resolve_signed_refs(
+    schema: &mut Value,
+    base_dir: &Path,
+    keys: &[PublicKey],
+) -> Result<(), RefError> {
+    Resolver {
+        keys: Some(keys),
+        ..Resolver::default()
+    }
+    .resolve(schema, base_dir)
+}
+
+/// Whether a `$ref` points at a file rather than into the current document
+/// or at a URL.
+fn is_file_ref(reference: &str) -> bool {
+    !reference.starts_with('#') && !reference.contains("://")
+}
+
+#[derive(Default)]
+struct Resolver<'a> {
+    /// Parsed referenced files
+    files: HashMap<PathBuf, Value>,
+
+    /// References being resolved, to detect cycles
+    stack: Vec<(PathBuf, String)>,
+
+    /// Keys one of which must have signed each file, if any must
+    keys: Option<&'a [PublicKey]>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, schema: &mut Value, base_dir: &Path) -> Result<(), RefError> {
+        match schema {
+            Value::Object(object) => {
+                let reference = object
+                    .get("$ref")
+                    .and_then(Value::as_str)
+                    .filter(|reference| is_file_ref(reference))
+                    .map(str::to_string);
+                if let Some(reference) = reference {
+                    object.remove("$ref");
+                    let mut resolved = self.load(&reference, base_dir)?;
+                    if let Value::Object(resolved) = &mut resolved {
+                        resolved.append(object);
+                    }
+                    *schema = resolved;
+                    return Ok(());
+                }
+                for value in object.values_mut() {
+                    self.resolve(value, base_dir)?;
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.resolve(value, base_dir)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Load the schema `reference` points at, with its own references
+    /// resolved.
+    fn load(&mut self, reference: &str, base_dir: &Path) -> Result<Value, RefError> {
+        let (file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        let path = base_dir.join(file);
+        let key = (
+            path.canonicalize().unwrap_or_else(|_| path.clone()),
+            pointer.to_string(),
+        );
+        if self.stack.contains(&key) {
+            return Err(RefError::Cycle { path });
+        }
+
+        if !self.files.contains_key(&key.0) {
+            if let Some(keys) = self.keys {
+                verify_file(&path, keys).map_err(|source| RefError::Unsigned { source })?;
+            }
+            let document = parse_file(&path)?;
+            self.files.insert(key.0.clone(), document);
+        }
+        let mut schema = self.files[&key.0]
+            .pointer(pointer)
+            .cloned()
+            .ok_or_else(|| RefError::MissingPointer {
+                path: path.clone(),
+                pointer: pointer.to_string(),
+            })?;
+
+        self.stack.push(key);
+        let result = self.resolve(&mut schema, path.parent().unwrap_or(Path::new("")));
+        self.stack.pop();
+        result.map(|()| schema)
+    }
+}
+
+/// Read a schema file, choosing the parser from its extension (JSON unless
+/// it is `.yaml`, `.yml`, or `.toml`).
+fn parse_file(path: &Path) -> Result<Value, RefError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| RefError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let parsed = match extension.as_deref() {
+        Some("yaml" | "yml") => serde_yaml_ng::from_str(&contents).map_err(|e| e.to_string()),
+        Some("toml") => toml::from_str(&contents).map_err(|e| e.to_string()),
+        _ => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+    };
+    parsed.map_err(|message| RefError::Parse {
+        path: path.to_path_buf(),
+        message,
+    })
+}
+
+/// Errors that can occur while resolving schema references.
+#[derive(Debug)]
+pub enum RefError {
+    /// A referenced file could not be read.
+    Io { path: PathBuf, source: io::Error },
+
+    /// A referenced file is not valid JSON, YAML, or TOML.
+    Parse { path: PathBuf, message: String },
+
+    /// A referenced file has nothing at the reference's JSON pointer.
+    MissingPointer { path: PathBuf, pointer: String },
+
+    /// A file refers back to itself through a chain of references.
+    Cycle { path: PathBuf },
+
+    /// A referenced file is not signed by a trusted key.
+    Unsigned { source: SignatureError },
+}
+
+impl fmt::Display for RefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefError::Io { path, source } => {
+                write!(f, "failed to read schema {}: {}", path.display(), source)
+            }
+            RefError::Parse { path, message } => {
+                write!(f, "invalid schema {}: {}", path.display(), message)
+            }
+            RefError::MissingPointer { path, pointer } => {
+                write!(f, "schema {} has nothing at #{}", path.display(), pointer)
+            }
+            RefError::Cycle { path } => {
+                write!(f, "schema {} refers to itself", path.display())
+            }
+            RefError::Unsigned { source } => write!(f, "{}", source),
+        }
+    }
+}
+
+impl std::error::Error for RefError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RefError::Io { source, .. } => Some(source),
+            RefError::Unsigned { source } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_file_refs() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("schemas")).unwrap();
+        fs::write(
+            dir.path().join("schemas/ticket.json"),
+            r#"{"type": "object", "properties": {"owner": {"$ref": "common.yaml#/$defs/user"}}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("schemas/common.yaml"),
+            "$defs:\n  user: { type: string }\n",
+        )
+        .unwrap();
+
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "ticket": {"$ref": "./schemas/ticket.json", "description": "The ticket"},
+                "local": {"$ref": "#/$defs/local"}
+            }
+        });
+        resolve_refs(&mut schema, dir.path()).unwrap();
+
+        assert_eq!(
+            schema["properties"]["ticket"],
+            json!({
+                "type": "object",
+                "description": "The ticket",
+                "properties": {"owner": {"type": "string"}}
+            })
+        );
+        assert_eq!(
+            schema["properties"]["local"],
+            json!({"$ref": "#/$defs/local"})
+        );
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("loop.json"), r#"{"$ref": "loop.json"}"#).unwrap();
+        fs::write(dir.path().join("defs.json"), r#"{"a": {}}"#).unwrap();
+
+        let mut schema = json!({"$ref": "missing.json"});
+        assert!(matches!(
+            resolve_refs(&mut schema, dir.path()),
+            Err(RefError::Io { .. })
+        ));
+
+        let mut schema = json!({"$ref": "defs.json#/b"});
+        assert!(matches!(
+            resolve_refs(&mut schema, dir.path()),
+            Err(RefError::MissingPointer { .. })
+        ));
+
+        let mut schema = json!({"$ref": "loop.json"});
+        assert!(matches!(
+            resolve_refs(&mut schema, dir.path()),
+            Err(RefError::Cycle { .. })
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+//! Statistics collected while scanning a tools directory.
+//!
+//! A [`ScanReport`] answers "why wasn't my tool found?": alongside the counts
+//! of what was discovered, it records every entry the scanner passed over and
+//! the reason it did so.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::{DiscoveredTool, MetadataSource};
+
+/// Why the scanner passed over a directory entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file is not executable (and was not detected by shebang).
+    NotExecutable,
+
+    /// The file is a definition file (sidecar or manifest), not a tool.
+    MetadataFile,
+
+    /// The entry is a symlink to a directory, and symlinks are not followed.
+    SymlinkedDirectory,
+
+    /// The entry is a directory beyond the configured depth limit.
+    DepthLimit,
+
+    /// The entry is a dotfile or dot-directory, and hidden entries are not
+    /// scanned.
+    Hidden,
+
+    /// The entry is neither a regular file nor a directory (a broken symlink,
+    /// socket, device, ...).
+    NotARegularFile,
+
+    /// The executable was found directly, but a manifest declares it too.
+    DeclaredInManifest,
+
+    /// The entry is the same file (and definition) as another discovered
+    /// tool, reached through a hard link, symlink, or bind mount.
+    DuplicateFile,
+
+    /// The entry matches an `ignore` pattern of a directory's
+    /// `.mcp-serve.yaml`.
+    Ignored,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            SkipReason::NotExecutable => "not executable",
+            SkipReason::MetadataFile => "definition file",
+            SkipReason::SymlinkedDirectory => "symlinked directory (not followed)",
+            SkipReason::DepthLimit => "beyond maximum depth",
+            SkipReason::Hidden => "hidden",
+            SkipReason::NotARegularFile => "not a regular file",
+            SkipReason::DeclaredInManifest => "declared by a manifest instead",
+            SkipReason::DuplicateFile => "same file as another tool",
+            SkipReason::Ignored => "ignored by .mcp-serve.yaml",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// A directory entry the scanner did not turn into a tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    /// Path of the skipped entry
+    pub path: PathBuf,
+
+    /// Why it was skipped
+    pub reason: SkipReason,
+}
+
+/// Summary statistics for one or more scans.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanReport {
+    /// Number of directories read
+    pub directories_scanned: usize,
+
+    /// The directories read, in scan order
+    pub scanned_directories: Vec<PathBuf>,
+
+    /// Number of non-directory entries examined
+    pub files_examined: usize,
+
+    /// Number of tools discovered (including manifest-declared tools)
+    pub executables_found: usize,
+
+    /// Number of tools whose definition comes from a sidecar file
+    pub sidecars_found: usize,
+
+    /// Number of tools expected to carry an embedded definition
+    pub embedded_candidates: usize,
+
+    /// Number of tools declared in `tools.yaml` manifests
+    pub manifest_tools: usize,
+
+    /// Entries that were examined but not turned into tools
+    pub skipped: Vec<SkippedEntry>,
+
+    /// Wall-clock time spent scanning
+    pub elapsed: Duration,
+}
+
+impl ScanReport {
+    pub(crate) fn skip(&mut self, path: PathBuf, reason: SkipReason) {
+        self.skipped.push(SkippedEntry { path, reason });
+    }
+
+    /// Tally a set of discovered tools into the report.
+    pub fn count_tools(&mut self, tools: &[DiscoveredTool]) {
+        self.executables_found += tools.len();
+        for tool in tools {
+            match tool.metadata_source {
+                MetadataSource::Sidecar(_) => self.sidecars_found += 1,
+                MetadataSource::Embedded => self.embedded_candidates += 1,
+                MetadataSource::Manifest { .. } => self.manifest_tools += 1,
+            }
+        }
+    }
+
+    /// Combine the statistics of another scan into this report.
+    pub fn merge(&mut self, other: ScanReport) {
+        self.directories_scanned += other.directories_scanned;
+        self.scanned_directories.extend(other.scanned_directories);
+        self.files_examined += other.files_examined;
+        self.executables_found += other.executables_found;
+        self.sidecars_found += other.sidecars_found;
+        self.embedded_candidates += other.embedded_candidates;
+        self.manifest_tools += other.manifest_tools;
+        self.skipped.extend(other.skipped);
+        self.elapsed += other.elapsed;
+    }
+}
+
+impl fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Found {} tool(s) ({} sidecar, {} embedded, {} manifest) \
+             after examining {} file(s) in {} director(ies); skipped {} in {:.1?}",
+            self.executables_found,
+            self.sidecars_found,
+            self.embedded_candidates,
+            self.manifest_tools,
+            self.files_examined,
+            self.directories_scanned,
+            self.skipped.len(),
+            self.elapsed
+        )
+    }
+}
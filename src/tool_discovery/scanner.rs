@@ -0,0 +1,1892 @@
+//! Directory scanning for executable tools.
+//!
+//! The scanner walks a tools directory, identifies executable files, and
+//! pairs each one with the source its metadata should be read from: either a
+//! sidecar file next to the executable, or a definition embedded in the
+//! executable itself.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::directory_config::{DirectoryConfig, DirectoryScope, DIRECTORY_CONFIG_FILE};
+use super::docs::{find_docs, DOCS_EXTENSION, TOOL_DIRECTORY_DOCS};
+use super::execution::ExecutionConfig;
+use super::manifest::{Manifest, ManifestError, MANIFEST_FILE_NAME};
+use super::report::{ScanReport, SkipReason};
+use super::source::{SourceCache, SourceError, ToolSource};
+use super::SidecarFormat;
+
+/// Where a discovered tool's definition should be loaded from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum MetadataSource {
+    /// A sidecar definition file (YAML, JSON, or TOML) placed alongside the
+    /// executable.
+    Sidecar(PathBuf),
+
+    /// No sidecar was found; the definition may be embedded in the
+    /// executable's leading comment block.
+    Embedded,
+
+    /// The tool is declared by an entry in a `tools.yaml` manifest.
+    Manifest {
+        /// Path to the manifest file
+        path: PathBuf,
+        /// Index of the tool's entry within the manifest
+        index: usize,
+    },
+}
+
+/// An executable found while scanning a tools directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscoveredTool {
+    /// Path to the executable (the scanned directory joined with `relative_path`)
+    pub path: PathBuf,
+
+    /// Path to the executable relative to the scanned directory
+    pub relative_path: PathBuf,
+
+    /// Where the tool's definition should be read from
+    pub metadata_source: MetadataSource,
+
+    /// Namespace prefixed to the tool's name, inherited from its [`ToolRoot`]
+    pub namespace: Option<String>,
+
+    /// Directory the tool runs in. Set for tools packaged as a directory
+    /// (their own directory, holding any assets they need); `None` runs the
+    /// tool in the server's working directory.
+    pub working_dir: Option<PathBuf>,
+
+    /// Markdown documentation for the tool (see [`docs`](super::docs))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<PathBuf>,
+
+    /// Execution settings the tool's directory sets, which its definition
+    /// may override (see [`directory_config`](super::directory_config))
+    #[serde(default, skip_serializing_if = "ExecutionConfig::is_default")]
+    pub defaults: Box<ExecutionConfig>,
+
+    /// The [`DIRECTORY_CONFIG_FILE`]s that set `namespace` and `defaults`,
+    /// outermost first
+    #[serde(default, skip_serializing_if = "<[PathBuf]>::is_empty")]
+    pub directory_configs: Box<[PathBuf]>,
+}
+
+/// Name of the definition file that marks a directory as a single tool.
+///
+/// A tool directory bundles an entrypoint with whatever assets it needs:
+///
+/// ```text
+/// deploy/
+///   tool.yaml
+///   run.sh
+///   templates/
+/// ```
+pub const TOOL_DIRECTORY_DEFINITION: &str = "tool.yaml";
+
+/// Entrypoints looked for in a tool directory, in order of preference.
+pub const TOOL_DIRECTORY_ENTRYPOINTS: &[&str] = &["run", "run.sh", "main.py"];
+
+/// Separator between a namespace and a tool name (`ops/restart-service`).
+pub const NAMESPACE_SEPARATOR: &str = "/";
+
+impl DiscoveredTool {
+    /// The name a tool declaring `name` is exposed under: `name` prefixed with
+    /// this tool's namespace, if it has one.
+    pub fn qualified_name(&self, name: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// The file the tool's definition is read from: its sidecar or
+    /// manifest, or the executable itself for embedded definitions. Paths
+    /// in the definition are relative to this file's directory.
+    pub fn definition_file(&self) -> &Path {
+        match &self.metadata_source {
+            MetadataSource::Sidecar(path) | MetadataSource::Manifest { path, .. } => path,
+            MetadataSource::Embedded => &self.path,
+        }
+    }
+}
+
+/// A directory of tools to serve, optionally namespaced.
+///
+/// When several directories are served together, namespacing their tools
+/// (`ops/restart-service`, `dev/restart-service`) avoids name collisions and
+/// tells the model where each tool came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolRoot {
+    /// The directory to scan
+    pub path: PathBuf,
+
+    /// Namespace prefixed to the names of tools found in this directory
+    pub namespace: Option<String>,
+}
+
+impl ToolRoot {
+    /// A root without a namespace.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            namespace: None,
+        }
+    }
+
+    /// Set the namespace for tools found in this root.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Use the directory's own name as its namespace, unless one was already
+    /// set explicitly. For remote sources, the repository or archive name is
+    /// used.
+    pub fn with_derived_namespace(mut self) -> Self {
+        if self.namespace.is_none() {
+            self.namespace = self.source().name();
+        }
+        self
+    }
+
+    /// Where the root's tools come from: its path may name a git repository
+    /// or tarball URL instead of a local directory.
+    pub fn source(&self) -> ToolSource {
+        match self.path.to_str() {
+            Some(spec) => ToolSource::parse(spec),
+            None => ToolSource::Local(self.path.clone()),
+        }
+    }
+
+    /// Fetch a remote root into `cache`, pointing the root at the local copy.
+    /// Local roots are returned unchanged.
+    pub fn fetch(mut self, cache: &SourceCache) -> Result<Self, SourceError> {
+        self.path = cache.fetch(&self.source())?;
+        Ok(self)
+    }
+}
+
+impl std::str::FromStr for ToolRoot {
+    type Err = std::convert::Infallible;
+
+    /// Parse `DIR` or `NAMESPACE=DIR`.
+    ///
+    /// The `NAMESPACE=` prefix is only recognized when it consists of ASCII
+    /// letters, digits, `-`, `_`, or `.`, so ordinary paths containing `=`
+    /// are still accepted as-is.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        if let Some((namespace, path)) = spec.split_once('=') {
+            let is_namespace = !namespace.is_empty()
+                && namespace
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+            if is_namespace && !path.is_empty() {
+                return Ok(ToolRoot::new(path).with_namespace(namespace));
+            }
+        }
+        Ok(ToolRoot::new(spec))
+    }
+}
+
+/// Errors that can occur while scanning a directory.
+#[derive(Debug)]
+pub enum ScanError {
+    /// An I/O error occurred while reading a directory or file.
+    IoError { path: PathBuf, source: io::Error },
+
+    /// The scanner was not permitted to read a directory.
+    PermissionDenied { path: PathBuf },
+
+    /// A `tools.yaml` manifest could not be loaded.
+    InvalidManifest(ManifestError),
+
+    /// A tool name given for `$PATH` resolution matched no executable.
+    CommandNotFound { name: String },
+
+    /// A tool's sidecar definition exists but could not be opened.
+    UnreadableSidecar {
+        path: PathBuf,
+        tool: PathBuf,
+        source: io::Error,
+    },
+
+    /// An executable's path is not valid UTF-8, so it cannot be named or
+    /// referenced in templates.
+    InvalidUtf8Path { path: PathBuf },
+
+    /// A symlinked directory points back at a directory that is already
+    /// being scanned.
+    SymlinkCycle { path: PathBuf, target: PathBuf },
+
+    /// A definition file is larger than the configured limit.
+    FileTooLarge {
+        path: PathBuf,
+        size: u64,
+        limit: u64,
+    },
+
+    /// A tool directory has a definition but none of the recognized
+    /// entrypoints.
+    MissingEntrypoint { path: PathBuf },
+
+    /// A directory's [`DIRECTORY_CONFIG_FILE`] could not be parsed.
+    InvalidDirectoryConfig {
+        path: PathBuf,
+        source: serde_yaml_ng::Error,
+    },
+}
+
+impl ScanError {
+    fn from_io(path: &Path, source: io::Error) -> Self {
+        if source.kind() == io::ErrorKind::PermissionDenied {
+            ScanError::PermissionDenied {
+                path: path.to_path_buf(),
+            }
+        } else {
+            ScanError::IoError {
+                path: path.to_path_buf(),
+                source,
+            }
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::IoError { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            ScanError::PermissionDenied { path } => {
+                write!(f, "permission denied: {}", path.display())
+            }
+            ScanError::InvalidManifest(e) => write!(f, "{}", e),
+            ScanError::CommandNotFound { name } => {
+                write!(f, "no executable named `{}` found on PATH", name)
+            }
+            ScanError::UnreadableSidecar { path, tool, source } => write!(
+                f,
+                "cannot read sidecar {} for {}: {}",
+                path.display(),
+                tool.display(),
+                source
+            ),
+            ScanError::InvalidUtf8Path { path } => write!(
+                f,
+                "tool path is not valid UTF-8 (rename it to use it as a tool): {}",
+                path.to_string_lossy()
+            ),
+            ScanError::SymlinkCycle { path, target } => write!(
+                f,
+                "symlink cycle: {} points back to {} (remove the link or disable following symlinks)",
+                path.display(),
+                target.display()
+            ),
+            ScanError::FileTooLarge { path, size, limit } => write!(
+                f,
+                "definition file {} is {} bytes, larger than the {} byte limit",
+                path.display(),
+                size,
+                limit
+            ),
+            ScanError::MissingEntrypoint { path } => write!(
+                f,
+                "tool directory {} has a {} but no entrypoint (expected one of: {})",
+                path.display(),
+                TOOL_DIRECTORY_DEFINITION,
+                TOOL_DIRECTORY_ENTRYPOINTS.join(", ")
+            ),
+            ScanError::InvalidDirectoryConfig { path, source } => {
+                write!(f, "invalid directory config {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScanError::IoError { source, .. } => Some(source),
+            ScanError::PermissionDenied { .. } => None,
+            ScanError::InvalidManifest(e) => Some(e),
+            ScanError::CommandNotFound { .. } => None,
+            ScanError::UnreadableSidecar { source, .. } => Some(source),
+            ScanError::InvalidUtf8Path { .. } => None,
+            ScanError::SymlinkCycle { .. } => None,
+            ScanError::FileTooLarge { .. } => None,
+            ScanError::MissingEntrypoint { .. } => None,
+            ScanError::InvalidDirectoryConfig { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Default limit on the size of sidecar and manifest files (1 MiB).
+pub const DEFAULT_MAX_DEFINITION_SIZE: u64 = 1024 * 1024;
+
+/// Options controlling how directories are scanned.
+///
+/// Usually assembled through [`DirectoryScanner::builder`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScannerConfig {
+    /// Descend into subdirectories (default: `true`)
+    pub recursive: bool,
+
+    /// Descend into symlinked directories (default: `false`). Symlinked files
+    /// are always followed.
+    pub follow_symlinks: bool,
+
+    /// Maximum number of directory levels to descend below the scanned
+    /// directory; `Some(0)` scans only the directory itself (default: no
+    /// limit)
+    pub max_depth: Option<usize>,
+
+    /// Treat files starting with a `#!` shebang as executables even without
+    /// the executable permission (default: `false`)
+    pub detect_shebangs: bool,
+
+    /// Extra file extensions treated as executable on Windows, on top of
+    /// `PATHEXT` (default: none)
+    pub executable_extensions: Vec<String>,
+
+    /// Scan dotfiles and dot-directories such as `.git` (default: `false`)
+    pub include_hidden: bool,
+
+    /// Largest sidecar or manifest file the scanner accepts, in bytes
+    /// (default: [`DEFAULT_MAX_DEFINITION_SIZE`])
+    pub max_definition_size: Option<u64>,
+
+    /// Execution settings every tool found inherits, under those of its
+    /// directories' `.mcp-serve.yaml` files (default: none)
+    #[serde(default, skip_serializing_if = "ExecutionConfig::is_default")]
+    pub defaults: ExecutionConfig,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            recursive: true,
+            follow_symlinks: false,
+            max_depth: None,
+            detect_shebangs: false,
+            executable_extensions: Vec::new(),
+            include_hidden: false,
+            max_definition_size: Some(DEFAULT_MAX_DEFINITION_SIZE),
+            defaults: ExecutionConfig::default(),
+        }
+    }
+}
+
+/// Builder for a [`DirectoryScanner`].
+///
+/// # Examples
+///
+/// ```
+/// use mcp_serve::tool_discovery::DirectoryScanner;
+///
+/// let scanner = DirectoryScanner::builder()
+///     .recursive(true)
+///     .follow_symlinks(false)
+///     .max_depth(4)
+///     .detect_shebangs(true)
+///     .build();
+/// assert_eq!(scanner.config().max_depth, Some(4));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScannerBuilder {
+    config: ScannerConfig,
+}
+
+impl ScannerBuilder {
+    /// Whether to descend into subdirectories.
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.config.recursive = recursive;
+        self
+    }
+
+    /// Whether to descend into symlinked directories.
+    ///
+    /// Symlink cycles are detected and skipped.
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.config.follow_symlinks = follow;
+        self
+    }
+
+    /// Limit how many directory levels below the scanned directory are
+    /// descended into.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.config.max_depth = Some(depth);
+        self
+    }
+
+    /// Also treat files starting with a `#!` shebang as executables, even when
+    /// they lack the executable permission.
+    ///
+    /// Scripts checked out from source control (or copied from archives)
+    /// frequently lose their executable bit; this lets them be discovered
+    /// anyway.
+    pub fn detect_shebangs(mut self, enabled: bool) -> Self {
+        self.config.detect_shebangs = enabled;
+        self
+    }
+
+    /// Additional file extensions (e.g. `"py"` or `".rb"`) to treat as
+    /// executable on Windows, on top of those listed in `PATHEXT`.
+    ///
+    /// Has no effect on Unix-like platforms, where the executable permission
+    /// bit is used instead.
+    pub fn executable_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.executable_extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether to scan dotfiles and dot-directories.
+    ///
+    /// Off by default, so scanning a project root does not surface VCS hooks
+    /// and other internals as tools.
+    pub fn include_hidden(mut self, include: bool) -> Self {
+        self.config.include_hidden = include;
+        self
+    }
+
+    /// Limit the size of sidecar and manifest files; larger ones fail the
+    /// scan with [`ScanError::FileTooLarge`]. `None` removes the limit.
+    pub fn max_definition_size(mut self, limit: Option<u64>) -> Self {
+        self.config.max_definition_size = limit;
+        self
+    }
+
+    /// Execution settings for every tool found, such as the variables of
+    /// the server's environment tools see. Directory settings and the tools'
+    /// own definitions take precedence.
+    pub fn defaults(mut self, defaults: ExecutionConfig) -> Self {
+        self.config.defaults = defaults;
+        self
+    }
+
+    /// Build the scanner.
+    pub fn build(self) -> DirectoryScanner {
+        DirectoryScanner::with_config(self.config)
+    }
+}
+
+/// Scans directories for executable tools.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mcp_serve::tool_discovery::DirectoryScanner;
+/// use std::path::Path;
+///
+/// let scanner = DirectoryScanner::builder().detect_shebangs(true).build();
+/// for tool in scanner.scan_directory(Path::new("./tools")).unwrap() {
+///     println!("{}", tool.relative_path.display());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryScanner {
+    config: ScannerConfig,
+}
+
+impl DirectoryScanner {
+    /// Create a scanner with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start building a scanner with non-default settings.
+    pub fn builder() -> ScannerBuilder {
+        ScannerBuilder::default()
+    }
+
+    /// Create a scanner from a complete configuration.
+    pub fn with_config(config: ScannerConfig) -> Self {
+        Self { config }
+    }
+
+    /// The scanner's configuration.
+    pub fn config(&self) -> &ScannerConfig {
+        &self.config
+    }
+
+    /// Also treat files starting with a `#!` shebang as executables.
+    #[deprecated(note = "use `DirectoryScanner::builder().detect_shebangs(..)`")]
+    pub fn with_shebang_detection(mut self, enabled: bool) -> Self {
+        self.config.detect_shebangs = enabled;
+        self
+    }
+
+    /// Additional file extensions to treat as executable on Windows.
+    #[deprecated(note = "use `DirectoryScanner::builder().executable_extensions(..)`")]
+    pub fn with_executable_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.executable_extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Scan `dir` for executable tools, descending into subdirectories as
+    /// configured.
+    ///
+    /// Symlinked files are always followed; symlinked directories only when
+    /// [`ScannerConfig::follow_symlinks`] is set.
+    ///
+    /// Tools declared in `tools.yaml` manifests are merged with the
+    /// executables found on disk; when a manifest declares an executable that
+    /// was also discovered directly, the manifest entry wins.
+    ///
+    /// Results are sorted by relative path, independent of the order in which
+    /// the filesystem enumerates entries.
+    pub fn scan_directory(&self, dir: &Path) -> Result<Vec<DiscoveredTool>, ScanError> {
+        self.scan_directory_with_report(dir).map(|(tools, _)| tools)
+    }
+
+    /// Like [`scan_directory`](Self::scan_directory), but also return a
+    /// [`ScanReport`] describing what was examined and skipped.
+    pub fn scan_directory_with_report(
+        &self,
+        dir: &Path,
+    ) -> Result<(Vec<DiscoveredTool>, ScanReport), ScanError> {
+        let started = Instant::now();
+        let mut state = ScanState::default();
+        state.ancestors.extend(dir.canonicalize().ok());
+        let scope = DirectoryScope::new(self.config.defaults.clone());
+        self.scan_into(dir, dir, 0, &scope, &mut state)?;
+
+        let ScanState {
+            mut tools,
+            manifest_tools,
+            mut report,
+            ..
+        } = state;
+
+        tools.retain(|tool| {
+            let declared = manifest_tools
+                .iter()
+                .any(|declared: &DiscoveredTool| declared.path == tool.path);
+            if declared {
+                report.skip(tool.path.clone(), SkipReason::DeclaredInManifest);
+            }
+            !declared
+        });
+        tools.extend(manifest_tools);
+
+        // Directory enumeration order is filesystem-dependent; sort so results
+        // are reproducible across runs and platforms.
+        tools.sort_by(|a, b| {
+            a.relative_path
+                .cmp(&b.relative_path)
+                .then_with(|| a.metadata_source.cmp(&b.metadata_source))
+        });
+        deduplicate(&mut tools, &mut report);
+        report.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+
+        report.count_tools(&tools);
+        report.elapsed = started.elapsed();
+        Ok((tools, report))
+    }
+
+    fn scan_into(
+        &self,
+        root: &Path,
+        dir: &Path,
+        depth: usize,
+        scope: &DirectoryScope,
+        state: &mut ScanState,
+    ) -> Result<(), ScanError> {
+        let entries = fs::read_dir(dir).map_err(|e| ScanError::from_io(dir, e))?;
+        state.report.directories_scanned += 1;
+        state.report.scanned_directories.push(dir.to_path_buf());
+        let config_path = dir.join(DIRECTORY_CONFIG_FILE);
+        let entered;
+        let scope = match self.directory_config(&config_path)? {
+            Some(config) => {
+                entered = scope.enter(dir, config);
+                &entered
+            }
+            None => scope,
+        };
+
+        for entry in entries {
+            let entry = entry.map_err(|e| ScanError::from_io(dir, e))?;
+            let path = entry.path();
+            if scope.ignores(&path) {
+                state.report.skip(path, SkipReason::Ignored);
+                continue;
+            }
+            if !self.config.include_hidden && is_hidden(&path) {
+                state.report.skip(path, SkipReason::Hidden);
+                continue;
+            }
+            let file_type = entry
+                .file_type()
+                .map_err(|e| ScanError::from_io(&path, e))?;
+
+            // Resolves symlinks, so a link to a regular file is treated as one.
+            let metadata = fs::metadata(&path).ok();
+
+            if file_type.is_dir() || metadata.as_ref().is_some_and(|m| m.is_dir()) {
+                if file_type.is_symlink() && !self.config.follow_symlinks {
+                    state.report.files_examined += 1;
+                    state.report.skip(path, SkipReason::SymlinkedDirectory);
+                } else if path.join(TOOL_DIRECTORY_DEFINITION).is_file() {
+                    let mut tool = self.directory_tool(root, &path)?;
+                    scope.apply(&mut tool);
+                    state.tools.push(tool);
+                } else if !self.may_descend(depth + 1) {
+                    state.report.skip(path, SkipReason::DepthLimit);
+                } else {
+                    if file_type.is_symlink() {
+                        if let Some(target) = state.cycle_target(&path) {
+                            return Err(ScanError::SymlinkCycle { path, target });
+                        }
+                    }
+                    let canonical = path.canonicalize().ok();
+                    state.ancestors.extend(canonical.clone());
+                    let result = self.scan_into(root, &path, depth + 1, scope, state);
+                    if canonical.is_some() {
+                        state.ancestors.pop();
+                    }
+                    result?;
+                }
+                continue;
+            }
+            state.report.files_examined += 1;
+
+            if !metadata.is_some_and(|m| m.is_file()) {
+                state.report.skip(path, SkipReason::NotARegularFile);
+                continue;
+            }
+            if !self.is_executable(&path) {
+                let reason = if is_metadata_file(&path) {
+                    SkipReason::MetadataFile
+                } else {
+                    SkipReason::NotExecutable
+                };
+                state.report.skip(path, reason);
+                continue;
+            }
+
+            if path.to_str().is_none() {
+                return Err(ScanError::InvalidUtf8Path { path });
+            }
+            let metadata_source = find_metadata_source(&path);
+            if let MetadataSource::Sidecar(sidecar) = &metadata_source {
+                self.check_definition_file(sidecar)?;
+                fs::File::open(sidecar).map_err(|source| ScanError::UnreadableSidecar {
+                    path: sidecar.clone(),
+                    tool: path.clone(),
+                    source,
+                })?;
+            }
+
+            let relative_path = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            let mut tool = DiscoveredTool {
+                metadata_source,
+                docs: find_docs(&path),
+                path,
+                relative_path,
+                namespace: None,
+                working_dir: None,
+                defaults: Box::default(),
+                directory_configs: Box::default(),
+            };
+            scope.apply(&mut tool);
+            state.tools.push(tool);
+        }
+
+        let manifest_path = dir.join(MANIFEST_FILE_NAME);
+        if manifest_path.is_file() {
+            self.check_definition_file(&manifest_path)?;
+            let mut tools = load_manifest_tools(root, dir, &manifest_path)?;
+            for tool in &mut tools {
+                scope.apply(tool);
+            }
+            state.manifest_tools.extend(tools);
+        }
+
+        Ok(())
+    }
+
+    /// Read the [`DIRECTORY_CONFIG_FILE`] at `path`, if there is one.
+    fn directory_config(&self, path: &Path) -> Result<Option<DirectoryConfig>, ScanError> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        self.check_definition_file(path)?;
+        let yaml = fs::read_to_string(path).map_err(|e| ScanError::from_io(path, e))?;
+        DirectoryConfig::from_yaml(&yaml)
+            .map(Some)
+            .map_err(|source| ScanError::InvalidDirectoryConfig {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Scan a [`ToolRoot`], tagging each discovered tool with the root's
+    /// namespace, unless its directory sets one of its own.
+    pub fn scan_root(&self, root: &ToolRoot) -> Result<Vec<DiscoveredTool>, ScanError> {
+        self.scan_root_with_report(root).map(|(tools, _)| tools)
+    }
+
+    /// Like [`scan_root`](Self::scan_root), but also return a [`ScanReport`].
+    pub fn scan_root_with_report(
+        &self,
+        root: &ToolRoot,
+    ) -> Result<(Vec<DiscoveredTool>, ScanReport), ScanError> {
+        let (mut tools, report) = self.scan_directory_with_report(&root.path)?;
+        for tool in tools.iter_mut().filter(|tool| tool.namespace.is_none()) {
+            tool.namespace = root.namespace.clone();
+        }
+        Ok((tools, report))
+    }
+
+    /// Resolve tools by name on `$PATH` instead of scanning a directory.
+    ///
+    /// Each name is looked up in the directories listed in `PATH` (trying the
+    /// `PATHEXT` extensions on Windows), and paired with a definition named
+    /// `<name>.yaml` (or `.yml`, `.json`, `.toml`) in `definitions_dir`. Tools
+    /// without such a definition fall back to an embedded one.
+    pub fn resolve_on_path(
+        &self,
+        names: &[String],
+        definitions_dir: &Path,
+    ) -> Result<Vec<DiscoveredTool>, ScanError> {
+        let path_var = std::env::var_os("PATH").unwrap_or_default();
+
+        names
+            .iter()
+            .map(|name| {
+                let path = self
+                    .find_on_path(name, &path_var)
+                    .ok_or_else(|| ScanError::CommandNotFound { name: name.clone() })?;
+
+                let docs = append_extension(&definitions_dir.join(name), DOCS_EXTENSION);
+                Ok(DiscoveredTool {
+                    metadata_source: find_named_definition(definitions_dir, name),
+                    relative_path: PathBuf::from(name),
+                    path,
+                    namespace: None,
+                    working_dir: None,
+                    docs: docs.is_file().then_some(docs),
+                    defaults: Box::new(self.config.defaults.clone()),
+                    directory_configs: Box::default(),
+                })
+            })
+            .collect()
+    }
+
+    /// Find the first executable named `name` in the directories listed in
+    /// `PATH` (trying the `PATHEXT` extensions on Windows).
+    pub fn find_executable(&self, name: &str) -> Option<PathBuf> {
+        let path_var = std::env::var_os("PATH")?;
+        self.find_on_path(name, &path_var)
+    }
+
+    /// Find the first executable named `name` in the directories of a
+    /// `PATH`-style list.
+    fn find_on_path(&self, name: &str, path_var: &OsStr) -> Option<PathBuf> {
+        std::env::split_paths(path_var)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .flat_map(|dir| self.path_candidates(&dir, name))
+            .find(|candidate| candidate.is_file() && self.is_executable(candidate))
+    }
+
+    #[cfg(not(windows))]
+    fn path_candidates(&self, dir: &Path, name: &str) -> Vec<PathBuf> {
+        vec![dir.join(name)]
+    }
+
+    #[cfg(windows)]
+    fn path_candidates(&self, dir: &Path, name: &str) -> Vec<PathBuf> {
+        let pathext = std::env::var("PATHEXT").ok();
+        let mut candidates = vec![dir.join(name)];
+        candidates.extend(
+            executable_extensions(pathext.as_deref(), &self.config.executable_extensions)
+                .iter()
+                .map(|ext| append_extension(&dir.join(name), ext)),
+        );
+        candidates
+    }
+
+    /// Describe the tool packaged as the directory `dir`, which contains a
+    /// [`TOOL_DIRECTORY_DEFINITION`] file.
+    fn directory_tool(&self, root: &Path, dir: &Path) -> Result<DiscoveredTool, ScanError> {
+        let definition = dir.join(TOOL_DIRECTORY_DEFINITION);
+        self.check_definition_file(&definition)?;
+
+        let path = TOOL_DIRECTORY_ENTRYPOINTS
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|entrypoint| entrypoint.is_file())
+            .ok_or_else(|| ScanError::MissingEntrypoint {
+                path: dir.to_path_buf(),
+            })?;
+        if path.to_str().is_none() {
+            return Err(ScanError::InvalidUtf8Path { path });
+        }
+
+        let docs = dir.join(TOOL_DIRECTORY_DOCS);
+        Ok(DiscoveredTool {
+            relative_path: path.strip_prefix(root).unwrap_or(&path).to_path_buf(),
+            metadata_source: MetadataSource::Sidecar(definition),
+            working_dir: Some(dir.to_path_buf()),
+            docs: docs.is_file().then_some(docs),
+            path,
+            namespace: None,
+            defaults: Box::default(),
+            directory_configs: Box::default(),
+        })
+    }
+
+    /// Reject definition files larger than the configured limit.
+    fn check_definition_file(&self, path: &Path) -> Result<(), ScanError> {
+        let Some(limit) = self.config.max_definition_size else {
+            return Ok(());
+        };
+        let size = fs::metadata(path)
+            .map_err(|e| ScanError::from_io(path, e))?
+            .len();
+        if size > limit {
+            return Err(ScanError::FileTooLarge {
+                path: path.to_path_buf(),
+                size,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether a directory `depth` levels below the scanned one may be entered.
+    fn may_descend(&self, depth: usize) -> bool {
+        self.config.recursive && self.config.max_depth.is_none_or(|max| depth <= max)
+    }
+
+    fn is_executable(&self, path: &Path) -> bool {
+        if self.has_executable_permission(path) {
+            return true;
+        }
+        self.config.detect_shebangs && has_shebang(path).unwrap_or(false)
+    }
+
+    #[cfg(unix)]
+    fn has_executable_permission(&self, path: &Path) -> bool {
+        use faccess::PathExt;
+        path.executable()
+    }
+
+    #[cfg(windows)]
+    fn has_executable_permission(&self, path: &Path) -> bool {
+        let pathext = std::env::var("PATHEXT").ok();
+        let extensions =
+            executable_extensions(pathext.as_deref(), &self.config.executable_extensions);
+        check_executable_by_extension(path, &extensions)
+    }
+}
+
+/// Produce a [`DiscoveredTool`] for each entry of the manifest at
+/// `manifest_path`, located in `dir`.
+fn load_manifest_tools(
+    root: &Path,
+    dir: &Path,
+    manifest_path: &Path,
+) -> Result<Vec<DiscoveredTool>, ScanError> {
+    let manifest = Manifest::load(manifest_path).map_err(ScanError::InvalidManifest)?;
+
+    manifest
+        .tools
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let path = entry.command_path(dir);
+            if !path.is_file() {
+                return Err(ScanError::InvalidManifest(ManifestError::MissingCommand {
+                    path: manifest_path.to_path_buf(),
+                    command: entry.command.clone(),
+                }));
+            }
+
+            Ok(DiscoveredTool {
+                relative_path: path.strip_prefix(root).unwrap_or(&path).to_path_buf(),
+                metadata_source: MetadataSource::Manifest {
+                    path: manifest_path.to_path_buf(),
+                    index,
+                },
+                docs: find_docs(&path),
+                path,
+                namespace: None,
+                working_dir: None,
+                defaults: Box::default(),
+                directory_configs: Box::default(),
+            })
+        })
+        .collect()
+}
+
+/// Accumulated results while walking a directory tree.
+#[derive(Default)]
+struct ScanState {
+    tools: Vec<DiscoveredTool>,
+    manifest_tools: Vec<DiscoveredTool>,
+    report: ScanReport,
+    /// Canonical paths of the directories currently being scanned, used to
+    /// detect symlink cycles
+    ancestors: Vec<PathBuf>,
+}
+
+impl ScanState {
+    /// If the directory at `path` resolves to one that is already being
+    /// scanned, return that directory.
+    fn cycle_target(&self, path: &Path) -> Option<PathBuf> {
+        path.canonicalize()
+            .ok()
+            .filter(|target| self.ancestors.contains(target))
+    }
+}
+
+/// Drop tools that are the same underlying file as another tool, reached
+/// through a hard link, symlink, or bind mount, and described by the same
+/// definition.
+///
+/// Of each set of duplicates, the first path that is not a symlink is kept.
+/// Copies with their own sidecars are distinct tools (a multi-call binary
+/// exposed under several names), and manifest entries are declared
+/// explicitly, so neither is deduplicated.
+fn deduplicate(tools: &mut Vec<DiscoveredTool>, report: &mut ScanReport) {
+    let mut preferred: Vec<usize> = (0..tools.len()).collect();
+    // Stable, so path order is kept among symlinks and among regular files.
+    preferred.sort_by_key(|&i| {
+        fs::symlink_metadata(&tools[i].path).is_ok_and(|m| m.file_type().is_symlink())
+    });
+
+    let mut seen = HashSet::new();
+    let mut duplicate = vec![false; tools.len()];
+    for i in preferred {
+        let tool = &tools[i];
+        let definition = match &tool.metadata_source {
+            MetadataSource::Embedded => None,
+            MetadataSource::Sidecar(sidecar) => match FileId::of(sidecar) {
+                Some(id) => Some(id),
+                None => continue,
+            },
+            MetadataSource::Manifest { .. } => continue,
+        };
+        if let Some(executable) = FileId::of(&tool.path) {
+            duplicate[i] = !seen.insert((executable, definition));
+        }
+    }
+
+    let mut index = 0;
+    tools.retain(|tool| {
+        let keep = !duplicate[index];
+        index += 1;
+        if !keep {
+            report.skip(tool.path.clone(), SkipReason::DuplicateFile);
+        }
+        keep
+    });
+}
+
+/// Identity of a file on disk, shared by every path that reaches it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum FileId {
+    /// Device and inode number
+    #[cfg(unix)]
+    Inode(u64, u64),
+
+    /// Canonical path (resolves symlinks, but not hard links)
+    #[cfg(not(unix))]
+    Canonical(PathBuf),
+}
+
+impl FileId {
+    #[cfg(unix)]
+    fn of(path: &Path) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = fs::metadata(path).ok()?;
+        Some(FileId::Inode(metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn of(path: &Path) -> Option<Self> {
+        path.canonicalize().ok().map(FileId::Canonical)
+    }
+}
+
+/// Whether `path` names a dotfile or dot-directory.
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."))
+}
+
+/// Whether `path` is a definition file (sidecar or manifest) rather than a
+/// potential tool.
+fn is_metadata_file(path: &Path) -> bool {
+    SidecarFormat::from_path(path).is_some()
+}
+
+/// Find the definition for a `$PATH`-resolved tool in `definitions_dir`.
+fn find_named_definition(definitions_dir: &Path, name: &str) -> MetadataSource {
+    SidecarFormat::EXTENSIONS
+        .iter()
+        .map(|ext| append_extension(&definitions_dir.join(name), ext))
+        .find(|definition| definition.is_file())
+        .map(MetadataSource::Sidecar)
+        .unwrap_or(MetadataSource::Embedded)
+}
+
+/// Determine where the definition for the executable at `path` lives.
+///
+/// The sidecar name is formed by appending the sidecar extension to the full
+/// executable file name, so `file-info.sh` is described by
+/// `file-info.sh.yaml`. For backward compatibility, a sidecar that replaces the
+/// executable's extension (`file-info.yaml`) is used as a fallback, unless an
+/// executable named `file-info` also exists and would own that file.
+///
+/// Within each naming convention, sidecars are looked up in
+/// [`SidecarFormat::EXTENSIONS`] order, so a `.yaml` sidecar takes precedence
+/// over `.yml`, `.json`, and `.toml`.
+fn find_metadata_source(path: &Path) -> MetadataSource {
+    let appended = SidecarFormat::EXTENSIONS
+        .iter()
+        .map(|ext| append_extension(path, ext));
+
+    let legacy: Vec<PathBuf> = if path.extension().is_some() && !path.with_extension("").exists() {
+        SidecarFormat::EXTENSIONS
+            .iter()
+            .map(|ext| path.with_extension(ext))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    appended
+        .chain(legacy)
+        .find(|sidecar| sidecar != path && sidecar.is_file())
+        .map(MetadataSource::Sidecar)
+        .unwrap_or(MetadataSource::Embedded)
+}
+
+/// Append `.{extension}` to the full file name of `path`, keeping any
+/// existing extension (`tool.sh` becomes `tool.sh.yaml`).
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+#[cfg(any(windows, test))]
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD;.VBS;.VBE;.JS;.JSE;.WSF;.WSH;.MSC";
+
+/// Extensions that are always treated as executable, even though Windows does
+/// not list them in `PATHEXT` by default.
+#[cfg(any(windows, test))]
+const ALWAYS_EXECUTABLE_EXTENSIONS: &[&str] = &["ps1"];
+
+/// Build the set of executable extensions from a `PATHEXT`-style list
+/// (`.COM;.EXE;...`) plus any extra configured extensions.
+///
+/// Extensions are normalized to lowercase without a leading dot.
+#[cfg(any(windows, test))]
+fn executable_extensions(pathext: Option<&str>, extra: &[String]) -> Vec<String> {
+    let pathext = pathext
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or(DEFAULT_PATHEXT);
+
+    let mut extensions: Vec<String> = pathext
+        .split(';')
+        .map(str::to_string)
+        .chain(
+            ALWAYS_EXECUTABLE_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string()),
+        )
+        .chain(extra.iter().cloned())
+        .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    extensions.sort();
+    extensions.dedup();
+    extensions
+}
+
+/// Windows has no executable bit; executability is decided by whether the
+/// file's extension appears in `extensions` (as built by
+/// [`executable_extensions`]).
+#[cfg(any(windows, test))]
+fn check_executable_by_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| extensions.contains(&ext.to_ascii_lowercase()))
+        .unwrap_or(false)
+}
+
+/// Check whether the file at `path` begins with a `#!` shebang.
+///
+/// Only the first two bytes are read.
+fn has_shebang(path: &Path) -> io::Result<bool> {
+    let mut magic = [0u8; 2];
+    let mut file = fs::File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == b"#!"),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_file(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    fn make_executable(path: &Path) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_finds_executables() {
+        let dir = TempDir::new().unwrap();
+        let tool = write_file(dir.path(), "create-ticket", "#!/bin/sh\necho hi\n");
+        make_executable(&tool);
+        write_file(dir.path(), "README.md", "# Tools\n");
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].path, tool);
+        assert_eq!(tools[0].relative_path, PathBuf::from("create-ticket"));
+        assert_eq!(tools[0].metadata_source, MetadataSource::Embedded);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_recurses_into_subdirectories() {
+        let dir = TempDir::new().unwrap();
+        let tool = write_file(dir.path(), "ops/restart", "#!/bin/sh\n");
+        make_executable(&tool);
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].relative_path, Path::new("ops").join("restart"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_results_are_sorted() {
+        let dir = TempDir::new().unwrap();
+        for name in ["zeta", "alpha", "ops/beta", "ops/alpha", "mid"] {
+            make_executable(&write_file(dir.path(), name, "binary"));
+        }
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+        let paths: Vec<PathBuf> = tools.into_iter().map(|t| t.relative_path).collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("alpha"),
+                PathBuf::from("mid"),
+                Path::new("ops").join("alpha"),
+                Path::new("ops").join("beta"),
+                PathBuf::from("zeta"),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_report() {
+        let dir = TempDir::new().unwrap();
+        make_executable(&write_file(dir.path(), "embedded", "#!/bin/sh\n"));
+        make_executable(&write_file(dir.path(), "ops/with-sidecar", "binary"));
+        write_file(dir.path(), "ops/with-sidecar.yaml", "name: x\n");
+        let notes = write_file(dir.path(), "notes.txt", "not a tool\n");
+        std::os::unix::fs::symlink(dir.path().join("ops"), dir.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("broken")).unwrap();
+
+        let (tools, report) = DirectoryScanner::new()
+            .scan_directory_with_report(dir.path())
+            .unwrap();
+
+        assert_eq!(tools.len(), 2);
+        assert_eq!(report.directories_scanned, 2);
+        assert_eq!(report.files_examined, 6);
+        assert_eq!(report.executables_found, 2);
+        assert_eq!(report.sidecars_found, 1);
+        assert_eq!(report.embedded_candidates, 1);
+        assert_eq!(report.manifest_tools, 0);
+
+        let reason = |name: &str| {
+            report
+                .skipped
+                .iter()
+                .find(|entry| entry.path.file_name().unwrap() == name)
+                .map(|entry| entry.reason)
+        };
+        assert_eq!(reason("notes.txt"), Some(SkipReason::NotExecutable));
+        assert_eq!(reason("with-sidecar.yaml"), Some(SkipReason::MetadataFile));
+        assert_eq!(reason("linked"), Some(SkipReason::SymlinkedDirectory));
+        assert_eq!(reason("broken"), Some(SkipReason::NotARegularFile));
+        assert!(report.skipped.iter().any(|entry| entry.path == notes));
+        assert!(report.to_string().starts_with("Found 2 tool(s)"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_depth_limits() {
+        let dir = TempDir::new().unwrap();
+        for name in ["top", "a/one", "a/b/two", "a/b/c/three"] {
+            make_executable(&write_file(dir.path(), name, "binary"));
+        }
+        let names = |scanner: DirectoryScanner| -> Vec<String> {
+            scanner
+                .scan_directory(dir.path())
+                .unwrap()
+                .into_iter()
+                .map(|t| t.path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(
+            names(DirectoryScanner::new()),
+            vec!["three", "two", "one", "top"]
+        );
+        assert_eq!(
+            names(DirectoryScanner::builder().recursive(false).build()),
+            vec!["top"]
+        );
+        assert_eq!(
+            names(DirectoryScanner::builder().max_depth(2).build()),
+            vec!["two", "one", "top"]
+        );
+
+        let (_, report) = DirectoryScanner::builder()
+            .max_depth(1)
+            .build()
+            .scan_directory_with_report(dir.path())
+            .unwrap();
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].reason, SkipReason::DepthLimit);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directory_tool() {
+        let dir = TempDir::new().unwrap();
+        write_file(dir.path(), "deploy/tool.yaml", "name: deploy\n");
+        make_executable(&write_file(dir.path(), "deploy/run.sh", "#!/bin/sh\n"));
+        make_executable(&write_file(dir.path(), "deploy/bin/helper", "binary"));
+        write_file(dir.path(), "deploy/main.py", "print()\n");
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+
+        // The directory is one tool; its contents are assets, not tools
+        assert_eq!(tools.len(), 1);
+        let tool = &tools[0];
+        assert_eq!(tool.path, dir.path().join("deploy").join("run.sh"));
+        assert_eq!(tool.relative_path, Path::new("deploy").join("run.sh"));
+        assert_eq!(tool.working_dir, Some(dir.path().join("deploy")));
+        assert_eq!(
+            tool.metadata_source,
+            MetadataSource::Sidecar(dir.path().join("deploy").join("tool.yaml"))
+        );
+    }
+
+    #[test]
+    fn test_directory_tool_without_entrypoint() {
+        let dir = TempDir::new().unwrap();
+        write_file(dir.path(), "broken/tool.yaml", "name: broken\n");
+        write_file(dir.path(), "broken/README.md", "docs\n");
+
+        match DirectoryScanner::new().scan_directory(dir.path()) {
+            Err(ScanError::MissingEntrypoint { path }) => {
+                assert_eq!(path, dir.path().join("broken"))
+            }
+            other => panic!("expected MissingEntrypoint, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_duplicate_files_are_deduplicated() {
+        let dir = TempDir::new().unwrap();
+        let original = write_file(dir.path(), "tool", "#!/bin/sh\n");
+        make_executable(&original);
+        fs::hard_link(&original, dir.path().join("hard-link")).unwrap();
+        std::os::unix::fs::symlink(&original, dir.path().join("a-symlink")).unwrap();
+        // Same binary, but described separately: a distinct tool
+        fs::hard_link(&original, dir.path().join("alias")).unwrap();
+        write_file(dir.path(), "alias.yaml", "name: alias\n");
+
+        let (tools, report) = DirectoryScanner::new()
+            .scan_directory_with_report(dir.path())
+            .unwrap();
+
+        let paths: Vec<PathBuf> = tools.into_iter().map(|t| t.relative_path).collect();
+        // `hard-link` sorts first and is not a symlink, so it is kept
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("alias"), PathBuf::from("hard-link")]
+        );
+        let duplicates: Vec<&Path> = report
+            .skipped
+            .iter()
+            .filter(|entry| entry.reason == SkipReason::DuplicateFile)
+            .map(|entry| entry.path.as_path())
+            .collect();
+        assert_eq!(
+            duplicates,
+            vec![dir.path().join("a-symlink"), dir.path().join("tool")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hidden_entries() {
+        let dir = TempDir::new().unwrap();
+        make_executable(&write_file(dir.path(), "visible", "binary"));
+        make_executable(&write_file(dir.path(), ".hidden-tool", "binary"));
+        make_executable(&write_file(dir.path(), ".git/hooks/pre-commit", "binary"));
+
+        let (tools, report) = DirectoryScanner::new()
+            .scan_directory_with_report(dir.path())
+            .unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].relative_path, PathBuf::from("visible"));
+        assert_eq!(report.skipped.len(), 2);
+        assert!(report
+            .skipped
+            .iter()
+            .all(|entry| entry.reason == SkipReason::Hidden));
+
+        let tools = DirectoryScanner::builder()
+            .include_hidden(true)
+            .build()
+            .scan_directory(dir.path())
+            .unwrap();
+        let paths: Vec<PathBuf> = tools.into_iter().map(|t| t.relative_path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new(".git").join("hooks").join("pre-commit"),
+                PathBuf::from(".hidden-tool"),
+                PathBuf::from("visible"),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        make_executable(&write_file(outside.path(), "linked-tool", "binary"));
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("shared")).unwrap();
+
+        let default_tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+        assert!(default_tools.is_empty());
+
+        let (tools, report) = DirectoryScanner::builder()
+            .follow_symlinks(true)
+            .build()
+            .scan_directory_with_report(dir.path())
+            .unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(
+            tools[0].relative_path,
+            Path::new("shared").join("linked-tool")
+        );
+        assert!(report.skipped.is_empty());
+
+        // A cycle back to the scanned directory
+        std::os::unix::fs::symlink(dir.path(), outside.path().join("loop")).unwrap();
+        let result = DirectoryScanner::builder()
+            .follow_symlinks(true)
+            .build()
+            .scan_directory(dir.path());
+        match result {
+            Err(ScanError::SymlinkCycle { path, target }) => {
+                assert_eq!(path, dir.path().join("shared").join("loop"));
+                assert_eq!(target, dir.path().canonicalize().unwrap());
+            }
+            other => panic!("expected SymlinkCycle, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_oversized_sidecar_is_error() {
+        let dir = TempDir::new().unwrap();
+        make_executable(&write_file(dir.path(), "tool", "binary"));
+        let sidecar = write_file(dir.path(), "tool.yaml", &"#".repeat(100));
+
+        let result = DirectoryScanner::builder()
+            .max_definition_size(Some(64))
+            .build()
+            .scan_directory(dir.path());
+        match result {
+            Err(ScanError::FileTooLarge { path, size, limit }) => {
+                assert_eq!(path, sidecar);
+                assert_eq!(size, 100);
+                assert_eq!(limit, 64);
+            }
+            other => panic!("expected FileTooLarge, got {:?}", other),
+        }
+
+        let tools = DirectoryScanner::builder()
+            .max_definition_size(None)
+            .build()
+            .scan_directory(dir.path())
+            .unwrap();
+        assert_eq!(tools.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_tool_path_is_error() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TempDir::new().unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"tool-\xff");
+        let path = dir.path().join(name);
+        fs::write(&path, "binary").unwrap();
+        make_executable(&path);
+
+        match DirectoryScanner::new().scan_directory(dir.path()) {
+            Err(ScanError::InvalidUtf8Path { path: error_path }) => assert_eq!(error_path, path),
+            other => panic!("expected InvalidUtf8Path, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_detects_sidecar() {
+        let dir = TempDir::new().unwrap();
+        let tool = write_file(dir.path(), "deploy", "binary");
+        make_executable(&tool);
+        let sidecar = write_file(dir.path(), "deploy.yaml", "name: deploy\n");
+        let docs = write_file(dir.path(), "deploy.md", "# deploy\n");
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].metadata_source, MetadataSource::Sidecar(sidecar));
+        assert_eq!(tools[0].docs, Some(docs));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sidecar_precedence() {
+        let dir = TempDir::new().unwrap();
+        let tool = write_file(dir.path(), "deploy", "binary");
+        make_executable(&tool);
+        write_file(dir.path(), "deploy.toml", "name = 'deploy'\n");
+        let json = write_file(dir.path(), "deploy.json", "{}");
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+        assert_eq!(tools[0].metadata_source, MetadataSource::Sidecar(json));
+
+        let yml = write_file(dir.path(), "deploy.yml", "name: deploy\n");
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+        assert_eq!(tools[0].metadata_source, MetadataSource::Sidecar(yml));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sidecar_appends_to_extension() {
+        let dir = TempDir::new().unwrap();
+        let tool = write_file(dir.path(), "file-info.sh", "#!/bin/sh\n");
+        make_executable(&tool);
+        write_file(dir.path(), "file-info.yaml", "name: legacy\n");
+        let appended = write_file(dir.path(), "file-info.sh.yaml", "name: file_info\n");
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+
+        assert_eq!(tools[0].metadata_source, MetadataSource::Sidecar(appended));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sidecar_legacy_fallback() {
+        let dir = TempDir::new().unwrap();
+        let tool = write_file(dir.path(), "file-info.sh", "#!/bin/sh\n");
+        make_executable(&tool);
+        let legacy = write_file(dir.path(), "file-info.yaml", "name: file_info\n");
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+
+        assert_eq!(tools[0].metadata_source, MetadataSource::Sidecar(legacy));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sidecar_legacy_fallback_does_not_collide() {
+        let dir = TempDir::new().unwrap();
+        let script = write_file(dir.path(), "file-info.sh", "#!/bin/sh\n");
+        make_executable(&script);
+        let binary = write_file(dir.path(), "file-info", "binary");
+        make_executable(&binary);
+        let sidecar = write_file(dir.path(), "file-info.yaml", "name: file_info\n");
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+
+        assert_eq!(tools[0].path, binary);
+        assert_eq!(tools[0].metadata_source, MetadataSource::Sidecar(sidecar));
+        assert_eq!(tools[1].path, script);
+        assert_eq!(tools[1].metadata_source, MetadataSource::Embedded);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_manifest_tools_are_merged() {
+        let dir = TempDir::new().unwrap();
+        let standalone = write_file(dir.path(), "standalone", "binary");
+        make_executable(&standalone);
+        let deploy = write_file(dir.path(), "bin/deploy", "binary");
+        make_executable(&deploy);
+        // Not executable, but declared explicitly by the manifest
+        let report = write_file(dir.path(), "bin/report.py", "print()\n");
+        let manifest = write_file(
+            dir.path(),
+            MANIFEST_FILE_NAME,
+            r#"
+tools:
+  - command: ./bin/deploy
+    name: deploy
+    description: Deploys things
+    input: { template: "", schema: {} }
+    output: { template: "", schema: {} }
+  - command: bin/report.py
+    name: report
+    description: Reports things
+    input: { template: "", schema: {} }
+    output: { template: "", schema: {} }
+"#,
+        );
+
+        let tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+
+        assert_eq!(tools.len(), 3);
+        let find = |path: &Path| tools.iter().find(|t| t.path == path).unwrap();
+        assert_eq!(find(&standalone).metadata_source, MetadataSource::Embedded);
+        assert_eq!(
+            find(&deploy).metadata_source,
+            MetadataSource::Manifest {
+                path: manifest.clone(),
+                index: 0
+            }
+        );
+        assert_eq!(
+            find(&report).metadata_source,
+            MetadataSource::Manifest {
+                path: manifest,
+                index: 1
+            }
+        );
+        assert_eq!(
+            find(&report).relative_path,
+            Path::new("bin").join("report.py")
+        );
+    }
+
+    #[test]
+    fn test_manifest_with_missing_command_is_error() {
+        let dir = TempDir::new().unwrap();
+        write_file(
+            dir.path(),
+            MANIFEST_FILE_NAME,
+            r#"
+tools:
+  - command: ./missing
+    name: missing
+    description: Missing tool
+    input: { template: "", schema: {} }
+    output: { template: "", schema: {} }
+"#,
+        );
+
+        let result = DirectoryScanner::new().scan_directory(dir.path());
+
+        assert!(matches!(
+            result,
+            Err(ScanError::InvalidManifest(
+                ManifestError::MissingCommand { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_tool_root_parsing() {
+        let plain: ToolRoot = "./tools".parse().unwrap();
+        assert_eq!(plain, ToolRoot::new("./tools"));
+
+        let namespaced: ToolRoot = "ops=./ops-tools".parse().unwrap();
+        assert_eq!(
+            namespaced,
+            ToolRoot::new("./ops-tools").with_namespace("ops")
+        );
+
+        // Not a namespace prefix: contains a path separator
+        let odd_path: ToolRoot = "./a=b".parse().unwrap();
+        assert_eq!(odd_path, ToolRoot::new("./a=b"));
+    }
+
+    #[test]
+    fn test_derived_namespace() {
+        let dir = TempDir::new().unwrap();
+        let ops = dir.path().join("ops");
+        fs::create_dir(&ops).unwrap();
+
+        let derived = ToolRoot::new(&ops).with_derived_namespace();
+        assert_eq!(derived.namespace, Some("ops".to_string()));
+
+        let explicit = ToolRoot::new(&ops)
+            .with_namespace("prod")
+            .with_derived_namespace();
+        assert_eq!(explicit.namespace, Some("prod".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_root_applies_namespace() {
+        let dir = TempDir::new().unwrap();
+        make_executable(&write_file(dir.path(), "restart-service", "binary"));
+
+        let root = ToolRoot::new(dir.path()).with_namespace("ops");
+        let tools = DirectoryScanner::new().scan_root(&root).unwrap();
+
+        assert_eq!(tools[0].namespace, Some("ops".to_string()));
+        assert_eq!(tools[0].qualified_name("restart"), "ops/restart");
+
+        let plain = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+        assert_eq!(plain[0].qualified_name("restart"), "restart");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directory_config() {
+        let dir = TempDir::new().unwrap();
+        write_file(
+            dir.path(),
+            DIRECTORY_CONFIG_FILE,
+            "timeout: 30s\nenv: { REGION: eu }\nignore: [\"*.bak\", scratch]\n",
+        );
+        write_file(
+            dir.path(),
+            &format!("billing/{}", DIRECTORY_CONFIG_FILE),
+            "namespace: billing\n",
+        );
+        for name in ["deploy", "deploy.bak", "scratch/try", "billing/invoice"] {
+            make_executable(&write_file(dir.path(), name, "binary"));
+        }
+
+        let root = ToolRoot::new(dir.path()).with_namespace("ops");
+        let (tools, report) = DirectoryScanner::new()
+            .scan_root_with_report(&root)
+            .unwrap();
+        let names: Vec<String> = tools
+            .iter()
+            .map(|tool| {
+                tool.qualified_name(&tool.relative_path.file_name().unwrap().to_string_lossy())
+            })
+            .collect();
+        assert_eq!(names, ["billing/invoice", "ops/deploy"]);
+        assert!(tools
+            .iter()
+            .all(|tool| tool.defaults.timeout == Some(std::time::Duration::from_secs(30))));
+        assert_eq!(tools[0].defaults.env["REGION"], "eu");
+        let ignored: Vec<&Path> = report
+            .skipped
+            .iter()
+            .filter(|entry| entry.reason == SkipReason::Ignored)
+            .map(|entry| entry.path.strip_prefix(dir.path()).unwrap())
+            .collect();
+        assert_eq!(ignored, [Path::new("deploy.bak"), Path::new("scratch")]);
+
+        write_file(dir.path(), DIRECTORY_CONFIG_FILE, "timeout: [soon]\n");
+        let error = DirectoryScanner::new().scan_root(&root).unwrap_err();
+        assert!(matches!(error, ScanError::InvalidDirectoryConfig { .. }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_on_path() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        write_file(first.path(), "jq", "not executable");
+        let jq = write_file(second.path(), "jq", "binary");
+        make_executable(&jq);
+        let gh = write_file(first.path(), "gh", "binary");
+        make_executable(&gh);
+
+        let path_var = std::env::join_paths([first.path(), Path::new(""), second.path()]).unwrap();
+        let scanner = DirectoryScanner::new();
+
+        assert_eq!(scanner.find_on_path("jq", &path_var), Some(jq));
+        assert_eq!(scanner.find_on_path("gh", &path_var), Some(gh));
+        assert_eq!(scanner.find_on_path("missing", &path_var), None);
+    }
+
+    #[test]
+    fn test_find_named_definition() {
+        let dir = TempDir::new().unwrap();
+        let gh = write_file(dir.path(), "gh.json", "{}");
+
+        assert_eq!(
+            find_named_definition(dir.path(), "gh"),
+            MetadataSource::Sidecar(gh)
+        );
+        assert_eq!(
+            find_named_definition(dir.path(), "jq"),
+            MetadataSource::Embedded
+        );
+    }
+
+    #[test]
+    fn test_resolve_on_path_unknown_command() {
+        let dir = TempDir::new().unwrap();
+        let names = vec!["definitely-not-a-real-command-xyz".to_string()];
+
+        let result = DirectoryScanner::new().resolve_on_path(&names, dir.path());
+
+        assert!(matches!(
+            result,
+            Err(ScanError::CommandNotFound { name }) if name == names[0]
+        ));
+    }
+
+    #[test]
+    fn test_shebang_detection_is_opt_in() {
+        let dir = TempDir::new().unwrap();
+        write_file(
+            dir.path(),
+            "lost-exec-bit",
+            "#!/usr/bin/env python3\nprint()\n",
+        );
+        write_file(dir.path(), "notes.txt", "not a script\n");
+        write_file(dir.path(), "empty", "");
+
+        let default_tools = DirectoryScanner::new().scan_directory(dir.path()).unwrap();
+        assert!(default_tools.is_empty());
+
+        let tools = DirectoryScanner::builder()
+            .detect_shebangs(true)
+            .build()
+            .scan_directory(dir.path())
+            .unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].relative_path, PathBuf::from("lost-exec-bit"));
+    }
+
+    #[test]
+    fn test_check_executable_by_extension() {
+        let extensions = executable_extensions(None, &[]);
+
+        assert!(check_executable_by_extension(
+            Path::new("tool.exe"),
+            &extensions
+        ));
+        assert!(check_executable_by_extension(
+            Path::new("tool.BAT"),
+            &extensions
+        ));
+        assert!(check_executable_by_extension(
+            Path::new("tool.cmd"),
+            &extensions
+        ));
+        assert!(check_executable_by_extension(
+            Path::new("tool.com"),
+            &extensions
+        ));
+        assert!(check_executable_by_extension(
+            Path::new("tool.vbs"),
+            &extensions
+        ));
+        assert!(check_executable_by_extension(
+            Path::new("tool.ps1"),
+            &extensions
+        ));
+        assert!(!check_executable_by_extension(
+            Path::new("tool.yaml"),
+            &extensions
+        ));
+        assert!(!check_executable_by_extension(
+            Path::new("tool"),
+            &extensions
+        ));
+    }
+
+    #[test]
+    fn test_executable_extensions_from_pathext() {
+        let extensions = executable_extensions(Some(".EXE;.Cmd;;.PL"), &[]);
+
+        assert_eq!(extensions, vec!["cmd", "exe", "pl", "ps1"]);
+        assert!(check_executable_by_extension(
+            Path::new("script.pl"),
+            &extensions
+        ));
+        assert!(!check_executable_by_extension(
+            Path::new("tool.bat"),
+            &extensions
+        ));
+    }
+
+    #[test]
+    fn test_executable_extensions_with_extra_configured() {
+        let extra = vec![".py".to_string(), "RB".to_string()];
+        let extensions = executable_extensions(Some(""), &extra);
+
+        assert!(check_executable_by_extension(
+            Path::new("tool.py"),
+            &extensions
+        ));
+        assert!(check_executable_by_extension(
+            Path::new("tool.rb"),
+            &extensions
+        ));
+        // Blank PATHEXT falls back to the Windows default
+        assert!(check_executable_by_extension(
+            Path::new("tool.exe"),
+            &extensions
+        ));
+    }
+
+    #[test]
+    fn test_scan_missing_directory_is_error() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("missing");
+
+        let result = DirectoryScanner::new().scan_directory(&missing);
+
+        match result {
+            Err(ScanError::IoError { path, .. }) => assert_eq!(path, missing),
+            other => panic!("expected IoError, got {:?}", other),
+        }
+    }
+}
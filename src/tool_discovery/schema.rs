@@ -0,0 +1,122 @@
+//! JSON Schemas of the definition file formats, for editors.
+//!
+//! The schemas are generated from the types definitions are read into, so
+//! they describe what the loader accepts, with the documentation of each
+//! field. They follow JSON Schema draft 7, which editors support widely.
+//! Pointed at a schema, yaml-language-server validates and completes a
+//! definition as it is written:
+//!
+//! ```yaml
+//! # yaml-language-server: $schema=./mcp-serve.schema.json
+//! name: create_ticket
+//! ```
+
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde_json::Value;
+
+use super::manifest::Manifest;
+use super::ToolDefinition;
+
+/// An mcp-serve sidecar: the definition of one tool, or of several listed
+/// under `tools`.
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[schemars(title = "mcp-serve tool definition")]
+// Only the type's schema is used; definitions are read by `SidecarFormat`.
+#[allow(dead_code)]
+enum Sidecar {
+    Tool(Box<ToolDefinition>),
+    Tools {
+        /// The tools the file defines
+        tools: Vec<ToolDefinition>,
+    },
+}
+
+/// The schema of sidecar files and embedded definitions.
+pub fn sidecar_schema() -> Value {
+    root_schema::<Sidecar>()
+}
+
+/// The schema of `tools.yaml` manifests.
+pub fn manifest_schema() -> Value {
+    root_schema::<Manifest>()
+}
+
+/// The draft 7 schema of `T`, with the types it refers to as definitions.
+pub(crate) fn root_schema<T: JsonSchema>() -> Value {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<T>()
+        .to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check `instance` against `schema`.
+    fn is_valid(schema: &Value, instance: &str) -> bool {
+        let instance: Value = serde_yaml_ng::from_str(instance).unwrap();
+        jsonschema::validator_for(schema)
+            .unwrap()
+            .is_valid(&instance)
+    }
+
+    #[test]
+    fn test_sidecar_schema() {
+        let schema = sidecar_schema();
+        assert_eq!(schema["title"], "mcp-serve tool definition");
+
+        let definition = r#"
+name: create_ticket
+description: Creates a ticket
+input:
+  template: ["--title", "{{title}}"]
+  schema: { type: object }
+output:
+  template:
+    - 'ID: (?<id>\d+)'
+    - { pattern: "exists", set: { id: 0 } }
+  schema: { type: object }
+  content: [structured, { resource_link: { uri: "ticket://{{id}}", name: ticket } }]
+annotations: { readOnlyHint: false }
+execution: { timeout: 30s, concurrency: 1 }
+deprecated: true
+icon: https://example.com/ticket.png
+"#;
+        assert!(ToolDefinition::from_yaml(definition).is_ok());
+        assert!(is_valid(&schema, definition));
+        assert!(is_valid(
+            &schema,
+            &format!("tools:\n  - {}", definition.trim().replace('\n', "\n    "))
+        ));
+
+        assert!(!is_valid(&schema, "name: create_ticket\n"));
+        assert!(!is_valid(
+            &schema,
+            &definition.replace("timeout: 30s", "timeout: [30]")
+        ));
+        assert!(!is_valid(
+            &schema,
+            &definition.replace("concurrency: 1", "concurrency: 0")
+        ));
+    }
+
+    #[test]
+    fn test_manifest_schema() {
+        let schema = manifest_schema();
+        assert!(is_valid(
+            &schema,
+            r#"
+tools:
+  - command: ./bin/deploy
+    name: deploy
+    description: Deploys the current branch
+    input: { template: "", schema: { type: object } }
+    output: { capture: full, schema: { type: object } }
+"#
+        ));
+        assert!(!is_valid(&schema, "tools:\n  - name: deploy\n"));
+    }
+}
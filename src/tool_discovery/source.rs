@@ -0,0 +1,787 @@
+//! Remote tool sources fetched into a local cache before scanning.
+//!
+//! Besides local directories, a tools directory may be given as a git
+//! repository or an HTTP tarball URL, so teams can share tool packs by URL:
+//!
+//! ```text
+//! mcp-serve https://github.com/team/tools.git
+//! mcp-serve git+https://git.example.com/tools#v2
+//! mcp-serve https://example.com/releases/tools-1.2.tar.gz
+//! mcp-serve ./tools.zip#sha256=9f86d081884c7d65...
+//! ```
+//!
+//! Remote sources and archives are fetched (and extracted) into a
+//! [`SourceCache`] and then scanned like any local folder. Fetching shells out
+//! to `git`, `curl`, and `tar` (or `unzip` for zip archives on Unix-like
+//! systems), which must be on `PATH`.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use sha2::{Digest, Sha256};
+
+/// Where a tools directory comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolSource {
+    /// A directory on the local filesystem.
+    Local(PathBuf),
+
+    /// A git repository, optionally pinned to a branch, tag, or commit.
+    Git {
+        url: String,
+        reference: Option<String>,
+    },
+
+    /// A `.tar.gz` or `.zip` archive, either a local file or a URL, with an
+    /// optional SHA-256 checksum (hex) it must match.
+    Archive {
+        location: String,
+        format: ArchiveFormat,
+        sha256: Option<String>,
+    },
+}
+
+/// The format of an archive source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// A gzip-compressed tarball (`.tar.gz`, `.tgz`)
+    TarGz,
+
+    /// A zip archive (`.zip`)
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// File name suffixes of each archive format.
+    const SUFFIXES: &'static [(&'static str, ArchiveFormat)] = &[
+        (".tar.gz", ArchiveFormat::TarGz),
+        (".tgz", ArchiveFormat::TarGz),
+        (".zip", ArchiveFormat::Zip),
+    ];
+
+    /// Detect the format of an archive from its file name or URL.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::SUFFIXES
+            .iter()
+            .find(|(suffix, _)| name.ends_with(suffix))
+            .map(|&(_, format)| format)
+    }
+
+    /// Strip the format's suffix from `name`, if present.
+    fn strip_suffix(name: &str) -> Option<&str> {
+        Self::SUFFIXES.iter().find_map(|(suffix, _)| {
+            let stem = name.len().checked_sub(suffix.len())?;
+            name[stem..]
+                .eq_ignore_ascii_case(suffix)
+                .then(|| &name[..stem])
+        })
+    }
+
+    /// The command extracting `archive` into `dir`.
+    fn extract_command(self, archive: &Path, dir: &Path) -> Command {
+        match self {
+            ArchiveFormat::Zip if !cfg!(windows) => {
+                let mut command = Command::new("unzip");
+                command.arg("-q").arg(archive).arg("-d").arg(dir);
+                command
+            }
+            // Windows' bundled (bsd)tar extracts zip archives too.
+            ArchiveFormat::Zip | ArchiveFormat::TarGz => {
+                let mut command = Command::new("tar");
+                command.arg("-xf").arg(archive).arg("-C").arg(dir);
+                command
+            }
+        }
+    }
+}
+
+impl ToolSource {
+    /// Interpret a tools directory argument.
+    ///
+    /// - `git+URL` or a URL ending in `.git` is a git repository; a `#REF`
+    ///   suffix selects a branch, tag, or commit.
+    /// - A path or URL ending in `.tar.gz`, `.tgz`, or `.zip` is an archive;
+    ///   a `#sha256=HEX` suffix gives the checksum it must match.
+    /// - Anything else is a local path.
+    pub fn parse(spec: &str) -> Self {
+        let is_url = spec.contains("://") || spec.starts_with("git@");
+
+        if let Some(url) = spec.strip_prefix("git+") {
+            return Self::git(url);
+        }
+        let (without_fragment, fragment) = match spec.split_once('#') {
+            Some((location, fragment)) => (location, Some(fragment)),
+            None => (spec, None),
+        };
+        if is_url && without_fragment.ends_with(".git") {
+            return Self::git(spec);
+        }
+        if let Some(format) = ArchiveFormat::from_name(without_fragment) {
+            let sha256 = fragment
+                .and_then(|fragment| fragment.strip_prefix("sha256="))
+                .map(str::to_ascii_lowercase);
+            if fragment.is_none() || sha256.is_some() {
+                return ToolSource::Archive {
+                    location: without_fragment.to_string(),
+                    format,
+                    sha256,
+                };
+            }
+        }
+        ToolSource::Local(PathBuf::from(spec))
+    }
+
+    fn git(spec: &str) -> Self {
+        match spec.split_once('#') {
+            Some((url, reference)) if !reference.is_empty() => ToolSource::Git {
+                url: url.to_string(),
+                reference: Some(reference.to_string()),
+            },
+            Some((url, _)) => ToolSource::Git {
+                url: url.to_string(),
+                reference: None,
+            },
+            None => ToolSource::Git {
+                url: spec.to_string(),
+                reference: None,
+            },
+        }
+    }
+
+    /// Whether the source must be fetched before it can be scanned.
+    pub fn is_remote(&self) -> bool {
+        !matches!(self, ToolSource::Local(_))
+    }
+
+    /// A short name for the source: the repository or archive name for remote
+    /// sources (`tools` for `https://github.com/team/tools.git`), the
+    /// directory name for local ones.
+    pub fn name(&self) -> Option<String> {
+        let url = match self {
+            ToolSource::Local(path) => {
+                return path
+                    .canonicalize()
+                    .unwrap_or_else(|_| path.clone())
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(str::to_string);
+            }
+            ToolSource::Git { url, .. } => url,
+            ToolSource::Archive { location, .. } => location,
+        };
+        let last = url
+            .trim_end_matches(['/', '\\'])
+            .rsplit(['/', '\\', ':'])
+            .next()
+            .unwrap_or(url);
+        let name = last
+            .strip_suffix(".git")
+            .or_else(|| ArchiveFormat::strip_suffix(last))
+            .unwrap_or(last);
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    /// The cache subdirectory for a remote source: its name, for whoever
+    /// looks in the cache, and the SHA-256 digest of its whole spec (URL,
+    /// reference, and checksum), so that distinct sources never share one.
+    fn cache_key(&self) -> String {
+        let spec = match self {
+            ToolSource::Local(path) => path.to_string_lossy().into_owned(),
+            ToolSource::Git {
+                url,
+                reference: Some(reference),
+            } => format!("{}#{}", url, reference),
+            ToolSource::Git { url, .. } => url.clone(),
+            ToolSource::Archive {
+                location,
+                sha256: Some(sha256),
+                ..
+            } => format!("{}#{}", location, sha256),
+            ToolSource::Archive { location, .. } => location.clone(),
+        };
+        let digest = hex(&Sha256::digest(spec.as_bytes()));
+        let name: String = self
+            .name()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if name.is_empty() {
+            digest
+        } else {
+            format!("{}-{}", name, digest)
+        }
+    }
+}
+
+impl fmt::Display for ToolSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolSource::Local(path) => write!(f, "{}", path.display()),
+            ToolSource::Git {
+                url,
+                reference: Some(reference),
+            } => write!(f, "{}#{}", url, reference),
+            ToolSource::Git { url, .. } => f.write_str(url),
+            ToolSource::Archive { location, .. } => f.write_str(location),
+        }
+    }
+}
+
+/// A directory holding fetched copies of remote sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceCache {
+    dir: PathBuf,
+}
+
+impl Default for SourceCache {
+    fn default() -> Self {
+        Self::new(Self::default_dir())
+    }
+}
+
+impl SourceCache {
+    /// A cache rooted at `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The platform cache directory for fetched sources:
+    /// `$XDG_CACHE_HOME/mcp-serve/sources` (or `~/.cache/...`) on Unix-like
+    /// systems, `%LOCALAPPDATA%\mcp-serve\sources` on Windows.
+    pub fn default_dir() -> PathBuf {
+        let base = if cfg!(windows) {
+            std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_CACHE_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        };
+        base.unwrap_or_else(std::env::temp_dir)
+            .join("mcp-serve")
+            .join("sources")
+    }
+
+    /// The cache's root directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Make `source` available locally, returning the directory to scan.
+    ///
+    /// Local directories are returned as-is. Git repositories are cloned on
+    /// first use and updated on every later call. Downloaded archives are
+    /// fetched once and reused until removed from the cache; local archives
+    /// are re-extracted every time, so edits to them are picked up.
+    pub fn fetch(&self, source: &ToolSource) -> Result<PathBuf, SourceError> {
+        match source {
+            ToolSource::Local(path) => Ok(path.clone()),
+            ToolSource::Git { url, reference } => {
+                let checkout = self.dir.join(source.cache_key());
+                self.fetch_git(url, reference.as_deref(), &checkout)?;
+                Ok(checkout)
+            }
+            ToolSource::Archive {
+                location,
+                format,
+                sha256,
+            } => {
+                let target = self.dir.join(source.cache_key());
+                let is_url = location.contains("://");
+                if !(is_url && target.is_dir()) {
+                    self.fetch_archive(location, *format, sha256.as_deref(), &target)?;
+                }
+                Ok(content_dir(&target))
+            }
+        }
+    }
+
+    fn fetch_git(
+        &self,
+        url: &str,
+        reference: Option<&str>,
+        checkout: &Path,
+    ) -> Result<(), SourceError> {
+        // A reference is passed to git after the URL, where one starting
+        // with `-` would still be read as an option.
+        if let Some(reference) = reference.filter(|reference| reference.starts_with('-')) {
+            return Err(SourceError::InvalidReference {
+                reference: reference.to_string(),
+            });
+        }
+        if !checkout.join(".git").exists() {
+            create_dir(checkout)?;
+            run(Command::new("git").arg("init").arg("--quiet").arg(checkout))?;
+        }
+        run(Command::new("git")
+            .arg("-C")
+            .arg(checkout)
+            .args(["fetch", "--quiet", "--depth", "1", "--", url])
+            .arg(reference.unwrap_or("HEAD")))?;
+        run(Command::new("git").arg("-C").arg(checkout).args([
+            "reset",
+            "--quiet",
+            "--hard",
+            "FETCH_HEAD",
+        ]))
+    }
+
+    /// Download (if needed), verify, and extract an archive into `target`,
+    /// replacing any previous extraction.
+    fn fetch_archive(
+        &self,
+        location: &str,
+        format: ArchiveFormat,
+        sha256: Option<&str>,
+        target: &Path,
+    ) -> Result<(), SourceError> {
+        create_dir(&self.dir)?;
+        let partial = with_suffix(target, "partial");
+        remove_dir(&partial)?;
+        create_dir(&partial)?;
+
+        let download = with_suffix(target, "download");
+        let archive = if location.contains("://") {
+            run(Command::new("curl")
+                .args([
+                    "--fail",
+                    "--silent",
+                    "--show-error",
+                    "--location",
+                    "--output",
+                ])
+                .arg(&download)
+                .arg(location))?;
+            download.clone()
+        } else {
+            PathBuf::from(location)
+        };
+
+        let result = verify_checksum(&archive, sha256)
+            .and_then(|()| run(&mut format.extract_command(&archive, &partial)));
+        if archive == download {
+            let _ = fs::remove_file(&download);
+        }
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&partial);
+            return Err(e);
+        }
+
+        remove_dir(target)?;
+        fs::rename(&partial, target).map_err(|source| SourceError::Io {
+            path: target.to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// Check that the file at `path` has the SHA-256 digest `expected` (hex), if
+/// one is given.
+fn verify_checksum(path: &Path, expected: Option<&str>) -> Result<(), SourceError> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    let actual = sha256_file(path).map_err(|source| SourceError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(SourceError::ChecksumMismatch {
+            path: path.to_path_buf(),
+            expected: expected.to_string(),
+            actual,
+        })
+    }
+}
+
+/// The SHA-256 digest of the file at `path`, as lowercase hex.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// `bytes` as lowercase hex.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Append `.{suffix}` to the file name of `path`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn remove_dir(path: &Path) -> Result<(), SourceError> {
+    match fs::remove_dir_all(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(SourceError::Io {
+            path: path.to_path_buf(),
+            source: e,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The directory holding an extracted archive's contents: its single
+/// top-level directory if it has one (as release archives usually do),
+/// otherwise the extraction directory itself.
+fn content_dir(extracted: &Path) -> PathBuf {
+    let entries: Vec<_> = fs::read_dir(extracted)
+        .map(|entries| entries.filter_map(Result::ok).collect())
+        .unwrap_or_default();
+    match entries.as_slice() {
+        [only] if only.path().is_dir() => only.path(),
+        _ => extracted.to_path_buf(),
+    }
+}
+
+fn create_dir(path: &Path) -> Result<(), SourceError> {
+    fs::create_dir_all(path).map_err(|source| SourceError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Run `command`, turning a missing program or a non-zero exit into an error.
+fn run(command: &mut Command) -> Result<(), SourceError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .map_err(|source| SourceError::CommandUnavailable {
+            program: program.clone(),
+            source,
+        })?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    Err(SourceError::CommandFailed {
+        command: format!("{} {}", program, args.join(" ")),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
+
+/// Errors that can occur while fetching a remote source.
+#[derive(Debug)]
+pub enum SourceError {
+    /// The cache directory could not be written, or an archive read.
+    Io { path: PathBuf, source: io::Error },
+
+    /// A program needed to fetch the source (`git`, `curl`, `tar`) could not
+    /// be started.
+    CommandUnavailable { program: String, source: io::Error },
+
+    /// A fetch command exited unsuccessfully.
+    CommandFailed { command: String, stderr: String },
+
+    /// An archive's SHA-256 digest does not match the expected checksum.
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    /// A git reference that would be taken for an option.
+    InvalidReference { reference: String },
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Io { path, source } => {
+                write!(f, "failed to access {}: {}", path.display(), source)
+            }
+            SourceError::CommandUnavailable { program, source } => {
+                write!(
+                    f,
+                    "could not run `{}` (is it installed?): {}",
+                    program, source
+                )
+            }
+            SourceError::CommandFailed { command, stderr } if stderr.is_empty() => {
+                write!(f, "`{}` failed", command)
+            }
+            SourceError::CommandFailed { command, stderr } => {
+                write!(f, "`{}` failed: {}", command, stderr)
+            }
+            SourceError::ChecksumMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch for {}: expected sha256 {}, got {}",
+                path.display(),
+                expected,
+                actual
+            ),
+            SourceError::InvalidReference { reference } => write!(
+                f,
+                "invalid git reference {:?}: references may not start with \"-\"",
+                reference
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SourceError::Io { source, .. } => Some(source),
+            SourceError::CommandUnavailable { source, .. } => Some(source),
+            SourceError::CommandFailed { .. } => None,
+            SourceError::ChecksumMismatch { .. } => None,
+            SourceError::InvalidReference { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sources() {
+        assert_eq!(
+            ToolSource::parse("./tools"),
+            ToolSource::Local(PathBuf::from("./tools"))
+        );
+        assert_eq!(
+            ToolSource::parse("https://github.com/team/tools.git"),
+            ToolSource::Git {
+                url: "https://github.com/team/tools.git".to_string(),
+                reference: None
+            }
+        );
+        assert_eq!(
+            ToolSource::parse("git+https://git.example.com/tools#v2"),
+            ToolSource::Git {
+                url: "https://git.example.com/tools".to_string(),
+                reference: Some("v2".to_string())
+            }
+        );
+        assert_eq!(
+            ToolSource::parse("git@github.com:team/tools.git"),
+            ToolSource::Git {
+                url: "git@github.com:team/tools.git".to_string(),
+                reference: None
+            }
+        );
+        assert_eq!(
+            ToolSource::parse("https://example.com/tools-1.2.tar.gz"),
+            ToolSource::Archive {
+                location: "https://example.com/tools-1.2.tar.gz".to_string(),
+                format: ArchiveFormat::TarGz,
+                sha256: None
+            }
+        );
+        assert_eq!(
+            ToolSource::parse("./tools.ZIP#sha256=ABCDEF"),
+            ToolSource::Archive {
+                location: "./tools.ZIP".to_string(),
+                format: ArchiveFormat::Zip,
+                sha256: Some("abcdef".to_string())
+            }
+        );
+        // A local directory that happens to end in `.git`
+        assert!(!ToolSource::parse("./vendor/tools.git").is_remote());
+    }
+
+    #[test]
+    fn test_source_names() {
+        let name = |spec: &str| ToolSource::parse(spec).name();
+        assert_eq!(
+            name("https://github.com/team/tools.git"),
+            Some("tools".to_string())
+        );
+        assert_eq!(
+            name("git@github.com:team/ops-tools.git#main"),
+            Some("ops-tools".to_string())
+        );
+        assert_eq!(
+            name("https://example.com/pack-1.2.tgz"),
+            Some("pack-1.2".to_string())
+        );
+        assert_eq!(name("./dist/pack.zip"), Some("pack".to_string()));
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let key = |spec: &str| ToolSource::parse(spec).cache_key();
+        let tools = key("https://github.com/team/tools.git#v1/2");
+        assert!(tools.starts_with("tools-"), "{}", tools);
+        assert_eq!(tools.len(), "tools-".len() + 64);
+        assert!(tools.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+
+        // Sources that differ only in characters a name cannot hold, or in
+        // their scheme, get their own directories.
+        assert_ne!(key("https://h/a/b.tgz"), key("https://h/a_b.tgz"));
+        assert_ne!(key("http://h/tools.git"), key("https://h/tools.git"));
+        assert_ne!(
+            key("https://h/tools.git#a/b"),
+            key("https://h/tools.git#a_b")
+        );
+        assert_eq!(tools, key("https://github.com/team/tools.git#v1/2"));
+    }
+
+    #[test]
+    fn test_reference_taken_for_option() {
+        let cache = SourceCache::new(std::env::temp_dir().join("mcp-serve-unused"));
+        let source = ToolSource::parse("https://example.com/tools.git#--upload-pack=touch");
+        assert!(matches!(
+            cache.fetch(&source),
+            Err(SourceError::InvalidReference { .. })
+        ));
+        assert!(!cache.dir().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fetch_git_repository() {
+        use tempfile::TempDir;
+
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let repo = TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(repo.path())
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "--quiet"]);
+        fs::write(repo.path().join("tool.sh"), "#!/bin/sh\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "Add tool"]);
+
+        let cache_dir = TempDir::new().unwrap();
+        let cache = SourceCache::new(cache_dir.path());
+        let source = ToolSource::Git {
+            url: format!("file://{}", repo.path().display()),
+            reference: None,
+        };
+
+        let checkout = cache.fetch(&source).unwrap();
+        assert!(checkout.starts_with(cache_dir.path()));
+        assert!(checkout.join("tool.sh").is_file());
+
+        // A second fetch picks up new commits
+        fs::write(repo.path().join("other.sh"), "#!/bin/sh\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "Add another tool"]);
+        assert_eq!(cache.fetch(&source).unwrap(), checkout);
+        assert!(checkout.join("other.sh").is_file());
+    }
+
+    /// Build `pack-1.0.{extension}` containing `pack-1.0/bin/tool` in `dir`,
+    /// returning `None` if the archiver is unavailable.
+    #[cfg(unix)]
+    fn build_archive(dir: &Path, format: ArchiveFormat) -> Option<PathBuf> {
+        fs::create_dir_all(dir.join("pack-1.0/bin")).unwrap();
+        fs::write(dir.join("pack-1.0/bin/tool"), "#!/bin/sh\n").unwrap();
+        let (archive, mut command) = match format {
+            ArchiveFormat::TarGz => {
+                let archive = dir.join("pack-1.0.tar.gz");
+                let mut command = Command::new("tar");
+                command.arg("-czf").arg(&archive).arg("pack-1.0");
+                (archive, command)
+            }
+            ArchiveFormat::Zip => {
+                let archive = dir.join("pack-1.0.zip");
+                let mut command = Command::new("zip");
+                command.arg("-qr").arg(&archive).arg("pack-1.0");
+                (archive, command)
+            }
+        };
+        let status = command.current_dir(dir).status();
+        status.is_ok_and(|s| s.success()).then_some(archive)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fetch_downloaded_tarball() {
+        use tempfile::TempDir;
+
+        let work = TempDir::new().unwrap();
+        let Some(archive) = build_archive(work.path(), ArchiveFormat::TarGz) else {
+            return;
+        };
+
+        let cache_dir = TempDir::new().unwrap();
+        let cache = SourceCache::new(cache_dir.path());
+        let source = ToolSource::Archive {
+            location: format!("file://{}", archive.display()),
+            format: ArchiveFormat::TarGz,
+            sha256: None,
+        };
+
+        // The archive's single top-level directory is served
+        let dir = cache.fetch(&source).unwrap();
+        assert_eq!(dir.file_name().unwrap(), "pack-1.0");
+        assert!(dir.join("bin").join("tool").is_file());
+
+        // Cached: a second fetch does not download again
+        fs::remove_file(&archive).unwrap();
+        assert_eq!(cache.fetch(&source).unwrap(), dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fetch_local_zip_with_checksum() {
+        use tempfile::TempDir;
+
+        let work = TempDir::new().unwrap();
+        let Some(archive) = build_archive(work.path(), ArchiveFormat::Zip) else {
+            return;
+        };
+        let checksum = sha256_file(&archive).unwrap();
+        let cache = SourceCache::new(work.path().join("cache"));
+
+        let spec = format!("{}#sha256={}", archive.display(), checksum);
+        let dir = cache.fetch(&ToolSource::parse(&spec)).unwrap();
+        assert!(dir.join("bin").join("tool").is_file());
+
+        let spec = format!("{}#sha256={}", archive.display(), "0".repeat(64));
+        match cache.fetch(&ToolSource::parse(&spec)) {
+            Err(SourceError::ChecksumMismatch {
+                expected, actual, ..
+            }) => {
+                assert_eq!(expected, "0".repeat(64));
+                assert_eq!(actual, checksum);
+            }
+            other => panic!("expected ChecksumMismatch, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Strict parsing, which rejects fields the definition format does not know.
+//!
+//! Definitions are parsed leniently by default so that files written for a
+//! newer version still load, but that also means a typo such as
+//! `descripton:` is silently ignored. Strict parsing turns any ignored field
+//! into an error naming the fields that were expected there, as
+//! `deny_unknown_fields` would.
+//!
+//! Manifest entries are always parsed leniently: their definitions are
+//! flattened next to `command`, which hides unknown keys from the parser.
+
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, Visitor};
+
+use super::{ExecutionConfig, TestCase, ToolDefinition, ToolInput, ToolOutput};
+
+/// Deserialize a `T` from `deserializer`, failing on the first unknown field.
+pub(crate) fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let mut unknown = None;
+    let value = serde_ignored::deserialize(deserializer, |path| {
+        unknown.get_or_insert_with(|| path.to_string());
+    })?;
+    match unknown {
+        Some(path) => Err(unknown_field(&path)),
+        None => Ok(value),
+    }
+}
+
+/// The error for the ignored field at `path` (such as `input.tempalte` or,
+/// in a list, `tools.0.descripton`).
+fn unknown_field<E: de::Error>(path: &str) -> E {
+    let (parent, key) = path.rsplit_once('.').unwrap_or(("", path));
+    let mut segments = parent.rsplit('.');
+    let expected = match (segments.next().unwrap_or_default(), segments.next()) {
+        ("input", _) => fields::<ToolInput>(),
+        ("output", _) => fields::<ToolOutput>(),
+        ("execution", _) => fields::<ExecutionConfig>(),
+        // An entry of the `tests` list
+        (_, Some("tests")) => fields::<TestCase>(),
+        _ => fields::<ToolDefinition>(),
+    };
+    let error = E::unknown_field(key, expected);
+    if parent.is_empty() {
+        error
+    } else {
+        E::custom(format!("{}: {}", parent, error))
+    }
+}
+
+/// The field names a derived `Deserialize` implementation accepts.
+fn fields<T: DeserializeOwned>() -> &'static [&'static str] {
+    /// A deserializer that records the fields it is asked for, then fails.
+    struct FieldNames(&'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for &mut FieldNames {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut names = FieldNames(&[]);
+    let _ = T::deserialize(&mut names);
+    names.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_discovery::SidecarFormat;
+
+    const TYPO: &str = "\
+name: deploy
+descripton: Deploys
+description: Deploys
+input:
+  template: \"\"
+  schema: { type: object }
+output:
+  template: \"\"
+  schema: { type: object }
+";
+
+    #[test]
+    fn test_strict_rejects_unknown_fields() {
+        assert!(ToolDefinition::from_yaml(TYPO).is_ok());
+
+        let error = ToolDefinition::from_yaml_strict(TYPO).unwrap_err();
+        assert!(error
+            .message()
+            .starts_with("unknown field `descripton`, expected one of `name`"));
+        assert_eq!(error.line(), Some(2));
+        assert_eq!(error.hint(), Some("did you mean `description:`?"));
+
+        let nested = TYPO.replace("descripton: Deploys\n", "").replacen(
+            "  template: \"\"\n",
+            "  template: \"\"\n  tempalte: x\n",
+            1,
+        );
+        let error = ToolDefinition::from_yaml_strict(&nested).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "input: unknown field `tempalte`, expected one of `template`, `stdin_template`, `env_template`, `engine`, `schema`"
+        );
+        assert_eq!(error.hint(), Some("did you mean `template:`?"));
+    }
+
+    #[test]
+    fn test_strict_sidecar_formats() {
+        let json = r#"{"name": "a", "description": "b", "titel": "A",
+            "input": {"template": "", "schema": {}},
+            "output": {"template": "", "schema": {}}}"#;
+        assert!(SidecarFormat::Json.parse_all(json).is_ok());
+        let error = SidecarFormat::Json.parse_all_strict(json).unwrap_err();
+        assert!(error.to_string().contains("unknown field `titel`"));
+
+        let list = format!("tools:\n  - {}", TYPO.replace('\n', "\n    "));
+        let error = SidecarFormat::Yaml.parse_all_strict(&list).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("tools.0: unknown field `descripton`"));
+    }
+}
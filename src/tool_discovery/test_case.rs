@@ -0,0 +1,73 @@
+//! Test cases declared alongside a definition.
+//!
+//! The optional `tests` section of a definition lists calls and what they
+//! should do, for `mcp-serve test` to check. A case gives the arguments of
+//! a call and any of: the command line they expand to, sample output and
+//! the result it parses to, or, with `run`, a real call of the tool:
+//!
+//! ```yaml
+//! tests:
+//!   - name: files a ticket
+//!     arguments: { title: Fix the build }
+//!     argv: [--title, Fix the build]
+//!     stdout: "ID: 42\n"
+//!     result: { id: 42 }
+//!   - name: runs
+//!     arguments: { title: Smoke test }
+//!     run: true
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A call of a tool, and what it should run and return.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TestCase {
+    /// What the case checks, shown in test output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The arguments of the call
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub arguments: Map<String, Value>,
+
+    /// The arguments the executable should be run with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argv: Option<Vec<String>>,
+
+    /// The script the shell should run, for tools with `execution.shell`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+
+    /// What the tool should be given on standard input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdin: Option<String>,
+
+    /// Sample standard output, parsed as the tool's output would be
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout: Option<String>,
+
+    /// Sample standard error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+
+    /// Sample exit code (default: 0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    /// The result the sample output should parse to, or a real call return
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+
+    /// Also call the tool for real, which must succeed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub run: bool,
+}
+
+impl TestCase {
+    /// Whether the case gives sample output to parse.
+    pub fn has_sample_output(&self) -> bool {
+        self.stdout.is_some() || self.stderr.is_some() || self.exit_code.is_some()
+    }
+}
@@ -0,0 +1,226 @@
+//! Incremental change events for a set of tool directories.
+//!
+//! A [`ToolWatcher`] remembers what the previous scan found and reports only
+//! the differences as [`ToolEvent`]s, so embedders (and the server's watch
+//! mode) can update what they expose without rebuilding everything on every
+//! change.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{DirectoryScanner, DiscoveredTool, MetadataSource, ScanError, ToolRoot};
+
+/// A change to the set of discovered tools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolEvent {
+    /// A tool appeared.
+    ToolAdded(DiscoveredTool),
+
+    /// A tool disappeared.
+    ToolRemoved(DiscoveredTool),
+
+    /// A tool's executable or definition was modified, or its definition moved
+    /// to a different source.
+    ToolChanged {
+        previous: Box<DiscoveredTool>,
+        current: Box<DiscoveredTool>,
+    },
+}
+
+impl ToolEvent {
+    /// The tool the event concerns (its current state for changes).
+    pub fn tool(&self) -> &DiscoveredTool {
+        match self {
+            ToolEvent::ToolAdded(tool) | ToolEvent::ToolRemoved(tool) => tool,
+            ToolEvent::ToolChanged { current, .. } => current,
+        }
+    }
+}
+
+/// Identifies a tool across scans: its executable, plus its position in a
+/// manifest, since one command may back several manifest entries.
+type ToolKey = (PathBuf, Option<usize>);
+
+/// Modification time and size of a file, used to notice edits.
+type FileStamp = Option<(SystemTime, u64)>;
+
+/// What a tool looked like at the last scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    tool: DiscoveredTool,
+    executable: FileStamp,
+    definition: FileStamp,
+    docs: FileStamp,
+}
+
+impl Snapshot {
+    fn take(tool: DiscoveredTool) -> Self {
+        let definition = match &tool.metadata_source {
+            MetadataSource::Sidecar(path) | MetadataSource::Manifest { path, .. } => stamp(path),
+            MetadataSource::Embedded => None,
+        };
+        Self {
+            executable: stamp(&tool.path),
+            definition,
+            docs: tool.docs.as_deref().and_then(stamp),
+            tool,
+        }
+    }
+
+    fn key(&self) -> ToolKey {
+        let index = match self.tool.metadata_source {
+            MetadataSource::Manifest { index, .. } => Some(index),
+            _ => None,
+        };
+        (self.tool.path.clone(), index)
+    }
+}
+
+fn stamp(path: &Path) -> FileStamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Rescans a set of tool roots, reporting what changed since the last scan.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mcp_serve::tool_discovery::{DirectoryScanner, ToolRoot, ToolWatcher};
+///
+/// let mut watcher = ToolWatcher::new(DirectoryScanner::new(), vec![ToolRoot::new("./tools")]);
+/// loop {
+///     for event in watcher.poll().unwrap() {
+///         println!("{:?}", event);
+///     }
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ToolWatcher {
+    scanner: DirectoryScanner,
+    roots: Vec<ToolRoot>,
+    known: BTreeMap<ToolKey, Snapshot>,
+}
+
+impl ToolWatcher {
+    /// Watch `roots`, scanning them with `scanner`. Nothing is scanned until
+    /// the first [`poll`](Self::poll), which reports every tool as added.
+    pub fn new(scanner: DirectoryScanner, roots: Vec<ToolRoot>) -> Self {
+        Self {
+            scanner,
+            roots,
+            known: BTreeMap::new(),
+        }
+    }
+
+    /// Rescan the roots and return the changes since the previous poll.
+    ///
+    /// Events are ordered removals first, then changes and additions in path
+    /// order. If scanning fails, the previous state is kept, so the next
+    /// successful poll reports everything that changed in between.
+    pub fn poll(&mut self) -> Result<Vec<ToolEvent>, ScanError> {
+        let mut current = BTreeMap::new();
+        for root in &self.roots {
+            for tool in self.scanner.scan_root(root)? {
+                let snapshot = Snapshot::take(tool);
+                current.insert(snapshot.key(), snapshot);
+            }
+        }
+
+        let mut events: Vec<ToolEvent> = self
+            .known
+            .iter()
+            .filter(|(key, _)| !current.contains_key(*key))
+            .map(|(_, previous)| ToolEvent::ToolRemoved(previous.tool.clone()))
+            .collect();
+        for (key, snapshot) in &current {
+            match self.known.get(key) {
+                None => events.push(ToolEvent::ToolAdded(snapshot.tool.clone())),
+                Some(previous) if previous != snapshot => events.push(ToolEvent::ToolChanged {
+                    previous: Box::new(previous.tool.clone()),
+                    current: Box::new(snapshot.tool.clone()),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        self.known = current;
+        Ok(events)
+    }
+
+    /// The tools found by the most recent poll.
+    pub fn tools(&self) -> impl Iterator<Item = &DiscoveredTool> {
+        self.known.values().map(|snapshot| &snapshot.tool)
+    }
+}
+
+// Discovery relies on the executable bit, so these tests are Unix-only.
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn write_executable(path: &Path, contents: &str) {
+        fs::write(path, contents).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_poll_reports_changes() {
+        let dir = TempDir::new().unwrap();
+        write_executable(&dir.path().join("keep"), "binary");
+        write_executable(&dir.path().join("remove"), "binary");
+        let mut watcher =
+            ToolWatcher::new(DirectoryScanner::new(), vec![ToolRoot::new(dir.path())]);
+
+        let events = watcher.poll().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .all(|event| matches!(event, ToolEvent::ToolAdded(_))));
+        assert!(watcher.poll().unwrap().is_empty());
+
+        fs::remove_file(dir.path().join("remove")).unwrap();
+        write_executable(&dir.path().join("new"), "binary");
+        fs::write(dir.path().join("keep.yaml"), "name: keep\n").unwrap();
+
+        let events = watcher.poll().unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(
+            matches!(&events[0], ToolEvent::ToolRemoved(tool) if tool.path.ends_with("remove"))
+        );
+        match &events[1] {
+            ToolEvent::ToolChanged { previous, current } => {
+                assert_eq!(previous.metadata_source, MetadataSource::Embedded);
+                assert_eq!(
+                    current.metadata_source,
+                    MetadataSource::Sidecar(dir.path().join("keep.yaml"))
+                );
+            }
+            other => panic!("expected ToolChanged, got {:?}", other),
+        }
+        assert!(matches!(&events[2], ToolEvent::ToolAdded(tool) if tool.path.ends_with("new")));
+        assert_eq!(watcher.tools().count(), 2);
+    }
+
+    #[test]
+    fn test_poll_detects_edited_definition() {
+        let dir = TempDir::new().unwrap();
+        write_executable(&dir.path().join("tool"), "binary");
+        let sidecar = dir.path().join("tool.yaml");
+        fs::write(&sidecar, "name: tool\n").unwrap();
+        let mut watcher =
+            ToolWatcher::new(DirectoryScanner::new(), vec![ToolRoot::new(dir.path())]);
+        watcher.poll().unwrap();
+
+        fs::write(&sidecar, "name: tool\ndescription: Edited\n").unwrap();
+
+        let events = watcher.poll().unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], ToolEvent::ToolChanged { .. }));
+    }
+}
@@ -0,0 +1,244 @@
+//! MCP wire format for tools.
+//!
+//! [`McpTool`] keeps the snake_case field names used in mcp-serve's own YAML
+//! files. MCP messages are JSON with camelCase keys (`inputSchema`,
+//! `outputSchema`), and omit absent optional fields rather than sending
+//! `null`; [`WireTool`] serializes exactly that shape. [`CallToolResult`]
+//! is the shape of a `tools/call` result, and [`Resource`] and
+//! [`ResourceContents`] those of `resources/list` and `resources/read`
+//! entries.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Icon, McpTool, ToolAnnotations};
+
+/// A tool as it appears in MCP `tools/list` results.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_serve::tool_discovery::wire::WireTool;
+/// use serde_json::json;
+///
+/// let tool: WireTool = serde_json::from_value(json!({
+///     "name": "uptime",
+///     "description": "Shows uptime",
+///     "inputSchema": {"type": "object"}
+/// }))
+/// .unwrap();
+/// assert_eq!(tool.name, "uptime");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WireTool {
+    /// Unique identifier for the tool
+    pub name: String,
+
+    /// Optional human-readable display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// Human-readable description of functionality
+    #[serde(default)]
+    pub description: String,
+
+    /// JSON Schema for input parameters
+    pub input_schema: serde_json::Value,
+
+    /// Optional JSON Schema for structured output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+
+    /// Optional behavior hints for clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+
+    /// Optional icons for clients to display
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icons: Option<Vec<Icon>>,
+
+    /// Optional metadata for clients
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl From<McpTool> for WireTool {
+    fn from(tool: McpTool) -> Self {
+        Self {
+            name: tool.name,
+            title: tool.title,
+            description: tool.description,
+            input_schema: tool.input_schema,
+            output_schema: tool.output_schema,
+            annotations: tool.annotations,
+            icons: tool.icons,
+            meta: tool.meta,
+        }
+    }
+}
+
+impl From<WireTool> for McpTool {
+    fn from(tool: WireTool) -> Self {
+        Self {
+            name: tool.name,
+            title: tool.title,
+            description: tool.description,
+            input_schema: tool.input_schema,
+            output_schema: tool.output_schema,
+            annotations: tool.annotations,
+            icons: tool.icons,
+            meta: tool.meta,
+        }
+    }
+}
+
+impl McpTool {
+    /// Serialize the tool in the MCP wire format.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(WireTool::from(self.clone())).expect("tool serialization cannot fail")
+    }
+
+    /// Parse a tool from the MCP wire format.
+    pub fn from_json(value: serde_json::Value) -> Result<Self, serde_json::Error> {
+        serde_json::from_value::<WireTool>(value).map(Into::into)
+    }
+}
+
+/// The result of a `tools/call` request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    /// Unstructured content blocks
+    #[serde(default)]
+    pub content: Vec<ContentBlock>,
+
+    /// The result as a JSON object matching the tool's output schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<serde_json::Value>,
+
+    /// Whether the tool reported an error
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl CallToolResult {
+    /// A result reporting that the call failed, with `message` as its text.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            content: vec![ContentBlock::Text {
+                text: message.into(),
+            }],
+            structured_content: None,
+            is_error: true,
+        }
+    }
+}
+
+/// A block of unstructured content in a tool result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    /// Plain text.
+    Text { text: String },
+
+    /// A link to a resource the client may fetch.
+    ResourceLink {
+        uri: String,
+        name: String,
+        #[serde(rename = "mimeType", default, skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+    },
+}
+
+/// A resource as it appears in MCP `resources/list` results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    /// Where the resource can be read from
+    pub uri: String,
+
+    /// A name for the resource
+    pub name: String,
+
+    /// What the resource is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The resource's MIME type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// The text of a resource, as returned by `resources/read`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    /// The resource's URI
+    pub uri: String,
+
+    /// The resource's MIME type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+
+    /// The resource's contents
+    pub text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_wire_format_matches_spec() {
+        let tool = McpTool {
+            name: "search".to_string(),
+            title: None,
+            description: "Searches".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: Some(json!({"type": "object"})),
+            annotations: Some(ToolAnnotations {
+                read_only_hint: Some(true),
+                ..ToolAnnotations::default()
+            }),
+            icons: None,
+            meta: Some(json!({"version": "1.0"}).as_object().unwrap().clone()),
+        };
+
+        let json = tool.to_json();
+
+        assert_eq!(
+            json,
+            json!({
+                "name": "search",
+                "description": "Searches",
+                "inputSchema": {"type": "object"},
+                "outputSchema": {"type": "object"},
+                "annotations": {"readOnlyHint": true},
+                "_meta": {"version": "1.0"}
+            })
+        );
+        assert_eq!(McpTool::from_json(json).unwrap(), tool);
+    }
+
+    #[test]
+    fn test_wire_format_omits_absent_fields() {
+        let tool = McpTool {
+            name: "noop".to_string(),
+            title: None,
+            description: "Does nothing".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+            annotations: None,
+            icons: None,
+            meta: None,
+        };
+
+        let json = tool.to_json();
+        let keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+
+        assert_eq!(keys, ["description", "inputSchema", "name"]);
+    }
+}
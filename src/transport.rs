@@ -0,0 +1,629 @@
+//! Carrying MCP messages between clients and an [`McpServer`].
+//!
+//! - [`Transport::Stdio`]: the client starts the server as a subprocess and
+//!   exchanges newline-delimited JSON-RPC messages over its standard input
+//!   and output. Standard output carries nothing else, so the server's own
+//!   messages are logged to stderr (see [`crate::logging`]). The server may
+//!   send requests of its own while it answers one, such as to have a call
+//!   confirmed (see [`crate::confirm`]); messages the client sends before
+//!   replying are answered afterwards.
+//! - [`Transport::Http`]: the server listens for clients posting JSON-RPC
+//!   messages to [`HTTP_ENDPOINT`], and answers each with a JSON response
+//!   (the "Streamable HTTP" transport, without server-initiated streams).
+//!   Requests from web pages on other origins are refused, unless allowed
+//!   explicitly, so a page in a browser cannot reach a server listening on
+//!   localhost.
+//!
+//! Either can be stopped from another thread: the `_until` variants stop
+//! taking messages once asked to, and return when the replies to those
+//! already taken are sent.
+
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read, Write};
+use std::net::SocketAddr;
+use std::sync::{mpsc, Arc, PoisonError, RwLock};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::server::{McpServer, Peer};
+
+/// How clients reach the server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Transport {
+    /// JSON-RPC over standard input and output.
+    #[default]
+    Stdio,
+
+    /// JSON-RPC over HTTP POST requests.
+    Http,
+}
+
+/// The path the HTTP transport serves MCP on.
+pub const HTTP_ENDPOINT: &str = "/mcp";
+
+/// The address the HTTP transport listens on by default.
+pub const DEFAULT_HTTP_ADDRESS: &str = "127.0.0.1:8080";
+
+/// Answer the messages read from `input`, one per line, writing each reply
+/// to `output` as a line, until `input` ends.
+pub fn serve_stdio(server: &McpServer, input: impl BufRead, output: impl Write) -> io::Result<()> {
+    let mut lines = input.lines();
+    Stdio::new(|| lines.next(), output).serve(server)
+}
+
+/// How often a transport checks whether it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Like [`serve_stdio`], but stop taking messages once `stop` returns true.
+/// `input` is read on a thread of its own, so that stopping does not wait
+/// for the next message.
+pub fn serve_stdio_until(
+    server: &McpServer,
+    input: impl BufRead + Send + 'static,
+    output: impl Write,
+    stop: impl Fn() -> bool,
+) -> io::Result<()> {
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in input.lines() {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let read = || {
+        while !stop() {
+            match lines.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => return Some(line),
+                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        None
+    };
+    Stdio::new(read, output).serve(server)
+}
+
+/// The server's end of the stdio transport.
+struct Stdio<R, W> {
+    /// The next line from the client, or `None` once there are no more
+    read: R,
+    output: W,
+    /// Messages received while waiting for the reply to a request of the
+    /// server's, to answer next
+    queued: VecDeque<String>,
+    /// How many requests the server has sent
+    requests: u64,
+}
+
+impl<R, W> Stdio<R, W>
+where
+    R: FnMut() -> Option<io::Result<String>>,
+    W: Write,
+{
+    fn new(read: R, output: W) -> Self {
+        Self {
+            read,
+            output,
+            queued: VecDeque::new(),
+            requests: 0,
+        }
+    }
+
+    /// Answer the client's messages until there are no more.
+    fn serve(mut self, server: &McpServer) -> io::Result<()> {
+        while let Some(line) = self.queued.pop_front().map(Ok).or_else(&mut self.read) {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            tracing::trace!("received {}", server.redact_message(&line));
+            if let Some(reply) = server.handle_text_with(&line, Some(&mut self)) {
+                self.send(&reply)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a message to the client, as a line.
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        tracing::trace!("sent {}", message);
+        writeln!(self.output, "{}", message)?;
+        self.output.flush()
+    }
+}
+
+impl<R, W> Peer for Stdio<R, W>
+where
+    R: FnMut() -> Option<io::Result<String>>,
+    W: Write,
+{
+    fn request(&mut self, method: &str, params: Value) -> io::Result<Result<Value, Value>> {
+        self.requests += 1;
+        let id = json!(format!("mcp-serve-{}", self.requests));
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        self.send(&request.to_string())?;
+        loop {
+            let Some(line) = (self.read)() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the client stopped before replying",
+                ));
+            };
+            let line = line?;
+            match serde_json::from_str::<Value>(&line) {
+                Ok(Value::Object(mut reply))
+                    if reply.get("id") == Some(&id) && !reply.contains_key("method") =>
+                {
+                    tracing::trace!("received {}", line);
+                    return Ok(match reply.remove("result") {
+                        Some(result) => Ok(result),
+                        None => Err(reply.remove("error").unwrap_or(Value::Null)),
+                    });
+                }
+                _ => self.queued.push_back(line),
+            }
+        }
+    }
+}
+
+/// A listening HTTP transport.
+pub struct HttpTransport {
+    listener: Server,
+    policy: HttpPolicy,
+}
+
+/// What an HTTP transport accepts. Clones share it, so it can be changed
+/// while the transport serves; requests already taken keep the policy they
+/// were taken under.
+#[derive(Debug, Clone, Default)]
+pub struct HttpPolicy(Arc<RwLock<Policy>>);
+
+#[derive(Debug, Clone, Default)]
+struct Policy {
+    /// Origins allowed besides those on this machine
+    allowed_origins: Vec<String>,
+
+    /// Largest request body, in bytes
+    max_request_size: Option<u64>,
+}
+
+impl HttpTransport {
+    /// Listen on `address` (a `host:port`; port 0 picks a free port).
+    pub fn bind(address: &str) -> io::Result<Self> {
+        let listener = Server::http(address).map_err(io::Error::other)?;
+        Ok(Self {
+            listener,
+            policy: HttpPolicy::default(),
+        })
+    }
+
+    /// Also accept requests from web pages on these origins
+    /// (`https://app.example.com`).
+    pub fn with_allowed_origins(self, origins: impl IntoIterator<Item = String>) -> Self {
+        self.policy.write().allowed_origins.extend(origins);
+        self
+    }
+
+    /// Refuse requests whose body is larger than `bytes`.
+    pub fn with_max_request_size(self, bytes: u64) -> Self {
+        self.policy.set_max_request_size(Some(bytes));
+        self
+    }
+
+    /// What the transport accepts, to change it while it serves.
+    pub fn policy(&self) -> HttpPolicy {
+        self.policy.clone()
+    }
+
+    /// The address the transport listens on.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.server_addr().to_ip()
+    }
+
+    /// Answer requests until the listener fails, each on its own thread so
+    /// that a slow tool does not hold up other clients.
+    pub fn serve(self, server: Arc<McpServer>) -> io::Result<()> {
+        self.serve_until(server, || false)
+    }
+
+    /// Like [`serve`](Self::serve), but stop listening once `stop` returns
+    /// true, and return when the requests already taken are answered.
+    pub fn serve_until(self, server: Arc<McpServer>, stop: impl Fn() -> bool) -> io::Result<()> {
+        let mut responders: Vec<thread::JoinHandle<()>> = Vec::new();
+        while !stop() {
+            responders.retain(|responder| !responder.is_finished());
+            let Some(request) = self.listener.recv_timeout(POLL_INTERVAL)? else {
+                continue;
+            };
+            let server = Arc::clone(&server);
+            let policy = self.policy.read().clone();
+            responders.push(thread::spawn(move || respond(&server, &policy, request)));
+        }
+        drop(self.listener);
+        for responder in responders {
+            let _ = responder.join();
+        }
+        Ok(())
+    }
+}
+
+impl HttpPolicy {
+    /// Accept requests from web pages on these origins, besides those on
+    /// this machine, instead of those allowed so far.
+    pub fn set_allowed_origins(&self, origins: Vec<String>) {
+        self.write().allowed_origins = origins;
+    }
+
+    /// Refuse requests whose body is larger than `bytes`; `None` removes the
+    /// limit.
+    pub fn set_max_request_size(&self, bytes: Option<u64>) {
+        self.write().max_request_size = bytes;
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Policy> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Policy> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn respond(server: &McpServer, policy: &Policy, mut request: Request) {
+    let path = request.url().split('?').next().unwrap_or_default();
+    let reply = if path != HTTP_ENDPOINT {
+        status(404)
+    } else if !is_allowed_origin(&request, &policy.allowed_origins) {
+        status(403)
+    } else if *request.method() != Method::Post {
+        // Without server-initiated streams there is nothing to GET.
+        status(405).with_header(header("Allow", "POST"))
+    } else {
+        match read_body(&mut request, policy.max_request_size) {
+            Err(code) => status(code),
+            Ok(body) => {
+                tracing::trace!("received {}", server.redact_message(&body));
+                match server.handle_text(&body) {
+                    Some(reply) => {
+                        tracing::trace!("sent {}", reply);
+                        Response::from_string(reply)
+                            .with_header(header("Content-Type", "application/json"))
+                    }
+                    // Notifications and responses are only acknowledged.
+                    None => status(202),
+                }
+            }
+        }
+    };
+    tracing::debug!(
+        "{} {} answered {}",
+        request.method(),
+        request.url(),
+        reply.status_code().0
+    );
+    // The client may already have gone away.
+    let _ = request.respond(reply);
+}
+
+/// Read the body of `request`, or the status refusing it: 413 if it is
+/// larger than `limit`, 400 if it cannot be read as text.
+fn read_body(request: &mut Request, limit: Option<u64>) -> Result<String, u16> {
+    let limit = limit.unwrap_or(u64::MAX);
+    if request
+        .body_length()
+        .is_some_and(|length| length as u64 > limit)
+    {
+        return Err(413);
+    }
+    // The declared length may be missing, as with chunked bodies.
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(limit.saturating_add(1))
+        .read_to_string(&mut body)
+        .map_err(|_| 400_u16)?;
+    if body.len() as u64 > limit {
+        return Err(413);
+    }
+    Ok(body)
+}
+
+/// Whether the request has no `Origin` (it does not come from a web page),
+/// or one on this machine or in `allowed`.
+fn is_allowed_origin(request: &Request, allowed: &[String]) -> bool {
+    let Some(origin) = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Origin"))
+    else {
+        return true;
+    };
+    let origin = origin.value.as_str();
+    if allowed
+        .iter()
+        .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    {
+        return true;
+    }
+    let host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn status(code: u16) -> Response<io::Cursor<Vec<u8>>> {
+    Response::from_data(Vec::new()).with_status_code(code)
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::confirm::ConfirmPolicy;
+    use crate::registry::{DuplicatePolicy, ToolRegistry};
+    use crate::tool_discovery::ToolDefinition;
+    use std::io::Read;
+    use std::net::TcpStream;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn server() -> McpServer {
+        McpServer::new(ToolRegistry::new(DuplicatePolicy::Error))
+    }
+
+    #[test]
+    fn test_stdio() {
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#,
+            "\n\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+
+        serve_stdio(&server(), input.as_bytes(), &mut output).unwrap();
+
+        let replies: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            replies,
+            [
+                json!({"jsonrpc": "2.0", "id": 1, "result": {}}),
+                json!({"jsonrpc": "2.0", "id": 2, "result": {"tools": []}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stdio_requests() {
+        let definition = ToolDefinition::from_yaml(
+            r#"
+name: wipe
+description: Wipe the disk
+annotations: { destructiveHint: true }
+input: { template: "", schema: { type: object } }
+output: { template: "", schema: { type: object } }
+"#,
+        )
+        .unwrap();
+        let mut registry = ToolRegistry::new(DuplicatePolicy::Error);
+        registry
+            .register_handler(definition, |_: &serde_json::Map<String, Value>| {
+                Ok(json!({"wiped": true}))
+            })
+            .unwrap();
+        let server = McpServer::new(registry).with_confirmation(ConfirmPolicy::default());
+        let input = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {"elicitation": {}}}}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "wipe"}}),
+            // Sent before the client replies, so answered after the call.
+            json!({"jsonrpc": "2.0", "id": 3, "method": "ping"}),
+            json!({"jsonrpc": "2.0", "id": "mcp-serve-1", "result": {"action": "accept", "content": {"confirm": true}}}),
+        ]
+        .map(|message| message.to_string() + "\n")
+        .concat();
+        let mut output = Vec::new();
+
+        serve_stdio(&server, input.as_bytes(), &mut output).unwrap();
+
+        let messages: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1]["id"], "mcp-serve-1");
+        assert_eq!(messages[1]["method"], "elicitation/create");
+        assert_eq!(messages[2]["id"], 2);
+        assert_eq!(
+            messages[2]["result"]["structuredContent"],
+            json!({"wiped": true})
+        );
+        assert_eq!(
+            messages[3],
+            json!({"jsonrpc": "2.0", "id": 3, "result": {}})
+        );
+    }
+
+    #[test]
+    fn test_stopping() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = |stop: &Arc<AtomicBool>| {
+            let stop = Arc::clone(stop);
+            move || stop.load(Ordering::Relaxed)
+        };
+
+        // Standard input stays open, as a client's would.
+        let (input, mut client) = io::pipe().unwrap();
+        let (replies, output) = mpsc::channel::<Vec<u8>>();
+        let stdio = {
+            let stopped = stopped(&stop);
+            thread::spawn(move || {
+                let mut output = Vec::new();
+                serve_stdio_until(&server(), io::BufReader::new(input), &mut output, stopped)
+                    .unwrap();
+                replies.send(output).unwrap();
+            })
+        };
+        writeln!(client, r#"{{"jsonrpc":"2.0","id":1,"method":"ping"}}"#).unwrap();
+
+        let http = HttpTransport::bind("127.0.0.1:0").unwrap();
+        let address = http.local_addr().unwrap();
+        let listening = {
+            let stopped = stopped(&stop);
+            thread::spawn(move || http.serve_until(Arc::new(server()), stopped))
+        };
+        let ping = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+        let response = send(address, &post(HTTP_ENDPOINT, "", ping));
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+        thread::sleep(POLL_INTERVAL);
+        stop.store(true, Ordering::Relaxed);
+        stdio.join().unwrap();
+        assert_eq!(
+            String::from_utf8(output.recv().unwrap()).unwrap(),
+            "{\"id\":1,\"jsonrpc\":\"2.0\",\"result\":{}}\n"
+        );
+        listening.join().unwrap().unwrap();
+        // The listener closes its socket on a thread of its own.
+        let closed = (0..20).any(|_| {
+            thread::sleep(Duration::from_millis(50));
+            TcpStream::connect(address).is_err()
+        });
+        assert!(closed);
+    }
+
+    /// Send a raw HTTP request and return the response.
+    fn send(address: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn post(path: &str, headers: &str, body: &str) -> String {
+        format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            path,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    #[test]
+    fn test_http() {
+        let http = HttpTransport::bind("127.0.0.1:0").unwrap();
+        let address = http.local_addr().unwrap();
+        thread::spawn(move || http.serve(Arc::new(server())));
+
+        let response = send(
+            address,
+            &post(
+                HTTP_ENDPOINT,
+                "",
+                r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#,
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains("Content-Type: application/json"));
+        assert!(response.ends_with(r#"{"id":1,"jsonrpc":"2.0","result":{}}"#));
+
+        let notification = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+        let response = send(address, &post(HTTP_ENDPOINT, "", notification));
+        assert!(response.starts_with("HTTP/1.1 202"), "{}", response);
+
+        let response = send(address, &post("/", "", notification));
+        assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+
+        let response = send(
+            address,
+            "GET /mcp HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 405"), "{}", response);
+
+        let response = send(
+            address,
+            &post(
+                HTTP_ENDPOINT,
+                "Origin: https://example.com\r\n",
+                notification,
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+        let response = send(
+            address,
+            &post(
+                HTTP_ENDPOINT,
+                "Origin: http://localhost:3000\r\n",
+                notification,
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 202"), "{}", response);
+    }
+
+    #[test]
+    fn test_http_policy() {
+        let http = HttpTransport::bind("127.0.0.1:0")
+            .unwrap()
+            .with_allowed_origins(["https://agents.example.com/".to_string()])
+            .with_max_request_size(64);
+        let address = http.local_addr().unwrap();
+        let policy = http.policy();
+        thread::spawn(move || http.serve(Arc::new(server())));
+
+        let notification = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+        let response = send(
+            address,
+            &post(
+                HTTP_ENDPOINT,
+                "Origin: https://agents.example.com\r\n",
+                notification,
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 202"), "{}", response);
+        let response = send(
+            address,
+            &post(
+                HTTP_ENDPOINT,
+                "Origin: https://example.com\r\n",
+                notification,
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+
+        let large = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"ping","params":{{"padding":"{}"}}}}"#,
+            "x".repeat(64)
+        );
+        let response = send(address, &post(HTTP_ENDPOINT, "", &large));
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+        policy.set_max_request_size(None);
+        policy.set_allowed_origins(Vec::new());
+        let response = send(address, &post(HTTP_ENDPOINT, "", &large));
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        let response = send(
+            address,
+            &post(
+                HTTP_ENDPOINT,
+                "Origin: https://agents.example.com\r\n",
+                notification,
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    }
+}
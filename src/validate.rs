@@ -0,0 +1,452 @@
+//! Checking tool definitions, for `mcp-serve validate`.
+//!
+//! [`check`] loads every discovered tool the way the server would, but
+//! strictly, and collects what is wrong with each as a [`Diagnostic`]
+//! located at a line of the file to fix. The [`ValidationReport`] prints
+//! them grouped by file:
+//!
+//! ```text
+//! tools/deploy.sh
+//!   error (deploy): input.template: unknown placeholder `{{target}}`
+//!     --> tools/deploy.sh:5
+//!
+//! Checked 2 tools: 1 broken, 0 warnings
+//! ```
+//!
+//! or as JSON ([`ValidationReport::to_json`]) for CI systems and editors.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::definitions::{Severity, ValidationIssue, Validator};
+use crate::registry::{DuplicatePolicy, ToolRegistry};
+use crate::tool_discovery::embedded::{extract_block, CommentSyntax};
+use crate::tool_discovery::{DiscoveredTool, FieldLocator, LoadError, Loader, MetadataSource};
+
+/// A problem found in a tool's definition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// How serious the problem is
+    pub severity: Severity,
+
+    /// The file to fix
+    pub path: PathBuf,
+
+    /// 1-based line of the problem, when known
+    pub line: Option<usize>,
+
+    /// 1-based column of the problem, when known
+    pub column: Option<usize>,
+
+    /// The tool the problem is in, if its definition could be read
+    pub tool: Option<String>,
+
+    /// What is wrong
+    pub message: String,
+
+    /// A suggestion for fixing the problem
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    fn load_error(tool: &DiscoveredTool, error: &LoadError) -> Self {
+        match error.definition_error() {
+            Some(e) => Self {
+                severity: Severity::Error,
+                path: e.path().unwrap_or(tool.definition_file()).to_path_buf(),
+                line: e.line(),
+                column: e.column(),
+                tool: None,
+                message: e.message().to_string(),
+                hint: e.hint().map(str::to_string),
+            },
+            None => Self::error(tool, None, error.to_string()),
+        }
+    }
+
+    fn issue(
+        tool: &DiscoveredTool,
+        name: &str,
+        issue: &ValidationIssue,
+        locator: Option<&FieldLocator>,
+    ) -> Self {
+        Self {
+            severity: issue.severity,
+            path: tool.definition_file().to_path_buf(),
+            line: locator.and_then(|locator| locator.line(&issue.field)),
+            column: None,
+            tool: Some(name.to_string()),
+            message: issue.to_string(),
+            hint: None,
+        }
+    }
+
+    fn error(tool: &DiscoveredTool, line: Option<usize>, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: tool.definition_file().to_path_buf(),
+            line,
+            column: None,
+            tool: None,
+            message,
+            hint: None,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity)?;
+        if let Some(tool) = &self.tool {
+            write!(f, " ({})", tool)?;
+        }
+        // Continuation lines of a message stay under it.
+        write!(f, ": {}", self.message.replace('\n', "\n  "))?;
+        write!(f, "\n  --> {}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  = hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything found wrong with a set of tools.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The problems found, in the order the tools were checked
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// How many tools were checked; a definition file that cannot be read
+    /// counts as one
+    pub tools: usize,
+
+    /// How many of those cannot be served
+    pub broken: usize,
+}
+
+impl ValidationReport {
+    /// Whether every tool can be served.
+    pub fn is_ok(&self) -> bool {
+        self.broken == 0
+    }
+
+    /// How many of the diagnostics are warnings.
+    pub fn warnings(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+            .count()
+    }
+
+    /// The report as a JSON object: the counts, and each diagnostic with
+    /// its `severity`, `path`, `line`, `column`, `tool`, `message`, and
+    /// `hint` (null where unknown).
+    pub fn to_json(&self) -> Value {
+        json!({
+            "ok": self.is_ok(),
+            "tools": self.tools,
+            "broken": self.broken,
+            "warnings": self.warnings(),
+            "diagnostics": self.diagnostics,
+        })
+    }
+
+    fn push(&mut self, diagnostic: Diagnostic) {
+        // Tools sharing a manifest share its errors; report them once.
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut files: Vec<&Path> = Vec::new();
+        for diagnostic in &self.diagnostics {
+            if !files.contains(&diagnostic.path.as_path()) {
+                files.push(&diagnostic.path);
+            }
+        }
+        for file in files {
+            writeln!(f, "{}", file.display())?;
+            for diagnostic in self.diagnostics.iter().filter(|d| d.path == file) {
+                let text = diagnostic.to_string();
+                for line in text.lines() {
+                    writeln!(f, "  {}", line)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        write!(
+            f,
+            "Checked {} tool{}: {} broken, {} warning{}",
+            self.tools,
+            plural(self.tools),
+            self.broken,
+            self.warnings(),
+            plural(self.warnings())
+        )
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Load and check each of `tools` with `validator`, and check that their
+/// names are unique under `policy`.
+pub fn check(
+    tools: impl IntoIterator<Item = DiscoveredTool>,
+    validator: &Validator,
+    policy: DuplicatePolicy,
+) -> ValidationReport {
+    let mut loader = Loader::new().with_strict(true);
+    let mut registry = ToolRegistry::new(policy);
+    let mut report = ValidationReport::default();
+    for tool in tools {
+        let definitions = match loader.load(&tool) {
+            Ok(definitions) => definitions,
+            Err(e) => {
+                report.tools += 1;
+                report.broken += 1;
+                report.push(Diagnostic::load_error(&tool, &e));
+                continue;
+            }
+        };
+        let locators = locators(&tool, definitions.len());
+        for (definition, locator) in definitions.into_iter().zip(locators) {
+            report.tools += 1;
+            let name = tool.qualified_name(&definition.name);
+            let issues = validator.validate(&definition);
+            for issue in &issues {
+                report.push(Diagnostic::issue(&tool, &name, issue, locator.as_ref()));
+            }
+            if issues.iter().any(ValidationIssue::is_error) {
+                report.broken += 1;
+                continue;
+            }
+            match registry.register(definition, tool.clone()) {
+                Ok(registration) => {
+                    if let Some(warning) = registration.warning(&tool.path) {
+                        report.push(Diagnostic {
+                            severity: Severity::Warning,
+                            tool: Some(name),
+                            ..Diagnostic::error(&tool, name_line(locator.as_ref()), warning)
+                        });
+                    }
+                }
+                Err(e) => {
+                    report.broken += 1;
+                    report.push(Diagnostic {
+                        tool: Some(name),
+                        ..Diagnostic::error(&tool, name_line(locator.as_ref()), e.to_string())
+                    });
+                }
+            }
+        }
+    }
+    report
+}
+
+fn name_line(locator: Option<&FieldLocator>) -> Option<usize> {
+    locator.and_then(|locator| locator.line("name"))
+}
+
+/// Locators for the fields of the `count` definitions `tool`'s definition
+/// file declares, where they can be found.
+fn locators(tool: &DiscoveredTool, count: usize) -> Vec<Option<FieldLocator>> {
+    let path = tool.definition_file();
+    let Ok(contents) = fs::read_to_string(path) else {
+        return vec![None; count];
+    };
+    let locator = match &tool.metadata_source {
+        // TOML is not laid out by indentation.
+        MetadataSource::Sidecar(_) if is_toml(path) => None,
+        MetadataSource::Sidecar(_) => {
+            let locator = FieldLocator::new(contents, 0);
+            // A sidecar declaring several tools lists them under `tools`.
+            let several = locator.line("name").is_none() && locator.line("tools").is_some();
+            if several {
+                return (0..count)
+                    .map(|i| Some(locator.clone().within(&format!("tools.{}", i))))
+                    .collect();
+            }
+            Some(locator)
+        }
+        MetadataSource::Manifest { index, .. } => {
+            Some(FieldLocator::new(contents, 0).within(&format!("tools.{}", index)))
+        }
+        MetadataSource::Embedded => {
+            let syntax = CommentSyntax::detect(path, &contents);
+            match extract_block(&contents, &syntax) {
+                Ok(Some(block)) => Some(FieldLocator::new(block.yaml, block.start_line)),
+                _ => None,
+            }
+        }
+    };
+    vec![locator; count]
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn discovered(path: PathBuf, metadata_source: MetadataSource) -> DiscoveredTool {
+        DiscoveredTool {
+            relative_path: PathBuf::from(path.file_name().unwrap()),
+            path,
+            metadata_source,
+            namespace: None,
+            working_dir: None,
+            docs: None,
+            defaults: Box::default(),
+            directory_configs: Box::default(),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = TempDir::new().unwrap();
+        let several = dir.path().join("tickets.yaml");
+        fs::write(
+            &several,
+            r#"tools:
+  - name: create_ticket
+    description: Creates a ticket
+    input:
+      template: "--title {{title}}"
+      schema:
+        type: object
+        properties:
+          name: { type: string }
+    output:
+      template: ""
+      schema: { type: object }
+  - name: create_ticket
+    description: Creates another ticket
+    input:
+      template: ""
+      schema: { type: object }
+    output:
+      template: ""
+      schema: { type: object }
+"#,
+        )
+        .unwrap();
+        let broken = dir.path().join("broken.yaml");
+        fs::write(&broken, "name: broken\ndescription: Broken\ninptu:\n").unwrap();
+        let tools = [
+            discovered(
+                dir.path().join("tickets"),
+                MetadataSource::Sidecar(several.clone()),
+            ),
+            discovered(
+                dir.path().join("broken"),
+                MetadataSource::Sidecar(broken.clone()),
+            ),
+        ];
+
+        let report = check(tools, &Validator::new(), DuplicatePolicy::Error);
+
+        assert_eq!((report.tools, report.broken), (3, 2));
+        assert!(!report.is_ok());
+        let located: Vec<_> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.severity, d.path.clone(), d.line))
+            .collect();
+        assert_eq!(
+            located,
+            [
+                (Severity::Warning, several.clone(), Some(5)),
+                (Severity::Error, several, Some(13)),
+                (Severity::Error, broken, Some(3)),
+            ]
+        );
+        assert_eq!(
+            report.diagnostics[2].hint.as_deref(),
+            Some("did you mean `input:`?")
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let report = ValidationReport {
+            diagnostics: vec![
+                Diagnostic {
+                    severity: Severity::Error,
+                    path: "tools/deploy.sh".into(),
+                    line: Some(5),
+                    column: None,
+                    tool: Some("deploy".to_string()),
+                    message: "input.template: unknown placeholder `{{target}}`".to_string(),
+                    hint: None,
+                },
+                Diagnostic {
+                    severity: Severity::Error,
+                    path: "tools/fix.yaml".into(),
+                    line: Some(3),
+                    column: Some(1),
+                    tool: None,
+                    message: "missing field `input`".to_string(),
+                    hint: Some("did you mean `input:`?".to_string()),
+                },
+            ],
+            tools: 3,
+            broken: 2,
+        };
+        assert_eq!(
+            report.to_string(),
+            concat!(
+                "tools/deploy.sh\n",
+                "  error (deploy): input.template: unknown placeholder `{{target}}`\n",
+                "    --> tools/deploy.sh:5\n",
+                "\n",
+                "tools/fix.yaml\n",
+                "  error: missing field `input`\n",
+                "    --> tools/fix.yaml:3:1\n",
+                "    = hint: did you mean `input:`?\n",
+                "\n",
+                "Checked 3 tools: 2 broken, 0 warnings",
+            )
+        );
+        assert!(ValidationReport::default().is_ok());
+
+        let json = report.to_json();
+        assert_eq!(
+            (json["ok"].clone(), json["broken"].clone()),
+            (json!(false), json!(2))
+        );
+        assert_eq!(
+            json["diagnostics"][1],
+            json!({
+                "severity": "error",
+                "path": "tools/fix.yaml",
+                "line": 3,
+                "column": 1,
+                "tool": null,
+                "message": "missing field `input`",
+                "hint": "did you mean `input:`?",
+            })
+        );
+    }
+}